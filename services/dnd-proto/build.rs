@@ -0,0 +1,36 @@
+//! Compiles every proto package in one pass so cross-package references
+//! (game.v1 -> grid.v1 / rules.v1) resolve to the same generated types.
+
+const PROTOS: &[&str] = &[
+    "../../proto/rules/v1/rules_service.proto",
+    "../../proto/rules/v1/dice.proto",
+    "../../proto/rules/v1/combat.proto",
+    "../../proto/rules/v1/spells.proto",
+    "../../proto/rules/v1/conditions.proto",
+    "../../proto/rules/v1/rest.proto",
+    "../../proto/rules/v1/resources.proto",
+    "../../proto/rules/v1/reactions.proto",
+    "../../proto/grid/v1/grid_service.proto",
+    "../../proto/grid/v1/los.proto",
+    "../../proto/grid/v1/pathfinding.proto",
+    "../../proto/grid/v1/aoe.proto",
+    "../../proto/game/v1/game_service.proto",
+    "../../proto/game/v1/session.proto",
+    "../../proto/game/v1/events.proto",
+];
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Fall back to the vendored protoc so the build does not depend on a
+    // system-wide protobuf install.
+    if std::env::var_os("PROTOC").is_none() {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+    }
+
+    tonic_build::configure()
+        .build_server(true)
+        .build_client(true)
+        .compile(PROTOS, &["../../proto"])?;
+
+    println!("cargo:rerun-if-changed=../../proto");
+    Ok(())
+}
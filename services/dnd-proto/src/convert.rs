@@ -0,0 +1,121 @@
+//! Conversions between generated proto types and `shared_rust` types.
+//!
+//! Proto messages use plain strings for ids and `Option` for every message
+//! field, so the fallible direction goes through [`ProtoError`], which maps
+//! onto `tonic::Status::invalid_argument` for handlers.
+
+use crate::{grid, rules};
+use shared_rust::{DndError, EntityId, GridPosition};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum ProtoError {
+    #[error("Missing required field: {0}")]
+    MissingField(&'static str),
+
+    #[error("Invalid {name}: {value}")]
+    InvalidEnum { name: &'static str, value: i32 },
+
+    #[error("Invalid entity id for {field}: {value}")]
+    InvalidEntityId { field: &'static str, value: String },
+}
+
+impl From<ProtoError> for tonic::Status {
+    fn from(err: ProtoError) -> Self {
+        tonic::Status::invalid_argument(err.to_string())
+    }
+}
+
+/// Unwrap a message field that the API treats as required.
+pub fn require<T>(value: Option<T>, field: &'static str) -> Result<T, ProtoError> {
+    value.ok_or(ProtoError::MissingField(field))
+}
+
+/// Parse an id string from a request into an [`EntityId`].
+pub fn entity_id(value: &str, field: &'static str) -> Result<EntityId, ProtoError> {
+    value
+        .parse()
+        .map_err(|_: DndError| ProtoError::InvalidEntityId {
+            field,
+            value: value.to_string(),
+        })
+}
+
+impl From<GridPosition> for grid::v1::GridPosition {
+    fn from(position: GridPosition) -> Self {
+        Self {
+            x: position.x,
+            y: position.y,
+        }
+    }
+}
+
+impl From<grid::v1::GridPosition> for GridPosition {
+    fn from(position: grid::v1::GridPosition) -> Self {
+        GridPosition::new(position.x, position.y)
+    }
+}
+
+impl From<&grid::v1::GridPosition> for GridPosition {
+    fn from(position: &grid::v1::GridPosition) -> Self {
+        GridPosition::new(position.x, position.y)
+    }
+}
+
+impl From<GridPosition> for rules::v1::Position {
+    fn from(position: GridPosition) -> Self {
+        Self {
+            x: position.x,
+            y: position.y,
+        }
+    }
+}
+
+impl From<rules::v1::Position> for GridPosition {
+    fn from(position: rules::v1::Position) -> Self {
+        GridPosition::new(position.x, position.y)
+    }
+}
+
+impl From<&rules::v1::Position> for GridPosition {
+    fn from(position: &rules::v1::Position) -> Self {
+        GridPosition::new(position.x, position.y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_position_round_trip() {
+        let position = GridPosition::new(3, -2);
+        let proto: grid::v1::GridPosition = position.into();
+        assert_eq!(GridPosition::from(proto), position);
+
+        let rules_proto: rules::v1::Position = position.into();
+        assert_eq!(GridPosition::from(&rules_proto), position);
+    }
+
+    #[test]
+    fn test_entity_id_parsing() {
+        let id = EntityId::new();
+        assert_eq!(entity_id(&String::from(id), "target_id"), Ok(id));
+        assert_eq!(
+            entity_id("goblin-1", "target_id"),
+            Err(ProtoError::InvalidEntityId {
+                field: "target_id",
+                value: "goblin-1".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_missing_field_becomes_invalid_argument() {
+        let status: tonic::Status = require::<grid::v1::GridMap>(None, "map")
+            .unwrap_err()
+            .into();
+        assert_eq!(status.code(), tonic::Code::InvalidArgument);
+        assert!(status.message().contains("map"));
+    }
+}
@@ -0,0 +1,27 @@
+//! Protobuf types and gRPC stubs for the D&D Platform, generated from the
+//! files under `proto/`.
+//!
+//! Module paths mirror the proto packages (`rules.v1` is [`rules::v1`]).
+//! Conversions to the shared Rust types live in [`convert`].
+
+pub mod convert;
+
+pub mod rules {
+    pub mod v1 {
+        tonic::include_proto!("rules.v1");
+    }
+}
+
+pub mod grid {
+    pub mod v1 {
+        tonic::include_proto!("grid.v1");
+    }
+}
+
+pub mod game {
+    pub mod v1 {
+        tonic::include_proto!("game.v1");
+    }
+}
+
+pub use convert::{entity_id, require, ProtoError};
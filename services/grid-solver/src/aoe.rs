@@ -0,0 +1,330 @@
+//! Area of effect templates (PHB p.204).
+//!
+//! Shapes are laid over the grid from the centre of the origin tile. A tile
+//! is inside the template when its centre is. Unless walls are ignored, a
+//! tile only counts as affected if there is a line of effect to it from the
+//! origin.
+
+use crate::cover::{calculate_cover, CoverLevel};
+use crate::grid::{distance_feet, footprint_distance_feet, DiagonalRule, Grid, FEET_PER_TILE};
+use crate::los::{blockers_on_segment, center};
+use serde::{Deserialize, Serialize};
+use shared_rust::GridPosition;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AoeShape {
+    Sphere,
+    Cube,
+    Cone,
+    Line,
+    Cylinder,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// Unit step on the grid; north is towards smaller `y`.
+    pub fn step(&self) -> (i32, i32) {
+        match self {
+            Direction::North => (0, -1),
+            Direction::NorthEast => (1, -1),
+            Direction::East => (1, 0),
+            Direction::SouthEast => (1, 1),
+            Direction::South => (0, 1),
+            Direction::SouthWest => (-1, 1),
+            Direction::West => (-1, 0),
+            Direction::NorthWest => (-1, -1),
+        }
+    }
+
+    fn unit_vector(&self) -> (f64, f64) {
+        let (dx, dy) = self.step();
+        let length = ((dx * dx + dy * dy) as f64).sqrt();
+        (dx as f64 / length, dy as f64 / length)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct AoeTemplate {
+    pub shape: AoeShape,
+    /// Radius for sphere/cylinder, side for cube, length for cone/line.
+    pub size_feet: i32,
+    /// Line width; defaults to 5 feet.
+    pub width_feet: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AoeTile {
+    pub position: GridPosition,
+    pub blocked_by_wall: bool,
+    pub distance_feet: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AoeTarget {
+    pub creature_id: String,
+    pub position: GridPosition,
+    pub distance_feet: i32,
+    pub cover: CoverLevel,
+}
+
+fn feet_to_tiles(feet: i32) -> f64 {
+    feet.max(0) as f64 / FEET_PER_TILE as f64
+}
+
+/// Projection of the origin->tile vector onto `direction`, and its
+/// perpendicular distance from the axis, both in tiles.
+fn axis_offsets(origin: GridPosition, tile: GridPosition, direction: Direction) -> (f64, f64) {
+    let (ux, uy) = direction.unit_vector();
+    let (vx, vy) = ((tile.x - origin.x) as f64, (tile.y - origin.y) as f64);
+    let along = vx * ux + vy * uy;
+    let across = (vx * uy - vy * ux).abs();
+    (along, across)
+}
+
+/// Every tile covered by the template, before walls are considered.
+pub fn template_tiles(
+    origin: GridPosition,
+    template: &AoeTemplate,
+    direction: Option<Direction>,
+) -> Vec<GridPosition> {
+    let size = feet_to_tiles(template.size_feet);
+    let reach = size.ceil() as i32 + 1;
+    let candidates = ((origin.y - reach)..=(origin.y + reach)).flat_map(move |y| {
+        ((origin.x - reach)..=(origin.x + reach)).map(move |x| GridPosition::new(x, y))
+    });
+
+    match template.shape {
+        AoeShape::Sphere | AoeShape::Cylinder => candidates
+            .filter(|t| {
+                let (dx, dy) = ((t.x - origin.x) as f64, (t.y - origin.y) as f64);
+                (dx * dx + dy * dy).sqrt() <= size + 1e-9
+            })
+            .collect(),
+        AoeShape::Cube => cube_tiles(origin, size.round() as i32, direction),
+        AoeShape::Cone => {
+            let direction = direction.unwrap_or(Direction::East);
+            candidates
+                .filter(|t| {
+                    let (along, across) = axis_offsets(origin, *t, direction);
+                    // A cone's width at any point equals its distance from
+                    // the point of origin.
+                    along > 0.0 && along <= size + 1e-9 && across <= along / 2.0 + 1e-9
+                })
+                .collect()
+        }
+        AoeShape::Line => {
+            let direction = direction.unwrap_or(Direction::East);
+            let width = feet_to_tiles(if template.width_feet > 0 {
+                template.width_feet
+            } else {
+                FEET_PER_TILE
+            });
+            candidates
+                .filter(|t| {
+                    let (along, across) = axis_offsets(origin, *t, direction);
+                    along > 0.0 && along <= size + 1e-9 && across <= width / 2.0 + 1e-9
+                })
+                .collect()
+        }
+    }
+}
+
+/// A cube projecting from the origin's face in `direction`; without a
+/// direction the origin is the cube's top-left tile.
+fn cube_tiles(origin: GridPosition, side: i32, direction: Option<Direction>) -> Vec<GridPosition> {
+    let side = side.max(1);
+    let (start_x, start_y) = match direction {
+        None => (origin.x, origin.y),
+        Some(direction) => {
+            let (dx, dy) = direction.step();
+            let offset = |d: i32, o: i32| match d {
+                1 => o + 1,
+                -1 => o - side,
+                _ => o - (side - 1) / 2,
+            };
+            (offset(dx, origin.x), offset(dy, origin.y))
+        }
+    };
+
+    let mut tiles = Vec::with_capacity((side * side) as usize);
+    for y in start_y..start_y + side {
+        for x in start_x..start_x + side {
+            tiles.push(GridPosition::new(x, y));
+        }
+    }
+    tiles
+}
+
+/// Template tiles on the map, flagged when a wall cuts them off from the origin.
+pub fn aoe_tiles(
+    grid: &Grid,
+    origin: GridPosition,
+    template: &AoeTemplate,
+    direction: Option<Direction>,
+    ignore_walls: bool,
+) -> Vec<AoeTile> {
+    template_tiles(origin, template, direction)
+        .into_iter()
+        .filter(|t| grid.in_bounds(*t))
+        .map(|position| {
+            let blocked_by_wall = !ignore_walls
+                && (grid.blocks_sight(position)
+                    || !blockers_on_segment(
+                        grid,
+                        center(origin),
+                        center(position),
+                        origin,
+                        position,
+                    )
+                    .is_empty());
+            AoeTile {
+                position,
+                blocked_by_wall,
+                distance_feet: distance_feet(origin, position, DiagonalRule::Uniform),
+            }
+        })
+        .collect()
+}
+
+/// Creatures with any part of their space in an affected tile.
+pub fn aoe_targets(
+    grid: &Grid,
+    origin: GridPosition,
+    template: &AoeTemplate,
+    direction: Option<Direction>,
+    ignore_walls: bool,
+    exclude_creature_id: Option<&str>,
+) -> Vec<AoeTarget> {
+    let affected: Vec<GridPosition> = aoe_tiles(grid, origin, template, direction, ignore_walls)
+        .into_iter()
+        .filter(|t| !t.blocked_by_wall)
+        .map(|t| t.position)
+        .collect();
+
+    grid.creatures()
+        .iter()
+        .filter(|c| exclude_creature_id != Some(c.creature_id.as_str()))
+        .filter_map(|creature| {
+            let footprint = creature.footprint();
+            let hit = footprint.iter().find(|p| affected.contains(p))?;
+            Some(AoeTarget {
+                creature_id: creature.creature_id.clone(),
+                position: creature.position,
+                distance_feet: footprint_distance_feet(&[origin], &footprint),
+                cover: if ignore_walls {
+                    CoverLevel::None
+                } else {
+                    calculate_cover(grid, origin, *hit).level
+                },
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{Blocking, Creature, Tile, TileType};
+
+    fn template(shape: AoeShape, size_feet: i32) -> AoeTemplate {
+        AoeTemplate {
+            shape,
+            size_feet,
+            width_feet: 5,
+        }
+    }
+
+    #[test]
+    fn test_fireball_sphere() {
+        let tiles = template_tiles(
+            GridPosition::new(10, 10),
+            &template(AoeShape::Sphere, 20),
+            None,
+        );
+        assert!(tiles.contains(&GridPosition::new(14, 10)));
+        assert!(!tiles.contains(&GridPosition::new(14, 14)));
+        assert!(tiles.contains(&GridPosition::new(10, 10)));
+    }
+
+    #[test]
+    fn test_cone_widens_with_distance() {
+        let tiles = template_tiles(
+            GridPosition::new(0, 5),
+            &template(AoeShape::Cone, 15),
+            Some(Direction::East),
+        );
+        assert!(!tiles.contains(&GridPosition::new(0, 5)));
+        assert!(tiles.contains(&GridPosition::new(1, 5)));
+        assert!(!tiles.contains(&GridPosition::new(1, 4)));
+        assert!(tiles.contains(&GridPosition::new(2, 4)));
+        assert!(tiles.contains(&GridPosition::new(3, 6)));
+        assert!(!tiles.contains(&GridPosition::new(4, 5)));
+    }
+
+    #[test]
+    fn test_line_is_one_tile_wide() {
+        let tiles = template_tiles(
+            GridPosition::new(0, 0),
+            &template(AoeShape::Line, 30),
+            Some(Direction::South),
+        );
+        assert_eq!(tiles.len(), 6);
+        assert!(tiles.iter().all(|t| t.x == 0));
+    }
+
+    #[test]
+    fn test_thunderwave_cube_projects_from_caster() {
+        let tiles = template_tiles(
+            GridPosition::new(5, 5),
+            &template(AoeShape::Cube, 15),
+            Some(Direction::East),
+        );
+        assert_eq!(tiles.len(), 9);
+        assert!(tiles.contains(&GridPosition::new(6, 4)));
+        assert!(tiles.contains(&GridPosition::new(8, 6)));
+        assert!(!tiles.contains(&GridPosition::new(5, 5)));
+    }
+
+    #[test]
+    fn test_walls_shield_targets() {
+        let mut grid = Grid::new(20, 20);
+        for y in 0..20 {
+            grid.set_tile(Tile {
+                tile_type: TileType::Wall,
+                blocking: Blocking::Full,
+                movement_cost: 999,
+                ..Tile::floor(GridPosition::new(12, y))
+            });
+        }
+        for (id, x) in [("near", 11), ("far", 13)] {
+            grid.add_creature(Creature {
+                creature_id: id.to_string(),
+                position: GridPosition::new(x, 10),
+                size: 1,
+                is_prone: false,
+            });
+        }
+
+        let targets = aoe_targets(
+            &grid,
+            GridPosition::new(10, 10),
+            &template(AoeShape::Sphere, 20),
+            None,
+            false,
+            None,
+        );
+        let ids: Vec<&str> = targets.iter().map(|t| t.creature_id.as_str()).collect();
+        assert_eq!(ids, vec!["near"]);
+    }
+}
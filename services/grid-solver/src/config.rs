@@ -0,0 +1,21 @@
+//! Service configuration loaded from the environment.
+
+use anyhow::Result;
+use std::env;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub port: u16,
+    pub log_level: String,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            port: env::var("GRID_SOLVER_PORT")
+                .unwrap_or_else(|_| "50052".to_string())
+                .parse()?,
+            log_level: env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string()),
+        })
+    }
+}
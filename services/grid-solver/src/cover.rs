@@ -0,0 +1,217 @@
+//! Cover calculation using the DMG grid method (DMG p.251).
+//!
+//! The attacker picks a corner of its square and traces lines to every
+//! corner of the target's square. One or two blocked lines give half cover,
+//! three or four give three-quarters cover; the attacker uses whichever of
+//! its corners is least obstructed. No line of sight at all is total cover.
+
+use crate::grid::{Blocking, Grid};
+use crate::los::{corners, line_of_sight, tiles_crossed};
+use serde::{Deserialize, Serialize};
+use shared_rust::GridPosition;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum CoverLevel {
+    None,
+    Half,
+    ThreeQuarters,
+    Total,
+}
+
+impl CoverLevel {
+    pub fn ac_bonus(&self) -> i32 {
+        match self {
+            CoverLevel::None | CoverLevel::Total => 0,
+            CoverLevel::Half => 2,
+            CoverLevel::ThreeQuarters => 5,
+        }
+    }
+
+    pub fn dex_save_bonus(&self) -> i32 {
+        self.ac_bonus()
+    }
+
+    fn from_blocked_lines(blocked: usize) -> Self {
+        match blocked {
+            0 => CoverLevel::None,
+            1 | 2 => CoverLevel::Half,
+            _ => CoverLevel::ThreeQuarters,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Cover {
+    pub level: CoverLevel,
+    pub providing_cover: Vec<GridPosition>,
+}
+
+impl Cover {
+    pub fn ac_bonus(&self) -> i32 {
+        self.level.ac_bonus()
+    }
+
+    pub fn dex_save_bonus(&self) -> i32 {
+        self.level.dex_save_bonus()
+    }
+}
+
+/// The most cover an obstacle on `position` can provide, if it is one.
+fn obstacle_strength(
+    grid: &Grid,
+    position: GridPosition,
+    attacker: GridPosition,
+    target: GridPosition,
+) -> Option<CoverLevel> {
+    if position == attacker || position == target {
+        return None;
+    }
+    if grid.blocks_sight(position) {
+        return Some(CoverLevel::ThreeQuarters);
+    }
+    if let Some(tile) = grid.tile_ref(position) {
+        if tile.provides_three_quarter_cover {
+            return Some(CoverLevel::ThreeQuarters);
+        }
+        if tile.provides_half_cover || tile.blocking == Blocking::Partial {
+            return Some(CoverLevel::Half);
+        }
+    }
+    // Other creatures provide half cover (PHB p.196).
+    grid.occupant(position).and_then(|creature| {
+        let occupies_endpoint = creature
+            .footprint()
+            .iter()
+            .any(|p| *p == attacker || *p == target);
+        (!occupies_endpoint).then_some(CoverLevel::Half)
+    })
+}
+
+pub fn calculate_cover(grid: &Grid, attacker: GridPosition, target: GridPosition) -> Cover {
+    if attacker == target {
+        return Cover {
+            level: CoverLevel::None,
+            providing_cover: vec![],
+        };
+    }
+    if !line_of_sight(grid, attacker, target).has_line_of_sight {
+        return Cover {
+            level: CoverLevel::Total,
+            providing_cover: crate::los::blockers_on_segment(
+                grid,
+                crate::los::center(attacker),
+                crate::los::center(target),
+                attacker,
+                target,
+            ),
+        };
+    }
+
+    let target_corners = corners(target);
+    let mut best: Option<Cover> = None;
+
+    for origin in corners(attacker) {
+        let mut blocked_lines = 0;
+        let mut strongest = CoverLevel::None;
+        let mut providing: Vec<GridPosition> = Vec::new();
+
+        for corner in target_corners {
+            let obstacles: Vec<(GridPosition, CoverLevel)> = tiles_crossed(origin, corner)
+                .into_iter()
+                .filter_map(|p| obstacle_strength(grid, p, attacker, target).map(|s| (p, s)))
+                .collect();
+            if obstacles.is_empty() {
+                continue;
+            }
+            blocked_lines += 1;
+            for (position, strength) in obstacles {
+                strongest = strongest.max(strength);
+                if !providing.contains(&position) {
+                    providing.push(position);
+                }
+            }
+        }
+
+        let level = CoverLevel::from_blocked_lines(blocked_lines).min(strongest);
+        let candidate = Cover {
+            level,
+            providing_cover: if level == CoverLevel::None {
+                vec![]
+            } else {
+                providing
+            },
+        };
+        if best.as_ref().is_none_or(|b| candidate.level < b.level) {
+            best = Some(candidate);
+        }
+    }
+
+    best.unwrap_or(Cover {
+        level: CoverLevel::None,
+        providing_cover: vec![],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{Creature, Tile, TileType};
+
+    fn low_wall(x: i32, y: i32) -> Tile {
+        Tile {
+            blocking: Blocking::Partial,
+            provides_half_cover: true,
+            ..Tile::floor(GridPosition::new(x, y))
+        }
+    }
+
+    #[test]
+    fn test_no_cover_in_the_open() {
+        let grid = Grid::new(10, 10);
+        let cover = calculate_cover(&grid, GridPosition::new(0, 0), GridPosition::new(5, 5));
+        assert_eq!(cover.level, CoverLevel::None);
+    }
+
+    #[test]
+    fn test_low_wall_gives_half_cover() {
+        let mut grid = Grid::new(10, 10);
+        for y in 0..10 {
+            grid.set_tile(low_wall(4, y));
+        }
+
+        let cover = calculate_cover(&grid, GridPosition::new(1, 5), GridPosition::new(5, 5));
+        assert_eq!(cover.level, CoverLevel::Half);
+        assert_eq!(cover.ac_bonus(), 2);
+    }
+
+    #[test]
+    fn test_creature_in_the_way_gives_half_cover() {
+        let mut grid = Grid::new(10, 10);
+        grid.add_creature(Creature {
+            creature_id: "goblin".to_string(),
+            position: GridPosition::new(3, 5),
+            size: 1,
+            is_prone: false,
+        });
+
+        let cover = calculate_cover(&grid, GridPosition::new(1, 5), GridPosition::new(5, 5));
+        assert_eq!(cover.level, CoverLevel::Half);
+        assert_eq!(cover.providing_cover, vec![GridPosition::new(3, 5)]);
+    }
+
+    #[test]
+    fn test_full_wall_is_total_cover() {
+        let mut grid = Grid::new(10, 10);
+        for y in 0..10 {
+            grid.set_tile(Tile {
+                tile_type: TileType::Wall,
+                blocking: Blocking::Full,
+                movement_cost: 999,
+                ..Tile::floor(GridPosition::new(4, y))
+            });
+        }
+
+        let cover = calculate_cover(&grid, GridPosition::new(1, 5), GridPosition::new(7, 5));
+        assert_eq!(cover.level, CoverLevel::Total);
+    }
+}
@@ -0,0 +1,271 @@
+//! In-memory grid map used by every solver.
+//!
+//! Each tile is a 5-foot square. Tile `(x, y)` covers the area
+//! `[x, x + 1] x [y, y + 1]` in grid units, so tile centres sit at `+0.5`.
+
+use serde::{Deserialize, Serialize};
+use shared_rust::GridPosition;
+use std::collections::HashMap;
+
+/// Feet per grid square.
+pub const FEET_PER_TILE: i32 = 5;
+
+/// Movement cost marking a tile as impassable.
+pub const IMPASSABLE_COST: i32 = 999;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TileType {
+    Floor,
+    Wall,
+    DoorClosed,
+    DoorOpen,
+    Window,
+    DifficultTerrain,
+    WaterShallow,
+    WaterDeep,
+    Pit,
+    Stairs,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Blocking {
+    None,
+    Partial,
+    Full,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tile {
+    pub position: GridPosition,
+    pub tile_type: TileType,
+    pub blocking: Blocking,
+    pub movement_cost: i32,
+    pub elevation: i32,
+    pub provides_half_cover: bool,
+    pub provides_three_quarter_cover: bool,
+    pub creature_id: Option<String>,
+}
+
+impl Tile {
+    /// Open floor at `position`; used for any tile the map does not list.
+    pub fn floor(position: GridPosition) -> Self {
+        Self {
+            position,
+            tile_type: TileType::Floor,
+            blocking: Blocking::None,
+            movement_cost: 1,
+            elevation: 0,
+            provides_half_cover: false,
+            provides_three_quarter_cover: false,
+            creature_id: None,
+        }
+    }
+
+    /// Walls and closed doors block sight and line of effect outright.
+    pub fn blocks_sight(&self) -> bool {
+        self.blocking == Blocking::Full
+            || matches!(self.tile_type, TileType::Wall | TileType::DoorClosed)
+    }
+
+    /// Windows let sight through but not bodies.
+    pub fn blocks_movement(&self) -> bool {
+        self.movement_cost >= IMPASSABLE_COST
+            || matches!(
+                self.tile_type,
+                TileType::Wall | TileType::DoorClosed | TileType::Window
+            )
+    }
+
+    pub fn is_difficult(&self) -> bool {
+        self.movement_cost > 1 || self.tile_type == TileType::DifficultTerrain
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Creature {
+    pub creature_id: String,
+    pub position: GridPosition,
+    /// Side length in tiles: 1 = Medium or smaller, 2 = Large, 3 = Huge, 4 = Gargantuan.
+    pub size: i32,
+    pub is_prone: bool,
+}
+
+impl Creature {
+    /// Every tile the creature occupies, anchored at its top-left tile.
+    pub fn footprint(&self) -> Vec<GridPosition> {
+        footprint(self.position, self.size)
+    }
+}
+
+/// Tiles covered by a creature of `size` whose top-left tile is `anchor`.
+pub fn footprint(anchor: GridPosition, size: i32) -> Vec<GridPosition> {
+    let size = size.max(1);
+    let mut tiles = Vec::with_capacity((size * size) as usize);
+    for dy in 0..size {
+        for dx in 0..size {
+            tiles.push(GridPosition::new(anchor.x + dx, anchor.y + dy));
+        }
+    }
+    tiles
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Grid {
+    pub width: i32,
+    pub height: i32,
+    tiles: HashMap<GridPosition, Tile>,
+    creatures: Vec<Creature>,
+    occupancy: HashMap<GridPosition, usize>,
+}
+
+impl Grid {
+    pub fn new(width: i32, height: i32) -> Self {
+        Self {
+            width,
+            height,
+            ..Default::default()
+        }
+    }
+
+    pub fn set_tile(&mut self, tile: Tile) {
+        self.tiles.insert(tile.position, tile);
+    }
+
+    pub fn add_creature(&mut self, creature: Creature) {
+        let index = self.creatures.len();
+        for position in creature.footprint() {
+            self.occupancy.insert(position, index);
+        }
+        self.creatures.push(creature);
+    }
+
+    pub fn in_bounds(&self, position: GridPosition) -> bool {
+        position.x >= 0 && position.y >= 0 && position.x < self.width && position.y < self.height
+    }
+
+    /// The tile at `position`, defaulting to open floor when not listed.
+    pub fn tile(&self, position: GridPosition) -> Tile {
+        self.tiles
+            .get(&position)
+            .cloned()
+            .unwrap_or_else(|| Tile::floor(position))
+    }
+
+    pub fn tile_ref(&self, position: GridPosition) -> Option<&Tile> {
+        self.tiles.get(&position)
+    }
+
+    pub fn creatures(&self) -> &[Creature] {
+        &self.creatures
+    }
+
+    pub fn creature(&self, creature_id: &str) -> Option<&Creature> {
+        self.creatures.iter().find(|c| c.creature_id == creature_id)
+    }
+
+    /// The creature occupying `position`, if any.
+    pub fn occupant(&self, position: GridPosition) -> Option<&Creature> {
+        self.occupancy
+            .get(&position)
+            .map(|&index| &self.creatures[index])
+            .or_else(|| {
+                let id = self.tiles.get(&position)?.creature_id.as_deref()?;
+                self.creature(id)
+            })
+    }
+
+    pub fn blocks_sight(&self, position: GridPosition) -> bool {
+        !self.in_bounds(position) || self.tiles.get(&position).is_some_and(Tile::blocks_sight)
+    }
+
+    /// Every in-bounds position, row by row.
+    pub fn positions(&self) -> impl Iterator<Item = GridPosition> + '_ {
+        (0..self.height).flat_map(move |y| (0..self.width).map(move |x| GridPosition::new(x, y)))
+    }
+}
+
+/// How diagonal movement is measured.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DiagonalRule {
+    /// PHB default: every square, diagonal or not, costs 5 feet.
+    Uniform,
+    /// DMG variant: diagonals alternate between 5 and 10 feet.
+    Alternating,
+}
+
+/// Distance in tiles between two positions under `rule`.
+pub fn distance_tiles(from: GridPosition, to: GridPosition, rule: DiagonalRule) -> i32 {
+    let dx = (from.x - to.x).abs();
+    let dy = (from.y - to.y).abs();
+    let diagonal = dx.min(dy);
+    let straight = dx.max(dy) - diagonal;
+    match rule {
+        DiagonalRule::Uniform => diagonal + straight,
+        DiagonalRule::Alternating => straight + diagonal + diagonal / 2,
+    }
+}
+
+/// Distance in feet between two positions under `rule`.
+pub fn distance_feet(from: GridPosition, to: GridPosition, rule: DiagonalRule) -> i32 {
+    distance_tiles(from, to, rule) * FEET_PER_TILE
+}
+
+/// Shortest PHB distance in feet between any tiles of two footprints.
+pub fn footprint_distance_feet(a: &[GridPosition], b: &[GridPosition]) -> i32 {
+    a.iter()
+        .flat_map(|p| {
+            b.iter()
+                .map(move |q| distance_feet(*p, *q, DiagonalRule::Uniform))
+        })
+        .min()
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uniform_diagonals() {
+        let from = GridPosition::new(0, 0);
+        assert_eq!(
+            distance_feet(from, GridPosition::new(3, 3), DiagonalRule::Uniform),
+            15
+        );
+        assert_eq!(
+            distance_feet(from, GridPosition::new(4, 1), DiagonalRule::Uniform),
+            20
+        );
+    }
+
+    #[test]
+    fn test_alternating_diagonals() {
+        let from = GridPosition::new(0, 0);
+        // 5 + 10 + 5
+        assert_eq!(
+            distance_feet(from, GridPosition::new(3, 3), DiagonalRule::Alternating),
+            20
+        );
+        assert_eq!(
+            distance_feet(from, GridPosition::new(4, 4), DiagonalRule::Alternating),
+            30
+        );
+    }
+
+    #[test]
+    fn test_large_creature_footprint() {
+        let mut grid = Grid::new(10, 10);
+        grid.add_creature(Creature {
+            creature_id: "ogre".to_string(),
+            position: GridPosition::new(2, 2),
+            size: 2,
+            is_prone: false,
+        });
+
+        assert_eq!(
+            grid.occupant(GridPosition::new(3, 3)).unwrap().creature_id,
+            "ogre"
+        );
+        assert!(grid.occupant(GridPosition::new(4, 4)).is_none());
+    }
+}
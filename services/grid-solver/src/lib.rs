@@ -0,0 +1,12 @@
+//! Grid Solver
+//!
+//! Line of sight, cover, area of effect and pathfinding on the combat grid,
+//! served over gRPC by the `grid-solver` binary.
+
+pub mod aoe;
+pub mod config;
+pub mod cover;
+pub mod grid;
+pub mod los;
+pub mod pathfinding;
+pub mod service;
@@ -0,0 +1,253 @@
+//! Line of sight and visibility.
+//!
+//! Sight is traced as straight segments in continuous grid space. A segment
+//! is blocked by a tile only when it passes through the tile's interior, so
+//! grazing a wall corner does not block.
+
+use crate::grid::{distance_tiles, DiagonalRule, Grid};
+use serde::{Deserialize, Serialize};
+use shared_rust::GridPosition;
+
+/// A point in continuous grid space.
+pub type Point = (f64, f64);
+
+const EPSILON: f64 = 1e-9;
+
+pub fn center(position: GridPosition) -> Point {
+    (position.x as f64 + 0.5, position.y as f64 + 0.5)
+}
+
+pub fn corners(position: GridPosition) -> [Point; 4] {
+    let (x, y) = (position.x as f64, position.y as f64);
+    [(x, y), (x + 1.0, y), (x, y + 1.0), (x + 1.0, y + 1.0)]
+}
+
+/// Whether the segment `a -> b` passes through the interior of `tile`.
+pub fn segment_crosses_tile(a: Point, b: Point, tile: GridPosition) -> bool {
+    let (min_x, max_x) = (tile.x as f64 + EPSILON, tile.x as f64 + 1.0 - EPSILON);
+    let (min_y, max_y) = (tile.y as f64 + EPSILON, tile.y as f64 + 1.0 - EPSILON);
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+
+    // Liang-Barsky clipping against the shrunken tile.
+    let mut t0: f64 = 0.0;
+    let mut t1: f64 = 1.0;
+    for (p, q) in [
+        (-dx, a.0 - min_x),
+        (dx, max_x - a.0),
+        (-dy, a.1 - min_y),
+        (dy, max_y - a.1),
+    ] {
+        if p.abs() < f64::EPSILON {
+            if q < 0.0 {
+                return false;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+        }
+    }
+    t0 < t1
+}
+
+/// Tiles whose interior the segment `a -> b` passes through.
+pub fn tiles_crossed(a: Point, b: Point) -> Vec<GridPosition> {
+    let (min_x, max_x) = (a.0.min(b.0).floor() as i32, a.0.max(b.0).floor() as i32);
+    let (min_y, max_y) = (a.1.min(b.1).floor() as i32, a.1.max(b.1).floor() as i32);
+
+    let mut crossed = Vec::new();
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let tile = GridPosition::new(x, y);
+            if segment_crosses_tile(a, b, tile) {
+                crossed.push(tile);
+            }
+        }
+    }
+    crossed
+}
+
+/// Sight-blocking tiles on the segment, ignoring the two endpoint tiles.
+pub fn blockers_on_segment(
+    grid: &Grid,
+    a: Point,
+    b: Point,
+    from: GridPosition,
+    to: GridPosition,
+) -> Vec<GridPosition> {
+    tiles_crossed(a, b)
+        .into_iter()
+        .filter(|&t| t != from && t != to && grid.blocks_sight(t))
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LineOfSight {
+    pub has_line_of_sight: bool,
+    pub blocked_by: Vec<GridPosition>,
+    /// Fraction of sight lines to the target square that are blocked.
+    pub obscured: f32,
+}
+
+/// Trace sight from the centre of `from` to the centre and each corner of
+/// `to`. The target is visible if any of those lines is clear.
+pub fn line_of_sight(grid: &Grid, from: GridPosition, to: GridPosition) -> LineOfSight {
+    if from == to {
+        return LineOfSight {
+            has_line_of_sight: true,
+            blocked_by: vec![],
+            obscured: 0.0,
+        };
+    }
+
+    let origin = center(from);
+    let mut targets = vec![center(to)];
+    targets.extend(corners(to));
+
+    let mut blocked_by: Vec<GridPosition> = Vec::new();
+    let mut blocked_rays = 0;
+    for (index, target) in targets.iter().enumerate() {
+        let blockers = blockers_on_segment(grid, origin, *target, from, to);
+        if !blockers.is_empty() {
+            blocked_rays += 1;
+            // Report what blocks the direct line; fall back to the corner
+            // rays only when nothing gets through at all.
+            let nothing_gets_through = blocked_rays == targets.len();
+            if index == 0 || (blocked_by.is_empty() && nothing_gets_through) {
+                blocked_by = blockers;
+            }
+        }
+    }
+
+    LineOfSight {
+        has_line_of_sight: blocked_rays < targets.len(),
+        blocked_by: if blocked_rays == 0 {
+            vec![]
+        } else {
+            blocked_by
+        },
+        obscured: blocked_rays as f32 / targets.len() as f32,
+    }
+}
+
+pub fn has_line_of_sight(grid: &Grid, from: GridPosition, to: GridPosition) -> bool {
+    line_of_sight(grid, from, to).has_line_of_sight
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VisibleTile {
+    pub position: GridPosition,
+    pub fully_visible: bool,
+    pub dimly_lit: bool,
+    pub in_darkness: bool,
+}
+
+/// Tiles visible from `from`. Tiles within `vision_radius` are seen
+/// normally; with darkvision, tiles beyond that but within
+/// `darkvision_radius` are seen as if dimly lit (PHB p.183). Radii are in tiles.
+pub fn visible_tiles(
+    grid: &Grid,
+    from: GridPosition,
+    vision_radius: i32,
+    darkvision_radius: Option<i32>,
+) -> Vec<VisibleTile> {
+    let max_radius = vision_radius.max(darkvision_radius.unwrap_or(0));
+
+    let mut visible = Vec::new();
+    for y in (from.y - max_radius)..=(from.y + max_radius) {
+        for x in (from.x - max_radius)..=(from.x + max_radius) {
+            let position = GridPosition::new(x, y);
+            if !grid.in_bounds(position) {
+                continue;
+            }
+            let distance = distance_tiles(from, position, DiagonalRule::Uniform);
+            if distance > max_radius || !has_line_of_sight(grid, from, position) {
+                continue;
+            }
+
+            let fully_visible = distance <= vision_radius;
+            visible.push(VisibleTile {
+                position,
+                fully_visible,
+                dimly_lit: !fully_visible,
+                in_darkness: false,
+            });
+        }
+    }
+    visible
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{Blocking, Tile, TileType};
+
+    fn wall(x: i32, y: i32) -> Tile {
+        Tile {
+            tile_type: TileType::Wall,
+            blocking: Blocking::Full,
+            movement_cost: 999,
+            ..Tile::floor(GridPosition::new(x, y))
+        }
+    }
+
+    #[test]
+    fn test_open_ground_is_visible() {
+        let grid = Grid::new(10, 10);
+        let los = line_of_sight(&grid, GridPosition::new(0, 0), GridPosition::new(7, 3));
+        assert!(los.has_line_of_sight);
+        assert_eq!(los.obscured, 0.0);
+    }
+
+    #[test]
+    fn test_wall_between_blocks_sight() {
+        let mut grid = Grid::new(10, 10);
+        for y in 0..10 {
+            grid.set_tile(wall(5, y));
+        }
+
+        let los = line_of_sight(&grid, GridPosition::new(2, 4), GridPosition::new(8, 4));
+        assert!(!los.has_line_of_sight);
+        assert!(los.blocked_by.contains(&GridPosition::new(5, 4)));
+    }
+
+    #[test]
+    fn test_peeking_past_a_pillar() {
+        let mut grid = Grid::new(10, 10);
+        grid.set_tile(wall(4, 4));
+
+        let los = line_of_sight(&grid, GridPosition::new(2, 4), GridPosition::new(6, 5));
+        assert!(los.has_line_of_sight);
+        assert!(los.obscured > 0.0);
+    }
+
+    #[test]
+    fn test_grazing_a_corner_does_not_block() {
+        assert!(!segment_crosses_tile(
+            (0.0, 0.0),
+            (2.0, 2.0),
+            GridPosition::new(1, 0)
+        ));
+        assert!(segment_crosses_tile(
+            (0.5, 0.5),
+            (2.5, 0.5),
+            GridPosition::new(1, 0)
+        ));
+    }
+
+    #[test]
+    fn test_darkvision_extends_sight_as_dim_light() {
+        let grid = Grid::new(20, 20);
+        let tiles = visible_tiles(&grid, GridPosition::new(10, 10), 1, Some(2));
+
+        let far = tiles
+            .iter()
+            .find(|t| t.position == GridPosition::new(12, 10))
+            .unwrap();
+        assert!(far.dimly_lit);
+        assert_eq!(tiles.len(), 25);
+    }
+}
@@ -2,21 +2,37 @@
 //!
 //! Handles line of sight, area of effect, and pathfinding calculations.
 
+use dnd_proto::grid::v1::grid_service_server::GridServiceServer;
+use grid_solver::config::Config;
+use grid_solver::service::GridServiceImpl;
+use std::net::SocketAddr;
+use tonic::transport::Server;
 use tracing::info;
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::from_env()?;
+
     // Initialize tracing
-    tracing_subscriber::fmt::init();
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.log_level)),
+        )
+        .init();
 
     info!("Starting Grid Solver...");
 
-    // TODO: Implement gRPC server in Phase 3
-    info!("Grid Solver ready on port 50052");
+    let addr: SocketAddr = format!("0.0.0.0:{}", config.port).parse()?;
+    info!("Grid Solver ready on port {}", config.port);
 
-    // Keep the server running
-    tokio::signal::ctrl_c().await?;
-    info!("Shutting down Grid Solver");
+    Server::builder()
+        .add_service(GridServiceServer::new(GridServiceImpl::new()))
+        .serve_with_shutdown(addr, async {
+            let _ = tokio::signal::ctrl_c().await;
+            info!("Shutting down Grid Solver");
+        })
+        .await?;
 
     Ok(())
 }
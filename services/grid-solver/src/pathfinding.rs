@@ -0,0 +1,439 @@
+//! Movement costs, A* pathfinding and reachable-area search.
+//!
+//! Costs are in feet. Every square costs 5 feet, diagonal or not (PHB
+//! p.192), doubled for difficult terrain, swimming without a swim speed and
+//! climbing without a climb speed (PHB p.182).
+
+use crate::grid::{footprint, Grid, TileType, FEET_PER_TILE};
+use serde::{Deserialize, Serialize};
+use shared_rust::GridPosition;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+
+const NEIGHBOURS: [(i32, i32); 8] = [
+    (0, -1),
+    (1, 0),
+    (0, 1),
+    (-1, 0),
+    (1, -1),
+    (1, 1),
+    (-1, 1),
+    (-1, -1),
+];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct MovementProfile {
+    pub can_fly: bool,
+    pub can_swim: bool,
+    pub can_climb: bool,
+    pub ignore_difficult_terrain: bool,
+    /// Side length in tiles; values below 1 are treated as 1.
+    pub creature_size: i32,
+    pub avoid: HashSet<GridPosition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathNode {
+    pub position: GridPosition,
+    pub cumulative_feet: i32,
+    pub is_difficult_terrain: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Path {
+    pub nodes: Vec<PathNode>,
+    pub total_feet: i32,
+}
+
+impl Path {
+    /// Cost in squares of normal movement.
+    pub fn total_cost(&self) -> i32 {
+        self.total_feet / FEET_PER_TILE
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ReachableTile {
+    pub position: GridPosition,
+    pub cost_feet: i32,
+    pub requires_dash: bool,
+    pub path_from_start: Vec<GridPosition>,
+}
+
+struct Mover<'a> {
+    grid: &'a Grid,
+    profile: &'a MovementProfile,
+    /// The moving creature never blocks itself.
+    self_id: Option<String>,
+}
+
+impl<'a> Mover<'a> {
+    fn new(grid: &'a Grid, profile: &'a MovementProfile, start: GridPosition) -> Self {
+        Self {
+            grid,
+            profile,
+            self_id: grid.occupant(start).map(|c| c.creature_id.clone()),
+        }
+    }
+
+    fn size(&self) -> i32 {
+        self.profile.creature_size.max(1)
+    }
+
+    /// Whether the creature's whole footprint fits with its anchor at `anchor`.
+    fn can_occupy(&self, anchor: GridPosition) -> bool {
+        footprint(anchor, self.size()).into_iter().all(|p| {
+            if !self.grid.in_bounds(p) || self.profile.avoid.contains(&p) {
+                return false;
+            }
+            let tile = self.grid.tile(p);
+            if tile.blocks_movement() {
+                return false;
+            }
+            if tile.tile_type == TileType::Pit && !self.profile.can_fly {
+                return false;
+            }
+            match self.grid.occupant(p) {
+                Some(other) => self.self_id.as_deref() == Some(other.creature_id.as_str()),
+                None => true,
+            }
+        })
+    }
+
+    /// Feet spent entering `to` from the adjacent `from`, if allowed.
+    fn step_cost(&self, from: GridPosition, to: GridPosition) -> Option<i32> {
+        if !self.can_occupy(to) {
+            return None;
+        }
+
+        // No squeezing diagonally between two walls.
+        let (dx, dy) = (to.x - from.x, to.y - from.y);
+        if dx != 0 && dy != 0 {
+            let side_a = self.grid.tile(GridPosition::new(from.x + dx, from.y));
+            let side_b = self.grid.tile(GridPosition::new(from.x, from.y + dy));
+            if side_a.blocks_movement() && side_b.blocks_movement() {
+                return None;
+            }
+        }
+
+        let mut multiplier = 1;
+        for p in footprint(to, self.size()) {
+            let tile = self.grid.tile(p);
+            let mut tile_multiplier = 1;
+            if tile.is_difficult()
+                && !self.profile.ignore_difficult_terrain
+                && !self.profile.can_fly
+            {
+                tile_multiplier = tile.movement_cost.max(2);
+            }
+            if tile.tile_type == TileType::WaterDeep
+                && !self.profile.can_swim
+                && !self.profile.can_fly
+            {
+                tile_multiplier = tile_multiplier.max(2);
+            }
+            let from_tile = self.grid.tile(from);
+            if (tile.elevation - from_tile.elevation).abs() > 1
+                && !self.profile.can_climb
+                && !self.profile.can_fly
+            {
+                tile_multiplier = tile_multiplier.max(2);
+            }
+            multiplier = multiplier.max(tile_multiplier);
+        }
+
+        Some(FEET_PER_TILE * multiplier)
+    }
+
+    fn is_difficult(&self, position: GridPosition) -> bool {
+        footprint(position, self.size())
+            .into_iter()
+            .any(|p| self.grid.tile(p).is_difficult())
+    }
+
+    fn neighbours(&self, position: GridPosition) -> impl Iterator<Item = (GridPosition, i32)> + '_ {
+        NEIGHBOURS.iter().filter_map(move |(dx, dy)| {
+            let next = GridPosition::new(position.x + dx, position.y + dy);
+            self.step_cost(position, next).map(|cost| (next, cost))
+        })
+    }
+}
+
+fn heuristic(from: GridPosition, to: GridPosition) -> i32 {
+    (from.x - to.x).abs().max((from.y - to.y).abs()) * FEET_PER_TILE
+}
+
+fn rebuild(
+    came_from: &HashMap<GridPosition, GridPosition>,
+    mut current: GridPosition,
+) -> Vec<GridPosition> {
+    let mut positions = vec![current];
+    while let Some(previous) = came_from.get(&current) {
+        current = *previous;
+        positions.push(current);
+    }
+    positions.reverse();
+    positions
+}
+
+/// Cheapest path from `from` to `to`, or `None` if there is none within
+/// `max_feet` (when given).
+pub fn find_path(
+    grid: &Grid,
+    from: GridPosition,
+    to: GridPosition,
+    profile: &MovementProfile,
+    max_feet: Option<i32>,
+) -> Option<Path> {
+    let mover = Mover::new(grid, profile, from);
+    if !mover.can_occupy(to) {
+        return None;
+    }
+
+    let mut open = BinaryHeap::new();
+    let mut best: HashMap<GridPosition, i32> = HashMap::from([(from, 0)]);
+    let mut came_from: HashMap<GridPosition, GridPosition> = HashMap::new();
+    open.push(Reverse((heuristic(from, to), 0, from.x, from.y)));
+
+    while let Some(Reverse((_, cost, x, y))) = open.pop() {
+        let current = GridPosition::new(x, y);
+        if current == to {
+            break;
+        }
+        if best.get(&current).is_some_and(|&b| cost > b) {
+            continue;
+        }
+        for (next, step) in mover.neighbours(current) {
+            let next_cost = cost + step;
+            if max_feet.is_some_and(|max| next_cost > max) {
+                continue;
+            }
+            if best.get(&next).is_none_or(|&b| next_cost < b) {
+                best.insert(next, next_cost);
+                came_from.insert(next, current);
+                open.push(Reverse((
+                    next_cost + heuristic(next, to),
+                    next_cost,
+                    next.x,
+                    next.y,
+                )));
+            }
+        }
+    }
+
+    let total_feet = *best.get(&to)?;
+    let nodes = rebuild(&came_from, to)
+        .into_iter()
+        .map(|position| PathNode {
+            position,
+            cumulative_feet: best[&position],
+            is_difficult_terrain: mover.is_difficult(position),
+        })
+        .collect();
+
+    Some(Path { nodes, total_feet })
+}
+
+/// Every tile reachable from `from` with `movement_feet`, or twice that when
+/// `include_dash` is set (tiles beyond the base budget are flagged).
+pub fn reachable_tiles(
+    grid: &Grid,
+    from: GridPosition,
+    movement_feet: i32,
+    profile: &MovementProfile,
+    include_dash: bool,
+) -> Vec<ReachableTile> {
+    let mover = Mover::new(grid, profile, from);
+    let budget = if include_dash {
+        movement_feet * 2
+    } else {
+        movement_feet
+    };
+
+    let mut open = BinaryHeap::new();
+    let mut best: HashMap<GridPosition, i32> = HashMap::from([(from, 0)]);
+    let mut came_from: HashMap<GridPosition, GridPosition> = HashMap::new();
+    open.push(Reverse((0, from.x, from.y)));
+
+    while let Some(Reverse((cost, x, y))) = open.pop() {
+        let current = GridPosition::new(x, y);
+        if best.get(&current).is_some_and(|&b| cost > b) {
+            continue;
+        }
+        for (next, step) in mover.neighbours(current) {
+            let next_cost = cost + step;
+            if next_cost > budget {
+                continue;
+            }
+            if best.get(&next).is_none_or(|&b| next_cost < b) {
+                best.insert(next, next_cost);
+                came_from.insert(next, current);
+                open.push(Reverse((next_cost, next.x, next.y)));
+            }
+        }
+    }
+
+    let mut tiles: Vec<ReachableTile> = best
+        .iter()
+        .filter(|(position, _)| **position != from)
+        .map(|(position, cost)| ReachableTile {
+            position: *position,
+            cost_feet: *cost,
+            requires_dash: *cost > movement_feet,
+            path_from_start: rebuild(&came_from, *position),
+        })
+        .collect();
+    tiles.sort_by_key(|t| (t.cost_feet, t.position.y, t.position.x));
+    tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::grid::{Blocking, Tile};
+
+    fn wall(x: i32, y: i32) -> Tile {
+        Tile {
+            tile_type: TileType::Wall,
+            blocking: Blocking::Full,
+            movement_cost: 999,
+            ..Tile::floor(GridPosition::new(x, y))
+        }
+    }
+
+    fn difficult(x: i32, y: i32) -> Tile {
+        Tile {
+            tile_type: TileType::DifficultTerrain,
+            movement_cost: 2,
+            ..Tile::floor(GridPosition::new(x, y))
+        }
+    }
+
+    #[test]
+    fn test_diagonals_cost_five_feet() {
+        let grid = Grid::new(10, 10);
+        let path = find_path(
+            &grid,
+            GridPosition::new(0, 0),
+            GridPosition::new(4, 4),
+            &MovementProfile::default(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(path.total_feet, 20);
+        assert_eq!(path.nodes.len(), 5);
+    }
+
+    #[test]
+    fn test_path_goes_around_walls() {
+        let mut grid = Grid::new(10, 10);
+        for y in 0..9 {
+            grid.set_tile(wall(5, y));
+        }
+
+        let path = find_path(
+            &grid,
+            GridPosition::new(3, 0),
+            GridPosition::new(7, 0),
+            &MovementProfile::default(),
+            None,
+        )
+        .unwrap();
+        assert!(path
+            .nodes
+            .iter()
+            .any(|n| n.position == GridPosition::new(5, 9)));
+    }
+
+    #[test]
+    fn test_difficult_terrain_doubles_cost() {
+        let mut grid = Grid::new(3, 1);
+        grid.set_tile(difficult(1, 0));
+
+        let profile = MovementProfile::default();
+        let path = find_path(
+            &grid,
+            GridPosition::new(0, 0),
+            GridPosition::new(2, 0),
+            &profile,
+            None,
+        )
+        .unwrap();
+        assert_eq!(path.total_feet, 15);
+        assert!(path.nodes[1].is_difficult_terrain);
+
+        let flying = MovementProfile {
+            can_fly: true,
+            ..Default::default()
+        };
+        let path = find_path(
+            &grid,
+            GridPosition::new(0, 0),
+            GridPosition::new(2, 0),
+            &flying,
+            None,
+        )
+        .unwrap();
+        assert_eq!(path.total_feet, 10);
+    }
+
+    #[test]
+    fn test_max_movement_limits_path() {
+        let grid = Grid::new(20, 1);
+        let path = find_path(
+            &grid,
+            GridPosition::new(0, 0),
+            GridPosition::new(10, 0),
+            &MovementProfile::default(),
+            Some(30),
+        );
+        assert!(path.is_none());
+    }
+
+    #[test]
+    fn test_large_creature_needs_room() {
+        let mut grid = Grid::new(6, 6);
+        // A one-square gap in a wall along x = 3
+        for y in 0..6 {
+            if y != 2 {
+                grid.set_tile(wall(3, y));
+            }
+        }
+        let large = MovementProfile {
+            creature_size: 2,
+            ..Default::default()
+        };
+        assert!(find_path(
+            &grid,
+            GridPosition::new(0, 2),
+            GridPosition::new(4, 2),
+            &large,
+            None
+        )
+        .is_none());
+        assert!(find_path(
+            &grid,
+            GridPosition::new(0, 2),
+            GridPosition::new(4, 2),
+            &MovementProfile::default(),
+            None
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn test_reachable_tiles_with_dash() {
+        let grid = Grid::new(20, 1);
+        let tiles = reachable_tiles(
+            &grid,
+            GridPosition::new(0, 0),
+            30,
+            &MovementProfile::default(),
+            true,
+        );
+        assert_eq!(tiles.len(), 12);
+        let dash_only: Vec<_> = tiles.iter().filter(|t| t.requires_dash).collect();
+        assert_eq!(dash_only.len(), 6);
+        assert_eq!(tiles[0].path_from_start.len(), 2);
+    }
+}
@@ -0,0 +1,508 @@
+//! gRPC implementation of grid.v1.GridService.
+//!
+//! The service is stateless: every request carries the map it should be
+//! solved against.
+
+// Helpers return tonic::Status directly so they compose with `?` in handlers.
+#![allow(clippy::result_large_err)]
+
+use crate::aoe::{self, AoeShape, AoeTemplate, Direction};
+use crate::cover::{calculate_cover, CoverLevel};
+use crate::grid::{
+    distance_tiles, footprint_distance_feet, Blocking, Creature, DiagonalRule, Grid, Tile,
+    TileType, FEET_PER_TILE,
+};
+use crate::los;
+use crate::pathfinding::{self, MovementProfile};
+use dnd_proto::grid::v1 as pb;
+use dnd_proto::grid::v1::grid_service_server::GridService;
+use dnd_proto::{require, ProtoError};
+use shared_rust::GridPosition;
+use tonic::{Request, Response, Status};
+
+#[derive(Debug, Default)]
+pub struct GridServiceImpl;
+
+impl GridServiceImpl {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[tonic::async_trait]
+impl GridService for GridServiceImpl {
+    async fn check_line_of_sight(
+        &self,
+        request: Request<pb::LineOfSightRequest>,
+    ) -> Result<Response<pb::LineOfSightResponse>, Status> {
+        let req = request.into_inner();
+        let grid = require_map(req.map.as_ref())?;
+        let from = require_position(req.from.as_ref(), "from")?;
+        let to = require_position(req.to.as_ref(), "to")?;
+
+        let result = los::line_of_sight(&grid, from, to);
+
+        Ok(Response::new(pb::LineOfSightResponse {
+            has_line_of_sight: result.has_line_of_sight,
+            blocked_by: result
+                .blocked_by
+                .into_iter()
+                .map(pb::GridPosition::from)
+                .collect(),
+            obscured_percentage: result.obscured,
+        }))
+    }
+
+    async fn get_visible_tiles(
+        &self,
+        request: Request<pb::VisibleTilesRequest>,
+    ) -> Result<Response<pb::VisibleTilesResponse>, Status> {
+        let req = request.into_inner();
+        let grid = require_map(req.map.as_ref())?;
+        let from = require_position(req.from.as_ref(), "from")?;
+        let darkvision_radius = req.darkvision.then_some(req.darkvision_radius);
+
+        let tiles = los::visible_tiles(&grid, from, req.vision_radius, darkvision_radius);
+
+        Ok(Response::new(pb::VisibleTilesResponse {
+            tiles: tiles
+                .into_iter()
+                .map(|t| pb::VisibleTile {
+                    position: Some(t.position.into()),
+                    fully_visible: t.fully_visible,
+                    dimly_lit: t.dimly_lit,
+                    in_darkness: t.in_darkness,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn calculate_cover(
+        &self,
+        request: Request<pb::CoverRequest>,
+    ) -> Result<Response<pb::CoverResponse>, Status> {
+        let req = request.into_inner();
+        let grid = require_map(req.map.as_ref())?;
+        let attacker = require_position(req.attacker.as_ref(), "attacker")?;
+        let target = require_position(req.target.as_ref(), "target")?;
+
+        let cover = calculate_cover(&grid, attacker, target);
+
+        Ok(Response::new(pb::CoverResponse {
+            cover: cover_level_to_proto(cover.level) as i32,
+            ac_bonus: cover.ac_bonus(),
+            dex_save_bonus: cover.dex_save_bonus(),
+            providing_cover: cover
+                .providing_cover
+                .into_iter()
+                .map(pb::GridPosition::from)
+                .collect(),
+        }))
+    }
+
+    async fn get_aoe_tiles(
+        &self,
+        request: Request<pb::AoeRequest>,
+    ) -> Result<Response<pb::AoeResponse>, Status> {
+        let req = request.into_inner();
+        let grid = require_map(req.map.as_ref())?;
+        let origin = require_position(req.origin.as_ref(), "origin")?;
+        let template = require_template(req.aoe.as_ref())?;
+        let direction = convert_direction(req.direction)?;
+
+        let tiles = aoe::aoe_tiles(&grid, origin, &template, direction, req.ignore_walls);
+
+        Ok(Response::new(pb::AoeResponse {
+            total_tiles: tiles.iter().filter(|t| !t.blocked_by_wall).count() as i32,
+            tiles: tiles
+                .into_iter()
+                .map(|t| pb::AoeTile {
+                    position: Some(t.position.into()),
+                    blocked_by_wall: t.blocked_by_wall,
+                    distance_from_origin: t.distance_feet,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn get_aoe_targets(
+        &self,
+        request: Request<pb::AoeTargetsRequest>,
+    ) -> Result<Response<pb::AoeTargetsResponse>, Status> {
+        let req = request.into_inner();
+        if req.allies_only || req.enemies_only {
+            // GridMap carries no faction data, so the caller has to filter.
+            return Err(Status::invalid_argument(
+                "allies_only/enemies_only are not supported: the grid map has no faction data",
+            ));
+        }
+        let grid = require_map(req.map.as_ref())?;
+        let origin = require_position(req.origin.as_ref(), "origin")?;
+        let template = require_template(req.aoe.as_ref())?;
+        let direction = convert_direction(req.direction)?;
+
+        let targets = aoe::aoe_targets(
+            &grid,
+            origin,
+            &template,
+            direction,
+            req.ignore_walls,
+            req.exclude_creature_id.as_deref(),
+        );
+
+        Ok(Response::new(pb::AoeTargetsResponse {
+            targets: targets
+                .into_iter()
+                .map(|t| pb::AoeTarget {
+                    creature_id: t.creature_id,
+                    position: Some(t.position.into()),
+                    distance_feet: t.distance_feet,
+                    has_cover: t.cover != CoverLevel::None,
+                    cover_level: cover_level_to_proto(t.cover) as i32,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn find_path(
+        &self,
+        request: Request<pb::PathfindingRequest>,
+    ) -> Result<Response<pb::PathfindingResponse>, Status> {
+        let req = request.into_inner();
+        let grid = require_map(req.map.as_ref())?;
+        let from = require_position(req.from.as_ref(), "from")?;
+        let to = require_position(req.to.as_ref(), "to")?;
+
+        let profile = MovementProfile {
+            can_fly: req.can_fly,
+            can_swim: req.can_swim,
+            can_climb: req.can_climb,
+            ignore_difficult_terrain: req.ignore_difficult_terrain,
+            creature_size: req.creature_size,
+            avoid: req.avoid_positions.iter().map(GridPosition::from).collect(),
+        };
+        let max_feet = (req.max_movement > 0).then_some(req.max_movement);
+
+        let response = match pathfinding::find_path(&grid, from, to, &profile, max_feet) {
+            Some(path) => pb::PathfindingResponse {
+                path_found: true,
+                total_cost: path.total_cost(),
+                total_feet: path.total_feet,
+                path: path
+                    .nodes
+                    .into_iter()
+                    .map(|node| pb::PathNode {
+                        position: Some(node.position.into()),
+                        cumulative_cost: node.cumulative_feet / FEET_PER_TILE,
+                        is_difficult_terrain: node.is_difficult_terrain,
+                    })
+                    .collect(),
+            },
+            None => pb::PathfindingResponse {
+                path_found: false,
+                path: vec![],
+                total_cost: 0,
+                total_feet: 0,
+            },
+        };
+
+        Ok(Response::new(response))
+    }
+
+    async fn get_reachable_tiles(
+        &self,
+        request: Request<pb::ReachableTilesRequest>,
+    ) -> Result<Response<pb::ReachableTilesResponse>, Status> {
+        let req = request.into_inner();
+        let grid = require_map(req.map.as_ref())?;
+        let from = require_position(req.from.as_ref(), "from")?;
+
+        let profile = MovementProfile {
+            can_fly: req.can_fly,
+            ignore_difficult_terrain: req.ignore_difficult_terrain,
+            creature_size: req.creature_size,
+            ..Default::default()
+        };
+        let tiles = pathfinding::reachable_tiles(
+            &grid,
+            from,
+            req.movement_remaining,
+            &profile,
+            req.include_dash,
+        );
+
+        Ok(Response::new(pb::ReachableTilesResponse {
+            tiles: tiles
+                .into_iter()
+                .map(|t| pb::ReachableTile {
+                    position: Some(t.position.into()),
+                    movement_cost: t.cost_feet,
+                    requires_dash: t.requires_dash,
+                    path_from_start: t
+                        .path_from_start
+                        .into_iter()
+                        .map(pb::GridPosition::from)
+                        .collect(),
+                })
+                .collect(),
+        }))
+    }
+
+    async fn get_tile_info(
+        &self,
+        request: Request<pb::TileInfoRequest>,
+    ) -> Result<Response<pb::TileInfoResponse>, Status> {
+        let req = request.into_inner();
+        let grid = require_map(req.map.as_ref())?;
+        let position = require_position(req.position.as_ref(), "position")?;
+        if !grid.in_bounds(position) {
+            return Err(Status::out_of_range(format!(
+                "Position ({}, {}) is outside the {}x{} map",
+                position.x, position.y, grid.width, grid.height
+            )));
+        }
+
+        Ok(Response::new(pb::TileInfoResponse {
+            tile: Some(tile_to_proto(&grid.tile(position))),
+            occupant: grid.occupant(position).map(creature_to_proto),
+            // Spell effects on tiles are tracked by the game session, not the map.
+            effects: vec![],
+        }))
+    }
+
+    async fn get_creatures_in_radius(
+        &self,
+        request: Request<pb::RadiusQueryRequest>,
+    ) -> Result<Response<pb::RadiusQueryResponse>, Status> {
+        let req = request.into_inner();
+        let grid = require_map(req.map.as_ref())?;
+        let center = require_position(req.center.as_ref(), "center")?;
+
+        let mut creatures = Vec::new();
+        for creature in grid.creatures() {
+            let footprint = creature.footprint();
+            let distance_feet = footprint_distance_feet(&[center], &footprint);
+            if distance_feet > req.radius_feet {
+                continue;
+            }
+
+            let visible_tile = footprint
+                .iter()
+                .copied()
+                .find(|p| los::has_line_of_sight(&grid, center, *p));
+            if req.require_line_of_sight && visible_tile.is_none() {
+                continue;
+            }
+
+            let cover = match visible_tile {
+                Some(tile) => calculate_cover(&grid, center, tile).level,
+                None => CoverLevel::Total,
+            };
+            creatures.push(pb::CreatureInRadius {
+                creature_id: creature.creature_id.clone(),
+                position: Some(creature.position.into()),
+                distance_feet,
+                has_line_of_sight: visible_tile.is_some(),
+                cover: cover_level_to_proto(cover) as i32,
+            });
+        }
+        creatures.sort_by_key(|c| c.distance_feet);
+
+        Ok(Response::new(pb::RadiusQueryResponse { creatures }))
+    }
+
+    async fn calculate_distance(
+        &self,
+        request: Request<pb::DistanceRequest>,
+    ) -> Result<Response<pb::DistanceResponse>, Status> {
+        let req = request.into_inner();
+        let from = require_position(req.from.as_ref(), "from")?;
+        let to = require_position(req.to.as_ref(), "to")?;
+
+        // use_diagonal selects the DMG 5-10-5 variant over the PHB 5-5-5 rule.
+        let rule = if req.use_diagonal {
+            DiagonalRule::Alternating
+        } else {
+            DiagonalRule::Uniform
+        };
+        let tiles = distance_tiles(from, to, rule);
+
+        Ok(Response::new(pb::DistanceResponse {
+            distance_tiles: tiles,
+            distance_feet: tiles * FEET_PER_TILE,
+        }))
+    }
+}
+
+fn require_map(map: Option<&pb::GridMap>) -> Result<Grid, Status> {
+    convert_map(require(map, "map")?)
+}
+
+fn require_position(
+    position: Option<&pb::GridPosition>,
+    field: &'static str,
+) -> Result<GridPosition, ProtoError> {
+    require(position, field).map(GridPosition::from)
+}
+
+fn require_template(aoe: Option<&pb::AoeDefinition>) -> Result<AoeTemplate, Status> {
+    let aoe = require(aoe, "aoe")?;
+    if aoe.size_feet <= 0 {
+        return Err(Status::invalid_argument("AoE size must be positive"));
+    }
+    Ok(AoeTemplate {
+        shape: convert_shape(aoe.shape)?,
+        size_feet: aoe.size_feet,
+        width_feet: aoe.width_feet,
+    })
+}
+
+// Helper functions to convert proto types to internal types
+
+pub fn convert_map(map: &pb::GridMap) -> Result<Grid, Status> {
+    if map.width <= 0 || map.height <= 0 {
+        return Err(Status::invalid_argument(format!(
+            "Invalid map dimensions: {}x{}",
+            map.width, map.height
+        )));
+    }
+
+    let mut grid = Grid::new(map.width, map.height);
+    for tile in &map.tiles {
+        grid.set_tile(convert_tile(tile)?);
+    }
+    for creature in &map.creatures {
+        grid.add_creature(Creature {
+            creature_id: creature.creature_id.clone(),
+            position: require_position(creature.position.as_ref(), "creature.position")?,
+            size: creature.size.max(1),
+            is_prone: creature.is_prone,
+        });
+    }
+    Ok(grid)
+}
+
+fn convert_tile(tile: &pb::TileData) -> Result<Tile, Status> {
+    let tile_type = match pb::TileType::try_from(tile.tile_type) {
+        Ok(pb::TileType::Unspecified) | Ok(pb::TileType::Floor) => TileType::Floor,
+        Ok(pb::TileType::Wall) => TileType::Wall,
+        Ok(pb::TileType::DoorClosed) => TileType::DoorClosed,
+        Ok(pb::TileType::DoorOpen) => TileType::DoorOpen,
+        Ok(pb::TileType::Window) => TileType::Window,
+        Ok(pb::TileType::DifficultTerrain) => TileType::DifficultTerrain,
+        Ok(pb::TileType::WaterShallow) => TileType::WaterShallow,
+        Ok(pb::TileType::WaterDeep) => TileType::WaterDeep,
+        Ok(pb::TileType::Pit) => TileType::Pit,
+        Ok(pb::TileType::Stairs) => TileType::Stairs,
+        Err(_) => {
+            return Err(Status::invalid_argument(format!(
+                "Invalid tile type: {}",
+                tile.tile_type
+            )))
+        }
+    };
+    let blocking = match pb::BlockingType::try_from(tile.blocking) {
+        Ok(pb::BlockingType::None) => Blocking::None,
+        Ok(pb::BlockingType::Partial) => Blocking::Partial,
+        Ok(pb::BlockingType::Full) => Blocking::Full,
+        Err(_) => {
+            return Err(Status::invalid_argument(format!(
+                "Invalid blocking type: {}",
+                tile.blocking
+            )))
+        }
+    };
+
+    Ok(Tile {
+        position: require_position(tile.position.as_ref(), "tile.position")?,
+        tile_type,
+        blocking,
+        // Unset cost means normal movement.
+        movement_cost: tile.movement_cost.max(1),
+        elevation: tile.elevation,
+        provides_half_cover: tile.provides_half_cover,
+        provides_three_quarter_cover: tile.provides_three_quarter_cover,
+        creature_id: tile.creature_id.clone(),
+    })
+}
+
+fn tile_to_proto(tile: &Tile) -> pb::TileData {
+    let tile_type = match tile.tile_type {
+        TileType::Floor => pb::TileType::Floor,
+        TileType::Wall => pb::TileType::Wall,
+        TileType::DoorClosed => pb::TileType::DoorClosed,
+        TileType::DoorOpen => pb::TileType::DoorOpen,
+        TileType::Window => pb::TileType::Window,
+        TileType::DifficultTerrain => pb::TileType::DifficultTerrain,
+        TileType::WaterShallow => pb::TileType::WaterShallow,
+        TileType::WaterDeep => pb::TileType::WaterDeep,
+        TileType::Pit => pb::TileType::Pit,
+        TileType::Stairs => pb::TileType::Stairs,
+    };
+    let blocking = match tile.blocking {
+        Blocking::None => pb::BlockingType::None,
+        Blocking::Partial => pb::BlockingType::Partial,
+        Blocking::Full => pb::BlockingType::Full,
+    };
+
+    pb::TileData {
+        position: Some(tile.position.into()),
+        tile_type: tile_type as i32,
+        blocking: blocking as i32,
+        movement_cost: tile.movement_cost,
+        elevation: tile.elevation,
+        provides_half_cover: tile.provides_half_cover,
+        provides_three_quarter_cover: tile.provides_three_quarter_cover,
+        creature_id: tile.creature_id.clone(),
+    }
+}
+
+fn creature_to_proto(creature: &Creature) -> pb::GridCreature {
+    pb::GridCreature {
+        creature_id: creature.creature_id.clone(),
+        position: Some(creature.position.into()),
+        size: creature.size,
+        is_prone: creature.is_prone,
+    }
+}
+
+fn cover_level_to_proto(level: CoverLevel) -> pb::CoverLevel {
+    match level {
+        CoverLevel::None => pb::CoverLevel::None,
+        CoverLevel::Half => pb::CoverLevel::Half,
+        CoverLevel::ThreeQuarters => pb::CoverLevel::ThreeQuarters,
+        CoverLevel::Total => pb::CoverLevel::Total,
+    }
+}
+
+fn convert_shape(shape: i32) -> Result<AoeShape, Status> {
+    match pb::AoeShapeType::try_from(shape) {
+        Ok(pb::AoeShapeType::Sphere) => Ok(AoeShape::Sphere),
+        Ok(pb::AoeShapeType::Cube) => Ok(AoeShape::Cube),
+        Ok(pb::AoeShapeType::Cone) => Ok(AoeShape::Cone),
+        Ok(pb::AoeShapeType::Line) => Ok(AoeShape::Line),
+        Ok(pb::AoeShapeType::Cylinder) => Ok(AoeShape::Cylinder),
+        _ => Err(Status::invalid_argument(format!(
+            "Invalid AoE shape: {}",
+            shape
+        ))),
+    }
+}
+
+pub fn convert_direction(direction: i32) -> Result<Option<Direction>, Status> {
+    match pb::Direction::try_from(direction) {
+        Ok(pb::Direction::Unspecified) => Ok(None),
+        Ok(pb::Direction::North) => Ok(Some(Direction::North)),
+        Ok(pb::Direction::Northeast) => Ok(Some(Direction::NorthEast)),
+        Ok(pb::Direction::East) => Ok(Some(Direction::East)),
+        Ok(pb::Direction::Southeast) => Ok(Some(Direction::SouthEast)),
+        Ok(pb::Direction::South) => Ok(Some(Direction::South)),
+        Ok(pb::Direction::Southwest) => Ok(Some(Direction::SouthWest)),
+        Ok(pb::Direction::West) => Ok(Some(Direction::West)),
+        Ok(pb::Direction::Northwest) => Ok(Some(Direction::NorthWest)),
+        Err(_) => Err(Status::invalid_argument(format!(
+            "Invalid direction: {}",
+            direction
+        ))),
+    }
+}
@@ -0,0 +1,381 @@
+//! What a creature can still do this turn (PHB p.189-193).
+//!
+//! Each turn brings one action, one bonus action, movement up to its speed
+//! and one free object interaction; the reaction comes back at the start of
+//! the creature's turn. Features add to the budget: Extra Attack makes more
+//! attacks per Attack action (movement can fall between them), Action Surge
+//! grants another action once per turn, and Haste adds a limited action and
+//! doubles speed. Limited-use features spend both a slot from this budget
+//! and a use from the [`ResourceTracker`].
+
+use crate::conditions::ConditionEffects;
+use crate::resources::ResourceTracker;
+use serde::{Deserialize, Serialize};
+use shared_rust::{DndError, EntityId};
+
+/// Actions from PHB chapter 9.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ActionChoice {
+    Attack,
+    CastSpell,
+    Dash,
+    Disengage,
+    Dodge,
+    Help,
+    Hide,
+    Ready,
+    Search,
+    UseObject,
+    /// A class feature that takes an action, like Channel Divinity or Wild
+    /// Shape.
+    UseFeature,
+    EscapeGrapple,
+}
+
+impl ActionChoice {
+    /// Actions the extra action from Haste may be used for.
+    pub fn allowed_by_haste(&self) -> bool {
+        matches!(
+            self,
+            ActionChoice::Attack
+                | ActionChoice::Dash
+                | ActionChoice::Disengage
+                | ActionChoice::Hide
+                | ActionChoice::UseObject
+        )
+    }
+}
+
+/// Features that change the per-turn budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EconomyFeatures {
+    /// Attacks per Attack action: 2 with Extra Attack, up to 4 for a
+    /// 20th-level fighter.
+    pub attacks_per_action: u8,
+    /// Action Surge has a use left.
+    pub action_surge: bool,
+    pub hasted: bool,
+}
+
+/// What a limited-use feature takes from the turn: Rage is a bonus action,
+/// Channel Divinity an action, Stunning Strike rides on a hit for free.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeatureCost {
+    Action,
+    BonusAction,
+    Reaction,
+    Free,
+}
+
+impl Default for EconomyFeatures {
+    fn default() -> Self {
+        Self {
+            attacks_per_action: 1,
+            action_surge: false,
+            hasted: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ActionEconomy {
+    pub creature_id: String,
+    pub features: EconomyFeatures,
+    pub actions: u8,
+    /// Haste's extra action, limited to [`ActionChoice::allowed_by_haste`].
+    pub hasted_action: bool,
+    pub bonus_action: bool,
+    pub reaction: bool,
+    pub object_interaction: bool,
+    pub speed: i32,
+    pub movement_remaining: i32,
+    /// Attacks left from an Attack action already taken.
+    pub attacks_remaining: u8,
+    pub action_surge_used: bool,
+    incapacitated: bool,
+}
+
+impl ActionEconomy {
+    /// The budget at the start of the creature's turn.
+    pub fn start_turn(
+        creature_id: impl Into<String>,
+        speed: i32,
+        effects: &ConditionEffects,
+        features: EconomyFeatures,
+    ) -> Self {
+        let mut speed = speed.max(0);
+        if features.hasted {
+            speed *= 2;
+        }
+        if effects.speed_is_zero {
+            speed = 0;
+        } else if effects.speed_halved {
+            speed /= 2;
+        }
+        let incapacitated = effects.cant_take_actions;
+        Self {
+            creature_id: creature_id.into(),
+            features,
+            actions: if incapacitated { 0 } else { 1 },
+            hasted_action: features.hasted && !incapacitated,
+            bonus_action: !incapacitated && !effects.cant_take_bonus_actions,
+            reaction: !incapacitated && !effects.cant_take_reactions,
+            object_interaction: !incapacitated,
+            speed,
+            movement_remaining: speed,
+            attacks_remaining: 0,
+            action_surge_used: false,
+            incapacitated,
+        }
+    }
+
+    fn violation(&self, reason: impl std::fmt::Display) -> DndError {
+        DndError::RulesViolation(format!("{} {}", self.creature_id, reason))
+    }
+
+    fn check_capable(&self) -> Result<(), DndError> {
+        if self.incapacitated {
+            Err(self.violation("is incapacitated and can't take actions"))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Spend an action on `choice`, using Haste's action only once the
+    /// regular ones are gone.
+    pub fn take_action(&mut self, choice: ActionChoice) -> Result<(), DndError> {
+        self.check_capable()?;
+        let attacks = if self.actions > 0 {
+            self.actions -= 1;
+            self.features.attacks_per_action.max(1)
+        } else if self.hasted_action && choice.allowed_by_haste() {
+            self.hasted_action = false;
+            // One weapon attack only.
+            1
+        } else if self.hasted_action {
+            return Err(self.violation(format_args!("can't use the hasted action to {:?}", choice)));
+        } else {
+            return Err(self.violation("has no action left this turn"));
+        };
+        match choice {
+            ActionChoice::Attack => self.attacks_remaining = attacks,
+            ActionChoice::Dash => self.movement_remaining += self.speed,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Make one attack, taking the Attack action if none is under way.
+    pub fn attack(&mut self) -> Result<(), DndError> {
+        if self.attacks_remaining == 0 {
+            self.take_action(ActionChoice::Attack)?;
+        }
+        self.attacks_remaining -= 1;
+        Ok(())
+    }
+
+    /// Once per turn, one additional action.
+    pub fn use_action_surge(&mut self) -> Result<(), DndError> {
+        self.check_capable()?;
+        if !self.features.action_surge {
+            return Err(self.violation("has no Action Surge available"));
+        }
+        if self.action_surge_used {
+            return Err(self.violation("can only use Action Surge once per turn"));
+        }
+        self.action_surge_used = true;
+        self.features.action_surge = false;
+        self.actions += 1;
+        Ok(())
+    }
+
+    pub fn take_bonus_action(&mut self) -> Result<(), DndError> {
+        self.check_capable()?;
+        if !self.bonus_action {
+            return Err(self.violation("has no bonus action left this turn"));
+        }
+        self.bonus_action = false;
+        Ok(())
+    }
+
+    /// Reactions can be taken on other creatures' turns too.
+    pub fn take_reaction(&mut self) -> Result<(), DndError> {
+        self.check_capable()?;
+        if !self.reaction {
+            return Err(self.violation("has already used its reaction"));
+        }
+        self.reaction = false;
+        Ok(())
+    }
+
+    /// The first object interaction is free; another needs the Use an Object
+    /// action.
+    pub fn interact_with_object(&mut self) -> Result<(), DndError> {
+        self.check_capable()?;
+        if self.object_interaction {
+            self.object_interaction = false;
+            Ok(())
+        } else {
+            self.take_action(ActionChoice::UseObject)
+        }
+    }
+
+    /// Use `amount` uses of `owner`'s `resource_id` for `cost`. Both are
+    /// checked first so a failure spends neither; returns the uses left.
+    pub fn use_feature(
+        &mut self,
+        resources: &mut ResourceTracker,
+        owner: EntityId,
+        resource_id: &str,
+        amount: i32,
+        cost: FeatureCost,
+    ) -> Result<i32, DndError> {
+        resources.check(owner, resource_id, amount)?;
+        match cost {
+            FeatureCost::Action => self.take_action(ActionChoice::UseFeature)?,
+            FeatureCost::BonusAction => self.take_bonus_action()?,
+            FeatureCost::Reaction => self.take_reaction()?,
+            FeatureCost::Free => self.check_capable()?,
+        }
+        resources.spend(owner, resource_id, amount)
+    }
+
+    /// Spend `feet` of movement; difficult terrain and the like should
+    /// already be counted in.
+    pub fn move_feet(&mut self, feet: i32) -> Result<(), DndError> {
+        if feet < 0 {
+            return Err(DndError::InvalidAction(format!(
+                "cannot move a negative distance ({} ft)",
+                feet
+            )));
+        }
+        if feet > self.movement_remaining {
+            return Err(self.violation(format_args!(
+                "has {} ft of movement left, not {}",
+                self.movement_remaining, feet
+            )));
+        }
+        self.movement_remaining -= feet;
+        Ok(())
+    }
+
+    /// Standing up from prone costs half the creature's speed.
+    pub fn stand_up(&mut self) -> Result<(), DndError> {
+        if self.speed == 0 {
+            return Err(self.violation("can't stand up with a speed of 0"));
+        }
+        self.move_feet(self.speed / 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn economy(features: EconomyFeatures) -> ActionEconomy {
+        ActionEconomy::start_turn("fighter", 30, &ConditionEffects::default(), features)
+    }
+
+    #[test]
+    fn test_extra_attack_split_around_movement() {
+        let mut turn = economy(EconomyFeatures {
+            attacks_per_action: 2,
+            ..Default::default()
+        });
+        turn.attack().unwrap();
+        turn.move_feet(20).unwrap();
+        turn.attack().unwrap();
+        assert_eq!(turn.actions, 0);
+        assert!(matches!(turn.attack(), Err(DndError::RulesViolation(_))));
+        assert!(turn.move_feet(15).is_err());
+        turn.move_feet(10).unwrap();
+    }
+
+    #[test]
+    fn test_action_surge_once_per_turn() {
+        let mut turn = economy(EconomyFeatures {
+            attacks_per_action: 2,
+            action_surge: true,
+            ..Default::default()
+        });
+        turn.take_action(ActionChoice::Dash).unwrap();
+        turn.use_action_surge().unwrap();
+        assert!(turn.use_action_surge().is_err());
+        for _ in 0..2 {
+            turn.attack().unwrap();
+        }
+        assert!(turn.attack().is_err());
+        assert_eq!(turn.movement_remaining, 60);
+    }
+
+    #[test]
+    fn test_haste_action_is_limited() {
+        let mut turn = economy(EconomyFeatures {
+            attacks_per_action: 2,
+            hasted: true,
+            ..Default::default()
+        });
+        assert_eq!(turn.speed, 60);
+        turn.take_action(ActionChoice::CastSpell).unwrap();
+        let err = turn.take_action(ActionChoice::CastSpell).unwrap_err();
+        assert!(err.to_string().contains("hasted action"));
+        // Only one weapon attack from the hasted action.
+        turn.attack().unwrap();
+        assert!(turn.attack().is_err());
+    }
+
+    #[test]
+    fn test_conditions_limit_the_budget() {
+        let stunned = ConditionEffects {
+            cant_take_actions: true,
+            cant_take_reactions: true,
+            speed_is_zero: true,
+            ..Default::default()
+        };
+        let mut turn = ActionEconomy::start_turn("orc", 30, &stunned, EconomyFeatures::default());
+        assert!(turn.take_action(ActionChoice::Dodge).is_err());
+        assert!(turn.take_reaction().is_err());
+        assert!(turn.move_feet(5).is_err());
+
+        let mut turn = economy(EconomyFeatures::default());
+        turn.interact_with_object().unwrap();
+        turn.interact_with_object().unwrap();
+        assert_eq!(turn.actions, 0);
+        turn.take_reaction().unwrap();
+        assert!(turn.take_reaction().is_err());
+        turn.stand_up().unwrap();
+        assert_eq!(turn.movement_remaining, 15);
+    }
+
+    #[test]
+    fn test_feature_spends_budget_and_uses() {
+        use crate::resources::{Recharge, Resource};
+
+        let barbarian = EntityId::new();
+        let mut resources = ResourceTracker::new();
+        resources.add(
+            barbarian,
+            Resource::new("rage", "Rage", 1, Recharge::LongRest),
+        );
+        let mut turn = economy(EconomyFeatures::default());
+
+        let left = turn
+            .use_feature(
+                &mut resources,
+                barbarian,
+                "rage",
+                1,
+                FeatureCost::BonusAction,
+            )
+            .unwrap();
+        assert_eq!(left, 0);
+        assert!(!turn.bonus_action);
+
+        // Out of uses: the action isn't spent either.
+        let err = turn
+            .use_feature(&mut resources, barbarian, "rage", 1, FeatureCost::Action)
+            .unwrap_err();
+        assert!(matches!(err, DndError::RulesViolation(_)));
+        assert_eq!(turn.actions, 1);
+    }
+}
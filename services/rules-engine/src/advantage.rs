@@ -0,0 +1,236 @@
+//! Advantage and disadvantage with source tracking (PHB p.173).
+//!
+//! Any number of advantage sources and any number of disadvantage sources
+//! cancel to a straight roll. Halfling Lucky and Elven Accuracy then change
+//! which dice are rolled, not the mode.
+
+use crate::combat::Ability;
+use crate::dice::DiceRoller;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RollMode {
+    Normal,
+    Advantage,
+    Disadvantage,
+}
+
+/// What the d20 is being rolled for; some traits only apply to some tests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum D20Test {
+    Attack(Ability),
+    AbilityCheck,
+    SavingThrow,
+    /// Any other d20 roll, e.g. initiative tie-breaks.
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum D20Trait {
+    /// Reroll a natural 1 on an attack, check or save and use the new roll.
+    HalflingLucky,
+    /// With advantage on a DEX/INT/WIS/CHA attack, roll a third die.
+    ElvenAccuracy,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct D20Sources {
+    pub advantage: Vec<String>,
+    pub disadvantage: Vec<String>,
+    pub traits: Vec<D20Trait>,
+}
+
+impl D20Sources {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_advantage(mut self, source: impl Into<String>) -> Self {
+        self.advantage.push(source.into());
+        self
+    }
+
+    pub fn with_disadvantage(mut self, source: impl Into<String>) -> Self {
+        self.disadvantage.push(source.into());
+        self
+    }
+
+    pub fn with_trait(mut self, d20_trait: D20Trait) -> Self {
+        self.traits.push(d20_trait);
+        self
+    }
+
+    pub fn has_trait(&self, d20_trait: D20Trait) -> bool {
+        self.traits.contains(&d20_trait)
+    }
+
+    pub fn mode(&self) -> RollMode {
+        match (self.advantage.is_empty(), self.disadvantage.is_empty()) {
+            (false, true) => RollMode::Advantage,
+            (true, false) => RollMode::Disadvantage,
+            _ => RollMode::Normal,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct D20Outcome {
+    pub mode: RollMode,
+    /// Every d20 rolled, in order, including any that were rerolled.
+    pub rolls: Vec<i32>,
+    /// The natural roll that counts.
+    pub kept: i32,
+    pub advantage_sources: Vec<String>,
+    pub disadvantage_sources: Vec<String>,
+    pub traits_applied: Vec<D20Trait>,
+    pub explanation: String,
+}
+
+fn describe(label: &str, sources: &[String]) -> String {
+    if sources.is_empty() {
+        label.to_string()
+    } else {
+        format!("{} ({})", label, sources.join(", "))
+    }
+}
+
+fn explain(sources: &D20Sources, mode: RollMode, traits_applied: &[D20Trait]) -> String {
+    let mut explanation = match mode {
+        RollMode::Advantage => describe("Advantage", &sources.advantage),
+        RollMode::Disadvantage => describe("Disadvantage", &sources.disadvantage),
+        RollMode::Normal if sources.advantage.is_empty() => "Straight roll".to_string(),
+        RollMode::Normal => format!(
+            "{} cancelled by {}",
+            describe("Advantage", &sources.advantage),
+            describe("Disadvantage", &sources.disadvantage)
+        ),
+    };
+    for applied in traits_applied {
+        explanation.push_str(match applied {
+            D20Trait::ElvenAccuracy => "; Elven Accuracy rolls a third die",
+            D20Trait::HalflingLucky => "; Halfling Lucky rerolls a 1",
+        });
+    }
+    explanation
+}
+
+/// Roll a d20 for `test` with every advantage/disadvantage source and trait.
+pub fn roll_d20(roller: &mut DiceRoller, test: D20Test, sources: &D20Sources) -> D20Outcome {
+    let mode = sources.mode();
+    let mut traits_applied = Vec::new();
+
+    let elven_accuracy = mode == RollMode::Advantage
+        && sources.has_trait(D20Trait::ElvenAccuracy)
+        && matches!(
+            test,
+            D20Test::Attack(Ability::DEX | Ability::INT | Ability::WIS | Ability::CHA)
+        );
+    let dice_count = match (mode, elven_accuracy) {
+        (RollMode::Normal, _) => 1,
+        (_, false) => 2,
+        (_, true) => 3,
+    };
+    if elven_accuracy {
+        traits_applied.push(D20Trait::ElvenAccuracy);
+    }
+
+    let mut rolls: Vec<i32> = (0..dice_count).map(|_| roller.roll_d20()).collect();
+    let mut live = rolls.clone();
+
+    // Lucky rerolls a single 1 even when more than one die shows it.
+    let lucky_applies = sources.has_trait(D20Trait::HalflingLucky) && test != D20Test::Other;
+    if lucky_applies {
+        if let Some(index) = live.iter().position(|&r| r == 1) {
+            let reroll = roller.roll_d20();
+            rolls.push(reroll);
+            live[index] = reroll;
+            traits_applied.push(D20Trait::HalflingLucky);
+        }
+    }
+
+    let kept = match mode {
+        RollMode::Advantage => live.iter().copied().max(),
+        RollMode::Disadvantage => live.iter().copied().min(),
+        RollMode::Normal => live.first().copied(),
+    }
+    .unwrap_or(1);
+
+    D20Outcome {
+        mode,
+        explanation: explain(sources, mode, &traits_applied),
+        rolls,
+        kept,
+        advantage_sources: sources.advantage.clone(),
+        disadvantage_sources: sources.disadvantage.clone(),
+        traits_applied,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sources_cancel_regardless_of_count() {
+        let sources = D20Sources::new()
+            .with_advantage("Reckless Attack")
+            .with_advantage("Faerie Fire")
+            .with_disadvantage("Poisoned");
+        assert_eq!(sources.mode(), RollMode::Normal);
+
+        let mut roller = DiceRoller::with_seed(1);
+        let outcome = roll_d20(&mut roller, D20Test::Attack(Ability::STR), &sources);
+        assert_eq!(outcome.rolls.len(), 1);
+        assert_eq!(
+            outcome.explanation,
+            "Advantage (Reckless Attack, Faerie Fire) cancelled by Disadvantage (Poisoned)"
+        );
+    }
+
+    #[test]
+    fn test_advantage_keeps_higher() {
+        let sources = D20Sources::new().with_advantage("Help");
+        let mut roller = DiceRoller::with_seed(8);
+        for _ in 0..20 {
+            let outcome = roll_d20(&mut roller, D20Test::AbilityCheck, &sources);
+            assert_eq!(outcome.kept, *outcome.rolls.iter().max().unwrap());
+            assert_eq!(outcome.explanation, "Advantage (Help)");
+        }
+    }
+
+    #[test]
+    fn test_elven_accuracy_needs_advantage_and_finesse_ability() {
+        let sources = D20Sources::new()
+            .with_advantage("Hidden")
+            .with_trait(D20Trait::ElvenAccuracy);
+        let mut roller = DiceRoller::with_seed(3);
+
+        let dex = roll_d20(&mut roller, D20Test::Attack(Ability::DEX), &sources);
+        assert_eq!(dex.rolls.len(), 3);
+        assert_eq!(dex.kept, *dex.rolls.iter().max().unwrap());
+
+        let str_attack = roll_d20(&mut roller, D20Test::Attack(Ability::STR), &sources);
+        assert_eq!(str_attack.rolls.len(), 2);
+
+        let save = roll_d20(&mut roller, D20Test::SavingThrow, &sources);
+        assert!(save.traits_applied.is_empty());
+    }
+
+    #[test]
+    fn test_halfling_lucky_rerolls_one_natural_one() {
+        let sources = D20Sources::new().with_trait(D20Trait::HalflingLucky);
+        let mut roller = DiceRoller::with_seed(0);
+        let mut saw_reroll = false;
+        for _ in 0..200 {
+            let outcome = roll_d20(&mut roller, D20Test::SavingThrow, &sources);
+            if outcome.rolls[0] == 1 {
+                saw_reroll = true;
+                assert_eq!(outcome.rolls.len(), 2);
+                assert_eq!(outcome.kept, outcome.rolls[1]);
+            } else {
+                assert_eq!(outcome.rolls.len(), 1);
+            }
+        }
+        assert!(saw_reroll);
+    }
+}
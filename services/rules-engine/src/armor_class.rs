@@ -0,0 +1,379 @@
+//! Armor Class from what a creature wears and the features it has (PHB
+//! p.14, p.144-145).
+//!
+//! A creature has exactly one base formula: worn armor, Unarmored Defense,
+//! Mage Armor, natural armor or plain 10 + DEX. When several apply the best
+//! one wins; a shield and flat bonuses such as a Ring of Protection are added
+//! on top. The result names every contribution so a character sheet can
+//! show the same number the engine attacks against.
+
+use crate::checks::Modifier;
+use crate::combat::{Ability, CreatureStats};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ArmorCategory {
+    Light,
+    Medium,
+    Heavy,
+}
+
+impl ArmorCategory {
+    /// Most of the DEX modifier that counts; `None` for no cap.
+    pub fn dex_cap(&self, medium_armor_master: bool) -> Option<i32> {
+        match self {
+            ArmorCategory::Light => None,
+            ArmorCategory::Medium if medium_armor_master => Some(3),
+            ArmorCategory::Medium => Some(2),
+            ArmorCategory::Heavy => Some(0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Armor {
+    pub name: String,
+    pub category: ArmorCategory,
+    pub base_ac: i32,
+    /// +1 to +3 armor.
+    pub magic_bonus: i32,
+}
+
+impl Armor {
+    pub fn new(name: impl Into<String>, category: ArmorCategory, base_ac: i32) -> Self {
+        Self {
+            name: name.into(),
+            category,
+            base_ac,
+            magic_bonus: 0,
+        }
+    }
+
+    pub fn with_magic_bonus(mut self, bonus: i32) -> Self {
+        self.magic_bonus = bonus;
+        self
+    }
+
+    /// Armor from the PHB table by name, e.g. "half plate" or "Studded
+    /// Leather".
+    pub fn srd(name: &str) -> Option<Self> {
+        let (category, base_ac) = match name.to_ascii_lowercase().replace(['-', '_'], " ").as_str()
+        {
+            "padded" => (ArmorCategory::Light, 11),
+            "leather" => (ArmorCategory::Light, 11),
+            "studded leather" => (ArmorCategory::Light, 12),
+            "hide" => (ArmorCategory::Medium, 12),
+            "chain shirt" => (ArmorCategory::Medium, 13),
+            "scale mail" => (ArmorCategory::Medium, 14),
+            "breastplate" => (ArmorCategory::Medium, 14),
+            "half plate" => (ArmorCategory::Medium, 15),
+            "ring mail" => (ArmorCategory::Heavy, 14),
+            "chain mail" => (ArmorCategory::Heavy, 16),
+            "splint" => (ArmorCategory::Heavy, 17),
+            "plate" => (ArmorCategory::Heavy, 18),
+            _ => return None,
+        };
+        Some(Self::new(name, category, base_ac))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum UnarmoredDefense {
+    /// 10 + DEX + CON; a shield still counts.
+    Barbarian,
+    /// 10 + DEX + WIS; lost with a shield.
+    Monk,
+}
+
+/// Natural armor from a race or stat block, e.g. a lizardfolk's 13 + DEX or
+/// a tortle's flat 17.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct NaturalArmor {
+    pub base: i32,
+    pub adds_dex: bool,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ArmorSetup {
+    pub armor: Option<Armor>,
+    pub shield: bool,
+    pub shield_magic_bonus: i32,
+    pub unarmored_defense: Option<UnarmoredDefense>,
+    /// Mage Armor is active (13 + DEX while not wearing armor).
+    pub mage_armor: bool,
+    pub natural_armor: Option<NaturalArmor>,
+    /// Draconic Resilience: 13 + DEX while not wearing armor.
+    pub draconic_resilience: bool,
+    /// The Medium Armor Master feat raises the medium DEX cap to 3.
+    pub medium_armor_master: bool,
+    /// Defense fighting style: +1 while wearing armor.
+    pub defense_style: bool,
+    /// Ring of Protection, Cloak of Protection and the like.
+    pub bonuses: Vec<Modifier>,
+}
+
+/// Which base formula set the AC.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BaseFormula {
+    Unarmored,
+    Armor(String),
+    UnarmoredDefense(UnarmoredDefense),
+    MageArmor,
+    NaturalArmor,
+    DraconicResilience,
+}
+
+impl BaseFormula {
+    pub fn describe(&self) -> String {
+        match self {
+            BaseFormula::Unarmored => "Unarmored".to_string(),
+            BaseFormula::Armor(name) => name.clone(),
+            BaseFormula::UnarmoredDefense(UnarmoredDefense::Barbarian) => {
+                "Unarmored Defense (Barbarian)".to_string()
+            }
+            BaseFormula::UnarmoredDefense(UnarmoredDefense::Monk) => {
+                "Unarmored Defense (Monk)".to_string()
+            }
+            BaseFormula::MageArmor => "Mage Armor".to_string(),
+            BaseFormula::NaturalArmor => "Natural Armor".to_string(),
+            BaseFormula::DraconicResilience => "Draconic Resilience".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArmorClass {
+    pub total: i32,
+    pub formula: BaseFormula,
+    /// What adds up to `total`, base formula first.
+    pub components: Vec<Modifier>,
+    /// Every formula that applied and the AC it would have given.
+    pub alternatives: Vec<(BaseFormula, i32)>,
+}
+
+/// Work out `stats`' AC wearing `setup`.
+pub fn calculate(stats: &CreatureStats, setup: &ArmorSetup) -> ArmorClass {
+    let dex = stats.get_modifier(Ability::DEX);
+    let dex_term = |cap: Option<i32>| {
+        let value = cap.map_or(dex, |cap| dex.min(cap));
+        Modifier::new("DEX", value)
+    };
+    let unarmored = setup.armor.is_none();
+
+    let mut candidates: Vec<(BaseFormula, Vec<Modifier>)> = Vec::new();
+    match &setup.armor {
+        Some(armor) => {
+            let mut parts = vec![Modifier::new(armor.name.clone(), armor.base_ac)];
+            let cap = armor.category.dex_cap(setup.medium_armor_master);
+            if cap != Some(0) {
+                parts.push(dex_term(cap));
+            }
+            if armor.magic_bonus != 0 {
+                parts.push(Modifier::new("Magic armor", armor.magic_bonus));
+            }
+            if setup.defense_style {
+                parts.push(Modifier::new("Defense", 1));
+            }
+            candidates.push((BaseFormula::Armor(armor.name.clone()), parts));
+        }
+        None => candidates.push((
+            BaseFormula::Unarmored,
+            vec![Modifier::new("Base", 10), dex_term(None)],
+        )),
+    }
+    if unarmored {
+        match setup.unarmored_defense {
+            Some(UnarmoredDefense::Barbarian) => candidates.push((
+                BaseFormula::UnarmoredDefense(UnarmoredDefense::Barbarian),
+                vec![
+                    Modifier::new("Base", 10),
+                    dex_term(None),
+                    Modifier::new("CON", stats.get_modifier(Ability::CON)),
+                ],
+            )),
+            Some(UnarmoredDefense::Monk) if !setup.shield => candidates.push((
+                BaseFormula::UnarmoredDefense(UnarmoredDefense::Monk),
+                vec![
+                    Modifier::new("Base", 10),
+                    dex_term(None),
+                    Modifier::new("WIS", stats.get_modifier(Ability::WIS)),
+                ],
+            )),
+            _ => {}
+        }
+        if setup.mage_armor {
+            candidates.push((
+                BaseFormula::MageArmor,
+                vec![Modifier::new("Mage Armor", 13), dex_term(None)],
+            ));
+        }
+        if setup.draconic_resilience {
+            candidates.push((
+                BaseFormula::DraconicResilience,
+                vec![Modifier::new("Draconic Resilience", 13), dex_term(None)],
+            ));
+        }
+    }
+    if let Some(natural) = setup.natural_armor.filter(|_| unarmored) {
+        let mut parts = vec![Modifier::new("Natural armor", natural.base)];
+        if natural.adds_dex {
+            parts.push(dex_term(None));
+        }
+        candidates.push((BaseFormula::NaturalArmor, parts));
+    }
+
+    let mut extras = Vec::new();
+    if setup.shield {
+        extras.push(Modifier::new("Shield", 2));
+        if setup.shield_magic_bonus != 0 {
+            extras.push(Modifier::new("Magic shield", setup.shield_magic_bonus));
+        }
+    }
+    extras.extend(setup.bonuses.iter().cloned());
+    let extra: i32 = extras.iter().map(|m| m.value).sum();
+
+    let alternatives: Vec<(BaseFormula, i32)> = candidates
+        .iter()
+        .map(|(formula, parts)| {
+            let base: i32 = parts.iter().map(|m| m.value).sum();
+            (formula.clone(), base + extra)
+        })
+        .collect();
+    // The first of equal formulas wins, so worn armor is kept on a tie.
+    let best = alternatives
+        .iter()
+        .enumerate()
+        .rev()
+        .max_by_key(|(_, (_, total))| *total)
+        .map_or(0, |(index, _)| index);
+    let (formula, mut components) = candidates.swap_remove(best);
+    components.extend(extras);
+
+    ArmorClass {
+        total: alternatives[best].1,
+        formula,
+        components,
+        alternatives,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(dex: i32, con: i32, wis: i32) -> CreatureStats {
+        let mut stats = CreatureStats::default();
+        stats.ability_scores.insert(Ability::DEX, dex);
+        stats.ability_scores.insert(Ability::CON, con);
+        stats.ability_scores.insert(Ability::WIS, wis);
+        stats
+    }
+
+    #[test]
+    fn test_armor_dex_caps() {
+        let nimble = stats(18, 10, 10);
+        let wearing = |name: &str| ArmorSetup {
+            armor: Armor::srd(name),
+            ..Default::default()
+        };
+        assert_eq!(calculate(&nimble, &wearing("studded leather")).total, 16);
+        assert_eq!(calculate(&nimble, &wearing("half plate")).total, 17);
+        assert_eq!(calculate(&nimble, &wearing("plate")).total, 18);
+
+        let master = ArmorSetup {
+            medium_armor_master: true,
+            ..wearing("half plate")
+        };
+        assert_eq!(calculate(&nimble, &master).total, 18);
+        assert!(Armor::srd("mithral underpants").is_none());
+    }
+
+    #[test]
+    fn test_shield_magic_and_bonuses() {
+        let fighter = stats(12, 14, 10);
+        let setup = ArmorSetup {
+            armor: Some(Armor::srd("chain mail").unwrap().with_magic_bonus(1)),
+            shield: true,
+            shield_magic_bonus: 1,
+            defense_style: true,
+            bonuses: vec![Modifier::new("Ring of Protection", 1)],
+            ..Default::default()
+        };
+        let ac = calculate(&fighter, &setup);
+        assert_eq!(ac.total, 16 + 1 + 1 + 2 + 1 + 1);
+        assert_eq!(ac.formula, BaseFormula::Armor("chain mail".to_string()));
+        let sources: Vec<_> = ac.components.iter().map(|m| m.source.as_str()).collect();
+        assert_eq!(
+            sources,
+            vec![
+                "chain mail",
+                "Magic armor",
+                "Defense",
+                "Shield",
+                "Magic shield",
+                "Ring of Protection"
+            ]
+        );
+        assert_eq!(ac.components.iter().map(|m| m.value).sum::<i32>(), ac.total);
+    }
+
+    #[test]
+    fn test_unarmored_defense_variants() {
+        let monk = stats(16, 12, 16);
+        let setup = ArmorSetup {
+            unarmored_defense: Some(UnarmoredDefense::Monk),
+            ..Default::default()
+        };
+        assert_eq!(calculate(&monk, &setup).total, 16);
+        // A shield turns Unarmored Defense (Monk) off entirely.
+        let with_shield = ArmorSetup {
+            shield: true,
+            ..setup
+        };
+        let ac = calculate(&monk, &with_shield);
+        assert_eq!(ac.formula, BaseFormula::Unarmored);
+        assert_eq!(ac.total, 15);
+
+        let barbarian = stats(14, 16, 8);
+        let setup = ArmorSetup {
+            unarmored_defense: Some(UnarmoredDefense::Barbarian),
+            shield: true,
+            ..Default::default()
+        };
+        assert_eq!(calculate(&barbarian, &setup).total, 17);
+        // Armor replaces it.
+        let armored = ArmorSetup {
+            armor: Armor::srd("hide"),
+            ..setup
+        };
+        assert_eq!(calculate(&barbarian, &armored).total, 16);
+    }
+
+    #[test]
+    fn test_best_formula_wins() {
+        let wizard = stats(14, 12, 10);
+        let setup = ArmorSetup {
+            mage_armor: true,
+            natural_armor: Some(NaturalArmor {
+                base: 17,
+                adds_dex: false,
+            }),
+            ..Default::default()
+        };
+        let ac = calculate(&wizard, &setup);
+        assert_eq!(ac.formula, BaseFormula::NaturalArmor);
+        assert_eq!(ac.total, 17);
+        assert_eq!(ac.alternatives.len(), 3);
+        assert!(ac.alternatives.contains(&(BaseFormula::MageArmor, 15)));
+
+        // Mage Armor does nothing over worn armor.
+        let armored = ArmorSetup {
+            armor: Armor::srd("leather"),
+            mage_armor: true,
+            ..Default::default()
+        };
+        let ac = calculate(&wizard, &armored);
+        assert_eq!(ac.total, 13);
+        assert_eq!(ac.alternatives.len(), 1);
+    }
+}
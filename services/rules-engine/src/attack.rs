@@ -0,0 +1,517 @@
+//! Attack rolls with every modifier named (PHB p.193-196).
+//!
+//! The outcome carries a line-by-line explanation so a DM can see exactly
+//! why an attack hit or missed: the d20 and why it was rolled that way,
+//! each bonus, the AC with cover, and the verdict.
+
+use crate::advantage::{self, D20Outcome, D20Sources, D20Test};
+use crate::checks::Modifier;
+use crate::combat::{Ability, CoverType, CreatureStats};
+use crate::conditions::{ActiveCondition, ConditionType};
+use crate::dice::DiceRoller;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttackSource {
+    Weapon,
+    Spell,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum AttackVerdict {
+    Miss,
+    Hit,
+    CriticalHit,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttackInput {
+    pub source: AttackSource,
+    pub ranged: bool,
+    /// Ability for the roll; `None` when the caller supplies the whole bonus
+    /// through `bonuses`.
+    pub ability: Option<Ability>,
+    /// Weapon proficiency. Spell attacks always add proficiency.
+    pub proficient: bool,
+    /// +N weapon, or a focus such as a Wand of the War Mage for spells.
+    pub magic_bonus: i32,
+    /// Archery fighting style: +2 to ranged weapon attacks.
+    pub archery: bool,
+    /// Lowest natural roll that crits: 20 normally, 19 for a Champion.
+    pub crit_threshold: i32,
+    pub cover: CoverType,
+    pub distance_feet: i32,
+    /// Normal and long range for ranged attacks.
+    pub range: Option<(i32, i32)>,
+    pub sources: D20Sources,
+    pub bonuses: Vec<Modifier>,
+}
+
+impl AttackInput {
+    pub fn new(source: AttackSource, ability: Option<Ability>) -> Self {
+        Self {
+            source,
+            ranged: false,
+            ability,
+            proficient: source == AttackSource::Spell,
+            magic_bonus: 0,
+            archery: false,
+            crit_threshold: 20,
+            cover: CoverType::None,
+            distance_feet: 5,
+            range: None,
+            sources: D20Sources::default(),
+            bonuses: Vec::new(),
+        }
+    }
+
+    pub fn weapon(ability: Ability) -> Self {
+        Self::new(AttackSource::Weapon, Some(ability))
+    }
+
+    pub fn spell(ability: Ability) -> Self {
+        Self::new(AttackSource::Spell, Some(ability))
+    }
+
+    pub fn ranged(mut self, normal: i32, long: i32) -> Self {
+        self.ranged = true;
+        self.range = Some((normal, long));
+        self
+    }
+
+    pub fn with_proficiency(mut self, proficient: bool) -> Self {
+        self.proficient = proficient || self.source == AttackSource::Spell;
+        self
+    }
+
+    pub fn with_magic_bonus(mut self, bonus: i32) -> Self {
+        self.magic_bonus = bonus;
+        self
+    }
+
+    pub fn with_archery(mut self, archery: bool) -> Self {
+        self.archery = archery;
+        self
+    }
+
+    pub fn with_crit_threshold(mut self, threshold: i32) -> Self {
+        self.crit_threshold = threshold.clamp(2, 20);
+        self
+    }
+
+    pub fn with_cover(mut self, cover: CoverType) -> Self {
+        self.cover = cover;
+        self
+    }
+
+    pub fn at_distance(mut self, feet: i32) -> Self {
+        self.distance_feet = feet;
+        self
+    }
+
+    pub fn with_sources(mut self, sources: D20Sources) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    pub fn with_bonus(mut self, source: impl Into<String>, value: i32) -> Self {
+        self.bonuses.push(Modifier::new(source, value));
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttackOutcome {
+    pub verdict: AttackVerdict,
+    pub natural_roll: i32,
+    pub modifier: i32,
+    pub total: i32,
+    /// Target AC including cover.
+    pub target_ac: i32,
+    pub breakdown: Vec<Modifier>,
+    pub is_fumble: bool,
+    /// Why the attack could not be rolled at all (total cover, out of range).
+    pub impossible: Option<String>,
+    pub d20: Option<D20Outcome>,
+    pub explanation: Vec<String>,
+}
+
+impl AttackOutcome {
+    pub fn hits(&self) -> bool {
+        self.verdict != AttackVerdict::Miss
+    }
+
+    pub fn is_critical(&self) -> bool {
+        self.verdict == AttackVerdict::CriticalHit
+    }
+}
+
+fn signed(value: i32) -> String {
+    format!("{:+}", value)
+}
+
+/// Named advantage and disadvantage from conditions, prone and range.
+fn situational_sources(
+    input: &AttackInput,
+    attacker_conditions: &[ActiveCondition],
+    target_conditions: &[ActiveCondition],
+) -> D20Sources {
+    let mut sources = input.sources.clone();
+    for condition in attacker_conditions {
+        let effects = condition.effects();
+        if effects.attacks_have_advantage {
+            sources.advantage.push(condition.label());
+        }
+        if effects.attacks_have_disadvantage {
+            sources.disadvantage.push(condition.label());
+        }
+    }
+
+    let within_5_feet = input.distance_feet <= 5;
+    let mut target_prone = false;
+    for condition in target_conditions {
+        let effects = condition.effects();
+        target_prone |= condition.condition_type == ConditionType::Prone || effects.falls_prone;
+        if effects.attacks_against_have_advantage {
+            sources
+                .advantage
+                .push(format!("Target {}", condition.label()));
+        }
+        if effects.attacks_against_have_disadvantage {
+            sources
+                .disadvantage
+                .push(format!("Target {}", condition.label()));
+        }
+    }
+    if target_prone {
+        if within_5_feet {
+            sources
+                .advantage
+                .push("Prone target within 5 ft".to_string());
+        } else {
+            sources
+                .disadvantage
+                .push("Prone target beyond 5 ft".to_string());
+        }
+    }
+
+    if let Some((normal, _)) = input.range {
+        if input.distance_feet > normal {
+            sources.disadvantage.push("Long range".to_string());
+        }
+    }
+    sources
+}
+
+fn attack_breakdown(attacker: &CreatureStats, input: &AttackInput) -> Vec<Modifier> {
+    let mut breakdown = Vec::new();
+    if let Some(ability) = input.ability {
+        breakdown.push(Modifier::new(
+            format!("{:?} modifier", ability),
+            attacker.get_modifier(ability),
+        ));
+    }
+    if input.proficient && input.ability.is_some() {
+        breakdown.push(Modifier::new("Proficiency", attacker.proficiency_bonus));
+    }
+    if input.magic_bonus != 0 {
+        let source = match input.source {
+            AttackSource::Weapon => "Magic weapon",
+            AttackSource::Spell => "Magic focus",
+        };
+        breakdown.push(Modifier::new(source, input.magic_bonus));
+    }
+    if input.archery && input.ranged && input.source == AttackSource::Weapon {
+        breakdown.push(Modifier::new("Archery", 2));
+    }
+    breakdown.extend(input.bonuses.iter().cloned());
+    breakdown
+}
+
+fn ac_line(target: &CreatureStats, cover: CoverType, target_ac: i32) -> String {
+    let cover_name = match cover {
+        CoverType::Half => Some("half cover"),
+        CoverType::ThreeQuarters => Some("three-quarters cover"),
+        CoverType::None | CoverType::Total => None,
+    };
+    match cover_name {
+        Some(name) => format!(
+            "AC {} ({} base {} {})",
+            target_ac,
+            target.armor_class,
+            signed(cover.ac_bonus()),
+            name
+        ),
+        None => format!("AC {}", target_ac),
+    }
+}
+
+/// Roll `input` from `attacker` against `target`.
+pub fn resolve_attack(
+    roller: &mut DiceRoller,
+    attacker: &CreatureStats,
+    target: &CreatureStats,
+    attacker_conditions: &[ActiveCondition],
+    target_conditions: &[ActiveCondition],
+    input: &AttackInput,
+) -> AttackOutcome {
+    let breakdown = attack_breakdown(attacker, input);
+    let modifier: i32 = breakdown.iter().map(|m| m.value).sum();
+    let target_ac = target.armor_class + input.cover.ac_bonus();
+
+    let impossible = if input.cover == CoverType::Total {
+        Some("Target has total cover and can't be targeted".to_string())
+    } else {
+        input
+            .range
+            .filter(|(_, long)| input.distance_feet > *long)
+            .map(|(_, long)| {
+                format!(
+                    "Target at {} ft is beyond long range ({} ft)",
+                    input.distance_feet, long
+                )
+            })
+    };
+    if let Some(reason) = impossible {
+        return AttackOutcome {
+            verdict: AttackVerdict::Miss,
+            natural_roll: 0,
+            modifier,
+            total: 0,
+            target_ac,
+            breakdown,
+            is_fumble: false,
+            explanation: vec![format!("Miss: {}", reason)],
+            impossible: Some(reason),
+            d20: None,
+        };
+    }
+
+    let sources = situational_sources(input, attacker_conditions, target_conditions);
+    let d20 = advantage::roll_d20(
+        roller,
+        D20Test::Attack(input.ability.unwrap_or(Ability::STR)),
+        &sources,
+    );
+    let natural_roll = d20.kept;
+    let total = natural_roll + modifier;
+
+    let mut explanation = Vec::new();
+    let rolls: Vec<String> = d20.rolls.iter().map(|r| r.to_string()).collect();
+    if d20.rolls.len() > 1 {
+        explanation.push(format!(
+            "d20 rolled {}, kept {}: {}",
+            rolls.join(", "),
+            natural_roll,
+            d20.explanation
+        ));
+    } else {
+        explanation.push(format!("d20 rolled {}: {}", natural_roll, d20.explanation));
+    }
+    for line in &breakdown {
+        explanation.push(format!("{} {}", signed(line.value), line.source));
+    }
+    explanation.push(format!(
+        "Total {} vs {}",
+        total,
+        ac_line(target, input.cover, target_ac)
+    ));
+
+    let is_fumble = natural_roll == 1;
+    // Paralyzed and Unconscious: any hit from within 5 feet is a crit.
+    let auto_crit = target_conditions
+        .iter()
+        .find(|c| c.effects().melee_attacks_against_auto_crit)
+        .filter(|_| input.distance_feet <= 5);
+    let (verdict, reason) = if is_fumble {
+        (AttackVerdict::Miss, "natural 1 always misses".to_string())
+    } else if natural_roll >= input.crit_threshold {
+        let reason = if input.crit_threshold < 20 {
+            format!(
+                "natural {} is in the crit range {}-20",
+                natural_roll, input.crit_threshold
+            )
+        } else {
+            "natural 20".to_string()
+        };
+        (AttackVerdict::CriticalHit, reason)
+    } else if total < target_ac {
+        (
+            AttackVerdict::Miss,
+            format!("{} is below AC {}", total, target_ac),
+        )
+    } else if let Some(condition) = auto_crit {
+        (
+            AttackVerdict::CriticalHit,
+            format!("hit against a {} target within 5 ft", condition.label()),
+        )
+    } else {
+        (
+            AttackVerdict::Hit,
+            format!("{} meets AC {}", total, target_ac),
+        )
+    };
+    explanation.push(match verdict {
+        AttackVerdict::Miss => format!("Miss: {}", reason),
+        AttackVerdict::Hit => format!("Hit: {}", reason),
+        AttackVerdict::CriticalHit => format!("Critical hit: {}", reason),
+    });
+
+    AttackOutcome {
+        verdict,
+        natural_roll,
+        modifier,
+        total,
+        target_ac,
+        breakdown,
+        is_fumble,
+        impossible: None,
+        d20: Some(d20),
+        explanation,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fighter() -> CreatureStats {
+        CreatureStats {
+            creature_id: "fighter".to_string(),
+            ability_scores: [(Ability::STR, 16), (Ability::DEX, 18)]
+                .into_iter()
+                .collect(),
+            proficiency_bonus: 3,
+            ..Default::default()
+        }
+    }
+
+    fn target(ac: i32) -> CreatureStats {
+        CreatureStats {
+            creature_id: "orc".to_string(),
+            armor_class: ac,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_breakdown_names_every_bonus() {
+        let mut roller = DiceRoller::with_seed(1);
+        let input = AttackInput::weapon(Ability::DEX)
+            .ranged(150, 600)
+            .at_distance(60)
+            .with_proficiency(true)
+            .with_magic_bonus(1)
+            .with_archery(true)
+            .with_cover(CoverType::Half);
+        let outcome = resolve_attack(&mut roller, &fighter(), &target(13), &[], &[], &input);
+
+        let sources: Vec<&str> = outcome
+            .breakdown
+            .iter()
+            .map(|m| m.source.as_str())
+            .collect();
+        assert_eq!(
+            sources,
+            vec!["DEX modifier", "Proficiency", "Magic weapon", "Archery"]
+        );
+        assert_eq!(outcome.modifier, 4 + 3 + 1 + 2);
+        assert_eq!(outcome.target_ac, 15);
+        assert!(outcome
+            .explanation
+            .iter()
+            .any(|l| l.ends_with("vs AC 15 (13 base +2 half cover)")));
+    }
+
+    #[test]
+    fn test_champion_crits_on_nineteen() {
+        let mut roller = DiceRoller::with_seed(4);
+        let input = AttackInput::weapon(Ability::STR).with_crit_threshold(19);
+        let mut saw_nineteen = false;
+        for _ in 0..300 {
+            let outcome = resolve_attack(&mut roller, &fighter(), &target(30), &[], &[], &input);
+            if outcome.natural_roll >= 19 {
+                saw_nineteen |= outcome.natural_roll == 19;
+                assert!(outcome.is_critical());
+            } else if outcome.natural_roll + outcome.modifier < 30 {
+                assert!(!outcome.hits());
+            }
+        }
+        assert!(saw_nineteen);
+    }
+
+    #[test]
+    fn test_paralyzed_target_within_5_feet_is_crit() {
+        let mut roller = DiceRoller::with_seed(2);
+        let input = AttackInput::weapon(Ability::STR);
+        for _ in 0..50 {
+            let outcome = resolve_attack(
+                &mut roller,
+                &fighter(),
+                &target(5),
+                &[],
+                &[ConditionType::Paralyzed.into()],
+                &input,
+            );
+            let d20 = outcome.d20.as_ref().unwrap();
+            assert_eq!(d20.advantage_sources, vec!["Target Paralyzed".to_string()]);
+            assert_eq!(outcome.is_critical(), !outcome.is_fumble);
+        }
+
+        let far = AttackInput::weapon(Ability::DEX)
+            .ranged(80, 320)
+            .at_distance(30);
+        let outcome = resolve_attack(
+            &mut roller,
+            &fighter(),
+            &target(5),
+            &[],
+            &[ConditionType::Paralyzed.into()],
+            &far,
+        );
+        assert!(!outcome.is_critical() || outcome.natural_roll == 20);
+    }
+
+    #[test]
+    fn test_impossible_attacks_explain_why() {
+        let mut roller = DiceRoller::with_seed(3);
+        let covered = AttackInput::spell(Ability::INT).with_cover(CoverType::Total);
+        let outcome = resolve_attack(&mut roller, &fighter(), &target(10), &[], &[], &covered);
+        assert!(!outcome.hits());
+        assert!(outcome.impossible.unwrap().contains("total cover"));
+
+        let too_far = AttackInput::weapon(Ability::DEX)
+            .ranged(80, 320)
+            .at_distance(400);
+        let outcome = resolve_attack(&mut roller, &fighter(), &target(10), &[], &[], &too_far);
+        assert_eq!(
+            outcome.explanation,
+            vec!["Miss: Target at 400 ft is beyond long range (320 ft)".to_string()]
+        );
+    }
+
+    #[test]
+    fn test_long_range_and_prone_target_impose_disadvantage() {
+        let mut roller = DiceRoller::with_seed(5);
+        let input = AttackInput::weapon(Ability::DEX)
+            .ranged(80, 320)
+            .at_distance(100);
+        let outcome = resolve_attack(
+            &mut roller,
+            &fighter(),
+            &target(10),
+            &[],
+            &[ConditionType::Prone.into()],
+            &input,
+        );
+        let d20 = outcome.d20.unwrap();
+        assert_eq!(
+            d20.disadvantage_sources,
+            vec![
+                "Prone target beyond 5 ft".to_string(),
+                "Long range".to_string()
+            ]
+        );
+        assert!(outcome.explanation[0].starts_with("d20 rolled"));
+    }
+}
@@ -0,0 +1,342 @@
+//! The Attack action as a sequence of weapon attacks (PHB p.192-195).
+//!
+//! Extra Attack gives one Attack action two attacks, three for an 11th-level
+//! fighter and four at 20th; a loading weapon still fires only once per
+//! action. Two-weapon fighting adds one bonus-action attack with a light
+//! melee weapon in the other hand, but only after the Attack action was
+//! taken with a light melee weapon held in one hand. The off-hand attack
+//! doesn't add a positive ability modifier to its damage unless the creature
+//! has the Two-Weapon Fighting style, and the Dual Wielder feat drops the
+//! light requirement for one-handed melee weapons.
+
+use crate::action_economy::ActionEconomy;
+use crate::combat::CreatureStats;
+use crate::weapons::{Weapon, WeaponAttack, WeaponKind, WeaponProperty, WeaponUse};
+use serde::{Deserialize, Serialize};
+use shared_rust::DndError;
+use std::collections::HashMap;
+
+/// Attacks per Attack action for a class at `level`. Barbarians, monks,
+/// paladins and rangers get Extra Attack at 5th level; fighters also get a
+/// third attack at 11th and a fourth at 20th.
+pub fn attacks_per_action(class: &str, level: u8) -> u8 {
+    match class.to_ascii_lowercase().as_str() {
+        "fighter" => match level {
+            20.. => 4,
+            11.. => 3,
+            5.. => 2,
+            _ => 1,
+        },
+        "barbarian" | "monk" | "paladin" | "ranger" if level >= 5 => 2,
+        _ => 1,
+    }
+}
+
+/// Features that change two-weapon fighting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TwoWeaponFeatures {
+    /// The Two-Weapon Fighting style: the off-hand attack adds its ability
+    /// modifier to damage.
+    pub fighting_style: bool,
+    /// The Dual Wielder feat: one-handed melee weapons needn't be light.
+    pub dual_wielder: bool,
+}
+
+/// Whether `weapon`, used as `usage`, can take part in two-weapon fighting.
+pub fn check_two_weapon(
+    weapon: &Weapon,
+    usage: &WeaponUse,
+    features: TwoWeaponFeatures,
+) -> Result<(), DndError> {
+    let violation = |reason: &str| {
+        DndError::RulesViolation(format!(
+            "{} can't be used for two-weapon fighting: {}",
+            weapon.name, reason
+        ))
+    };
+    if weapon.kind != WeaponKind::Melee {
+        return Err(violation("not a melee weapon"));
+    }
+    if usage.two_hands || weapon.has(&WeaponProperty::TwoHanded) {
+        return Err(violation("it must be held in one hand"));
+    }
+    if !weapon.has(&WeaponProperty::Light) && !features.dual_wielder {
+        return Err(violation("not a light weapon"));
+    }
+    Ok(())
+}
+
+/// Damage modifier for the off-hand attack: a negative modifier always
+/// applies, a positive one only with the fighting style.
+pub fn off_hand_damage_modifier(ability_modifier: i32, features: TwoWeaponFeatures) -> i32 {
+    if features.fighting_style {
+        ability_modifier
+    } else {
+        ability_modifier.min(0)
+    }
+}
+
+/// One attack made this turn.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttackStep {
+    pub weapon_id: String,
+    /// The bonus-action attack from two-weapon fighting.
+    pub off_hand: bool,
+    /// 1-based position within its Attack action; 1 for the off-hand attack.
+    pub number: u8,
+    /// Ability modifier added to the attack's damage.
+    pub damage_modifier: i32,
+}
+
+/// The weapon attacks a creature makes over one turn, on top of its
+/// [`ActionEconomy`].
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AttackSequence {
+    pub features: TwoWeaponFeatures,
+    pub steps: Vec<AttackStep>,
+    /// Attacks per weapon in the Attack action under way, for loading.
+    this_action: HashMap<String, u8>,
+    /// An Attack action attack was made with a weapon that allows an
+    /// off-hand attack.
+    off_hand_allowed: bool,
+}
+
+impl AttackSequence {
+    pub fn new(features: TwoWeaponFeatures) -> Self {
+        Self {
+            features,
+            ..Default::default()
+        }
+    }
+
+    /// Make one attack of the Attack action with `weapon`, taking the action
+    /// if none is under way.
+    pub fn attack(
+        &mut self,
+        economy: &mut ActionEconomy,
+        wielder: &CreatureStats,
+        weapon: &Weapon,
+        attack: &WeaponAttack,
+        usage: &WeaponUse,
+    ) -> Result<&AttackStep, DndError> {
+        let new_action = economy.attacks_remaining == 0;
+        let fired = if new_action {
+            0
+        } else {
+            self.this_action.get(&weapon.id).copied().unwrap_or(0)
+        };
+        if attack
+            .max_attacks_per_action
+            .is_some_and(|max| fired >= max)
+        {
+            return Err(DndError::RulesViolation(format!(
+                "{} has the loading property and fires once per action",
+                weapon.name
+            )));
+        }
+        economy.attack()?;
+        if new_action {
+            self.this_action.clear();
+        }
+        *self.this_action.entry(weapon.id.clone()).or_default() += 1;
+        self.off_hand_allowed |= check_two_weapon(weapon, usage, self.features).is_ok();
+
+        let number = self.this_action.values().sum();
+        Ok(self.push(AttackStep {
+            weapon_id: weapon.id.clone(),
+            off_hand: false,
+            number,
+            damage_modifier: wielder.get_modifier(attack.ability),
+        }))
+    }
+
+    /// The bonus-action attack with the weapon in the other hand.
+    pub fn off_hand_attack(
+        &mut self,
+        economy: &mut ActionEconomy,
+        wielder: &CreatureStats,
+        weapon: &Weapon,
+        attack: &WeaponAttack,
+        usage: &WeaponUse,
+    ) -> Result<&AttackStep, DndError> {
+        if !self.off_hand_allowed {
+            return Err(DndError::RulesViolation(format!(
+                "{} needs the Attack action with a light melee weapon held in one hand first",
+                economy.creature_id
+            )));
+        }
+        if self.steps.iter().any(|s| s.off_hand) {
+            return Err(DndError::RulesViolation(format!(
+                "{} already made its off-hand attack",
+                economy.creature_id
+            )));
+        }
+        check_two_weapon(weapon, usage, self.features)?;
+        economy.take_bonus_action()?;
+        let damage_modifier =
+            off_hand_damage_modifier(wielder.get_modifier(attack.ability), self.features);
+        Ok(self.push(AttackStep {
+            weapon_id: weapon.id.clone(),
+            off_hand: true,
+            number: 1,
+            damage_modifier,
+        }))
+    }
+
+    fn push(&mut self, step: AttackStep) -> &AttackStep {
+        self.steps.push(step);
+        self.steps.last().expect("just pushed")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action_economy::EconomyFeatures;
+    use crate::combat::Ability;
+    use crate::conditions::ConditionEffects;
+    use crate::weapons::{derive_attack, Armory};
+
+    fn weapon(id: &str) -> &'static Weapon {
+        Armory::builtin().get(id).unwrap()
+    }
+
+    fn fighter(dex: i32) -> CreatureStats {
+        let mut stats = CreatureStats {
+            creature_id: "fighter".to_string(),
+            ..Default::default()
+        };
+        stats.ability_scores.insert(Ability::STR, 16);
+        stats.ability_scores.insert(Ability::DEX, dex);
+        stats
+    }
+
+    fn turn(attacks_per_action: u8) -> ActionEconomy {
+        ActionEconomy::start_turn(
+            "fighter",
+            30,
+            &ConditionEffects::default(),
+            EconomyFeatures {
+                attacks_per_action,
+                ..Default::default()
+            },
+        )
+    }
+
+    fn swing(
+        sequence: &mut AttackSequence,
+        economy: &mut ActionEconomy,
+        wielder: &CreatureStats,
+        id: &str,
+        off_hand: bool,
+    ) -> Result<AttackStep, DndError> {
+        let usage = WeaponUse::default();
+        let attack = derive_attack(weapon(id), wielder, &usage)?;
+        if off_hand {
+            sequence
+                .off_hand_attack(economy, wielder, weapon(id), &attack, &usage)
+                .cloned()
+        } else {
+            sequence
+                .attack(economy, wielder, weapon(id), &attack, &usage)
+                .cloned()
+        }
+    }
+
+    #[test]
+    fn test_attacks_per_action_by_level() {
+        assert_eq!(attacks_per_action("Fighter", 4), 1);
+        assert_eq!(attacks_per_action("fighter", 5), 2);
+        assert_eq!(attacks_per_action("fighter", 11), 3);
+        assert_eq!(attacks_per_action("fighter", 20), 4);
+        assert_eq!(attacks_per_action("paladin", 20), 2);
+        assert_eq!(attacks_per_action("wizard", 20), 1);
+    }
+
+    #[test]
+    fn test_extra_attack_then_off_hand() {
+        let wielder = fighter(14);
+        let mut economy = turn(attacks_per_action("fighter", 11));
+        let mut sequence = AttackSequence::new(TwoWeaponFeatures::default());
+        for n in 1..=3 {
+            let step = swing(&mut sequence, &mut economy, &wielder, "shortsword", false).unwrap();
+            assert_eq!(step.number, n);
+            assert_eq!(step.damage_modifier, 3);
+        }
+        assert!(economy.attack().is_err());
+
+        // No positive modifier on the off-hand damage without the style.
+        let off = swing(&mut sequence, &mut economy, &wielder, "dagger", true).unwrap();
+        assert!(off.off_hand);
+        assert_eq!(off.damage_modifier, 0);
+        assert!(!economy.bonus_action);
+        assert!(swing(&mut sequence, &mut economy, &wielder, "dagger", true).is_err());
+    }
+
+    #[test]
+    fn test_off_hand_needs_light_attack_action() {
+        let wielder = fighter(14);
+        let mut economy = turn(1);
+        let mut sequence = AttackSequence::new(TwoWeaponFeatures::default());
+        let err = swing(&mut sequence, &mut economy, &wielder, "dagger", true).unwrap_err();
+        assert!(err.to_string().contains("Attack action"));
+        assert!(economy.bonus_action);
+
+        // A longsword isn't light, so it doesn't open up the off-hand attack.
+        swing(&mut sequence, &mut economy, &wielder, "longsword", false).unwrap();
+        assert!(swing(&mut sequence, &mut economy, &wielder, "dagger", true).is_err());
+
+        let mut economy = turn(1);
+        let mut sequence = AttackSequence::new(TwoWeaponFeatures::default());
+        swing(&mut sequence, &mut economy, &wielder, "handaxe", false).unwrap();
+        let err = swing(&mut sequence, &mut economy, &wielder, "longsword", true).unwrap_err();
+        assert!(err.to_string().contains("not a light weapon"));
+    }
+
+    #[test]
+    fn test_dual_wielder_and_fighting_style() {
+        let features = TwoWeaponFeatures {
+            fighting_style: true,
+            dual_wielder: true,
+        };
+        let wielder = fighter(10);
+        let mut economy = turn(2);
+        let mut sequence = AttackSequence::new(features);
+        swing(&mut sequence, &mut economy, &wielder, "longsword", false).unwrap();
+        let off = swing(&mut sequence, &mut economy, &wielder, "rapier", true).unwrap();
+        assert_eq!(off.damage_modifier, 3);
+        // The feat doesn't make two-handed weapons work.
+        assert!(check_two_weapon(weapon("greatsword"), &WeaponUse::default(), features).is_err());
+
+        // A negative modifier applies to the off-hand attack regardless.
+        assert_eq!(
+            off_hand_damage_modifier(-1, TwoWeaponFeatures::default()),
+            -1
+        );
+    }
+
+    #[test]
+    fn test_loading_fires_once_per_action() {
+        let wielder = fighter(16);
+        let mut economy = turn(2);
+        let mut sequence = AttackSequence::new(TwoWeaponFeatures::default());
+        let usage = WeaponUse {
+            two_hands: true,
+            distance_feet: 30,
+            ..Default::default()
+        };
+        let crossbow = weapon("light-crossbow");
+        let attack = derive_attack(crossbow, &wielder, &usage).unwrap();
+        sequence
+            .attack(&mut economy, &wielder, crossbow, &attack, &usage)
+            .unwrap();
+        let err = sequence
+            .attack(&mut economy, &wielder, crossbow, &attack, &usage)
+            .unwrap_err();
+        assert!(err.to_string().contains("loading"));
+        // The second attack is still there for another weapon.
+        assert_eq!(economy.attacks_remaining, 1);
+        swing(&mut sequence, &mut economy, &wielder, "dagger", false).unwrap();
+        assert_eq!(sequence.steps.len(), 2);
+    }
+}
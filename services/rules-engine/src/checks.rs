@@ -0,0 +1,451 @@
+//! Ability checks, skill checks and saving throws (PHB p.174-179).
+//!
+//! Natural 20s and 1s mean nothing special on checks and saves; only death
+//! saving throws treat them specially (PHB p.197). Every modifier that goes
+//! into the total is returned as a named line so the roll log can show it.
+
+use crate::advantage::{self, D20Outcome, D20Sources, D20Test};
+use crate::combat::{Ability, CreatureStats};
+use crate::conditions::{ActiveCondition, ConditionType};
+use crate::dice::DiceRoller;
+use serde::{Deserialize, Serialize};
+
+/// DC for death saving throws.
+pub const DEATH_SAVE_DC: i32 = 10;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Proficiency {
+    #[default]
+    None,
+    Proficient,
+    Expertise,
+}
+
+/// Class features that change how checks are totalled.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct CheckFeatures {
+    /// Bard 2: half proficiency (rounded down) on checks without proficiency.
+    pub jack_of_all_trades: bool,
+    /// Champion 7: half proficiency (rounded up) on STR/DEX/CON checks
+    /// without proficiency.
+    pub remarkable_athlete: bool,
+    /// Rogue 11: a d20 of 9 or lower counts as 10 on proficient checks.
+    pub reliable_talent: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CheckKind {
+    Ability(Ability),
+    Skill { skill: String, ability: Ability },
+    SavingThrow(Ability),
+    DeathSave,
+}
+
+impl CheckKind {
+    /// Skill check using the skill's default ability.
+    pub fn skill(skill: &str) -> Option<Self> {
+        Ability::for_skill(skill).map(|ability| CheckKind::Skill {
+            skill: skill.to_string(),
+            ability,
+        })
+    }
+
+    pub fn ability(&self) -> Option<Ability> {
+        match self {
+            CheckKind::Ability(ability) | CheckKind::SavingThrow(ability) => Some(*ability),
+            CheckKind::Skill { ability, .. } => Some(*ability),
+            CheckKind::DeathSave => None,
+        }
+    }
+
+    pub fn is_ability_check(&self) -> bool {
+        matches!(self, CheckKind::Ability(_) | CheckKind::Skill { .. })
+    }
+}
+
+/// One named contribution to a roll's total.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Modifier {
+    pub source: String,
+    pub value: i32,
+}
+
+impl Modifier {
+    pub fn new(source: impl Into<String>, value: i32) -> Self {
+        Self {
+            source: source.into(),
+            value,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckInput {
+    pub kind: CheckKind,
+    pub dc: i32,
+    /// Proficiency in the skill or tool; saves use the creature's stats.
+    pub proficiency: Proficiency,
+    pub features: CheckFeatures,
+    pub sources: D20Sources,
+    /// Flat bonuses and penalties from other effects, e.g. Bless.
+    pub bonuses: Vec<Modifier>,
+}
+
+impl CheckInput {
+    pub fn new(kind: CheckKind, dc: i32) -> Self {
+        Self {
+            kind,
+            dc,
+            proficiency: Proficiency::None,
+            features: CheckFeatures::default(),
+            sources: D20Sources::default(),
+            bonuses: Vec::new(),
+        }
+    }
+
+    pub fn death_save() -> Self {
+        Self::new(CheckKind::DeathSave, DEATH_SAVE_DC)
+    }
+
+    pub fn with_proficiency(mut self, proficiency: Proficiency) -> Self {
+        self.proficiency = proficiency;
+        self
+    }
+
+    pub fn with_features(mut self, features: CheckFeatures) -> Self {
+        self.features = features;
+        self
+    }
+
+    pub fn with_sources(mut self, sources: D20Sources) -> Self {
+        self.sources = sources;
+        self
+    }
+
+    pub fn with_bonus(mut self, source: impl Into<String>, value: i32) -> Self {
+        self.bonuses.push(Modifier::new(source, value));
+        self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DeathSaveOutcome {
+    Success,
+    Failure,
+    /// Natural 20: regain 1 hit point.
+    RegainHitPoint,
+    /// Natural 1: counts as two failures.
+    TwoFailures,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckOutcome {
+    pub kind: CheckKind,
+    pub success: bool,
+    /// The natural d20 that was kept.
+    pub natural_roll: i32,
+    /// The d20 value used in the total, after Reliable Talent.
+    pub d20_value: i32,
+    pub ability_modifier: i32,
+    /// Proficiency actually added: full, doubled, halved or zero.
+    pub proficiency_bonus: i32,
+    pub modifier: i32,
+    pub total: i32,
+    pub dc: i32,
+    pub breakdown: Vec<Modifier>,
+    /// Condition that made a save fail without a roll.
+    pub auto_fail: Option<ConditionType>,
+    pub reliable_talent_applied: bool,
+    pub death_save: Option<DeathSaveOutcome>,
+    pub d20: Option<D20Outcome>,
+}
+
+fn auto_fail_condition(kind: &CheckKind, conditions: &[ActiveCondition]) -> Option<ConditionType> {
+    conditions
+        .iter()
+        .find(|c| {
+            let effects = c.effects();
+            match kind {
+                CheckKind::SavingThrow(Ability::STR) => effects.str_saves_auto_fail,
+                CheckKind::SavingThrow(Ability::DEX) => effects.dex_saves_auto_fail,
+                _ => false,
+            }
+        })
+        .map(|c| c.condition_type)
+}
+
+fn condition_disadvantage(kind: &CheckKind, conditions: &[ActiveCondition]) -> Vec<String> {
+    conditions
+        .iter()
+        .filter(|c| {
+            let effects = c.effects();
+            match kind {
+                CheckKind::Ability(_) | CheckKind::Skill { .. } => {
+                    effects.ability_checks_have_disadvantage
+                }
+                CheckKind::SavingThrow(ability) => {
+                    effects.saves_have_disadvantage
+                        || (*ability == Ability::DEX && effects.dex_saves_have_disadvantage)
+                }
+                CheckKind::DeathSave => effects.saves_have_disadvantage,
+            }
+        })
+        .map(|c| c.label())
+        .collect()
+}
+
+/// The proficiency line for `input`, if any applies.
+fn proficiency_line(stats: &CreatureStats, input: &CheckInput) -> Option<Modifier> {
+    let pb = stats.proficiency_bonus;
+    match &input.kind {
+        CheckKind::SavingThrow(ability) => stats
+            .proficient_saves
+            .contains(ability)
+            .then(|| Modifier::new("Proficiency", pb)),
+        CheckKind::DeathSave => None,
+        CheckKind::Ability(ability) | CheckKind::Skill { ability, .. } => match input.proficiency {
+            Proficiency::Expertise => Some(Modifier::new("Expertise", pb * 2)),
+            Proficiency::Proficient => Some(Modifier::new("Proficiency", pb)),
+            Proficiency::None => {
+                let athlete = input.features.remarkable_athlete
+                    && matches!(ability, Ability::STR | Ability::DEX | Ability::CON);
+                if athlete {
+                    Some(Modifier::new("Remarkable Athlete", (pb + 1) / 2))
+                } else if input.features.jack_of_all_trades {
+                    Some(Modifier::new("Jack of All Trades", pb / 2))
+                } else {
+                    None
+                }
+            }
+        },
+    }
+}
+
+/// Roll and total a check or save for `stats` under `conditions`.
+pub fn resolve_check(
+    roller: &mut DiceRoller,
+    stats: &CreatureStats,
+    conditions: &[ActiveCondition],
+    input: &CheckInput,
+) -> CheckOutcome {
+    let mut breakdown = Vec::new();
+    let ability_modifier = input.kind.ability().map_or(0, |a| stats.get_modifier(a));
+    if let Some(ability) = input.kind.ability() {
+        breakdown.push(Modifier::new(
+            format!("{:?} modifier", ability),
+            ability_modifier,
+        ));
+    }
+    let proficiency = proficiency_line(stats, input);
+    let proficiency_bonus = proficiency.as_ref().map_or(0, |p| p.value);
+    breakdown.extend(proficiency);
+    breakdown.extend(input.bonuses.iter().cloned());
+    let modifier: i32 = breakdown.iter().map(|m| m.value).sum();
+
+    if let Some(condition) = auto_fail_condition(&input.kind, conditions) {
+        return CheckOutcome {
+            kind: input.kind.clone(),
+            success: false,
+            natural_roll: 0,
+            d20_value: 0,
+            ability_modifier,
+            proficiency_bonus,
+            modifier,
+            total: 0,
+            dc: input.dc,
+            breakdown,
+            auto_fail: Some(condition),
+            reliable_talent_applied: false,
+            death_save: None,
+            d20: None,
+        };
+    }
+
+    let mut sources = input.sources.clone();
+    sources
+        .disadvantage
+        .extend(condition_disadvantage(&input.kind, conditions));
+    let test = if input.kind.is_ability_check() {
+        D20Test::AbilityCheck
+    } else {
+        D20Test::SavingThrow
+    };
+    let d20 = advantage::roll_d20(roller, test, &sources);
+    let natural_roll = d20.kept;
+
+    let reliable_talent_applied = input.features.reliable_talent
+        && input.kind.is_ability_check()
+        && input.proficiency != Proficiency::None
+        && natural_roll < 10;
+    let d20_value = if reliable_talent_applied {
+        10
+    } else {
+        natural_roll
+    };
+    let total = d20_value + modifier;
+
+    let death_save = (input.kind == CheckKind::DeathSave).then_some(match natural_roll {
+        20 => DeathSaveOutcome::RegainHitPoint,
+        1 => DeathSaveOutcome::TwoFailures,
+        _ if total >= input.dc => DeathSaveOutcome::Success,
+        _ => DeathSaveOutcome::Failure,
+    });
+    let success = match death_save {
+        Some(outcome) => matches!(
+            outcome,
+            DeathSaveOutcome::Success | DeathSaveOutcome::RegainHitPoint
+        ),
+        None => total >= input.dc,
+    };
+
+    CheckOutcome {
+        kind: input.kind.clone(),
+        success,
+        natural_roll,
+        d20_value,
+        ability_modifier,
+        proficiency_bonus,
+        modifier,
+        total,
+        dc: input.dc,
+        breakdown,
+        auto_fail: None,
+        reliable_talent_applied,
+        death_save,
+        d20: Some(d20),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rogue() -> CreatureStats {
+        CreatureStats {
+            creature_id: "rogue".to_string(),
+            ability_scores: [(Ability::DEX, 18), (Ability::STR, 10)]
+                .into_iter()
+                .collect(),
+            proficiency_bonus: 4,
+            proficient_saves: [Ability::DEX].into_iter().collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_expertise_doubles_proficiency() {
+        let mut roller = DiceRoller::with_seed(1);
+        let input = CheckInput::new(CheckKind::skill("stealth").unwrap(), 15)
+            .with_proficiency(Proficiency::Expertise);
+        let outcome = resolve_check(&mut roller, &rogue(), &[], &input);
+
+        assert_eq!(outcome.modifier, 4 + 8);
+        assert_eq!(outcome.breakdown[1], Modifier::new("Expertise", 8));
+        assert_eq!(outcome.total, outcome.natural_roll + 12);
+    }
+
+    #[test]
+    fn test_half_proficiency_features() {
+        let mut roller = DiceRoller::with_seed(2);
+        let stats = CreatureStats {
+            proficiency_bonus: 3,
+            ..rogue()
+        };
+        let features = CheckFeatures {
+            jack_of_all_trades: true,
+            remarkable_athlete: true,
+            ..Default::default()
+        };
+
+        let athletics =
+            CheckInput::new(CheckKind::skill("athletics").unwrap(), 10).with_features(features);
+        let outcome = resolve_check(&mut roller, &stats, &[], &athletics);
+        assert_eq!(outcome.proficiency_bonus, 2);
+
+        let arcana =
+            CheckInput::new(CheckKind::skill("arcana").unwrap(), 10).with_features(features);
+        let outcome = resolve_check(&mut roller, &stats, &[], &arcana);
+        assert_eq!(outcome.proficiency_bonus, 1);
+
+        let save =
+            CheckInput::new(CheckKind::SavingThrow(Ability::STR), 10).with_features(features);
+        assert_eq!(
+            resolve_check(&mut roller, &stats, &[], &save).proficiency_bonus,
+            0
+        );
+    }
+
+    #[test]
+    fn test_reliable_talent_floors_proficient_checks() {
+        let mut roller = DiceRoller::with_seed(3);
+        let features = CheckFeatures {
+            reliable_talent: true,
+            ..Default::default()
+        };
+        for _ in 0..30 {
+            let input = CheckInput::new(CheckKind::skill("acrobatics").unwrap(), 10)
+                .with_proficiency(Proficiency::Proficient)
+                .with_features(features);
+            let outcome = resolve_check(&mut roller, &rogue(), &[], &input);
+            assert!(outcome.d20_value >= 10);
+            assert_eq!(outcome.reliable_talent_applied, outcome.natural_roll < 10);
+        }
+    }
+
+    #[test]
+    fn test_paralyzed_auto_fails_dex_saves() {
+        let mut roller = DiceRoller::with_seed(4);
+        let input = CheckInput::new(CheckKind::SavingThrow(Ability::DEX), 5);
+        let outcome = resolve_check(
+            &mut roller,
+            &rogue(),
+            &[ConditionType::Paralyzed.into()],
+            &input,
+        );
+        assert!(!outcome.success);
+        assert_eq!(outcome.auto_fail, Some(ConditionType::Paralyzed));
+        assert!(outcome.d20.is_none());
+
+        let wis = CheckInput::new(CheckKind::SavingThrow(Ability::WIS), 5);
+        let outcome = resolve_check(
+            &mut roller,
+            &rogue(),
+            &[ConditionType::Paralyzed.into()],
+            &wis,
+        );
+        assert!(outcome.auto_fail.is_none());
+    }
+
+    #[test]
+    fn test_conditions_add_named_disadvantage() {
+        let mut roller = DiceRoller::with_seed(5);
+        let input = CheckInput::new(CheckKind::Ability(Ability::STR), 10);
+        let outcome = resolve_check(
+            &mut roller,
+            &rogue(),
+            &[ConditionType::Poisoned.into()],
+            &input,
+        );
+        let d20 = outcome.d20.unwrap();
+        assert_eq!(d20.disadvantage_sources, vec!["Poisoned".to_string()]);
+        assert_eq!(d20.rolls.len(), 2);
+    }
+
+    #[test]
+    fn test_death_save_naturals() {
+        let mut roller = DiceRoller::with_seed(6);
+        let mut seen_twenty = false;
+        for _ in 0..200 {
+            let outcome = resolve_check(&mut roller, &rogue(), &[], &CheckInput::death_save());
+            let expected = match outcome.natural_roll {
+                20 => DeathSaveOutcome::RegainHitPoint,
+                1 => DeathSaveOutcome::TwoFailures,
+                n if n >= 10 => DeathSaveOutcome::Success,
+                _ => DeathSaveOutcome::Failure,
+            };
+            seen_twenty |= outcome.natural_roll == 20;
+            assert_eq!(outcome.death_save, Some(expected));
+        }
+        assert!(seen_twenty);
+    }
+}
@@ -0,0 +1,769 @@
+//! Class features that hook into attacks, damage, saves and turns.
+//!
+//! A [`ClassFeature`] overrides only the hooks it cares about: Reckless
+//! Attack changes the attack roll, Sneak Attack, Divine Smite and Hunter's
+//! Mark add dice after a hit, Rage adds flat damage and helps Strength
+//! saves, Aura of Protection adds to the saves of the paladin and of allies
+//! within the aura. Features are created by id
+//! from a [`FeatureRegistry`], so new content registers a constructor rather
+//! than touching the attack pipeline, and a creature's features live in a
+//! [`FeatureSet`] that runs each hook over all of them in order.
+//!
+//! Spending the uses a feature costs (Rage, a spell slot for Divine Smite,
+//! the bonus action for Hunter's Mark) is left to the caller, through the
+//! [`ResourceTracker`](crate::resources::ResourceTracker) and
+//! [`ActionEconomy`](crate::action_economy::ActionEconomy).
+
+use crate::advantage::RollMode;
+use crate::attack::{AttackInput, AttackSource};
+use crate::checks::{CheckInput, CheckKind, Modifier};
+use crate::combat::{Ability, CreatureStats, DamageType};
+use serde::{Deserialize, Serialize};
+use shared_rust::DndError;
+use std::collections::BTreeMap;
+use std::sync::OnceLock;
+
+/// What a feature sees of an attack.
+#[derive(Debug, Clone)]
+pub struct AttackContext<'a> {
+    pub attacker: &'a CreatureStats,
+    pub target: &'a CreatureStats,
+    pub source: AttackSource,
+    pub melee: bool,
+    pub ability: Option<Ability>,
+    /// A finesse or ranged weapon, as Sneak Attack needs.
+    pub finesse_or_ranged: bool,
+    /// Another enemy of the target is within 5 feet of it.
+    pub ally_adjacent_to_target: bool,
+    /// Undead and fiends take an extra die from Divine Smite.
+    pub target_undead_or_fiend: bool,
+    /// How the d20 was rolled; `Normal` until the roll is made.
+    pub roll_mode: RollMode,
+    pub critical: bool,
+}
+
+impl AttackContext<'_> {
+    fn melee_weapon(&self) -> bool {
+        self.melee && self.source == AttackSource::Weapon
+    }
+}
+
+/// What a feature sees of a saving throw. The feature belongs to `owner`;
+/// `saver` is the creature rolling, which for auras may be someone else.
+#[derive(Debug, Clone)]
+pub struct SaveContext<'a> {
+    pub owner: &'a CreatureStats,
+    pub saver: &'a CreatureStats,
+    /// Feet between the owner and the saver.
+    pub distance_ft: i32,
+    /// The saver is the owner or one of its allies.
+    pub friendly: bool,
+    /// Auras lapse while their owner is unconscious.
+    pub owner_conscious: bool,
+}
+
+impl<'a> SaveContext<'a> {
+    /// The owner making one of its own saves.
+    pub fn own(owner: &'a CreatureStats) -> Self {
+        Self {
+            owner,
+            saver: owner,
+            distance_ft: 0,
+            friendly: true,
+            owner_conscious: true,
+        }
+    }
+
+    fn is_own(&self) -> bool {
+        self.owner.creature_id == self.saver.creature_id
+    }
+}
+
+/// Dice a feature adds to a hit. Critical hits double them like any other
+/// damage dice.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExtraDamage {
+    pub feature_id: String,
+    pub source: String,
+    pub dice: String,
+    /// `None` deals the attack's own damage type.
+    pub damage_type: Option<DamageType>,
+    /// Spell slot level the caller should spend, for Divine Smite.
+    pub slot_spent: Option<u8>,
+}
+
+/// How a feature is switched on.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Activation {
+    /// The creature marked by Hunter's Mark.
+    pub target_id: Option<String>,
+    /// The slot readied for Divine Smite.
+    pub slot_level: Option<u8>,
+}
+
+impl Activation {
+    pub fn target(target_id: impl Into<String>) -> Self {
+        Self {
+            target_id: Some(target_id.into()),
+            ..Default::default()
+        }
+    }
+
+    pub fn slot(level: u8) -> Self {
+        Self {
+            slot_level: Some(level),
+            ..Default::default()
+        }
+    }
+}
+
+/// Hook points in attack, damage and save resolution. Every hook does
+/// nothing by default.
+pub trait ClassFeature: Send {
+    fn id(&self) -> &str;
+
+    fn name(&self) -> &str;
+
+    /// Switch the feature on, e.g. entering a Rage.
+    fn activate(&mut self, _activation: &Activation) -> Result<(), DndError> {
+        Err(DndError::InvalidAction(format!(
+            "{} is always on and can't be activated",
+            self.name()
+        )))
+    }
+
+    fn is_active(&self) -> bool {
+        true
+    }
+
+    /// Add bonuses or advantage before the d20 is rolled.
+    fn before_attack_roll(&mut self, _attack: &AttackContext, _input: &mut AttackInput) {}
+
+    /// Extra dice once the attack has hit.
+    fn after_hit(&mut self, _attack: &AttackContext) -> Option<ExtraDamage> {
+        None
+    }
+
+    /// Flat bonuses to the hit's damage.
+    fn on_damage(&mut self, _attack: &AttackContext, _bonuses: &mut Vec<Modifier>) {}
+
+    /// At the start of the owner's turn; returns a note when something
+    /// changes, like a Rage running out.
+    fn on_turn_start(&mut self, _owner: &CreatureStats) -> Option<String> {
+        None
+    }
+
+    /// Adjust a saving throw before it is rolled. Most features only touch
+    /// their owner's own saves.
+    fn on_save(&mut self, _context: &SaveContext, _save: &mut CheckInput) {}
+}
+
+/// Rogue 1: extra d6s once per turn with a finesse or ranged weapon, given
+/// advantage or an ally next to the target and no disadvantage.
+#[derive(Debug, Clone, Default)]
+pub struct SneakAttack {
+    pub level: u8,
+    pub used_this_turn: bool,
+}
+
+impl SneakAttack {
+    pub fn dice(&self) -> String {
+        format!("{}d6", self.level.max(1).div_ceil(2))
+    }
+}
+
+impl ClassFeature for SneakAttack {
+    fn id(&self) -> &str {
+        "sneak-attack"
+    }
+
+    fn name(&self) -> &str {
+        "Sneak Attack"
+    }
+
+    fn after_hit(&mut self, attack: &AttackContext) -> Option<ExtraDamage> {
+        let qualifies = match attack.roll_mode {
+            RollMode::Advantage => true,
+            RollMode::Normal => attack.ally_adjacent_to_target,
+            RollMode::Disadvantage => false,
+        };
+        if self.used_this_turn
+            || !qualifies
+            || attack.source != AttackSource::Weapon
+            || !attack.finesse_or_ranged
+        {
+            return None;
+        }
+        self.used_this_turn = true;
+        Some(ExtraDamage {
+            feature_id: self.id().to_string(),
+            source: self.name().to_string(),
+            dice: self.dice(),
+            damage_type: None,
+            slot_spent: None,
+        })
+    }
+
+    fn on_turn_start(&mut self, _owner: &CreatureStats) -> Option<String> {
+        self.used_this_turn = false;
+        None
+    }
+}
+
+/// Paladin 2: a spell slot readied before the attack turns a melee weapon
+/// hit into 2d8 radiant, plus 1d8 per slot level above 1st (at most 5d8)
+/// and 1d8 more against undead and fiends.
+#[derive(Debug, Clone, Default)]
+pub struct DivineSmite {
+    pub readied_slot: Option<u8>,
+}
+
+impl ClassFeature for DivineSmite {
+    fn id(&self) -> &str {
+        "divine-smite"
+    }
+
+    fn name(&self) -> &str {
+        "Divine Smite"
+    }
+
+    fn activate(&mut self, activation: &Activation) -> Result<(), DndError> {
+        match activation.slot_level {
+            Some(level @ 1..=9) => {
+                self.readied_slot = Some(level);
+                Ok(())
+            }
+            other => Err(DndError::InvalidAction(format!(
+                "Divine Smite needs a spell slot of 1st level or higher, not {:?}",
+                other
+            ))),
+        }
+    }
+
+    fn is_active(&self) -> bool {
+        self.readied_slot.is_some()
+    }
+
+    fn after_hit(&mut self, attack: &AttackContext) -> Option<ExtraDamage> {
+        if !attack.melee_weapon() {
+            return None;
+        }
+        let slot = self.readied_slot.take()?;
+        let mut dice = (1 + i32::from(slot)).min(5);
+        if attack.target_undead_or_fiend {
+            dice += 1;
+        }
+        Some(ExtraDamage {
+            feature_id: self.id().to_string(),
+            source: format!("{} (level {} slot)", self.name(), slot),
+            dice: format!("{}d8", dice),
+            damage_type: Some(DamageType::Radiant),
+            slot_spent: Some(slot),
+        })
+    }
+}
+
+/// Barbarian 1: while raging, melee weapon attacks using Strength deal +2
+/// damage (+3 from 9th level, +4 from 16th) and Strength saves have
+/// advantage. A Rage lasts a minute.
+#[derive(Debug, Clone, Default)]
+pub struct Rage {
+    pub level: u8,
+    /// Rounds left, `None` when not raging.
+    pub rounds_left: Option<u8>,
+}
+
+impl Rage {
+    pub const ROUNDS: u8 = 10;
+
+    pub fn damage_bonus(&self) -> i32 {
+        match self.level {
+            16.. => 4,
+            9.. => 3,
+            _ => 2,
+        }
+    }
+}
+
+impl ClassFeature for Rage {
+    fn id(&self) -> &str {
+        "rage"
+    }
+
+    fn name(&self) -> &str {
+        "Rage"
+    }
+
+    fn activate(&mut self, _activation: &Activation) -> Result<(), DndError> {
+        self.rounds_left = Some(Self::ROUNDS);
+        Ok(())
+    }
+
+    fn is_active(&self) -> bool {
+        self.rounds_left.is_some()
+    }
+
+    fn on_damage(&mut self, attack: &AttackContext, bonuses: &mut Vec<Modifier>) {
+        if self.is_active() && attack.melee_weapon() && attack.ability == Some(Ability::STR) {
+            bonuses.push(Modifier::new(self.name(), self.damage_bonus()));
+        }
+    }
+
+    fn on_turn_start(&mut self, _owner: &CreatureStats) -> Option<String> {
+        let rounds = self.rounds_left?;
+        if rounds <= 1 {
+            self.rounds_left = None;
+            Some("Rage ends".to_string())
+        } else {
+            self.rounds_left = Some(rounds - 1);
+            None
+        }
+    }
+
+    fn on_save(&mut self, context: &SaveContext, save: &mut CheckInput) {
+        if self.is_active() && context.is_own() && save.kind == CheckKind::SavingThrow(Ability::STR)
+        {
+            save.sources.advantage.push(self.name().to_string());
+        }
+    }
+}
+
+/// Barbarian 2: advantage on Strength melee weapon attacks this turn.
+#[derive(Debug, Clone, Default)]
+pub struct RecklessAttack {
+    pub this_turn: bool,
+}
+
+impl ClassFeature for RecklessAttack {
+    fn id(&self) -> &str {
+        "reckless-attack"
+    }
+
+    fn name(&self) -> &str {
+        "Reckless Attack"
+    }
+
+    fn activate(&mut self, _activation: &Activation) -> Result<(), DndError> {
+        self.this_turn = true;
+        Ok(())
+    }
+
+    fn is_active(&self) -> bool {
+        self.this_turn
+    }
+
+    fn before_attack_roll(&mut self, attack: &AttackContext, input: &mut AttackInput) {
+        if self.this_turn && attack.melee_weapon() && attack.ability == Some(Ability::STR) {
+            input.sources.advantage.push(self.name().to_string());
+        }
+    }
+
+    fn on_turn_start(&mut self, _owner: &CreatureStats) -> Option<String> {
+        self.this_turn = false;
+        None
+    }
+}
+
+/// Hunter's Mark: an extra 1d6 on every weapon hit against the marked
+/// creature.
+#[derive(Debug, Clone, Default)]
+pub struct HuntersMark {
+    pub target_id: Option<String>,
+}
+
+impl ClassFeature for HuntersMark {
+    fn id(&self) -> &str {
+        "hunters-mark"
+    }
+
+    fn name(&self) -> &str {
+        "Hunter's Mark"
+    }
+
+    fn activate(&mut self, activation: &Activation) -> Result<(), DndError> {
+        let target = activation.target_id.clone().ok_or_else(|| {
+            DndError::InvalidAction("Hunter's Mark needs a creature to mark".to_string())
+        })?;
+        self.target_id = Some(target);
+        Ok(())
+    }
+
+    fn is_active(&self) -> bool {
+        self.target_id.is_some()
+    }
+
+    fn after_hit(&mut self, attack: &AttackContext) -> Option<ExtraDamage> {
+        let marked = self.target_id.as_deref() == Some(attack.target.creature_id.as_str());
+        (marked && attack.source == AttackSource::Weapon).then(|| ExtraDamage {
+            feature_id: self.id().to_string(),
+            source: self.name().to_string(),
+            dice: "1d6".to_string(),
+            damage_type: None,
+            slot_spent: None,
+        })
+    }
+}
+
+/// Paladin 6: the paladin's Charisma modifier (at least +1) on saving
+/// throws made by the paladin and allies within 10 feet, 30 feet from 18th
+/// level, while the paladin is conscious.
+#[derive(Debug, Clone, Default)]
+pub struct AuraOfProtection {
+    pub level: u8,
+}
+
+impl ClassFeature for AuraOfProtection {
+    fn id(&self) -> &str {
+        "aura-of-protection"
+    }
+
+    fn name(&self) -> &str {
+        "Aura of Protection"
+    }
+
+    fn on_save(&mut self, context: &SaveContext, save: &mut CheckInput) {
+        let range = if self.level >= 18 { 30 } else { 10 };
+        if self.level >= 6
+            && context.owner_conscious
+            && context.friendly
+            && (context.is_own() || context.distance_ft <= range)
+        {
+            let bonus = context.owner.get_modifier(Ability::CHA).max(1);
+            save.bonuses.push(Modifier::new(self.name(), bonus));
+        }
+    }
+}
+
+/// Builds a feature for a creature of the given class level.
+pub type FeatureConstructor = fn(level: u8) -> Box<dyn ClassFeature>;
+
+/// Feature constructors by id.
+#[derive(Debug, Clone, Default)]
+pub struct FeatureRegistry {
+    constructors: BTreeMap<String, FeatureConstructor>,
+}
+
+impl FeatureRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The SRD features above, ready for content to add to.
+    pub fn srd() -> Self {
+        let mut registry = Self::new();
+        let srd: [(&str, FeatureConstructor); 6] = [
+            ("sneak-attack", |level| {
+                Box::new(SneakAttack {
+                    level,
+                    ..Default::default()
+                })
+            }),
+            ("divine-smite", |_| Box::new(DivineSmite::default())),
+            ("rage", |level| {
+                Box::new(Rage {
+                    level,
+                    ..Default::default()
+                })
+            }),
+            ("reckless-attack", |_| Box::new(RecklessAttack::default())),
+            ("hunters-mark", |_| Box::new(HuntersMark::default())),
+            ("aura-of-protection", |level| {
+                Box::new(AuraOfProtection { level })
+            }),
+        ];
+        for (id, constructor) in srd {
+            registry
+                .register(id, constructor)
+                .expect("SRD feature ids are unique");
+        }
+        registry
+    }
+
+    pub fn builtin() -> &'static FeatureRegistry {
+        static BUILTIN: OnceLock<FeatureRegistry> = OnceLock::new();
+        BUILTIN.get_or_init(FeatureRegistry::srd)
+    }
+
+    pub fn register(
+        &mut self,
+        id: impl Into<String>,
+        constructor: FeatureConstructor,
+    ) -> Result<(), DndError> {
+        let id = id.into();
+        if self.constructors.contains_key(&id) {
+            return Err(DndError::InvalidAction(format!(
+                "class feature {} is registered twice",
+                id
+            )));
+        }
+        self.constructors.insert(id, constructor);
+        Ok(())
+    }
+
+    pub fn create(&self, id: &str, level: u8) -> Result<Box<dyn ClassFeature>, DndError> {
+        self.constructors
+            .get(id)
+            .map(|constructor| constructor(level))
+            .ok_or_else(|| DndError::EntityNotFound(format!("Unknown class feature: {}", id)))
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.constructors.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.constructors.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.constructors.is_empty()
+    }
+}
+
+/// One creature's features, run in the order they were added.
+#[derive(Default)]
+pub struct FeatureSet {
+    features: Vec<Box<dyn ClassFeature>>,
+}
+
+impl FeatureSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, feature: Box<dyn ClassFeature>) {
+        self.features.push(feature);
+    }
+
+    pub fn get(&self, id: &str) -> Option<&dyn ClassFeature> {
+        self.features.iter().find(|f| f.id() == id).map(|f| &**f)
+    }
+
+    pub fn activate(&mut self, id: &str, activation: &Activation) -> Result<(), DndError> {
+        self.features
+            .iter_mut()
+            .find(|f| f.id() == id)
+            .ok_or_else(|| DndError::EntityNotFound(format!("No class feature {}", id)))?
+            .activate(activation)
+    }
+
+    pub fn before_attack_roll(&mut self, attack: &AttackContext, input: &mut AttackInput) {
+        for feature in &mut self.features {
+            feature.before_attack_roll(attack, input);
+        }
+    }
+
+    pub fn after_hit(&mut self, attack: &AttackContext) -> Vec<ExtraDamage> {
+        self.features
+            .iter_mut()
+            .filter_map(|f| f.after_hit(attack))
+            .collect()
+    }
+
+    pub fn on_damage(&mut self, attack: &AttackContext) -> Vec<Modifier> {
+        let mut bonuses = Vec::new();
+        for feature in &mut self.features {
+            feature.on_damage(attack, &mut bonuses);
+        }
+        bonuses
+    }
+
+    pub fn on_turn_start(&mut self, owner: &CreatureStats) -> Vec<String> {
+        self.features
+            .iter_mut()
+            .filter_map(|f| f.on_turn_start(owner))
+            .collect()
+    }
+
+    pub fn on_save(&mut self, context: &SaveContext, save: &mut CheckInput) {
+        for feature in &mut self.features {
+            feature.on_save(context, save);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats(id: &str) -> CreatureStats {
+        CreatureStats {
+            creature_id: id.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn melee<'a>(attacker: &'a CreatureStats, target: &'a CreatureStats) -> AttackContext<'a> {
+        AttackContext {
+            attacker,
+            target,
+            source: AttackSource::Weapon,
+            melee: true,
+            ability: Some(Ability::STR),
+            finesse_or_ranged: false,
+            ally_adjacent_to_target: false,
+            target_undead_or_fiend: false,
+            roll_mode: RollMode::Normal,
+            critical: false,
+        }
+    }
+
+    fn features(ids: &[(&str, u8)]) -> FeatureSet {
+        let mut set = FeatureSet::new();
+        for (id, level) in ids {
+            set.add(FeatureRegistry::builtin().create(id, *level).unwrap());
+        }
+        set
+    }
+
+    #[test]
+    fn test_registry_creates_and_extends() {
+        let registry = FeatureRegistry::builtin();
+        assert_eq!(registry.len(), 6);
+        assert!(matches!(
+            registry.create("wild-shape", 2),
+            Err(DndError::EntityNotFound(_))
+        ));
+
+        let mut registry = FeatureRegistry::srd();
+        registry
+            .register("martial-arts", |_| Box::new(RecklessAttack::default()))
+            .unwrap();
+        assert!(registry
+            .register("rage", |_| Box::new(Rage::default()))
+            .is_err());
+        assert!(registry.ids().any(|id| id == "martial-arts"));
+    }
+
+    #[test]
+    fn test_sneak_attack_once_per_turn() {
+        let rogue = stats("rogue");
+        let orc = stats("orc");
+        let mut set = features(&[("sneak-attack", 5)]);
+        let mut attack = AttackContext {
+            finesse_or_ranged: true,
+            roll_mode: RollMode::Advantage,
+            ..melee(&rogue, &orc)
+        };
+        let extra = set.after_hit(&attack);
+        assert_eq!(extra[0].dice, "3d6");
+        assert!(set.after_hit(&attack).is_empty());
+
+        set.on_turn_start(&rogue);
+        // An adjacent ally only counts without disadvantage.
+        attack.roll_mode = RollMode::Disadvantage;
+        attack.ally_adjacent_to_target = true;
+        assert!(set.after_hit(&attack).is_empty());
+        attack.roll_mode = RollMode::Normal;
+        assert_eq!(set.after_hit(&attack).len(), 1);
+    }
+
+    #[test]
+    fn test_divine_smite_and_hunters_mark() {
+        let paladin = stats("paladin");
+        let zombie = stats("zombie");
+        let mut set = features(&[("divine-smite", 5), ("hunters-mark", 5)]);
+        let attack = AttackContext {
+            target_undead_or_fiend: true,
+            ..melee(&paladin, &zombie)
+        };
+        assert!(set.after_hit(&attack).is_empty());
+
+        set.activate("divine-smite", &Activation::slot(4)).unwrap();
+        set.activate("hunters-mark", &Activation::target("zombie"))
+            .unwrap();
+        let extra = set.after_hit(&attack);
+        assert_eq!(extra.len(), 2);
+        // 5d8 is the cap before the undead die.
+        assert_eq!(extra[0].dice, "6d8");
+        assert_eq!(extra[0].damage_type, Some(DamageType::Radiant));
+        assert_eq!(extra[0].slot_spent, Some(4));
+        assert_eq!(extra[1].dice, "1d6");
+        // The slot is spent; the mark stays.
+        assert_eq!(set.after_hit(&attack).len(), 1);
+        assert!(set.activate("divine-smite", &Activation::slot(0)).is_err());
+    }
+
+    #[test]
+    fn test_rage_damage_saves_and_duration() {
+        let barbarian = stats("barbarian");
+        let orc = stats("orc");
+        let mut set = features(&[("rage", 9), ("reckless-attack", 2)]);
+        let attack = melee(&barbarian, &orc);
+        assert!(set.on_damage(&attack).is_empty());
+
+        set.activate("rage", &Activation::default()).unwrap();
+        set.activate("reckless-attack", &Activation::default())
+            .unwrap();
+        let mut input = AttackInput::weapon(Ability::STR);
+        set.before_attack_roll(&attack, &mut input);
+        assert_eq!(input.sources.advantage, vec!["Reckless Attack".to_string()]);
+        assert_eq!(set.on_damage(&attack), vec![Modifier::new("Rage", 3)]);
+        let finesse = AttackContext {
+            ability: Some(Ability::DEX),
+            ..melee(&barbarian, &orc)
+        };
+        assert!(set.on_damage(&finesse).is_empty());
+
+        let mut save = CheckInput::new(CheckKind::SavingThrow(Ability::STR), 15);
+        set.on_save(&SaveContext::own(&barbarian), &mut save);
+        assert_eq!(save.sources.advantage, vec!["Rage".to_string()]);
+
+        let mut notes = Vec::new();
+        for _ in 0..Rage::ROUNDS {
+            notes.extend(set.on_turn_start(&barbarian));
+        }
+        assert_eq!(notes, vec!["Rage ends".to_string()]);
+        assert!(!set.get("rage").unwrap().is_active());
+        assert!(!set.get("reckless-attack").unwrap().is_active());
+    }
+
+    #[test]
+    fn test_aura_of_protection() {
+        let mut paladin = stats("paladin");
+        paladin.ability_scores.insert(Ability::CHA, 16);
+        let mut save = CheckInput::new(CheckKind::SavingThrow(Ability::WIS), 13);
+        features(&[("aura-of-protection", 5)]).on_save(&SaveContext::own(&paladin), &mut save);
+        assert!(save.bonuses.is_empty());
+        features(&[("aura-of-protection", 6)]).on_save(&SaveContext::own(&paladin), &mut save);
+        assert_eq!(save.bonuses, vec![Modifier::new("Aura of Protection", 3)]);
+    }
+
+    #[test]
+    fn test_aura_of_protection_covers_nearby_allies() {
+        let mut paladin = stats("paladin");
+        paladin.ability_scores.insert(Ability::CHA, 16);
+        let ally = stats("cleric");
+        let aura = |level: u8, distance_ft: i32, friendly: bool, owner_conscious: bool| {
+            let mut save = CheckInput::new(CheckKind::SavingThrow(Ability::DEX), 15);
+            let context = SaveContext {
+                owner: &paladin,
+                saver: &ally,
+                distance_ft,
+                friendly,
+                owner_conscious,
+            };
+            features(&[("aura-of-protection", level)]).on_save(&context, &mut save);
+            save.bonuses.iter().map(|b| b.value).sum::<i32>()
+        };
+        assert_eq!(aura(6, 10, true, true), 3);
+        assert_eq!(aura(6, 15, true, true), 0);
+        assert_eq!(aura(18, 30, true, true), 3);
+        assert_eq!(aura(6, 5, false, true), 0);
+        assert_eq!(aura(6, 5, true, false), 0);
+
+        // Rage only ever helps the barbarian.
+        let mut rage = features(&[("rage", 3)]);
+        rage.activate("rage", &Activation::default()).unwrap();
+        let mut save = CheckInput::new(CheckKind::SavingThrow(Ability::STR), 15);
+        let context = SaveContext {
+            owner: &paladin,
+            saver: &ally,
+            distance_ft: 5,
+            friendly: true,
+            owner_conscious: true,
+        };
+        rage.on_save(&context, &mut save);
+        assert!(save.sources.advantage.is_empty());
+    }
+}
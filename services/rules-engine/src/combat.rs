@@ -0,0 +1,318 @@
+//! Core combat types: abilities, creature stats, cover and initiative.
+//! Attacks are resolved in [`crate::attack`], saves and checks in
+//! [`crate::checks`].
+
+use crate::damage::FlatReduction;
+use crate::dice::{DiceRoller, DieRoll};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+pub use shared_rust::DamageType;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Ability {
+    STR,
+    DEX,
+    CON,
+    INT,
+    WIS,
+    CHA,
+}
+
+impl Ability {
+    /// Parse the three-letter abbreviation used in CreatureStats maps.
+    pub fn from_abbrev(abbrev: &str) -> Option<Self> {
+        match abbrev.to_ascii_uppercase().as_str() {
+            "STR" => Some(Ability::STR),
+            "DEX" => Some(Ability::DEX),
+            "CON" => Some(Ability::CON),
+            "INT" => Some(Ability::INT),
+            "WIS" => Some(Ability::WIS),
+            "CHA" => Some(Ability::CHA),
+            _ => None,
+        }
+    }
+
+    /// The ability a skill is keyed to (PHB p.174), e.g. "stealth" -> DEX.
+    pub fn for_skill(skill: &str) -> Option<Self> {
+        let normalized = skill.to_ascii_lowercase().replace(['_', '-'], " ");
+        match normalized.as_str() {
+            "athletics" => Some(Ability::STR),
+            "acrobatics" | "sleight of hand" | "stealth" => Some(Ability::DEX),
+            "arcana" | "history" | "investigation" | "nature" | "religion" => Some(Ability::INT),
+            "animal handling" | "insight" | "medicine" | "perception" | "survival" => {
+                Some(Ability::WIS)
+            }
+            "deception" | "intimidation" | "performance" | "persuasion" => Some(Ability::CHA),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CoverType {
+    None,
+    Half,
+    ThreeQuarters,
+    Total,
+}
+
+impl CoverType {
+    pub fn ac_bonus(&self) -> i32 {
+        match self {
+            CoverType::None => 0,
+            CoverType::Half => 2,
+            CoverType::ThreeQuarters => 5,
+            CoverType::Total => 0, // Can't be targeted
+        }
+    }
+
+    pub fn dex_save_bonus(&self) -> i32 {
+        self.ac_bonus() // Same bonus
+    }
+}
+
+/// Creature size (PHB p.191).
+#[derive(
+    Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize,
+)]
+pub enum Size {
+    Tiny,
+    Small,
+    #[default]
+    Medium,
+    Large,
+    Huge,
+    Gargantuan,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CreatureStats {
+    pub creature_id: String,
+    pub ability_scores: HashMap<Ability, i32>,
+    pub armor_class: i32,
+    pub proficiency_bonus: i32,
+    pub proficient_saves: HashSet<Ability>,
+    pub resistances: HashSet<DamageType>,
+    pub vulnerabilities: HashSet<DamageType>,
+    pub immunities: HashSet<DamageType>,
+    pub active_conditions: Vec<String>,
+    pub current_hp: i32,
+    pub max_hp: i32,
+    pub temp_hp: i32,
+    pub size: Size,
+    pub nonmagical_physical_resistance: bool,
+    pub nonmagical_physical_immunity: bool,
+    pub damage_reductions: Vec<FlatReduction>,
+}
+
+impl CreatureStats {
+    pub fn get_modifier(&self, ability: Ability) -> i32 {
+        let score = self.ability_scores.get(&ability).copied().unwrap_or(10);
+        CombatEngine::calculate_modifier(score)
+    }
+
+    pub fn get_save_modifier(&self, ability: Ability) -> i32 {
+        let base = self.get_modifier(ability);
+        if self.proficient_saves.contains(&ability) {
+            base + self.proficiency_bonus
+        } else {
+            base
+        }
+    }
+
+    pub fn has_condition(&self, condition: &str) -> bool {
+        self.active_conditions
+            .iter()
+            .any(|c| c.eq_ignore_ascii_case(condition))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DamageResult {
+    pub rolls: Vec<DieRoll>,
+    pub base_damage: i32,
+    pub modifier: i32,
+    pub damage_type: DamageType,
+    pub is_resistant: bool,
+    pub is_vulnerable: bool,
+    pub is_immune: bool,
+    pub final_damage: i32,
+}
+
+pub struct CombatEngine {
+    roller: DiceRoller,
+}
+
+impl CombatEngine {
+    pub fn new() -> Self {
+        Self {
+            roller: DiceRoller::new(),
+        }
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            roller: DiceRoller::with_seed(seed),
+        }
+    }
+
+    pub fn with_roller(roller: DiceRoller) -> Self {
+        Self { roller }
+    }
+
+    /// The underlying roller, e.g. for initiative or hit dice.
+    pub fn roller(&mut self) -> &mut DiceRoller {
+        &mut self.roller
+    }
+
+    /// Calculate ability modifier from score
+    pub fn calculate_modifier(score: i32) -> i32 {
+        (score - 10).div_euclid(2)
+    }
+
+    /// Get proficiency bonus for level
+    pub fn get_proficiency_bonus(level: i32) -> i32 {
+        ((level.max(1) - 1) / 4) + 2
+    }
+
+    /// Concentration save DC: 10 or half the damage, whichever is higher
+    pub fn concentration_dc(damage: i32) -> i32 {
+        (damage / 2).max(10)
+    }
+
+    /// Roll initiative
+    pub fn roll_initiative(
+        &mut self,
+        dex_modifier: i32,
+        initiative_bonus: i32,
+        advantage: bool,
+    ) -> (i32, i32) {
+        roll_initiative(&mut self.roller, dex_modifier, initiative_bonus, advantage)
+    }
+}
+
+/// [`CombatEngine::roll_initiative`] on a borrowed roller: the natural roll
+/// and the total.
+pub fn roll_initiative(
+    roller: &mut DiceRoller,
+    dex_modifier: i32,
+    initiative_bonus: i32,
+    advantage: bool,
+) -> (i32, i32) {
+    let (_, natural_roll) = roller.roll_d20_with_state(advantage, false);
+    (natural_roll, natural_roll + dex_modifier + initiative_bonus)
+}
+
+impl Default for CombatEngine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::damage::{self, DamageDefenses};
+    use crate::dice::DiceExpression;
+    use crate::hit_points::{DeathRule, HitPoints, HpEvent};
+    use shared_rust::DamageInstance;
+
+    fn create_test_creature() -> CreatureStats {
+        let ability_scores = HashMap::from([
+            (Ability::STR, 16),
+            (Ability::DEX, 14),
+            (Ability::CON, 14),
+            (Ability::INT, 10),
+            (Ability::WIS, 12),
+            (Ability::CHA, 8),
+        ]);
+
+        CreatureStats {
+            creature_id: "test-creature".to_string(),
+            ability_scores,
+            armor_class: 15,
+            proficiency_bonus: 2,
+            proficient_saves: HashSet::from([Ability::STR, Ability::CON]),
+            resistances: HashSet::from([DamageType::Fire]),
+            vulnerabilities: HashSet::from([DamageType::Cold]),
+            immunities: HashSet::new(),
+            active_conditions: vec![],
+            current_hp: 45,
+            max_hp: 45,
+            temp_hp: 0,
+            size: Size::Medium,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_modifier_calculation() {
+        assert_eq!(CombatEngine::calculate_modifier(10), 0);
+        assert_eq!(CombatEngine::calculate_modifier(14), 2);
+        assert_eq!(CombatEngine::calculate_modifier(9), -1);
+        assert_eq!(CombatEngine::calculate_modifier(8), -1);
+        assert_eq!(CombatEngine::calculate_modifier(1), -5);
+        assert_eq!(CombatEngine::calculate_modifier(20), 5);
+    }
+
+    #[test]
+    fn test_proficiency_bonus() {
+        assert_eq!(CombatEngine::get_proficiency_bonus(1), 2);
+        assert_eq!(CombatEngine::get_proficiency_bonus(5), 3);
+        assert_eq!(CombatEngine::get_proficiency_bonus(9), 4);
+        assert_eq!(CombatEngine::get_proficiency_bonus(17), 6);
+    }
+
+    fn damage_to(
+        target: &CreatureStats,
+        dice: &str,
+        damage_type: DamageType,
+        critical: bool,
+    ) -> (Vec<DieRoll>, i32, damage::AppliedDamage) {
+        let mut roller = DiceRoller::with_seed(42);
+        let expression = DiceExpression::parse(dice).unwrap();
+        let (rolls, amount) = damage::roll_damage(&mut roller, &expression, 3, critical);
+        let applied = damage::apply_defenses(
+            DamageInstance::new(damage_type, amount),
+            &DamageDefenses::from_stats(target),
+        );
+        (rolls, amount, applied)
+    }
+
+    #[test]
+    fn test_resistance_and_vulnerability_from_stats() {
+        let target = create_test_creature();
+
+        let (_, amount, fire) = damage_to(&target, "2d6", DamageType::Fire, false);
+        assert!(fire.resisted);
+        assert_eq!(fire.final_amount, amount / 2);
+        let (_, amount, cold) = damage_to(&target, "2d6", DamageType::Cold, false);
+        assert!(cold.vulnerable);
+        assert_eq!(cold.final_amount, amount * 2);
+        let (rolls, _, _) = damage_to(&target, "1d8", DamageType::Slashing, true);
+        assert_eq!(rolls.len(), 2);
+    }
+
+    #[test]
+    fn test_hit_points_from_stats() {
+        let mut creature = create_test_creature();
+        creature.temp_hp = 5;
+        let mut hp = HitPoints::from_stats(&creature, DeathRule::DeathSaves);
+        hp.take_damage(8, false).unwrap();
+        assert_eq!((hp.temp, hp.current), (0, 42));
+        assert!(hp.is_conscious());
+
+        creature.current_hp = 10;
+        creature.temp_hp = 0;
+        let mut hp = HitPoints::from_stats(&creature, DeathRule::DeathSaves);
+        let events = hp.take_damage(55, false).unwrap();
+        assert!(events.contains(&HpEvent::Died) && hp.is_dead());
+    }
+
+    #[test]
+    fn test_concentration_dc() {
+        assert_eq!(CombatEngine::concentration_dc(8), 10);
+        assert_eq!(CombatEngine::concentration_dc(30), 15);
+    }
+}
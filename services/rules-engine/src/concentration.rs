@@ -0,0 +1,390 @@
+//! Concentration (PHB p.203-204).
+//!
+//! A caster concentrates on one spell at a time. Everything that spell keeps
+//! going (conditions on other creatures, summoned creatures, zones on the
+//! map) is recorded against the caster, so when concentration ends for any
+//! reason it all ends together. Each ending is returned as a
+//! [`ConcentrationEnded`] for the gateway to broadcast.
+
+use crate::advantage::D20Sources;
+use crate::checks::{self, CheckInput, CheckKind, CheckOutcome};
+use crate::combat::{Ability, CombatEngine, CreatureStats};
+use crate::conditions::{ActiveCondition, ConditionManager};
+use crate::dice::DiceRoller;
+use serde::{Deserialize, Serialize};
+use shared_rust::DndError;
+use std::collections::HashMap;
+
+/// Something a concentration spell sustains.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DependentEffect {
+    Condition {
+        target_id: String,
+        condition_id: String,
+    },
+    Summon {
+        entity_id: String,
+    },
+    Zone {
+        zone_id: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum EndReason {
+    /// The caster started concentrating on another spell.
+    NewSpell,
+    FailedSave,
+    Incapacitated,
+    Died,
+    Voluntary,
+    DurationExpired,
+}
+
+impl EndReason {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EndReason::NewSpell => "new_spell",
+            EndReason::FailedSave => "failed_save",
+            EndReason::Incapacitated => "incapacitated",
+            EndReason::Died => "died",
+            EndReason::Voluntary => "voluntary",
+            EndReason::DurationExpired => "duration_expired",
+        }
+    }
+}
+
+/// The spell a caster is concentrating on and what depends on it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Concentration {
+    pub caster_id: String,
+    pub spell_id: String,
+    pub effects: Vec<DependentEffect>,
+}
+
+/// Concentration ended; `ended` is everything that ended with it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ConcentrationEnded {
+    pub caster_id: String,
+    pub spell_id: String,
+    pub reason: EndReason,
+    pub ended: Vec<DependentEffect>,
+}
+
+/// The Constitution saves forced by one batch of damage.
+#[derive(Debug, Clone, Default)]
+pub struct ConcentrationSaves {
+    /// One save per damage instance, stopping at the first failure.
+    pub saves: Vec<CheckOutcome>,
+    pub ended: Option<ConcentrationEnded>,
+}
+
+impl ConcentrationSaves {
+    pub fn maintained(&self) -> bool {
+        self.saves.iter().all(|s| s.success)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ConcentrationTracker {
+    active: HashMap<String, Concentration>,
+}
+
+impl ConcentrationTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn current(&self, caster_id: &str) -> Option<&Concentration> {
+        self.active.get(caster_id)
+    }
+
+    pub fn is_concentrating(&self, caster_id: &str) -> bool {
+        self.active.contains_key(caster_id)
+    }
+
+    /// Start concentrating on `spell_id`. A caster has one slot, so any
+    /// earlier spell ends first and is returned.
+    pub fn begin(
+        &mut self,
+        caster_id: &str,
+        spell_id: &str,
+        conditions: &mut ConditionManager,
+    ) -> Option<ConcentrationEnded> {
+        let previous = self.end(caster_id, EndReason::NewSpell, conditions);
+        self.active.insert(
+            caster_id.to_string(),
+            Concentration {
+                caster_id: caster_id.to_string(),
+                spell_id: spell_id.to_string(),
+                effects: Vec::new(),
+            },
+        );
+        previous
+    }
+
+    /// Tie `effect` to the caster's current spell.
+    pub fn add_effect(&mut self, caster_id: &str, effect: DependentEffect) -> Result<(), DndError> {
+        let concentration = self.active.get_mut(caster_id).ok_or_else(|| {
+            DndError::InvalidAction(format!("{} is not concentrating on a spell", caster_id))
+        })?;
+        if !concentration.effects.contains(&effect) {
+            concentration.effects.push(effect);
+        }
+        Ok(())
+    }
+
+    /// End the caster's concentration, removing its dependent conditions
+    /// along with any condition applied "on concentration" by the caster.
+    /// Summons and zones are reported for the caller to remove.
+    pub fn end(
+        &mut self,
+        caster_id: &str,
+        reason: EndReason,
+        conditions: &mut ConditionManager,
+    ) -> Option<ConcentrationEnded> {
+        let concentration = self.active.remove(caster_id)?;
+        let mut ended = Vec::new();
+        for effect in concentration.effects {
+            if let DependentEffect::Condition {
+                target_id,
+                condition_id,
+            } = &effect
+            {
+                if conditions
+                    .remove_condition(target_id, condition_id)
+                    .is_none()
+                {
+                    // Already saved against or removed some other way.
+                    continue;
+                }
+            }
+            ended.push(effect);
+        }
+        ended.extend(conditions.end_concentration(caster_id).into_iter().map(
+            |(target_id, condition_id)| DependentEffect::Condition {
+                target_id,
+                condition_id,
+            },
+        ));
+        Some(ConcentrationEnded {
+            caster_id: concentration.caster_id,
+            spell_id: concentration.spell_id,
+            reason,
+            ended,
+        })
+    }
+
+    /// Concentration ends as soon as the caster is incapacitated.
+    pub fn check_incapacitated(
+        &mut self,
+        caster_id: &str,
+        conditions: &mut ConditionManager,
+    ) -> Option<ConcentrationEnded> {
+        if !conditions.get_combined_effects(caster_id).cant_take_actions {
+            return None;
+        }
+        self.end(caster_id, EndReason::Incapacitated, conditions)
+    }
+
+    /// Make a Constitution save against DC max(10, half damage) for each
+    /// separate instance of damage the caster took. The first failure ends
+    /// concentration; no further saves are needed after that. `None` when
+    /// the caster isn't concentrating, so there is nothing to save for.
+    pub fn on_damage(
+        &mut self,
+        roller: &mut DiceRoller,
+        caster: &CreatureStats,
+        caster_conditions: &[ActiveCondition],
+        damage_instances: &[i32],
+        sources: &D20Sources,
+        conditions: &mut ConditionManager,
+    ) -> Option<ConcentrationSaves> {
+        if !self.is_concentrating(&caster.creature_id) {
+            return None;
+        }
+        let mut result = ConcentrationSaves::default();
+        for &damage in damage_instances.iter().filter(|d| **d > 0) {
+            let input = CheckInput::new(
+                CheckKind::SavingThrow(Ability::CON),
+                CombatEngine::concentration_dc(damage),
+            )
+            .with_sources(sources.clone());
+            let save = checks::resolve_check(roller, caster, caster_conditions, &input);
+            let failed = !save.success;
+            result.saves.push(save);
+            if failed {
+                result.ended = self.end(&caster.creature_id, EndReason::FailedSave, conditions);
+                break;
+            }
+        }
+        Some(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conditions::ConditionType;
+
+    fn caster(con: i32) -> CreatureStats {
+        let mut stats = CreatureStats {
+            creature_id: "wizard".to_string(),
+            ..Default::default()
+        };
+        stats.ability_scores.insert(Ability::CON, con);
+        stats
+    }
+
+    fn hold_person(manager: &mut ConditionManager, target: &str) -> String {
+        let id = format!("held-{}", target);
+        manager.apply_condition(
+            target,
+            ActiveCondition::new(id.clone(), ConditionType::Paralyzed).from_source("wizard"),
+        );
+        id
+    }
+
+    #[test]
+    fn test_new_spell_replaces_old_and_ends_its_effects() {
+        let mut manager = ConditionManager::new();
+        let mut tracker = ConcentrationTracker::new();
+        assert!(tracker
+            .begin("wizard", "hold-person", &mut manager)
+            .is_none());
+        let held = hold_person(&mut manager, "orc");
+        tracker
+            .add_effect(
+                "wizard",
+                DependentEffect::Condition {
+                    target_id: "orc".to_string(),
+                    condition_id: held.clone(),
+                },
+            )
+            .unwrap();
+
+        let ended = tracker.begin("wizard", "spirit-guardians", &mut manager);
+        let ended = ended.unwrap();
+        assert_eq!(ended.spell_id, "hold-person");
+        assert_eq!(ended.reason, EndReason::NewSpell);
+        assert_eq!(ended.ended.len(), 1);
+        assert!(manager.get_conditions("orc").is_empty());
+        assert_eq!(
+            tracker.current("wizard").unwrap().spell_id,
+            "spirit-guardians"
+        );
+    }
+
+    #[test]
+    fn test_end_reports_summons_zones_and_flagged_conditions() {
+        let mut manager = ConditionManager::new();
+        let mut tracker = ConcentrationTracker::new();
+        tracker.begin("wizard", "conjure-animals", &mut manager);
+        tracker
+            .add_effect(
+                "wizard",
+                DependentEffect::Summon {
+                    entity_id: "wolf-1".to_string(),
+                },
+            )
+            .unwrap();
+        tracker
+            .add_effect(
+                "wizard",
+                DependentEffect::Zone {
+                    zone_id: "fog".to_string(),
+                },
+            )
+            .unwrap();
+        manager.apply_condition(
+            "goblin",
+            ActiveCondition::new("faerie", ConditionType::Blinded)
+                .from_source("wizard")
+                .on_concentration(),
+        );
+
+        let ended = tracker
+            .end("wizard", EndReason::Voluntary, &mut manager)
+            .unwrap();
+        assert_eq!(ended.ended.len(), 3);
+        assert!(ended.ended.contains(&DependentEffect::Condition {
+            target_id: "goblin".to_string(),
+            condition_id: "faerie".to_string(),
+        }));
+        assert!(!tracker.is_concentrating("wizard"));
+        assert!(tracker
+            .add_effect(
+                "wizard",
+                DependentEffect::Zone {
+                    zone_id: "fog".to_string()
+                }
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn test_each_damage_instance_forces_a_save() {
+        let mut manager = ConditionManager::new();
+        let mut tracker = ConcentrationTracker::new();
+        tracker.begin("wizard", "bless", &mut manager);
+        // CON 30 (+10) beats DC 10 on any roll.
+        let tough = caster(30);
+        let mut roller = DiceRoller::with_seed(3);
+        let result = tracker
+            .on_damage(
+                &mut roller,
+                &tough,
+                &[],
+                &[4, 6, 0],
+                &D20Sources::new(),
+                &mut manager,
+            )
+            .unwrap();
+        assert_eq!(result.saves.len(), 2);
+        assert!(result.saves.iter().all(|s| s.dc == 10));
+
+        // CON 1 (-5) against DC 25 always fails, and stops after the first.
+        let frail = caster(1);
+        let result = tracker
+            .on_damage(
+                &mut roller,
+                &frail,
+                &[],
+                &[50, 50],
+                &D20Sources::new(),
+                &mut manager,
+            )
+            .unwrap();
+        assert_eq!(result.saves.len(), 1);
+        assert_eq!(result.saves[0].dc, 25);
+        assert!(!result.maintained());
+        assert_eq!(result.ended.unwrap().reason, EndReason::FailedSave);
+        assert!(!tracker.is_concentrating("wizard"));
+
+        // With nothing to hold on to, damage forces no save at all.
+        assert!(tracker
+            .on_damage(
+                &mut roller,
+                &frail,
+                &[],
+                &[50],
+                &D20Sources::new(),
+                &mut manager
+            )
+            .is_none());
+    }
+
+    #[test]
+    fn test_incapacitation_ends_concentration() {
+        let mut manager = ConditionManager::new();
+        let mut tracker = ConcentrationTracker::new();
+        tracker.begin("wizard", "fly", &mut manager);
+        assert!(tracker
+            .check_incapacitated("wizard", &mut manager)
+            .is_none());
+
+        manager.apply_condition("wizard", ConditionType::Stunned.into());
+        let ended = tracker.check_incapacitated("wizard", &mut manager).unwrap();
+        assert_eq!(ended.reason, EndReason::Incapacitated);
+    }
+}
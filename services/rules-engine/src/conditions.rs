@@ -0,0 +1,849 @@
+//! The fifteen PHB conditions and per-creature condition tracking.
+
+use crate::combat::Ability;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ConditionType {
+    Blinded,
+    Charmed,
+    Deafened,
+    Exhaustion,
+    Frightened,
+    Grappled,
+    Incapacitated,
+    Invisible,
+    Paralyzed,
+    Petrified,
+    Poisoned,
+    Prone,
+    Restrained,
+    Stunned,
+    Unconscious,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ConditionEffects {
+    pub attacks_have_disadvantage: bool,
+    pub attacks_have_advantage: bool,
+    pub attacks_against_have_advantage: bool,
+    pub attacks_against_have_disadvantage: bool,
+    pub melee_attacks_against_auto_crit: bool,
+    pub str_saves_auto_fail: bool,
+    pub dex_saves_auto_fail: bool,
+    pub dex_saves_have_disadvantage: bool,
+    pub saves_have_disadvantage: bool,
+    pub ability_checks_have_disadvantage: bool,
+    pub speed_is_zero: bool,
+    pub speed_halved: bool,
+    pub cant_move_closer_to_source: bool,
+    pub cant_take_actions: bool,
+    pub cant_take_reactions: bool,
+    pub cant_take_bonus_actions: bool,
+    pub drops_held_items: bool,
+    pub falls_prone: bool,
+    pub auto_fail_sight_checks: bool,
+    pub auto_fail_hearing_checks: bool,
+    pub hp_max_halved: bool,
+    pub dies: bool,
+}
+
+impl ConditionEffects {
+    /// Merge another set of effects into this one; any flag set by either wins.
+    pub fn merge(&mut self, other: &ConditionEffects) {
+        self.attacks_have_disadvantage |= other.attacks_have_disadvantage;
+        self.attacks_have_advantage |= other.attacks_have_advantage;
+        self.attacks_against_have_advantage |= other.attacks_against_have_advantage;
+        self.attacks_against_have_disadvantage |= other.attacks_against_have_disadvantage;
+        self.melee_attacks_against_auto_crit |= other.melee_attacks_against_auto_crit;
+        self.str_saves_auto_fail |= other.str_saves_auto_fail;
+        self.dex_saves_auto_fail |= other.dex_saves_auto_fail;
+        self.dex_saves_have_disadvantage |= other.dex_saves_have_disadvantage;
+        self.saves_have_disadvantage |= other.saves_have_disadvantage;
+        self.ability_checks_have_disadvantage |= other.ability_checks_have_disadvantage;
+        self.speed_is_zero |= other.speed_is_zero;
+        self.speed_halved |= other.speed_halved;
+        self.cant_move_closer_to_source |= other.cant_move_closer_to_source;
+        self.cant_take_actions |= other.cant_take_actions;
+        self.cant_take_reactions |= other.cant_take_reactions;
+        self.cant_take_bonus_actions |= other.cant_take_bonus_actions;
+        self.drops_held_items |= other.drops_held_items;
+        self.falls_prone |= other.falls_prone;
+        self.auto_fail_sight_checks |= other.auto_fail_sight_checks;
+        self.auto_fail_hearing_checks |= other.auto_fail_hearing_checks;
+        self.hp_max_halved |= other.hp_max_halved;
+        self.dies |= other.dies;
+    }
+
+    /// Human-readable list of the active flags, for logs and the UI.
+    pub fn describe(&self) -> Vec<String> {
+        [
+            (self.attacks_have_disadvantage, "attacks_have_disadvantage"),
+            (self.attacks_have_advantage, "attacks_have_advantage"),
+            (
+                self.attacks_against_have_advantage,
+                "attacks_against_have_advantage",
+            ),
+            (
+                self.attacks_against_have_disadvantage,
+                "attacks_against_have_disadvantage",
+            ),
+            (
+                self.melee_attacks_against_auto_crit,
+                "melee_attacks_against_auto_crit",
+            ),
+            (self.str_saves_auto_fail, "str_saves_auto_fail"),
+            (self.dex_saves_auto_fail, "dex_saves_auto_fail"),
+            (
+                self.dex_saves_have_disadvantage,
+                "dex_saves_have_disadvantage",
+            ),
+            (self.saves_have_disadvantage, "saves_have_disadvantage"),
+            (
+                self.ability_checks_have_disadvantage,
+                "ability_checks_have_disadvantage",
+            ),
+            (self.speed_is_zero, "speed_is_zero"),
+            (self.speed_halved, "speed_halved"),
+            (
+                self.cant_move_closer_to_source,
+                "cant_move_closer_to_source",
+            ),
+            (self.cant_take_actions, "cant_take_actions"),
+            (self.cant_take_reactions, "cant_take_reactions"),
+            (self.cant_take_bonus_actions, "cant_take_bonus_actions"),
+            (self.drops_held_items, "drops_held_items"),
+            (self.falls_prone, "falls_prone"),
+            (self.auto_fail_sight_checks, "auto_fail_sight_checks"),
+            (self.auto_fail_hearing_checks, "auto_fail_hearing_checks"),
+            (self.hp_max_halved, "hp_max_halved"),
+            (self.dies, "dies"),
+        ]
+        .into_iter()
+        .filter(|(active, _)| *active)
+        .map(|(_, name)| name.to_string())
+        .collect()
+    }
+}
+
+impl ConditionType {
+    /// Parse a condition name as used in CreatureStats ("PARALYZED", "Prone").
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_uppercase().as_str() {
+            "BLINDED" => Some(ConditionType::Blinded),
+            "CHARMED" => Some(ConditionType::Charmed),
+            "DEAFENED" => Some(ConditionType::Deafened),
+            "EXHAUSTION" => Some(ConditionType::Exhaustion),
+            "FRIGHTENED" => Some(ConditionType::Frightened),
+            "GRAPPLED" => Some(ConditionType::Grappled),
+            "INCAPACITATED" => Some(ConditionType::Incapacitated),
+            "INVISIBLE" => Some(ConditionType::Invisible),
+            "PARALYZED" => Some(ConditionType::Paralyzed),
+            "PETRIFIED" => Some(ConditionType::Petrified),
+            "POISONED" => Some(ConditionType::Poisoned),
+            "PRONE" => Some(ConditionType::Prone),
+            "RESTRAINED" => Some(ConditionType::Restrained),
+            "STUNNED" => Some(ConditionType::Stunned),
+            "UNCONSCIOUS" => Some(ConditionType::Unconscious),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ConditionType::Blinded => "BLINDED",
+            ConditionType::Charmed => "CHARMED",
+            ConditionType::Deafened => "DEAFENED",
+            ConditionType::Exhaustion => "EXHAUSTION",
+            ConditionType::Frightened => "FRIGHTENED",
+            ConditionType::Grappled => "GRAPPLED",
+            ConditionType::Incapacitated => "INCAPACITATED",
+            ConditionType::Invisible => "INVISIBLE",
+            ConditionType::Paralyzed => "PARALYZED",
+            ConditionType::Petrified => "PETRIFIED",
+            ConditionType::Poisoned => "POISONED",
+            ConditionType::Prone => "PRONE",
+            ConditionType::Restrained => "RESTRAINED",
+            ConditionType::Stunned => "STUNNED",
+            ConditionType::Unconscious => "UNCONSCIOUS",
+        }
+    }
+
+    pub fn get_effects(&self) -> ConditionEffects {
+        match self {
+            ConditionType::Blinded => ConditionEffects {
+                attacks_have_disadvantage: true,
+                attacks_against_have_advantage: true,
+                auto_fail_sight_checks: true,
+                ..Default::default()
+            },
+            ConditionType::Charmed => ConditionEffects::default(),
+            ConditionType::Deafened => ConditionEffects {
+                auto_fail_hearing_checks: true,
+                ..Default::default()
+            },
+            ConditionType::Exhaustion => ConditionEffects {
+                ability_checks_have_disadvantage: true, // Level 1+
+                ..Default::default()
+            },
+            ConditionType::Frightened => ConditionEffects {
+                attacks_have_disadvantage: true,
+                ability_checks_have_disadvantage: true,
+                cant_move_closer_to_source: true,
+                ..Default::default()
+            },
+            ConditionType::Grappled => ConditionEffects {
+                speed_is_zero: true,
+                ..Default::default()
+            },
+            ConditionType::Incapacitated => ConditionEffects {
+                cant_take_actions: true,
+                cant_take_reactions: true,
+                ..Default::default()
+            },
+            ConditionType::Invisible => ConditionEffects {
+                attacks_have_advantage: true,
+                attacks_against_have_disadvantage: true,
+                ..Default::default()
+            },
+            ConditionType::Paralyzed => ConditionEffects {
+                cant_take_actions: true,
+                cant_take_reactions: true,
+                speed_is_zero: true,
+                str_saves_auto_fail: true,
+                dex_saves_auto_fail: true,
+                attacks_against_have_advantage: true,
+                melee_attacks_against_auto_crit: true,
+                ..Default::default()
+            },
+            ConditionType::Petrified => ConditionEffects {
+                cant_take_actions: true,
+                cant_take_reactions: true,
+                speed_is_zero: true,
+                str_saves_auto_fail: true,
+                dex_saves_auto_fail: true,
+                attacks_against_have_advantage: true,
+                ..Default::default()
+            },
+            ConditionType::Poisoned => ConditionEffects {
+                attacks_have_disadvantage: true,
+                ability_checks_have_disadvantage: true,
+                ..Default::default()
+            },
+            ConditionType::Prone => ConditionEffects {
+                attacks_have_disadvantage: true,
+                ..Default::default()
+            },
+            ConditionType::Restrained => ConditionEffects {
+                speed_is_zero: true,
+                attacks_have_disadvantage: true,
+                attacks_against_have_advantage: true,
+                dex_saves_have_disadvantage: true,
+                ..Default::default()
+            },
+            ConditionType::Stunned => ConditionEffects {
+                cant_take_actions: true,
+                cant_take_reactions: true,
+                speed_is_zero: true,
+                str_saves_auto_fail: true,
+                dex_saves_auto_fail: true,
+                attacks_against_have_advantage: true,
+                ..Default::default()
+            },
+            ConditionType::Unconscious => ConditionEffects {
+                cant_take_actions: true,
+                cant_take_reactions: true,
+                speed_is_zero: true,
+                str_saves_auto_fail: true,
+                dex_saves_auto_fail: true,
+                attacks_against_have_advantage: true,
+                melee_attacks_against_auto_crit: true,
+                drops_held_items: true,
+                falls_prone: true,
+                ..Default::default()
+            },
+        }
+    }
+}
+
+/// Highest exhaustion level; a creature at this level dies (PHB p.291).
+pub const MAX_EXHAUSTION: i32 = 6;
+
+/// Cumulative effects of `level` levels of exhaustion.
+pub fn exhaustion_effects(level: i32) -> ConditionEffects {
+    ConditionEffects {
+        ability_checks_have_disadvantage: level >= 1,
+        speed_halved: (2..5).contains(&level),
+        attacks_have_disadvantage: level >= 3,
+        saves_have_disadvantage: level >= 3,
+        hp_max_halved: level >= 4,
+        speed_is_zero: level >= 5,
+        dies: level >= MAX_EXHAUSTION,
+        ..Default::default()
+    }
+}
+
+impl ConditionType {
+    /// Conditions that come with this one and end with it (PHB p.290-292).
+    pub fn implied(&self) -> &'static [ConditionType] {
+        match self {
+            ConditionType::Paralyzed | ConditionType::Petrified | ConditionType::Stunned => {
+                &[ConditionType::Incapacitated]
+            }
+            ConditionType::Unconscious => &[ConditionType::Incapacitated],
+            _ => &[],
+        }
+    }
+}
+
+/// Combined effects of a list of condition names, ignoring unknown names.
+pub fn combined_effects_of(names: &[String]) -> ConditionEffects {
+    let mut combined = ConditionEffects::default();
+    for condition in names.iter().filter_map(|n| ConditionType::from_name(n)) {
+        combined.merge(&condition.get_effects());
+    }
+    combined
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConditionDuration {
+    /// Ticks down at the start of the affected creature's turns.
+    Rounds(i32),
+    /// Ends when the source creature has finished this many more turns;
+    /// "until the end of your next turn" is resolved to 1 or 2 on apply.
+    SourceTurnEnds(i32),
+    UntilRemoved,
+}
+
+impl ConditionDuration {
+    /// "Until the end of the source's next turn".
+    pub fn end_of_source_next_turn() -> Self {
+        ConditionDuration::SourceTurnEnds(1)
+    }
+}
+
+/// A save the creature repeats to end the condition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SaveToEnd {
+    pub ability: Ability,
+    pub dc: i32,
+    /// At the end of each of its turns; otherwise only when prompted, e.g.
+    /// when it takes damage or uses its action.
+    pub at_end_of_turn: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ActiveCondition {
+    pub id: String,
+    pub condition_type: ConditionType,
+    pub source_id: Option<String>,
+    pub duration: ConditionDuration,
+    pub save: Option<SaveToEnd>,
+    /// Ends when the source stops concentrating on the effect.
+    pub concentration: bool,
+    pub exhaustion_level: Option<i32>,
+    /// The condition this one was implied by, e.g. Incapacitated from Stunned.
+    pub implied_by: Option<String>,
+}
+
+impl ActiveCondition {
+    pub fn new(id: impl Into<String>, condition_type: ConditionType) -> Self {
+        Self {
+            id: id.into(),
+            condition_type,
+            source_id: None,
+            duration: ConditionDuration::UntilRemoved,
+            save: None,
+            concentration: false,
+            exhaustion_level: (condition_type == ConditionType::Exhaustion).then_some(1),
+            implied_by: None,
+        }
+    }
+
+    pub fn from_source(mut self, source_id: impl Into<String>) -> Self {
+        self.source_id = Some(source_id.into());
+        self
+    }
+
+    pub fn lasting(mut self, duration: ConditionDuration) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    pub fn with_save(mut self, save: SaveToEnd) -> Self {
+        self.save = Some(save);
+        self
+    }
+
+    pub fn on_concentration(mut self) -> Self {
+        self.concentration = true;
+        self
+    }
+
+    pub fn with_exhaustion_level(mut self, level: i32) -> Self {
+        self.exhaustion_level = Some(level.clamp(1, MAX_EXHAUSTION));
+        self
+    }
+
+    pub fn remaining_rounds(&self) -> Option<i32> {
+        match self.duration {
+            ConditionDuration::Rounds(rounds) => Some(rounds),
+            _ => None,
+        }
+    }
+
+    /// Effects of this instance, taking the exhaustion level into account.
+    pub fn effects(&self) -> ConditionEffects {
+        match self.exhaustion_level {
+            Some(level) if self.condition_type == ConditionType::Exhaustion => {
+                exhaustion_effects(level)
+            }
+            _ => self.condition_type.get_effects(),
+        }
+    }
+
+    pub fn label(&self) -> String {
+        match self.exhaustion_level {
+            Some(level) if self.condition_type == ConditionType::Exhaustion => {
+                format!("Exhaustion {}", level)
+            }
+            _ => format!("{:?}", self.condition_type),
+        }
+    }
+}
+
+/// An untracked instance, e.g. a condition named on a stats snapshot.
+impl From<ConditionType> for ActiveCondition {
+    fn from(condition_type: ConditionType) -> Self {
+        ActiveCondition::new(condition_type.name().to_lowercase(), condition_type)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ApplyOutcome {
+    /// Applied, with the ids of any implied conditions added alongside and
+    /// of the Prone condition an unconscious creature falls into.
+    Applied {
+        implied: Vec<String>,
+        fell_prone: Option<String>,
+    },
+    /// Exhaustion levels were added to the existing instance.
+    ExhaustionIncreased { level: i32 },
+    /// The same condition from the same source is already active.
+    AlreadyActive,
+}
+
+impl ApplyOutcome {
+    pub fn applied(&self) -> bool {
+        *self != ApplyOutcome::AlreadyActive
+    }
+}
+
+pub struct ConditionManager {
+    active_conditions: HashMap<String, Vec<ActiveCondition>>,
+    /// Whose turn it is, for "until the end of your next turn".
+    current_turn: Option<String>,
+}
+
+impl ConditionManager {
+    pub fn new() -> Self {
+        Self {
+            active_conditions: HashMap::new(),
+            current_turn: None,
+        }
+    }
+
+    pub fn apply_condition(
+        &mut self,
+        creature_id: &str,
+        mut condition: ActiveCondition,
+    ) -> ApplyOutcome {
+        let conditions = self
+            .active_conditions
+            .entry(creature_id.to_string())
+            .or_default();
+
+        // Exhaustion is one track of levels, not separate instances.
+        if condition.condition_type == ConditionType::Exhaustion {
+            if let Some(existing) = conditions
+                .iter_mut()
+                .find(|c| c.condition_type == ConditionType::Exhaustion)
+            {
+                let level = (existing.exhaustion_level.unwrap_or(1)
+                    + condition.exhaustion_level.unwrap_or(1))
+                .min(MAX_EXHAUSTION);
+                existing.exhaustion_level = Some(level);
+                return ApplyOutcome::ExhaustionIncreased { level };
+            }
+        }
+
+        // The same condition from the same source doesn't apply twice.
+        // Instances from different sources coexist, each with its own
+        // duration, but their effects don't stack.
+        let already_active = conditions.iter().any(|c| {
+            c.condition_type == condition.condition_type
+                && c.source_id == condition.source_id
+                && c.implied_by.is_none()
+        });
+        if already_active {
+            return ApplyOutcome::AlreadyActive;
+        }
+
+        if let ConditionDuration::SourceTurnEnds(_) = condition.duration {
+            let during_source_turn =
+                condition.source_id.is_some() && condition.source_id == self.current_turn;
+            condition.duration =
+                ConditionDuration::SourceTurnEnds(if during_source_turn { 2 } else { 1 });
+        }
+
+        let implied: Vec<ActiveCondition> = condition
+            .condition_type
+            .implied()
+            .iter()
+            .map(|implied_type| ActiveCondition {
+                id: format!("{}:{}", condition.id, implied_type.name().to_lowercase()),
+                condition_type: *implied_type,
+                implied_by: Some(condition.id.clone()),
+                save: None,
+                exhaustion_level: None,
+                ..condition.clone()
+            })
+            .collect();
+        let implied_ids = implied.iter().map(|c| c.id.clone()).collect();
+
+        // A creature falling unconscious drops prone, and is still prone
+        // when it comes to (PHB p.292), so Prone is its own condition
+        // rather than one that ends with Unconscious.
+        let prone = (condition.condition_type == ConditionType::Unconscious
+            && !conditions
+                .iter()
+                .any(|c| c.condition_type == ConditionType::Prone))
+        .then(|| ActiveCondition {
+            id: format!("{}:prone", condition.id),
+            source_id: condition.source_id.clone(),
+            ..ActiveCondition::new("", ConditionType::Prone)
+        });
+        let fell_prone = prone.as_ref().map(|c| c.id.clone());
+
+        conditions.push(condition);
+        conditions.extend(implied);
+        conditions.extend(prone);
+        ApplyOutcome::Applied {
+            implied: implied_ids,
+            fell_prone,
+        }
+    }
+
+    /// Remove a condition and everything it implied.
+    pub fn remove_condition(
+        &mut self,
+        creature_id: &str,
+        condition_id: &str,
+    ) -> Option<ActiveCondition> {
+        let conditions = self.active_conditions.get_mut(creature_id)?;
+        let index = conditions.iter().position(|c| c.id == condition_id)?;
+        let removed = conditions.remove(index);
+        conditions.retain(|c| c.implied_by.as_deref() != Some(condition_id));
+        Some(removed)
+    }
+
+    /// Remove matching conditions on every creature, as (creature, condition) IDs.
+    fn remove_where(
+        &mut self,
+        mut matches: impl FnMut(&ActiveCondition) -> bool,
+    ) -> Vec<(String, String)> {
+        self.active_conditions
+            .iter_mut()
+            .flat_map(|(creature_id, conditions)| {
+                remove_ended(conditions, &mut matches)
+                    .into_iter()
+                    .map(move |id| (creature_id.clone(), id))
+            })
+            .collect()
+    }
+
+    /// End every condition `source_id` maintains by concentration. Returns
+    /// (creature, condition) ID pairs.
+    pub fn end_concentration(&mut self, source_id: &str) -> Vec<(String, String)> {
+        self.remove_where(|c| c.concentration && c.source_id.as_deref() == Some(source_id))
+    }
+
+    /// Record the result of a save against a condition; a success ends it.
+    pub fn resolve_save(
+        &mut self,
+        creature_id: &str,
+        condition_id: &str,
+        succeeded: bool,
+    ) -> Option<ActiveCondition> {
+        if !succeeded {
+            return None;
+        }
+        self.remove_condition(creature_id, condition_id)
+    }
+
+    pub fn get_conditions(&self, creature_id: &str) -> Vec<&ActiveCondition> {
+        self.active_conditions
+            .get(creature_id)
+            .map(|c| c.iter().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn has_condition(&self, creature_id: &str, condition_type: ConditionType) -> bool {
+        self.get_conditions(creature_id)
+            .iter()
+            .any(|c| c.condition_type == condition_type)
+    }
+
+    pub fn exhaustion_level(&self, creature_id: &str) -> i32 {
+        self.get_conditions(creature_id)
+            .iter()
+            .find(|c| c.condition_type == ConditionType::Exhaustion)
+            .and_then(|c| c.exhaustion_level)
+            .unwrap_or(0)
+    }
+
+    /// Remove `levels` of exhaustion, e.g. one per long rest. Returns the new level.
+    pub fn reduce_exhaustion(&mut self, creature_id: &str, levels: i32) -> i32 {
+        let Some(conditions) = self.active_conditions.get_mut(creature_id) else {
+            return 0;
+        };
+        let Some(index) = conditions
+            .iter()
+            .position(|c| c.condition_type == ConditionType::Exhaustion)
+        else {
+            return 0;
+        };
+        let level = conditions[index].exhaustion_level.unwrap_or(1) - levels.max(0);
+        if level <= 0 {
+            conditions.remove(index);
+            0
+        } else {
+            conditions[index].exhaustion_level = Some(level);
+            level
+        }
+    }
+
+    pub fn get_combined_effects(&self, creature_id: &str) -> ConditionEffects {
+        let mut combined = ConditionEffects::default();
+        for condition in self.get_conditions(creature_id) {
+            combined.merge(&condition.effects());
+        }
+        combined
+    }
+
+    /// Tick down round-based durations. Returns the IDs of expired conditions.
+    pub fn process_turn_start(&mut self, creature_id: &str) -> Vec<String> {
+        self.current_turn = Some(creature_id.to_string());
+        let Some(conditions) = self.active_conditions.get_mut(creature_id) else {
+            return Vec::new();
+        };
+        for condition in conditions.iter_mut().filter(|c| c.implied_by.is_none()) {
+            if let ConditionDuration::Rounds(ref mut remaining) = condition.duration {
+                *remaining -= 1;
+            }
+        }
+        remove_ended(
+            conditions,
+            |c| matches!(c.duration, ConditionDuration::Rounds(r) if r <= 0),
+        )
+    }
+
+    /// End-of-turn bookkeeping for `creature_id`: conditions it imposed
+    /// "until the end of its next turn" count down. Returns expired IDs.
+    pub fn process_turn_end(&mut self, creature_id: &str) -> Vec<String> {
+        for condition in self
+            .active_conditions
+            .values_mut()
+            .flatten()
+            .filter(|c| c.implied_by.is_none() && c.source_id.as_deref() == Some(creature_id))
+        {
+            if let ConditionDuration::SourceTurnEnds(ref mut remaining) = condition.duration {
+                *remaining -= 1;
+            }
+        }
+        if self.current_turn.as_deref() == Some(creature_id) {
+            self.current_turn = None;
+        }
+        self.remove_where(|c| matches!(c.duration, ConditionDuration::SourceTurnEnds(r) if r <= 0))
+            .into_iter()
+            .map(|(_, id)| id)
+            .collect()
+    }
+}
+
+/// Remove the conditions matching `matches` and everything they implied.
+fn remove_ended(
+    conditions: &mut Vec<ActiveCondition>,
+    mut matches: impl FnMut(&ActiveCondition) -> bool,
+) -> Vec<String> {
+    let ended: Vec<String> = conditions
+        .iter()
+        .filter(|c| c.implied_by.is_none() && matches(c))
+        .map(|c| c.id.clone())
+        .collect();
+    conditions.retain(|c| {
+        !ended.contains(&c.id) && !c.implied_by.as_ref().is_some_and(|p| ended.contains(p))
+    });
+    ended
+}
+
+impl Default for ConditionManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn condition(id: &str, condition_type: ConditionType, rounds: Option<i32>) -> ActiveCondition {
+        let condition = ActiveCondition::new(id, condition_type).from_source("caster");
+        match rounds {
+            Some(rounds) => condition.lasting(ConditionDuration::Rounds(rounds)),
+            None => condition,
+        }
+    }
+
+    #[test]
+    fn test_duplicate_from_same_source_rejected() {
+        let mut manager = ConditionManager::new();
+        assert!(manager
+            .apply_condition("orc", condition("a", ConditionType::Prone, None))
+            .applied());
+        assert!(!manager
+            .apply_condition("orc", condition("b", ConditionType::Prone, None))
+            .applied());
+    }
+
+    #[test]
+    fn test_combined_effects() {
+        let mut manager = ConditionManager::new();
+        manager.apply_condition("orc", condition("a", ConditionType::Paralyzed, None));
+
+        let effects = manager.get_combined_effects("orc");
+        assert!(effects.str_saves_auto_fail);
+        assert!(effects.melee_attacks_against_auto_crit);
+    }
+
+    #[test]
+    fn test_round_durations_expire() {
+        let mut manager = ConditionManager::new();
+        manager.apply_condition("orc", condition("a", ConditionType::Blinded, Some(1)));
+
+        assert_eq!(manager.process_turn_start("orc"), vec!["a".to_string()]);
+        assert!(manager.get_conditions("orc").is_empty());
+    }
+
+    #[test]
+    fn test_implied_conditions_end_with_their_parent() {
+        let mut manager = ConditionManager::new();
+        let outcome =
+            manager.apply_condition("orc", condition("a", ConditionType::Unconscious, None));
+        assert_eq!(
+            outcome,
+            ApplyOutcome::Applied {
+                implied: vec!["a:incapacitated".to_string()],
+                fell_prone: Some("a:prone".to_string()),
+            }
+        );
+        assert!(manager.has_condition("orc", ConditionType::Incapacitated));
+        assert!(manager.has_condition("orc", ConditionType::Prone));
+
+        // Coming to ends Incapacitated, but the orc still has to stand up.
+        manager.remove_condition("orc", "a");
+        assert!(!manager.has_condition("orc", ConditionType::Incapacitated));
+        assert!(manager.has_condition("orc", ConditionType::Prone));
+        manager.remove_condition("orc", "a:prone");
+        assert!(manager.get_conditions("orc").is_empty());
+    }
+
+    #[test]
+    fn test_unconscious_while_prone_adds_no_second_prone() {
+        let mut manager = ConditionManager::new();
+        manager.apply_condition("orc", condition("a", ConditionType::Prone, None));
+        let outcome =
+            manager.apply_condition("orc", condition("b", ConditionType::Unconscious, None));
+        assert!(matches!(
+            outcome,
+            ApplyOutcome::Applied {
+                fell_prone: None,
+                ..
+            }
+        ));
+        manager.remove_condition("orc", "b");
+        assert_eq!(manager.get_conditions("orc").len(), 1);
+    }
+
+    #[test]
+    fn test_exhaustion_levels_accumulate() {
+        let mut manager = ConditionManager::new();
+        let exhaustion = |id: &str, level| {
+            ActiveCondition::new(id, ConditionType::Exhaustion).with_exhaustion_level(level)
+        };
+        manager.apply_condition("pc", exhaustion("a", 2));
+        assert_eq!(
+            manager.apply_condition("pc", exhaustion("b", 1)),
+            ApplyOutcome::ExhaustionIncreased { level: 3 }
+        );
+        let effects = manager.get_combined_effects("pc");
+        assert!(effects.speed_halved && effects.saves_have_disadvantage);
+        assert!(!effects.hp_max_halved);
+
+        manager.apply_condition("pc", exhaustion("c", 5));
+        assert_eq!(manager.exhaustion_level("pc"), 6);
+        assert!(manager.get_combined_effects("pc").dies);
+
+        assert_eq!(manager.reduce_exhaustion("pc", 1), 5);
+        assert!(manager.get_combined_effects("pc").speed_is_zero);
+    }
+
+    #[test]
+    fn test_until_end_of_source_next_turn() {
+        let mut manager = ConditionManager::new();
+        manager.process_turn_start("paladin");
+        manager.apply_condition(
+            "orc",
+            ActiveCondition::new("a", ConditionType::Frightened)
+                .from_source("paladin")
+                .lasting(ConditionDuration::end_of_source_next_turn()),
+        );
+
+        assert!(manager.process_turn_end("paladin").is_empty());
+        manager.process_turn_start("orc");
+        assert!(manager.process_turn_end("orc").is_empty());
+        manager.process_turn_start("paladin");
+        assert_eq!(manager.process_turn_end("paladin"), vec!["a".to_string()]);
+    }
+
+    #[test]
+    fn test_concentration_and_saves_end_conditions() {
+        let mut manager = ConditionManager::new();
+        let save = SaveToEnd {
+            ability: Ability::WIS,
+            dc: 15,
+            at_end_of_turn: true,
+        };
+        manager.apply_condition(
+            "orc",
+            condition("held", ConditionType::Paralyzed, Some(10))
+                .on_concentration()
+                .with_save(save),
+        );
+        manager.apply_condition(
+            "goblin",
+            condition("also-held", ConditionType::Paralyzed, Some(10)).on_concentration(),
+        );
+
+        assert!(manager.resolve_save("orc", "held", false).is_none());
+        assert!(manager.resolve_save("orc", "held", true).is_some());
+        assert!(manager.get_conditions("orc").is_empty());
+
+        assert_eq!(
+            manager.end_concentration("caster"),
+            vec![("goblin".to_string(), "also-held".to_string())]
+        );
+        assert!(manager.get_conditions("goblin").is_empty());
+    }
+}
@@ -0,0 +1,29 @@
+//! Service configuration loaded from the environment.
+
+use crate::rest::RestVariant;
+use anyhow::{anyhow, Result};
+use std::env;
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub port: u16,
+    pub log_level: String,
+    /// Rest lengths for requests that don't name a variant.
+    pub rest_variant: RestVariant,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            port: env::var("RULES_ENGINE_PORT")
+                .unwrap_or_else(|_| "50051".to_string())
+                .parse()?,
+            log_level: env::var("LOG_LEVEL").unwrap_or_else(|_| "info".to_string()),
+            rest_variant: match env::var("RULES_REST_VARIANT") {
+                Ok(name) => RestVariant::from_name(&name)
+                    .ok_or_else(|| anyhow!("Unknown rest variant: {}", name))?,
+                Err(_) => RestVariant::default(),
+            },
+        })
+    }
+}
@@ -0,0 +1,381 @@
+//! Typed damage against a creature's or object's defenses (PHB p.196-197,
+//! DMG p.246-247).
+//!
+//! Each instance goes through the same steps in RAW order: immunity, flat
+//! reductions (Heavy Armor Master), resistance, then vulnerability. An
+//! object's damage threshold is checked against the whole attack or effect,
+//! and temporary hit points soak whatever is left before hit points do.
+
+use crate::combat::CreatureStats;
+use crate::dice::{DiceExpression, DiceRoller, DieRoll};
+use serde::{Deserialize, Serialize};
+use shared_rust::{DamageInstance, DamageType, DndError};
+use std::collections::{BTreeMap, HashSet};
+
+/// Which damage a flat reduction applies to.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ReductionScope {
+    All,
+    /// Bludgeoning, piercing and slashing from nonmagical attacks.
+    NonmagicalPhysical,
+    Types(Vec<DamageType>),
+}
+
+impl ReductionScope {
+    fn applies_to(&self, instance: &DamageInstance) -> bool {
+        match self {
+            ReductionScope::All => true,
+            ReductionScope::NonmagicalPhysical => instance.is_nonmagical_physical(),
+            ReductionScope::Types(types) => types.contains(&instance.damage_type),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FlatReduction {
+    pub source: String,
+    pub amount: i32,
+    pub scope: ReductionScope,
+}
+
+impl FlatReduction {
+    /// Heavy Armor Master: nonmagical B/P/S damage is reduced by 3.
+    pub fn heavy_armor_master() -> Self {
+        Self {
+            source: "Heavy Armor Master".to_string(),
+            amount: 3,
+            scope: ReductionScope::NonmagicalPhysical,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DamageDefenses {
+    pub resistances: HashSet<DamageType>,
+    pub vulnerabilities: HashSet<DamageType>,
+    pub immunities: HashSet<DamageType>,
+    /// "Resistance to bludgeoning, piercing, and slashing from nonmagical
+    /// attacks", as on many monster stat blocks.
+    pub nonmagical_physical_resistance: bool,
+    pub nonmagical_physical_immunity: bool,
+    pub reductions: Vec<FlatReduction>,
+    /// Objects only: a single attack or effect must deal at least this much
+    /// or the object takes none of it.
+    pub damage_threshold: Option<i32>,
+}
+
+impl DamageDefenses {
+    pub fn from_stats(stats: &CreatureStats) -> Self {
+        Self {
+            resistances: stats.resistances.clone(),
+            vulnerabilities: stats.vulnerabilities.clone(),
+            immunities: stats.immunities.clone(),
+            nonmagical_physical_resistance: stats.nonmagical_physical_resistance,
+            nonmagical_physical_immunity: stats.nonmagical_physical_immunity,
+            reductions: stats.damage_reductions.clone(),
+            damage_threshold: None,
+        }
+    }
+
+    pub fn with_reduction(mut self, reduction: FlatReduction) -> Self {
+        self.reductions.push(reduction);
+        self
+    }
+
+    pub fn with_threshold(mut self, threshold: i32) -> Self {
+        self.damage_threshold = Some(threshold);
+        self
+    }
+
+    fn is_immune(&self, instance: &DamageInstance) -> bool {
+        self.immunities.contains(&instance.damage_type)
+            || (self.nonmagical_physical_immunity && instance.is_nonmagical_physical())
+    }
+
+    fn is_resistant(&self, instance: &DamageInstance) -> bool {
+        self.resistances.contains(&instance.damage_type)
+            || (self.nonmagical_physical_resistance && instance.is_nonmagical_physical())
+    }
+}
+
+/// One instance after defenses, with each step that changed it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AppliedDamage {
+    pub instance: DamageInstance,
+    pub immune: bool,
+    pub reduced_by: i32,
+    pub resisted: bool,
+    pub vulnerable: bool,
+    pub final_amount: i32,
+    pub steps: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DamageReport {
+    pub instances: Vec<AppliedDamage>,
+    /// Final damage per type after defenses, before temporary hit points.
+    pub by_type: BTreeMap<DamageType, i32>,
+    pub total: i32,
+    /// The total fell short of an object's damage threshold.
+    pub below_threshold: bool,
+    pub absorbed_by_temp_hp: i32,
+    pub temp_hp_remaining: i32,
+    /// What is left for hit points after temporary hit points.
+    pub hp_damage: i32,
+}
+
+/// Run a single instance through immunity, reductions, resistance and
+/// vulnerability.
+pub fn apply_defenses(instance: DamageInstance, defenses: &DamageDefenses) -> AppliedDamage {
+    let mut applied = AppliedDamage {
+        instance,
+        immune: false,
+        reduced_by: 0,
+        resisted: false,
+        vulnerable: false,
+        final_amount: instance.amount,
+        steps: vec![format!("{} {}", instance.amount, instance.damage_type)],
+    };
+    if defenses.is_immune(&instance) {
+        applied.immune = true;
+        applied.final_amount = 0;
+        applied.steps.push("immune: 0".to_string());
+        return applied;
+    }
+
+    for reduction in defenses
+        .reductions
+        .iter()
+        .filter(|r| r.scope.applies_to(&instance))
+    {
+        let before = applied.final_amount;
+        applied.final_amount = (before - reduction.amount).max(0);
+        applied.reduced_by += before - applied.final_amount;
+        applied.steps.push(format!(
+            "{} -{}: {}",
+            reduction.source, reduction.amount, applied.final_amount
+        ));
+    }
+    if defenses.is_resistant(&instance) {
+        applied.resisted = true;
+        applied.final_amount /= 2;
+        applied
+            .steps
+            .push(format!("resistance: {}", applied.final_amount));
+    }
+    if defenses.vulnerabilities.contains(&instance.damage_type) {
+        applied.vulnerable = true;
+        applied.final_amount *= 2;
+        applied
+            .steps
+            .push(format!("vulnerability: {}", applied.final_amount));
+    }
+    applied
+}
+
+/// Roll one instance's dice and add `modifier`; a critical hit rolls the
+/// dice twice. Returns the dice and the amount, which is at least 0.
+pub fn roll_damage(
+    roller: &mut DiceRoller,
+    expression: &DiceExpression,
+    modifier: i32,
+    critical: bool,
+) -> (Vec<DieRoll>, i32) {
+    let roll = expression.roll(roller);
+    let mut rolls = roll.rolls;
+    let mut dice_total = roll.total;
+    if critical {
+        // Only the dice double; subtracting the flat part keeps the sign of
+        // negative dice terms such as `1d6-1d4`.
+        let crit = expression.roll(roller);
+        dice_total = dice_total.saturating_add(crit.total - expression.modifier());
+        rolls.extend(crit.rolls);
+    }
+    (rolls, dice_total.saturating_add(modifier).max(0))
+}
+
+/// Apply the damage of one attack or effect to `defenses`, then let
+/// `temp_hp` absorb what it can.
+pub fn resolve_damage(
+    instances: &[DamageInstance],
+    defenses: &DamageDefenses,
+    temp_hp: i32,
+) -> Result<DamageReport, DndError> {
+    if let Some(bad) = instances.iter().find(|i| i.amount < 0) {
+        return Err(DndError::NegativeDamage {
+            damage_type: bad.damage_type,
+            amount: bad.amount,
+        });
+    }
+
+    let mut report = DamageReport {
+        instances: instances
+            .iter()
+            .map(|i| apply_defenses(*i, defenses))
+            .collect(),
+        ..Default::default()
+    };
+    for applied in &report.instances {
+        *report
+            .by_type
+            .entry(applied.instance.damage_type)
+            .or_insert(0) += applied.final_amount;
+    }
+    report.total = report.by_type.values().sum();
+
+    if let Some(threshold) = defenses.damage_threshold {
+        if report.total < threshold {
+            report.below_threshold = true;
+            report.total = 0;
+            report.by_type.values_mut().for_each(|v| *v = 0);
+        }
+    }
+
+    let temp_hp = temp_hp.max(0);
+    report.absorbed_by_temp_hp = report.total.min(temp_hp);
+    report.temp_hp_remaining = temp_hp - report.absorbed_by_temp_hp;
+    report.hp_damage = report.total - report.absorbed_by_temp_hp;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reduction_before_resistance_before_vulnerability() {
+        let mut defenses =
+            DamageDefenses::default().with_reduction(FlatReduction::heavy_armor_master());
+        defenses.resistances.insert(DamageType::Slashing);
+        defenses.vulnerabilities.insert(DamageType::Slashing);
+
+        let report = resolve_damage(
+            &[DamageInstance::new(DamageType::Slashing, 11)],
+            &defenses,
+            0,
+        )
+        .unwrap();
+        let applied = &report.instances[0];
+        // (11 - 3) / 2 * 2
+        assert_eq!(applied.final_amount, 8);
+        assert_eq!(
+            applied.steps,
+            vec![
+                "11 slashing",
+                "Heavy Armor Master -3: 8",
+                "resistance: 4",
+                "vulnerability: 8"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_magical_attacks_bypass_nonmagical_defenses() {
+        let defenses = DamageDefenses {
+            nonmagical_physical_resistance: true,
+            ..Default::default()
+        }
+        .with_reduction(FlatReduction::heavy_armor_master());
+
+        let mundane = resolve_damage(
+            &[DamageInstance::new(DamageType::Piercing, 10)],
+            &defenses,
+            0,
+        )
+        .unwrap();
+        assert_eq!(mundane.total, 3);
+
+        let magic = resolve_damage(
+            &[DamageInstance::magical(DamageType::Piercing, 10)],
+            &defenses,
+            0,
+        )
+        .unwrap();
+        assert_eq!(magic.total, 10);
+
+        let fire =
+            resolve_damage(&[DamageInstance::new(DamageType::Fire, 10)], &defenses, 0).unwrap();
+        assert_eq!(fire.total, 10);
+    }
+
+    #[test]
+    fn test_reports_per_type_and_temp_hp() {
+        let mut defenses = DamageDefenses::default();
+        defenses.immunities.insert(DamageType::Fire);
+        let flame_tongue = [
+            DamageInstance::magical(DamageType::Slashing, 7),
+            DamageInstance::magical(DamageType::Fire, 9),
+        ];
+        let report = resolve_damage(&flame_tongue, &defenses, 5).unwrap();
+
+        assert_eq!(report.by_type[&DamageType::Slashing], 7);
+        assert_eq!(report.by_type[&DamageType::Fire], 0);
+        assert_eq!(report.absorbed_by_temp_hp, 5);
+        assert_eq!(report.temp_hp_remaining, 0);
+        assert_eq!(report.hp_damage, 2);
+    }
+
+    #[test]
+    fn test_object_damage_threshold() {
+        let door = DamageDefenses::default().with_threshold(10);
+        let weak =
+            resolve_damage(&[DamageInstance::new(DamageType::Bludgeoning, 9)], &door, 0).unwrap();
+        assert!(weak.below_threshold);
+        assert_eq!(weak.hp_damage, 0);
+
+        let strong = resolve_damage(
+            &[DamageInstance::new(DamageType::Bludgeoning, 12)],
+            &door,
+            0,
+        )
+        .unwrap();
+        assert_eq!(strong.hp_damage, 12);
+
+        assert!(matches!(
+            resolve_damage(&[DamageInstance::new(DamageType::Acid, -1)], &door, 0),
+            Err(DndError::NegativeDamage { amount: -1, .. })
+        ));
+    }
+
+    #[test]
+    fn test_defenses_from_stats_and_critical_dice() {
+        let stats = CreatureStats {
+            nonmagical_physical_resistance: true,
+            damage_reductions: vec![FlatReduction::heavy_armor_master()],
+            ..Default::default()
+        };
+        let defenses = DamageDefenses::from_stats(&stats);
+        let report = resolve_damage(
+            &[
+                DamageInstance::new(DamageType::Slashing, 13),
+                DamageInstance::magical(DamageType::Slashing, 13),
+            ],
+            &defenses,
+            0,
+        )
+        .unwrap();
+        // (13 - 3) / 2 for the mundane blade, all 13 for the magic one.
+        assert_eq!(report.by_type[&DamageType::Slashing], 18);
+
+        let mut roller = DiceRoller::from_optional_seed(Some(7));
+        let two_d6 = DiceExpression::parse("2d6").unwrap();
+        let (rolls, amount) = roll_damage(&mut roller, &two_d6, 3, true);
+        assert_eq!(rolls.len(), 4);
+        assert!((7..=27).contains(&amount));
+    }
+
+    #[test]
+    fn test_critical_keeps_sign_of_negative_dice() {
+        let expression = DiceExpression::parse("1d6-1d4+2").unwrap();
+        for seed in 0..20 {
+            let mut roller = DiceRoller::with_seed(seed);
+            let (rolls, amount) = roll_damage(&mut roller, &expression, 10, true);
+            let values: Vec<i32> = rolls.iter().map(|r| r.result).collect();
+            // d6, d4, then the crit's d6, d4; the +2 only counts once.
+            assert_eq!(
+                amount,
+                values[0] - values[1] + values[2] - values[3] + 2 + 10
+            );
+        }
+    }
+}
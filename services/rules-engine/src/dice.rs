@@ -0,0 +1,237 @@
+//! Dice rolling.
+
+pub mod expression;
+pub mod session;
+
+pub use expression::{DiceExpression, RolledDie};
+pub use session::{RollPurpose, RollRecord, SessionDice};
+
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum DieType {
+    D4 = 4,
+    D6 = 6,
+    D8 = 8,
+    D10 = 10,
+    D12 = 12,
+    D20 = 20,
+    D100 = 100,
+}
+
+impl DieType {
+    pub fn from_size(size: i32) -> Option<Self> {
+        match size {
+            4 => Some(DieType::D4),
+            6 => Some(DieType::D6),
+            8 => Some(DieType::D8),
+            10 => Some(DieType::D10),
+            12 => Some(DieType::D12),
+            20 => Some(DieType::D20),
+            100 => Some(DieType::D100),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DieRoll {
+    pub die_type: DieType,
+    pub result: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollResult {
+    pub rolls: Vec<DieRoll>,
+    pub total: i32,
+    pub kept: Vec<i32>,
+    pub dropped: Vec<i32>,
+    /// Every die rolled, in order, with how it was used.
+    pub dice: Vec<RolledDie>,
+}
+
+#[derive(Debug, Error)]
+pub enum DiceError {
+    #[error("Invalid dice expression: {0}")]
+    InvalidExpression(String),
+    #[error("Invalid die type: {0}")]
+    InvalidDieType(i32),
+    #[error("Invalid dice count: {0} (must be 1-{max})", max = expression::MAX_DICE_PER_TERM)]
+    TooManyDice(i32),
+    #[error("No roll #{0} in the session log")]
+    UnknownRoll(u64),
+    #[error("Replay diverged at roll #{0}")]
+    ReplayMismatch(u64),
+}
+
+/// Dice roller over an explicit seeded stream.
+///
+/// ChaCha12 is the algorithm behind `StdRng`, used directly so the stream is
+/// stable across `rand` releases and can be positioned for audits.
+pub struct DiceRoller {
+    rng: ChaCha12Rng,
+    seed: u64,
+    /// Dice rolled since recording started; see [`SessionDice`].
+    journal: Option<Vec<DieRoll>>,
+}
+
+impl DiceRoller {
+    /// Roller with a fresh random seed, still readable through [`Self::seed`].
+    pub fn new() -> Self {
+        Self::with_seed(rand::random())
+    }
+
+    pub fn with_seed(seed: u64) -> Self {
+        Self {
+            rng: ChaCha12Rng::seed_from_u64(seed),
+            seed,
+            journal: None,
+        }
+    }
+
+    /// Roller for `seed`, fast-forwarded to a [`Self::position`].
+    pub fn at_position(seed: u64, position: u128) -> Self {
+        let mut roller = Self::with_seed(seed);
+        roller.rng.set_word_pos(position);
+        roller
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    /// How far into the seed's stream the roller has read.
+    pub fn position(&self) -> u128 {
+        self.rng.get_word_pos()
+    }
+
+    /// Seeded roller when a seed is supplied, entropy otherwise.
+    pub fn from_optional_seed(seed: Option<i64>) -> Self {
+        match seed {
+            Some(seed) => Self::with_seed(seed as u64),
+            None => Self::new(),
+        }
+    }
+
+    /// Roll a single die
+    pub fn roll_die(&mut self, die_type: DieType) -> DieRoll {
+        let max = die_type as i32;
+        let result = self.rng.gen_range(1..=max);
+        let roll = DieRoll { die_type, result };
+        if let Some(journal) = &mut self.journal {
+            journal.push(roll.clone());
+        }
+        roll
+    }
+
+    /// Roll multiple dice
+    pub fn roll_dice(&mut self, count: u32, die_type: DieType) -> Vec<DieRoll> {
+        (0..count).map(|_| self.roll_die(die_type)).collect()
+    }
+
+    /// Roll a d20 and return the natural result
+    pub fn roll_d20(&mut self) -> i32 {
+        self.roll_die(DieType::D20).result
+    }
+
+    /// Roll with advantage (roll 2d20, keep highest)
+    pub fn roll_advantage(&mut self) -> (i32, i32, i32) {
+        let roll1 = self.roll_d20();
+        let roll2 = self.roll_d20();
+        (roll1, roll2, roll1.max(roll2))
+    }
+
+    /// Roll with disadvantage (roll 2d20, keep lowest)
+    pub fn roll_disadvantage(&mut self) -> (i32, i32, i32) {
+        let roll1 = self.roll_d20();
+        let roll2 = self.roll_d20();
+        (roll1, roll2, roll1.min(roll2))
+    }
+
+    /// Roll a d20 honouring advantage/disadvantage, which cancel each other.
+    /// Returns every die rolled and the one that was used.
+    pub fn roll_d20_with_state(&mut self, advantage: bool, disadvantage: bool) -> (Vec<i32>, i32) {
+        match (advantage, disadvantage) {
+            (true, false) => {
+                let (r1, r2, used) = self.roll_advantage();
+                (vec![r1, r2], used)
+            }
+            (false, true) => {
+                let (r1, r2, used) = self.roll_disadvantage();
+                (vec![r1, r2], used)
+            }
+            _ => {
+                let roll = self.roll_d20();
+                (vec![roll], roll)
+            }
+        }
+    }
+
+    /// Parse and roll a dice expression like "2d6+1d4+3" or "4d6kh3".
+    /// See [`expression`] for the full syntax.
+    pub fn roll_expression(&mut self, expression: &str) -> Result<RollResult, DiceError> {
+        Ok(DiceExpression::parse(expression)?.roll(self))
+    }
+}
+
+impl Default for DiceRoller {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seeded_roller() {
+        let mut roller1 = DiceRoller::with_seed(12345);
+        let mut roller2 = DiceRoller::with_seed(12345);
+
+        let roll1 = roller1.roll_die(DieType::D20);
+        let roll2 = roller2.roll_die(DieType::D20);
+
+        assert_eq!(roll1.result, roll2.result);
+    }
+
+    #[test]
+    fn test_roll_expression() {
+        let mut roller = DiceRoller::with_seed(42);
+
+        let result = roller.roll_expression("2d6+3").unwrap();
+        assert_eq!(result.rolls.len(), 2);
+        assert!(result.total >= 5 && result.total <= 15);
+    }
+
+    #[test]
+    fn test_keep_highest() {
+        let mut roller = DiceRoller::with_seed(7);
+
+        let result = roller.roll_expression("4d6kh3").unwrap();
+        assert_eq!(result.kept.len(), 3);
+        assert_eq!(result.dropped.len(), 1);
+        assert!(result.kept.iter().all(|k| *k >= result.dropped[0]));
+    }
+
+    #[test]
+    fn test_invalid_expressions() {
+        let mut roller = DiceRoller::with_seed(1);
+
+        assert!(roller.roll_expression("banana").is_err());
+        assert!(matches!(
+            roller.roll_expression("1d7"),
+            Err(DiceError::InvalidDieType(7))
+        ));
+    }
+
+    #[test]
+    fn test_advantage_keeps_higher() {
+        let mut roller = DiceRoller::with_seed(100);
+        let (roll1, roll2, used) = roller.roll_advantage();
+        assert_eq!(used, roll1.max(roll2));
+    }
+}
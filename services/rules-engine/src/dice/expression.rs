@@ -0,0 +1,594 @@
+//! Dice expression parsing and evaluation.
+//!
+//! Grammar (case-insensitive, whitespace ignored):
+//!
+//! ```text
+//! expression := ['+' | '-'] term (('+' | '-') term)*
+//! term       := number | dice
+//! dice       := [count] 'd' (sides | '%') modifier*
+//! modifier   := 'kh' n | 'kl' n | 'k' n       keep highest / lowest
+//!             | '!' [compare]                 explode (default: on max)
+//!             | 'r' compare                   reroll once, keep the new roll
+//!             | 'min' n | 'max' n             clamp each die
+//! compare    := ['<' | '>' | '='] n
+//! ```
+//!
+//! Comparisons follow the usual VTT convention: `<n` means "n or lower" and
+//! `>n` means "n or higher", so Great Weapon Fighting is `2d6r<2`.
+
+use super::{DiceError, DiceRoller, DieRoll, DieType, RollResult};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Most dice a single term may ask for.
+pub const MAX_DICE_PER_TERM: u32 = 100;
+
+/// Exploding dice stop adding new dice once a term holds this many.
+pub const MAX_EXPLODED_DICE: usize = 1000;
+
+/// Most terms an expression may have.
+pub const MAX_TERMS: usize = 50;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Comparison {
+    Equal(i32),
+    AtMost(i32),
+    AtLeast(i32),
+}
+
+impl Comparison {
+    pub fn matches(&self, value: i32) -> bool {
+        match *self {
+            Comparison::Equal(n) => value == n,
+            Comparison::AtMost(n) => value <= n,
+            Comparison::AtLeast(n) => value >= n,
+        }
+    }
+}
+
+impl fmt::Display for Comparison {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Comparison::Equal(n) => write!(f, "{}", n),
+            Comparison::AtMost(n) => write!(f, "<{}", n),
+            Comparison::AtLeast(n) => write!(f, ">{}", n),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Keep {
+    Highest(u32),
+    Lowest(u32),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiceTerm {
+    pub count: u32,
+    pub die_type: DieType,
+    pub keep: Option<Keep>,
+    pub explode: Option<Comparison>,
+    pub reroll: Option<Comparison>,
+    pub min: Option<i32>,
+    pub max: Option<i32>,
+}
+
+impl DiceTerm {
+    pub fn new(count: u32, die_type: DieType) -> Self {
+        Self {
+            count,
+            die_type,
+            keep: None,
+            explode: None,
+            reroll: None,
+            min: None,
+            max: None,
+        }
+    }
+
+    /// The most this term can add to or take from a total: every die that
+    /// could count, at its highest face after clamps. `None` if that
+    /// doesn't fit in an `i32`.
+    fn largest_total(&self) -> Option<i32> {
+        let sides = self.die_type as i32;
+        let face = sides.max(self.min.unwrap_or(0));
+        let dice = if self.explode.is_some() {
+            (MAX_EXPLODED_DICE as i32).checked_add(self.count as i32)?
+        } else {
+            self.count as i32
+        };
+        dice.checked_mul(face)
+    }
+
+    fn clamp(&self, value: i32) -> i32 {
+        let value = self.min.map_or(value, |min| value.max(min));
+        self.max.map_or(value, |max| value.min(max))
+    }
+
+    fn roll(&self, roller: &mut DiceRoller, negative: bool) -> Vec<RolledDie> {
+        let mut dice = Vec::new();
+        let mut pending = self.count as usize;
+        let mut exploded = false;
+
+        while pending > 0 {
+            pending -= 1;
+            let mut natural = roller.roll_die(self.die_type).result;
+
+            if self.reroll.is_some_and(|c| c.matches(natural)) {
+                dice.push(RolledDie {
+                    die_type: self.die_type,
+                    natural,
+                    value: self.clamp(natural),
+                    negative,
+                    kept: false,
+                    rerolled: true,
+                    exploded,
+                });
+                natural = roller.roll_die(self.die_type).result;
+            }
+
+            dice.push(RolledDie {
+                die_type: self.die_type,
+                natural,
+                value: self.clamp(natural),
+                negative,
+                kept: true,
+                rerolled: false,
+                exploded,
+            });
+
+            let explodes_on = self
+                .explode
+                .unwrap_or(Comparison::AtLeast(self.die_type as i32));
+            exploded = self.explode.is_some() && explodes_on.matches(natural);
+            if exploded && dice.len() < MAX_EXPLODED_DICE {
+                pending += 1;
+            }
+        }
+
+        if let Some(keep) = self.keep {
+            let mut live: Vec<usize> = (0..dice.len()).filter(|&i| !dice[i].rerolled).collect();
+            let n = match keep {
+                Keep::Highest(n) => {
+                    live.sort_by_key(|&i| std::cmp::Reverse(dice[i].value));
+                    n
+                }
+                Keep::Lowest(n) => {
+                    live.sort_by_key(|&i| dice[i].value);
+                    n
+                }
+            };
+            for &index in live.iter().skip(n as usize) {
+                dice[index].kept = false;
+            }
+        }
+
+        dice
+    }
+}
+
+impl fmt::Display for DiceTerm {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}d{}", self.count, self.die_type as i32)?;
+        match self.keep {
+            Some(Keep::Highest(n)) => write!(f, "kh{}", n)?,
+            Some(Keep::Lowest(n)) => write!(f, "kl{}", n)?,
+            None => {}
+        }
+        if let Some(explode) = self.explode {
+            write!(f, "!")?;
+            if explode != Comparison::AtLeast(self.die_type as i32) {
+                write!(f, "{}", explode)?;
+            }
+        }
+        if let Some(reroll) = self.reroll {
+            write!(f, "r{}", reroll)?;
+        }
+        if let Some(min) = self.min {
+            write!(f, "min{}", min)?;
+        }
+        if let Some(max) = self.max {
+            write!(f, "max{}", max)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TermKind {
+    Constant(i32),
+    Dice(DiceTerm),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Term {
+    pub negative: bool,
+    pub kind: TermKind,
+}
+
+/// A parsed dice expression: a signed sum of dice and constant terms.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiceExpression {
+    pub terms: Vec<Term>,
+}
+
+/// One physical die in a roll, including dice that ended up not counting.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RolledDie {
+    pub die_type: DieType,
+    /// Face shown on the die.
+    pub natural: i32,
+    /// Face after min/max clamps; what the die contributes if kept.
+    pub value: i32,
+    /// Belongs to a subtracted term.
+    pub negative: bool,
+    /// Counts toward the total.
+    pub kept: bool,
+    /// Replaced by a reroll.
+    pub rerolled: bool,
+    /// Added because the previous die exploded.
+    pub exploded: bool,
+}
+
+impl DiceExpression {
+    pub fn parse(expression: &str) -> Result<Self, DiceError> {
+        Parser::new(expression).parse()
+    }
+
+    /// Sum of the constant terms.
+    pub fn modifier(&self) -> i32 {
+        self.terms
+            .iter()
+            .map(|term| match term.kind {
+                TermKind::Constant(n) if term.negative => -n,
+                TermKind::Constant(n) => n,
+                TermKind::Dice(_) => 0,
+            })
+            .sum()
+    }
+
+    pub fn dice_terms(&self) -> impl Iterator<Item = &DiceTerm> {
+        self.terms.iter().filter_map(|term| match &term.kind {
+            TermKind::Dice(dice) => Some(dice),
+            TermKind::Constant(_) => None,
+        })
+    }
+
+    /// Parsing bounds the largest possible total, so the sums here can't
+    /// overflow.
+    pub fn roll(&self, roller: &mut DiceRoller) -> RollResult {
+        let mut dice = Vec::new();
+        for term in &self.terms {
+            if let TermKind::Dice(term_dice) = &term.kind {
+                dice.extend(term_dice.roll(roller, term.negative));
+            }
+        }
+
+        let dice_total: i32 = dice
+            .iter()
+            .filter(|d| d.kept)
+            .map(|d| if d.negative { -d.value } else { d.value })
+            .sum();
+
+        RollResult {
+            rolls: dice
+                .iter()
+                .map(|d| DieRoll {
+                    die_type: d.die_type,
+                    result: d.natural,
+                })
+                .collect(),
+            total: dice_total + self.modifier(),
+            kept: dice.iter().filter(|d| d.kept).map(|d| d.value).collect(),
+            dropped: dice.iter().filter(|d| !d.kept).map(|d| d.value).collect(),
+            dice,
+        }
+    }
+}
+
+impl FromStr for DiceExpression {
+    type Err = DiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s)
+    }
+}
+
+impl fmt::Display for DiceExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, term) in self.terms.iter().enumerate() {
+            match (index, term.negative) {
+                (_, true) => write!(f, "-")?,
+                (0, false) => {}
+                (_, false) => write!(f, "+")?,
+            }
+            match &term.kind {
+                TermKind::Constant(n) => write!(f, "{}", n)?,
+                TermKind::Dice(dice) => write!(f, "{}", dice)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+struct Parser<'a> {
+    source: &'a str,
+    chars: Vec<char>,
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn new(source: &'a str) -> Self {
+        Self {
+            source,
+            chars: source
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| c.to_ascii_lowercase())
+                .collect(),
+            position: 0,
+        }
+    }
+
+    fn error(&self, reason: &str) -> DiceError {
+        DiceError::InvalidExpression(format!(
+            "{} ({} at position {})",
+            self.source, reason, self.position
+        ))
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.position).copied()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.position += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn eat_str(&mut self, s: &str) -> bool {
+        let matches = s
+            .chars()
+            .enumerate()
+            .all(|(i, c)| self.chars.get(self.position + i) == Some(&c));
+        if matches {
+            self.position += s.len();
+        }
+        matches
+    }
+
+    fn number(&mut self) -> Option<Result<i32, DiceError>> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        if start == self.position {
+            return None;
+        }
+        let digits: String = self.chars[start..self.position].iter().collect();
+        Some(digits.parse().map_err(|_| self.error("number too large")))
+    }
+
+    fn require_number(&mut self, what: &str) -> Result<i32, DiceError> {
+        self.number()
+            .unwrap_or_else(|| Err(self.error(&format!("expected {}", what))))
+    }
+
+    fn comparison(&mut self) -> Result<Option<Comparison>, DiceError> {
+        let make: fn(i32) -> Comparison = if self.eat('<') {
+            Comparison::AtMost
+        } else if self.eat('>') {
+            Comparison::AtLeast
+        } else {
+            self.eat('=');
+            Comparison::Equal
+        };
+        Ok(self.number().transpose()?.map(make))
+    }
+
+    fn parse(mut self) -> Result<DiceExpression, DiceError> {
+        if self.chars.is_empty() {
+            return Err(self.error("empty expression"));
+        }
+
+        let mut terms = Vec::new();
+        // The largest total the terms so far could reach either way.
+        let mut largest: i32 = 0;
+        let mut negative = self.eat('-');
+        if !negative {
+            self.eat('+');
+        }
+        loop {
+            if terms.len() == MAX_TERMS {
+                return Err(self.error(&format!("more than {} terms", MAX_TERMS)));
+            }
+            let kind = self.term()?;
+            let term_largest = match &kind {
+                TermKind::Constant(n) => Some(*n),
+                TermKind::Dice(dice) => dice.largest_total(),
+            };
+            largest = term_largest
+                .and_then(|n| largest.checked_add(n))
+                .ok_or_else(|| self.error("total is too large"))?;
+            terms.push(Term { negative, kind });
+            negative = match self.peek() {
+                None => break,
+                Some('+') => false,
+                Some('-') => true,
+                Some(_) => return Err(self.error("expected '+' or '-'")),
+            };
+            self.position += 1;
+        }
+
+        Ok(DiceExpression { terms })
+    }
+
+    fn term(&mut self) -> Result<TermKind, DiceError> {
+        let count = self.number().transpose()?;
+        if !self.eat('d') {
+            return count
+                .map(TermKind::Constant)
+                .ok_or_else(|| self.error("expected a number or dice"));
+        }
+
+        let count = count.unwrap_or(1);
+        if count < 1 || count as u32 > MAX_DICE_PER_TERM {
+            return Err(DiceError::TooManyDice(count));
+        }
+        let sides = if self.eat('%') {
+            100
+        } else {
+            self.require_number("die size")?
+        };
+        let die_type = DieType::from_size(sides).ok_or(DiceError::InvalidDieType(sides))?;
+
+        let mut dice = DiceTerm::new(count as u32, die_type);
+        loop {
+            if self.eat_str("kh") {
+                dice.keep = Some(Keep::Highest(self.require_number("keep count")? as u32));
+            } else if self.eat_str("kl") {
+                dice.keep = Some(Keep::Lowest(self.require_number("keep count")? as u32));
+            } else if self.eat('k') {
+                dice.keep = Some(Keep::Highest(self.require_number("keep count")? as u32));
+            } else if self.eat('!') {
+                dice.explode = Some(self.comparison()?.unwrap_or(Comparison::AtLeast(sides)));
+            } else if self.eat('r') {
+                dice.reroll = Some(
+                    self.comparison()?
+                        .ok_or_else(|| self.error("expected reroll value"))?,
+                );
+            } else if self.eat_str("min") {
+                dice.min = Some(self.require_number("minimum")?);
+            } else if self.eat_str("max") {
+                dice.max = Some(self.require_number("maximum")?);
+            } else {
+                break;
+            }
+        }
+
+        if let (Some(min), Some(max)) = (dice.min, dice.max) {
+            if min > max {
+                return Err(self.error("min is greater than max"));
+            }
+        }
+        // A die that explodes on every face would never stop.
+        if dice
+            .explode
+            .is_some_and(|c| (1..=sides).all(|face| c.matches(face)))
+        {
+            return Err(self.error("die explodes on every face"));
+        }
+
+        Ok(TermKind::Dice(dice))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mixed_terms() {
+        let expr = DiceExpression::parse("2d6 + 1d4 + 3").unwrap();
+        assert_eq!(expr.terms.len(), 3);
+        assert_eq!(expr.modifier(), 3);
+        assert_eq!(expr.to_string(), "2d6+1d4+3");
+
+        let mut roller = DiceRoller::with_seed(5);
+        let result = expr.roll(&mut roller);
+        assert_eq!(result.dice.len(), 3);
+        assert!(result.total >= 6 && result.total <= 19);
+    }
+
+    #[test]
+    fn test_keep_lowest() {
+        let mut roller = DiceRoller::with_seed(11);
+        let result = DiceExpression::parse("2d20kl1").unwrap().roll(&mut roller);
+        assert_eq!(result.kept.len(), 1);
+        assert_eq!(result.total, result.kept[0]);
+        assert!(result.kept[0] <= result.dropped[0]);
+    }
+
+    #[test]
+    fn test_exploding_dice_add_extra_dice() {
+        let mut roller = DiceRoller::with_seed(3);
+        for _ in 0..50 {
+            let result = DiceExpression::parse("1d4!").unwrap().roll(&mut roller);
+            let maxes = result.dice.iter().filter(|d| d.natural == 4).count();
+            assert_eq!(result.dice.len(), maxes + 1);
+            assert_eq!(
+                result.total,
+                result.dice.iter().map(|d| d.value).sum::<i32>()
+            );
+        }
+    }
+
+    #[test]
+    fn test_great_weapon_fighting_rerolls_once() {
+        let mut roller = DiceRoller::with_seed(9);
+        for _ in 0..50 {
+            let result = DiceExpression::parse("2d6r<2").unwrap().roll(&mut roller);
+            let rerolled = result.dice.iter().filter(|d| d.rerolled).count();
+            assert!(result
+                .dice
+                .iter()
+                .filter(|d| d.rerolled)
+                .all(|d| d.natural <= 2));
+            assert_eq!(result.dice.len(), 2 + rerolled);
+            assert_eq!(result.kept.len(), 2);
+        }
+    }
+
+    #[test]
+    fn test_clamps_apply_per_die() {
+        let mut roller = DiceRoller::with_seed(21);
+        for _ in 0..20 {
+            let result = DiceExpression::parse("1d20min10")
+                .unwrap()
+                .roll(&mut roller);
+            assert!(result.total >= 10);
+            let result = DiceExpression::parse("4d6max3").unwrap().roll(&mut roller);
+            assert!(result.kept.iter().all(|v| *v <= 3));
+        }
+    }
+
+    #[test]
+    fn test_subtracted_dice() {
+        let mut roller = DiceRoller::with_seed(2);
+        let result = DiceExpression::parse("-1d4+10").unwrap().roll(&mut roller);
+        assert!(result.total >= 6 && result.total <= 9);
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(DiceExpression::parse("").is_err());
+        assert!(DiceExpression::parse("2d6+").is_err());
+        assert!(DiceExpression::parse("2d6x").is_err());
+        assert!(DiceExpression::parse("1d6!<6").is_err());
+        assert!(DiceExpression::parse("1d6min5max2").is_err());
+        assert!(matches!(
+            DiceExpression::parse("1000d6"),
+            Err(DiceError::TooManyDice(1000))
+        ));
+    }
+
+    #[test]
+    fn test_totals_that_could_overflow_are_rejected() {
+        assert!(DiceExpression::parse("2147483647").is_ok());
+        assert!(DiceExpression::parse("2147483647+1").is_err());
+        assert!(DiceExpression::parse("-2147483647-1d4").is_err());
+        assert!(DiceExpression::parse("100d6min30000000").is_err());
+        assert!(DiceExpression::parse("1d6!min3000000").is_err());
+        assert!(DiceExpression::parse("100d100+100d100").is_ok());
+
+        let many = vec!["1"; MAX_TERMS + 1].join("+");
+        let err = DiceExpression::parse(&many).unwrap_err();
+        assert!(err.to_string().contains("terms"));
+        assert!(DiceExpression::parse(&many[2..]).is_ok());
+    }
+}
@@ -0,0 +1,259 @@
+//! Per-session dice with an audit trail.
+//!
+//! Each game session owns its own seeded [`DiceRoller`]; nothing is shared
+//! between sessions. Every roll is logged with where in the seed's stream it
+//! started, what it was for and who asked for it, so any single roll can be
+//! re-derived for a DM and a whole session can be replayed in tests.
+
+use super::{DiceError, DiceExpression, DiceRoller, DieRoll, RollResult};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RollPurpose {
+    Attack,
+    Damage,
+    SavingThrow,
+    AbilityCheck,
+    Initiative,
+    DeathSave,
+    Concentration,
+    Healing,
+    Other(String),
+}
+
+impl fmt::Display for RollPurpose {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RollPurpose::Attack => write!(f, "attack"),
+            RollPurpose::Damage => write!(f, "damage"),
+            RollPurpose::SavingThrow => write!(f, "save"),
+            RollPurpose::AbilityCheck => write!(f, "check"),
+            RollPurpose::Initiative => write!(f, "initiative"),
+            RollPurpose::DeathSave => write!(f, "death_save"),
+            RollPurpose::Concentration => write!(f, "concentration"),
+            RollPurpose::Healing => write!(f, "healing"),
+            RollPurpose::Other(purpose) => write!(f, "{}", purpose),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RollRecord {
+    pub sequence: u64,
+    /// Word position in the seed's stream when the roll started.
+    pub seed_position: u128,
+    pub purpose: RollPurpose,
+    pub requester: String,
+    /// Expression or short description of what was rolled.
+    pub description: String,
+    pub dice: Vec<DieRoll>,
+    /// Final total, when the roll produced one.
+    pub total: Option<i32>,
+}
+
+pub struct SessionDice {
+    session_id: String,
+    roller: DiceRoller,
+    log: Vec<RollRecord>,
+}
+
+impl SessionDice {
+    pub fn new(session_id: impl Into<String>, seed: u64) -> Self {
+        Self {
+            session_id: session_id.into(),
+            roller: DiceRoller::with_seed(seed),
+            log: Vec::new(),
+        }
+    }
+
+    /// Session with a random seed. The seed is still recorded, so the
+    /// session stays auditable.
+    pub fn from_entropy(session_id: impl Into<String>) -> Self {
+        Self::new(session_id, DiceRoller::new().seed())
+    }
+
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.roller.seed()
+    }
+
+    pub fn log(&self) -> &[RollRecord] {
+        &self.log
+    }
+
+    pub fn rolls_by<'a>(&'a self, requester: &'a str) -> impl Iterator<Item = &'a RollRecord> {
+        self.log.iter().filter(move |r| r.requester == requester)
+    }
+
+    pub fn roll_expression(
+        &mut self,
+        expression: &str,
+        purpose: RollPurpose,
+        requester: &str,
+    ) -> Result<RollResult, DiceError> {
+        let parsed = DiceExpression::parse(expression)?;
+        let logged = self.log.len();
+        let result = self.roll_with(purpose, requester, expression, |roller| parsed.roll(roller));
+        if let Some(record) = self.log.get_mut(logged) {
+            record.total = Some(result.total);
+        }
+        Ok(result)
+    }
+
+    /// Run `f` against the session roller and log every die it rolls as one
+    /// entry. Use this to route combat resolution through the session. A
+    /// call that rolls nothing is still logged, with no dice, so the log
+    /// shows every roll that was asked for.
+    pub fn roll_with<T>(
+        &mut self,
+        purpose: RollPurpose,
+        requester: &str,
+        description: &str,
+        f: impl FnOnce(&mut DiceRoller) -> T,
+    ) -> T {
+        let seed_position = self.roller.position();
+        self.roller.journal = Some(Vec::new());
+        let value = f(&mut self.roller);
+        let dice = self.roller.journal.take().unwrap_or_default();
+        self.log.push(RollRecord {
+            sequence: self.log.len() as u64,
+            seed_position,
+            purpose,
+            requester: requester.to_string(),
+            description: description.to_string(),
+            dice,
+            total: None,
+        });
+        value
+    }
+
+    /// Re-derive a logged roll from the seed and its position, returning the
+    /// dice it must have produced.
+    pub fn audit(&self, sequence: u64) -> Result<Vec<DieRoll>, DiceError> {
+        let record = self
+            .log
+            .get(sequence as usize)
+            .ok_or(DiceError::UnknownRoll(sequence))?;
+
+        let mut roller = DiceRoller::at_position(self.seed(), record.seed_position);
+        let dice: Vec<DieRoll> = record
+            .dice
+            .iter()
+            .map(|d| roller.roll_die(d.die_type))
+            .collect();
+        if dice != record.dice {
+            return Err(DiceError::ReplayMismatch(sequence));
+        }
+        Ok(dice)
+    }
+
+    /// Replay the whole log from the seed and check every roll, in order,
+    /// starts where it was logged and produces the same dice.
+    pub fn verify(&self) -> Result<(), DiceError> {
+        let mut roller = DiceRoller::with_seed(self.seed());
+        for record in &self.log {
+            if roller.position() != record.seed_position {
+                return Err(DiceError::ReplayMismatch(record.sequence));
+            }
+            for die in &record.dice {
+                if roller.roll_die(die.die_type) != *die {
+                    return Err(DiceError::ReplayMismatch(record.sequence));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sessions_do_not_share_rolls() {
+        let mut a = SessionDice::new("a", 99);
+        let mut b = SessionDice::new("b", 99);
+
+        let first = a
+            .roll_expression("1d20", RollPurpose::Attack, "fighter")
+            .unwrap();
+        b.roll_expression("8d6", RollPurpose::Damage, "wizard")
+            .unwrap();
+        let mut fresh = SessionDice::new("c", 99);
+        let replayed = fresh
+            .roll_expression("1d20", RollPurpose::Attack, "fighter")
+            .unwrap();
+
+        assert_eq!(first.total, replayed.total);
+    }
+
+    #[test]
+    fn test_log_records_purpose_and_requester() {
+        let mut session = SessionDice::new("s", 7);
+        session
+            .roll_expression("1d20+5", RollPurpose::Attack, "fighter")
+            .unwrap();
+        session
+            .roll_expression("2d6+3", RollPurpose::Damage, "fighter")
+            .unwrap();
+        session
+            .roll_expression("1d20", RollPurpose::SavingThrow, "goblin")
+            .unwrap();
+
+        assert_eq!(session.log().len(), 3);
+        assert_eq!(session.rolls_by("fighter").count(), 2);
+        let damage = &session.log()[1];
+        assert_eq!(damage.purpose, RollPurpose::Damage);
+        assert_eq!(damage.dice.len(), 2);
+        assert!(damage.seed_position > session.log()[0].seed_position);
+    }
+
+    #[test]
+    fn test_audit_and_verify() {
+        let mut session = SessionDice::new("s", 1234);
+        for _ in 0..10 {
+            session
+                .roll_expression("4d6kh3", RollPurpose::Other("stats".into()), "dm")
+                .unwrap();
+        }
+        session.roll_with(
+            RollPurpose::Attack,
+            "rogue",
+            "1d20 with advantage",
+            |roller| roller.roll_d20_with_state(true, false),
+        );
+        session.roll_with(RollPurpose::Healing, "cleric", "no dice", |_| 5);
+        session
+            .roll_expression("3", RollPurpose::Healing, "cleric")
+            .unwrap();
+        assert_eq!(session.log().len(), 13);
+        assert_eq!(session.log()[10].total, None);
+        assert!(session.log()[11].dice.is_empty());
+        assert_eq!(session.log()[12].total, Some(3));
+
+        assert_eq!(session.audit(10).unwrap().len(), 2);
+        assert!(session.audit(11).unwrap().is_empty());
+        assert_eq!(session.audit(4).unwrap(), session.log()[4].dice);
+        assert!(session.verify().is_ok());
+        assert!(matches!(session.audit(13), Err(DiceError::UnknownRoll(13))));
+    }
+
+    #[test]
+    fn test_tampered_log_fails_verification() {
+        let mut session = SessionDice::new("s", 5);
+        session
+            .roll_expression("1d20", RollPurpose::Attack, "fighter")
+            .unwrap();
+        let natural = session.log[0].dice[0].result;
+        session.log[0].dice[0].result = if natural == 20 { 1 } else { natural + 1 };
+
+        assert!(matches!(
+            session.verify(),
+            Err(DiceError::ReplayMismatch(0))
+        ));
+    }
+}
@@ -0,0 +1,349 @@
+//! Hit points and the dying/death-save lifecycle (PHB p.196-198).
+//!
+//! A creature is conscious, dying (at 0 HP making death saves), stable (at
+//! 0 HP, no longer saving) or dead. Monsters without death saves skip
+//! straight from 0 HP to dead. Every transition returns the events it
+//! caused so callers can log them or trigger conditions.
+
+use crate::checks::DeathSaveOutcome;
+use crate::combat::CreatureStats;
+use serde::{Deserialize, Serialize};
+use shared_rust::DndError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LifeState {
+    Conscious,
+    Dying { successes: u8, failures: u8 },
+    Stable,
+    Dead,
+}
+
+/// What happens at 0 hit points.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum DeathRule {
+    /// Fall unconscious and make death saving throws.
+    #[default]
+    DeathSaves,
+    /// Most monsters die outright (MM p.10).
+    DiesAtZero,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HpEvent {
+    TempHpAbsorbed(i32),
+    HpLost(i32),
+    DroppedToZero,
+    /// Damage at 0 HP; a critical hit counts as two failures.
+    DeathSaveFailures(u8),
+    DeathSaveSuccess,
+    Stabilized,
+    /// Damage left over after reaching 0 HP was at least the HP maximum.
+    MassiveDamage,
+    Died,
+    Healed(i32),
+    RegainedConsciousness,
+    TempHpGained(i32),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HitPoints {
+    pub current: i32,
+    pub max: i32,
+    pub temp: i32,
+    pub state: LifeState,
+    pub rule: DeathRule,
+}
+
+const DEATH_SAVES_TO_RESOLVE: u8 = 3;
+
+impl HitPoints {
+    pub fn new(max: i32) -> Self {
+        Self {
+            current: max,
+            max,
+            temp: 0,
+            state: LifeState::Conscious,
+            rule: DeathRule::DeathSaves,
+        }
+    }
+
+    pub fn monster(max: i32) -> Self {
+        Self {
+            rule: DeathRule::DiesAtZero,
+            ..Self::new(max)
+        }
+    }
+
+    /// Snapshot from stats; a creature at 0 HP is taken to be dying with no
+    /// saves recorded.
+    pub fn from_stats(stats: &CreatureStats, rule: DeathRule) -> Self {
+        let current = stats.current_hp.max(0);
+        let state = match (current, rule) {
+            (0, DeathRule::DiesAtZero) => LifeState::Dead,
+            (0, DeathRule::DeathSaves) => LifeState::Dying {
+                successes: 0,
+                failures: 0,
+            },
+            _ => LifeState::Conscious,
+        };
+        Self {
+            current,
+            max: stats.max_hp,
+            temp: stats.temp_hp.max(0),
+            state,
+            rule,
+        }
+    }
+
+    pub fn is_conscious(&self) -> bool {
+        self.state == LifeState::Conscious
+    }
+
+    pub fn is_dead(&self) -> bool {
+        self.state == LifeState::Dead
+    }
+
+    fn die(&mut self, events: &mut Vec<HpEvent>) {
+        self.state = LifeState::Dead;
+        self.current = 0;
+        events.push(HpEvent::Died);
+    }
+
+    /// Take `amount` damage that has already been through resistances.
+    pub fn take_damage(
+        &mut self,
+        amount: i32,
+        is_critical: bool,
+    ) -> Result<Vec<HpEvent>, DndError> {
+        if amount < 0 {
+            return Err(DndError::InvalidAction(format!(
+                "cannot take negative damage ({})",
+                amount
+            )));
+        }
+        let mut events = Vec::new();
+        if self.is_dead() || amount == 0 {
+            return Ok(events);
+        }
+
+        let absorbed = self.temp.min(amount);
+        self.temp -= absorbed;
+        if absorbed > 0 {
+            events.push(HpEvent::TempHpAbsorbed(absorbed));
+        }
+        let remaining = amount - absorbed;
+        if remaining == 0 {
+            return Ok(events);
+        }
+
+        match self.state {
+            LifeState::Conscious => {
+                let lost = remaining.min(self.current);
+                let overflow = remaining - lost;
+                self.current -= lost;
+                events.push(HpEvent::HpLost(lost));
+                if self.current > 0 {
+                    return Ok(events);
+                }
+                events.push(HpEvent::DroppedToZero);
+                if overflow >= self.max {
+                    events.push(HpEvent::MassiveDamage);
+                    self.die(&mut events);
+                } else if self.rule == DeathRule::DiesAtZero {
+                    self.die(&mut events);
+                } else {
+                    self.state = LifeState::Dying {
+                        successes: 0,
+                        failures: 0,
+                    };
+                }
+            }
+            LifeState::Dying { .. } | LifeState::Stable => {
+                if remaining >= self.max {
+                    events.push(HpEvent::MassiveDamage);
+                    self.die(&mut events);
+                } else {
+                    self.add_failures(if is_critical { 2 } else { 1 }, &mut events);
+                }
+            }
+            LifeState::Dead => {}
+        }
+        Ok(events)
+    }
+
+    fn add_failures(&mut self, count: u8, events: &mut Vec<HpEvent>) {
+        let (successes, failures) = match self.state {
+            LifeState::Dying {
+                successes,
+                failures,
+            } => (successes, failures),
+            // Taking damage while stable starts the saves over.
+            _ => (0, 0),
+        };
+        let failures = (failures + count).min(DEATH_SAVES_TO_RESOLVE);
+        events.push(HpEvent::DeathSaveFailures(count));
+        if failures >= DEATH_SAVES_TO_RESOLVE {
+            self.die(events);
+        } else {
+            self.state = LifeState::Dying {
+                successes,
+                failures,
+            };
+        }
+    }
+
+    /// Record a death saving throw made at the start of a dying turn.
+    pub fn record_death_save(
+        &mut self,
+        outcome: DeathSaveOutcome,
+    ) -> Result<Vec<HpEvent>, DndError> {
+        let LifeState::Dying { successes, .. } = self.state else {
+            return Err(DndError::InvalidAction(
+                "only a dying creature makes death saving throws".to_string(),
+            ));
+        };
+        let mut events = Vec::new();
+        match outcome {
+            DeathSaveOutcome::RegainHitPoint => {
+                events.extend(self.heal(1)?);
+            }
+            DeathSaveOutcome::Success => {
+                events.push(HpEvent::DeathSaveSuccess);
+                if successes + 1 >= DEATH_SAVES_TO_RESOLVE {
+                    self.stabilize(&mut events);
+                } else if let LifeState::Dying { successes, .. } = &mut self.state {
+                    *successes += 1;
+                }
+            }
+            DeathSaveOutcome::Failure => self.add_failures(1, &mut events),
+            DeathSaveOutcome::TwoFailures => self.add_failures(2, &mut events),
+        }
+        Ok(events)
+    }
+
+    fn stabilize(&mut self, events: &mut Vec<HpEvent>) {
+        self.state = LifeState::Stable;
+        events.push(HpEvent::Stabilized);
+    }
+
+    /// Spare the Dying, a Medicine check or a healer's kit.
+    pub fn make_stable(&mut self) -> Result<Vec<HpEvent>, DndError> {
+        if !matches!(self.state, LifeState::Dying { .. }) {
+            return Err(DndError::InvalidAction(
+                "only a dying creature can be stabilized".to_string(),
+            ));
+        }
+        let mut events = Vec::new();
+        self.stabilize(&mut events);
+        Ok(events)
+    }
+
+    pub fn heal(&mut self, amount: i32) -> Result<Vec<HpEvent>, DndError> {
+        if self.is_dead() {
+            return Err(DndError::InvalidAction(
+                "dead creatures cannot regain hit points".to_string(),
+            ));
+        }
+        let mut events = Vec::new();
+        let healed = amount.max(0).min(self.max - self.current);
+        if healed == 0 {
+            return Ok(events);
+        }
+        self.current += healed;
+        events.push(HpEvent::Healed(healed));
+        if self.state != LifeState::Conscious {
+            self.state = LifeState::Conscious;
+            events.push(HpEvent::RegainedConsciousness);
+        }
+        Ok(events)
+    }
+
+    /// Temporary hit points don't stack: keep whichever is higher.
+    pub fn grant_temp_hp(&mut self, amount: i32) -> Vec<HpEvent> {
+        if amount > self.temp {
+            self.temp = amount;
+            vec![HpEvent::TempHpGained(amount)]
+        } else {
+            Vec::new()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dying(hp: &mut HitPoints) {
+        hp.take_damage(hp.current, false).unwrap();
+        assert!(matches!(hp.state, LifeState::Dying { .. }));
+    }
+
+    #[test]
+    fn test_temp_hp_absorbs_first_and_does_not_stack() {
+        let mut hp = HitPoints::new(20);
+        hp.grant_temp_hp(8);
+        assert!(hp.grant_temp_hp(5).is_empty());
+        assert_eq!(hp.temp, 8);
+
+        let events = hp.take_damage(10, false).unwrap();
+        assert_eq!(events, vec![HpEvent::TempHpAbsorbed(8), HpEvent::HpLost(2)]);
+        assert_eq!((hp.current, hp.temp), (18, 0));
+    }
+
+    #[test]
+    fn test_massive_damage_and_monsters() {
+        let mut pc = HitPoints::new(12);
+        let events = pc.take_damage(12 + 12, false).unwrap();
+        assert!(events.contains(&HpEvent::MassiveDamage));
+        assert!(pc.is_dead());
+
+        let mut goblin = HitPoints::monster(7);
+        goblin.take_damage(7, false).unwrap();
+        assert!(goblin.is_dead());
+    }
+
+    #[test]
+    fn test_damage_while_dying_counts_as_failures() {
+        let mut hp = HitPoints::new(30);
+        dying(&mut hp);
+        hp.take_damage(4, false).unwrap();
+        assert_eq!(
+            hp.state,
+            LifeState::Dying {
+                successes: 0,
+                failures: 1
+            }
+        );
+        let events = hp.take_damage(4, true).unwrap();
+        assert_eq!(events.last(), Some(&HpEvent::Died));
+    }
+
+    #[test]
+    fn test_death_saves_stabilize_and_revive() {
+        let mut hp = HitPoints::new(10);
+        dying(&mut hp);
+        for _ in 0..3 {
+            hp.record_death_save(DeathSaveOutcome::Success).unwrap();
+        }
+        assert_eq!(hp.state, LifeState::Stable);
+        assert!(hp.record_death_save(DeathSaveOutcome::Success).is_err());
+
+        hp.take_damage(1, false).unwrap();
+        assert_eq!(
+            hp.state,
+            LifeState::Dying {
+                successes: 0,
+                failures: 1
+            }
+        );
+        let events = hp
+            .record_death_save(DeathSaveOutcome::RegainHitPoint)
+            .unwrap();
+        assert_eq!(
+            events,
+            vec![HpEvent::Healed(1), HpEvent::RegainedConsciousness]
+        );
+        assert_eq!(hp.current, 1);
+        assert!(hp.is_conscious());
+    }
+}
@@ -0,0 +1,699 @@
+//! Initiative order and the round/turn scheduler (PHB p.189, MM p.11).
+//!
+//! Combatants act in descending initiative. Ties go to the higher Dexterity,
+//! then to a d20 roll-off. Lair actions take initiative count 20 and lose
+//! every tie. Creatures can join mid-combat, delay their turn or ready an
+//! action, and per-creature hooks fire at the start and end of their turns.
+
+use crate::combat::Ability;
+use crate::dice::{DiceError, DiceRoller, DieRoll};
+use serde::{Deserialize, Serialize};
+use shared_rust::{DamageInstance, DamageType, DndError};
+use std::collections::{HashMap, HashSet};
+
+pub const LAIR_INITIATIVE: i32 = 20;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InitiativeEntry {
+    pub creature_id: String,
+    pub initiative: i32,
+    /// Dexterity score, the first tiebreaker.
+    pub dexterity: i32,
+    /// d20 roll-off among combatants still tied on initiative and Dexterity.
+    pub roll_off: i32,
+    pub lair: bool,
+}
+
+impl InitiativeEntry {
+    /// Higher sorts first.
+    fn key(&self) -> (i32, bool, i32, i32) {
+        (self.initiative, !self.lair, self.dexterity, self.roll_off)
+    }
+
+    fn ties_with(&self, other: &InitiativeEntry) -> bool {
+        !self.lair
+            && !other.lair
+            && self.initiative == other.initiative
+            && self.dexterity == other.dexterity
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HookTiming {
+    StartOfTurn,
+    EndOfTurn,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TurnEffect {
+    /// Regain hit points, unless one of `suppressed_by` damaged the creature
+    /// since its last turn (a troll's fire and acid).
+    Regeneration {
+        amount: i32,
+        suppressed_by: Vec<DamageType>,
+    },
+    OngoingDamage {
+        dice: String,
+        damage_type: DamageType,
+    },
+    /// Another save against a condition, e.g. Hold Person.
+    RepeatSave {
+        condition_id: String,
+        ability: Ability,
+        dc: i32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnHook {
+    pub id: String,
+    pub creature_id: String,
+    pub timing: HookTiming,
+    pub effect: TurnEffect,
+}
+
+impl TurnHook {
+    pub fn new(
+        id: impl Into<String>,
+        creature_id: impl Into<String>,
+        timing: HookTiming,
+        effect: TurnEffect,
+    ) -> Self {
+        Self {
+            id: id.into(),
+            creature_id: creature_id.into(),
+            timing,
+            effect,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HookOutcome {
+    Regenerated {
+        hook_id: String,
+        amount: i32,
+    },
+    RegenerationSuppressed {
+        hook_id: String,
+    },
+    Damage {
+        hook_id: String,
+        instance: DamageInstance,
+        rolls: Vec<DieRoll>,
+    },
+    SaveDue {
+        hook_id: String,
+        condition_id: String,
+        ability: Ability,
+        dc: i32,
+    },
+}
+
+/// Resolve `hooks`, rolling any ongoing damage. Saves are returned for the
+/// caller to roll against the creature's current stats.
+pub fn fire_hooks<'a>(
+    roller: &mut DiceRoller,
+    hooks: impl IntoIterator<Item = &'a TurnHook>,
+    regeneration_suppressed: bool,
+) -> Result<Vec<HookOutcome>, DiceError> {
+    let mut outcomes = Vec::new();
+    for hook in hooks {
+        let hook_id = hook.id.clone();
+        outcomes.push(match &hook.effect {
+            TurnEffect::Regeneration { .. } if regeneration_suppressed => {
+                HookOutcome::RegenerationSuppressed { hook_id }
+            }
+            TurnEffect::Regeneration { amount, .. } => HookOutcome::Regenerated {
+                hook_id,
+                amount: *amount,
+            },
+            TurnEffect::OngoingDamage { dice, damage_type } => {
+                let roll = roller.roll_expression(dice)?;
+                HookOutcome::Damage {
+                    hook_id,
+                    instance: DamageInstance::new(*damage_type, roll.total.max(0)),
+                    rolls: roll.rolls,
+                }
+            }
+            TurnEffect::RepeatSave {
+                condition_id,
+                ability,
+                dc,
+            } => HookOutcome::SaveDue {
+                hook_id,
+                condition_id: condition_id.clone(),
+                ability: *ability,
+                dc: *dc,
+            },
+        });
+    }
+    Ok(outcomes)
+}
+
+/// One creature's turn ended and the next began.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TurnChange {
+    pub round: u32,
+    pub new_round: bool,
+    pub ended: Option<String>,
+    pub started: String,
+    pub lair_action: bool,
+    pub end_of_turn: Vec<HookOutcome>,
+    pub start_of_turn: Vec<HookOutcome>,
+    /// The trigger of a readied action that lapsed unused.
+    pub readied_expired: Option<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct CombatScheduler {
+    order: Vec<InitiativeEntry>,
+    current: Option<usize>,
+    /// The acting creature left the order; `current` already points at the
+    /// next one.
+    current_removed: bool,
+    /// Whoever's turn it is or, if it left the order, just was.
+    acting: Option<InitiativeEntry>,
+    round: u32,
+    delayed: Vec<InitiativeEntry>,
+    readied: HashMap<String, String>,
+    hooks: Vec<TurnHook>,
+    regeneration_suppressed: HashSet<String>,
+}
+
+impl CombatScheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn order(&self) -> &[InitiativeEntry] {
+        &self.order
+    }
+
+    pub fn round(&self) -> u32 {
+        self.round
+    }
+
+    pub fn current(&self) -> Option<&InitiativeEntry> {
+        self.current
+            .filter(|_| !self.current_removed)
+            .and_then(|i| self.order.get(i))
+    }
+
+    pub fn is_delaying(&self, creature_id: &str) -> bool {
+        self.delayed.iter().any(|e| e.creature_id == creature_id)
+    }
+
+    fn contains(&self, creature_id: &str) -> bool {
+        self.order
+            .iter()
+            .chain(&self.delayed)
+            .any(|e| e.creature_id == creature_id)
+    }
+
+    /// Add a combatant, before or during combat. One joining mid-round whose
+    /// count has already passed first acts next round.
+    pub fn add(
+        &mut self,
+        roller: &mut DiceRoller,
+        creature_id: &str,
+        initiative: i32,
+        dexterity: i32,
+    ) -> Result<(), DndError> {
+        let mut entry = InitiativeEntry {
+            creature_id: creature_id.to_string(),
+            initiative,
+            dexterity,
+            roll_off: 0,
+            lair: false,
+        };
+        let taken: HashSet<i32> = self
+            .order
+            .iter()
+            .chain(&self.delayed)
+            .filter(|e| e.ties_with(&entry))
+            .map(|e| e.roll_off)
+            .collect();
+        // Reroll until no tied combatant has the same roll-off, as long as
+        // there is a d20 face left to land on.
+        loop {
+            entry.roll_off = roller.roll_d20();
+            if !taken.contains(&entry.roll_off) || taken.len() >= 20 {
+                break;
+            }
+        }
+        self.insert(entry)
+    }
+
+    /// The lair acts on initiative count 20, losing all ties.
+    pub fn add_lair_actions(&mut self, lair_id: &str) -> Result<(), DndError> {
+        self.insert(InitiativeEntry {
+            creature_id: lair_id.to_string(),
+            initiative: LAIR_INITIATIVE,
+            dexterity: 0,
+            roll_off: 0,
+            lair: true,
+        })
+    }
+
+    fn insert(&mut self, entry: InitiativeEntry) -> Result<(), DndError> {
+        if self.contains(&entry.creature_id) {
+            return Err(DndError::InvalidAction(format!(
+                "{} is already in the initiative order",
+                entry.creature_id
+            )));
+        }
+        let position = self
+            .order
+            .iter()
+            .position(|e| e.key() < entry.key())
+            .unwrap_or(self.order.len());
+        self.order.insert(position, entry);
+        if let Some(current) = self.current.as_mut() {
+            if position < *current || (position == *current && !self.current_removed) {
+                *current += 1;
+            }
+        }
+        Ok(())
+    }
+
+    /// Take a combatant out of the order, e.g. when it dies or flees.
+    pub fn remove(&mut self, creature_id: &str) -> Result<InitiativeEntry, DndError> {
+        self.hooks.retain(|h| h.creature_id != creature_id);
+        self.readied.remove(creature_id);
+        self.regeneration_suppressed.remove(creature_id);
+        if let Some(index) = self
+            .delayed
+            .iter()
+            .position(|e| e.creature_id == creature_id)
+        {
+            return Ok(self.delayed.remove(index));
+        }
+        let index = self
+            .order
+            .iter()
+            .position(|e| e.creature_id == creature_id)
+            .ok_or_else(|| DndError::EntityNotFound(creature_id.to_string()))?;
+        Ok(self.take(index))
+    }
+
+    fn take(&mut self, index: usize) -> InitiativeEntry {
+        let entry = self.order.remove(index);
+        if let Some(current) = self.current.as_mut() {
+            if index < *current {
+                *current -= 1;
+            } else if index == *current {
+                self.current_removed = true;
+            }
+        }
+        entry
+    }
+
+    pub fn add_hook(&mut self, hook: TurnHook) {
+        self.hooks.retain(|h| h.id != hook.id);
+        self.hooks.push(hook);
+    }
+
+    pub fn remove_hook(&mut self, hook_id: &str) -> Option<TurnHook> {
+        let index = self.hooks.iter().position(|h| h.id == hook_id)?;
+        Some(self.hooks.remove(index))
+    }
+
+    /// Note damage taken, switching off regeneration that type suppresses.
+    pub fn record_damage(&mut self, creature_id: &str, damage_type: DamageType) {
+        let suppresses = self.hooks.iter().any(|h| {
+            h.creature_id == creature_id
+                && matches!(&h.effect, TurnEffect::Regeneration { suppressed_by, .. }
+                    if suppressed_by.contains(&damage_type))
+        });
+        if suppresses {
+            self.regeneration_suppressed.insert(creature_id.to_string());
+        }
+    }
+
+    fn fire(
+        &mut self,
+        roller: &mut DiceRoller,
+        creature_id: &str,
+        timing: HookTiming,
+    ) -> Result<Vec<HookOutcome>, DndError> {
+        let suppressed =
+            timing == HookTiming::StartOfTurn && self.regeneration_suppressed.remove(creature_id);
+        let hooks = self
+            .hooks
+            .iter()
+            .filter(|h| h.creature_id == creature_id && h.timing == timing);
+        fire_hooks(roller, hooks, suppressed).map_err(|e| DndError::InvalidAction(e.to_string()))
+    }
+
+    /// Begin round 1 with the highest initiative.
+    pub fn start(&mut self, roller: &mut DiceRoller) -> Result<TurnChange, DndError> {
+        if self.current.is_some() {
+            return Err(DndError::InvalidAction(
+                "combat has already started".to_string(),
+            ));
+        }
+        if self.order.is_empty() {
+            return Err(DndError::InvalidAction(
+                "no combatants in the initiative order".to_string(),
+            ));
+        }
+        self.round = 1;
+        self.current = Some(0);
+        self.begin_turn(roller, None, Vec::new(), true)
+    }
+
+    /// End the current turn and start the next, wrapping into a new round.
+    pub fn advance(&mut self, roller: &mut DiceRoller) -> Result<TurnChange, DndError> {
+        let current = self
+            .current
+            .ok_or_else(|| DndError::InvalidAction("combat has not started".to_string()))?;
+        let (ended, end_of_turn, next) = if self.current_removed {
+            (None, Vec::new(), current)
+        } else {
+            let ended = self.order[current].creature_id.clone();
+            let outcomes = self.fire(roller, &ended, HookTiming::EndOfTurn)?;
+            (Some(ended), outcomes, current + 1)
+        };
+        self.current_removed = false;
+        if self.order.is_empty() {
+            self.current = None;
+            return Err(DndError::InvalidAction(
+                "no combatants left in the initiative order".to_string(),
+            ));
+        }
+        let new_round = next >= self.order.len();
+        if new_round {
+            self.round += 1;
+            // Creatures that ended a delay behind a lair action took count
+            // 20, which lair actions lose ties on; put them back in place.
+            self.order.sort_by_key(|e| std::cmp::Reverse(e.key()));
+        }
+        self.current = Some(if new_round { 0 } else { next });
+        self.begin_turn(roller, ended, end_of_turn, new_round)
+    }
+
+    fn begin_turn(
+        &mut self,
+        roller: &mut DiceRoller,
+        ended: Option<String>,
+        end_of_turn: Vec<HookOutcome>,
+        new_round: bool,
+    ) -> Result<TurnChange, DndError> {
+        let entry = self.order[self.current.unwrap_or(0)].clone();
+        self.acting = Some(entry.clone());
+        let start_of_turn = self.fire(roller, &entry.creature_id, HookTiming::StartOfTurn)?;
+        Ok(TurnChange {
+            round: self.round,
+            new_round,
+            ended,
+            started: entry.creature_id.clone(),
+            lair_action: entry.lair,
+            end_of_turn,
+            start_of_turn,
+            readied_expired: self.readied.remove(&entry.creature_id),
+        })
+    }
+
+    fn require_turn(&self, creature_id: &str, what: &str) -> Result<(), DndError> {
+        if self.current().is_some_and(|e| e.creature_id == creature_id) {
+            Ok(())
+        } else {
+            Err(DndError::RulesViolation(format!(
+                "{} can only {} on its own turn",
+                creature_id, what
+            )))
+        }
+    }
+
+    /// The current creature holds its turn; it leaves the order until
+    /// [`CombatScheduler::end_delay`]. The last creature in a round can
+    /// delay into the next one, but not when no one else is left to act.
+    pub fn delay(&mut self, roller: &mut DiceRoller) -> Result<TurnChange, DndError> {
+        let index = self
+            .current
+            .filter(|_| !self.current_removed)
+            .ok_or_else(|| DndError::InvalidAction("no one is taking a turn".to_string()))?;
+        if self.order.len() == 1 {
+            return Err(DndError::RulesViolation(format!(
+                "{} has no one to delay behind",
+                self.order[index].creature_id
+            )));
+        }
+        let entry = self.take(index);
+        let creature_id = entry.creature_id.clone();
+        self.delayed.push(entry);
+        let mut change = self.advance(roller)?;
+        change.ended = Some(creature_id);
+        Ok(change)
+    }
+
+    /// A delaying creature acts right after the current turn, taking the
+    /// initiative count of the creature that just acted from now on.
+    pub fn end_delay(&mut self, creature_id: &str) -> Result<(), DndError> {
+        let acted = self
+            .acting
+            .clone()
+            .ok_or_else(|| DndError::InvalidAction("combat has not started".to_string()))?;
+        let index = self
+            .delayed
+            .iter()
+            .position(|e| e.creature_id == creature_id)
+            .ok_or_else(|| DndError::InvalidAction(format!("{} is not delaying", creature_id)))?;
+        let mut entry = self.delayed.remove(index);
+        // Tied with the creature that acted, it sorts right after it.
+        entry.initiative = acted.initiative;
+        entry.dexterity = acted.dexterity;
+        entry.roll_off = acted.roll_off;
+        let current = self.current.unwrap_or(0);
+        let position = if self.current_removed {
+            current
+        } else {
+            current + 1
+        };
+        self.order.insert(position.min(self.order.len()), entry);
+        Ok(())
+    }
+
+    /// Ready an action; it lapses at the start of the creature's next turn.
+    pub fn ready(&mut self, creature_id: &str, trigger: &str) -> Result<(), DndError> {
+        self.require_turn(creature_id, "ready an action")?;
+        self.readied
+            .insert(creature_id.to_string(), trigger.to_string());
+        Ok(())
+    }
+
+    /// The trigger happened; returns it and uses up the readied action.
+    pub fn trigger_readied(&mut self, creature_id: &str) -> Result<String, DndError> {
+        self.readied.remove(creature_id).ok_or_else(|| {
+            DndError::InvalidAction(format!("{} has no readied action", creature_id))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ids(scheduler: &CombatScheduler) -> Vec<&str> {
+        scheduler
+            .order()
+            .iter()
+            .map(|e| e.creature_id.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn test_ties_break_on_dexterity_then_roll_off_and_lair_loses() {
+        let mut roller = DiceRoller::with_seed(11);
+        let mut scheduler = CombatScheduler::new();
+        scheduler.add(&mut roller, "fighter", 20, 12).unwrap();
+        scheduler.add_lair_actions("lair").unwrap();
+        scheduler.add(&mut roller, "rogue", 20, 18).unwrap();
+        scheduler.add(&mut roller, "goblin-1", 14, 14).unwrap();
+        scheduler.add(&mut roller, "goblin-2", 14, 14).unwrap();
+        scheduler.add(&mut roller, "dragon", 22, 10).unwrap();
+
+        let order = ids(&scheduler);
+        assert_eq!(&order[..4], &["dragon", "rogue", "fighter", "lair"]);
+        let goblins = &scheduler.order()[4..];
+        assert!(goblins[0].roll_off > goblins[1].roll_off);
+        assert!(scheduler.add(&mut roller, "rogue", 5, 10).is_err());
+    }
+
+    #[test]
+    fn test_rounds_wrap_and_late_joiners_wait_their_turn() {
+        let mut roller = DiceRoller::with_seed(1);
+        let mut scheduler = CombatScheduler::new();
+        scheduler.add(&mut roller, "a", 18, 10).unwrap();
+        scheduler.add(&mut roller, "b", 10, 10).unwrap();
+
+        assert_eq!(scheduler.start(&mut roller).unwrap().started, "a");
+        assert_eq!(scheduler.advance(&mut roller).unwrap().started, "b");
+        // Joins at 15 after that count passed: waits for round 2.
+        scheduler.add(&mut roller, "c", 15, 10).unwrap();
+        scheduler.add(&mut roller, "d", 5, 10).unwrap();
+        assert_eq!(scheduler.current().unwrap().creature_id, "b");
+        assert_eq!(scheduler.advance(&mut roller).unwrap().started, "d");
+
+        let change = scheduler.advance(&mut roller).unwrap();
+        assert!(change.new_round);
+        assert_eq!((change.round, change.started.as_str()), (2, "a"));
+        assert_eq!(scheduler.advance(&mut roller).unwrap().started, "c");
+    }
+
+    #[test]
+    fn test_removing_the_acting_creature_passes_the_turn() {
+        let mut roller = DiceRoller::with_seed(2);
+        let mut scheduler = CombatScheduler::new();
+        for (id, init) in [("a", 15), ("b", 10), ("c", 5)] {
+            scheduler.add(&mut roller, id, init, 10).unwrap();
+        }
+        scheduler.start(&mut roller).unwrap();
+        scheduler.advance(&mut roller).unwrap();
+        scheduler.remove("b").unwrap();
+        assert!(scheduler.current().is_none());
+
+        let change = scheduler.advance(&mut roller).unwrap();
+        assert_eq!((change.ended, change.started.as_str()), (None, "c"));
+    }
+
+    #[test]
+    fn test_delay_and_ready() {
+        let mut roller = DiceRoller::with_seed(3);
+        let mut scheduler = CombatScheduler::new();
+        for (id, init) in [("a", 15), ("b", 10), ("c", 5)] {
+            scheduler.add(&mut roller, id, init, 10).unwrap();
+        }
+        scheduler.start(&mut roller).unwrap();
+        let change = scheduler.delay(&mut roller).unwrap();
+        assert_eq!(
+            (change.ended.as_deref(), change.started.as_str()),
+            (Some("a"), "b")
+        );
+        assert!(scheduler.is_delaying("a"));
+
+        // "a" steps back in after "b" and keeps b's count from now on.
+        scheduler.end_delay("a").unwrap();
+        assert_eq!(ids(&scheduler), vec!["b", "a", "c"]);
+        assert_eq!(scheduler.order()[1].initiative, 10);
+
+        assert!(scheduler.ready("c", "goblin appears").is_err());
+        scheduler.ready("b", "goblin appears").unwrap();
+        assert_eq!(scheduler.advance(&mut roller).unwrap().started, "a");
+        scheduler.advance(&mut roller).unwrap();
+        let change = scheduler.advance(&mut roller).unwrap();
+        assert_eq!(change.started, "b");
+        assert_eq!(change.readied_expired.as_deref(), Some("goblin appears"));
+    }
+
+    #[test]
+    fn test_end_delay_after_the_acting_creature_left() {
+        let mut roller = DiceRoller::with_seed(5);
+        let mut scheduler = CombatScheduler::new();
+        for (id, init, dex) in [("a", 15, 10), ("b", 12, 8), ("c", 12, 16), ("d", 5, 10)] {
+            scheduler.add(&mut roller, id, init, dex).unwrap();
+        }
+        assert_eq!(ids(&scheduler), vec!["a", "c", "b", "d"]);
+        scheduler.start(&mut roller).unwrap();
+        scheduler.delay(&mut roller).unwrap();
+        scheduler.advance(&mut roller).unwrap();
+        // "b" drops mid-turn; "a" comes in behind it on b's count and
+        // Dexterity, not d's, and stays sorted.
+        scheduler.remove("b").unwrap();
+        scheduler.end_delay("a").unwrap();
+        assert_eq!(ids(&scheduler), vec!["c", "a", "d"]);
+        assert_eq!(
+            (
+                scheduler.order()[1].initiative,
+                scheduler.order()[1].dexterity
+            ),
+            (12, 8)
+        );
+        assert_eq!(scheduler.advance(&mut roller).unwrap().started, "a");
+    }
+
+    #[test]
+    fn test_delaying_at_the_end_of_the_round() {
+        let mut roller = DiceRoller::with_seed(6);
+        let mut scheduler = CombatScheduler::new();
+        scheduler.add(&mut roller, "a", 15, 10).unwrap();
+        scheduler.add(&mut roller, "b", 10, 10).unwrap();
+        scheduler.start(&mut roller).unwrap();
+        scheduler.advance(&mut roller).unwrap();
+
+        // The last creature of the round delays into the next one.
+        let change = scheduler.delay(&mut roller).unwrap();
+        assert!(change.new_round);
+        assert_eq!((change.round, change.started.as_str()), (2, "a"));
+        assert!(scheduler.is_delaying("b"));
+        scheduler.end_delay("b").unwrap();
+        assert_eq!(scheduler.advance(&mut roller).unwrap().started, "b");
+
+        // Alone in the order, there is no one to wait for.
+        scheduler.remove("a").unwrap();
+        scheduler.advance(&mut roller).unwrap();
+        assert!(scheduler.delay(&mut roller).is_err());
+        assert_eq!(scheduler.current().unwrap().creature_id, "b");
+        assert!(!scheduler.is_delaying("b"));
+    }
+
+    #[test]
+    fn test_turn_hooks_fire_and_regeneration_can_be_suppressed() {
+        let mut roller = DiceRoller::with_seed(4);
+        let mut scheduler = CombatScheduler::new();
+        scheduler.add(&mut roller, "troll", 12, 13).unwrap();
+        scheduler.add(&mut roller, "wizard", 8, 14).unwrap();
+        scheduler.add_hook(TurnHook::new(
+            "regen",
+            "troll",
+            HookTiming::StartOfTurn,
+            TurnEffect::Regeneration {
+                amount: 10,
+                suppressed_by: vec![DamageType::Fire, DamageType::Acid],
+            },
+        ));
+        scheduler.add_hook(TurnHook::new(
+            "burning",
+            "troll",
+            HookTiming::StartOfTurn,
+            TurnEffect::OngoingDamage {
+                dice: "1d4".to_string(),
+                damage_type: DamageType::Fire,
+            },
+        ));
+        scheduler.add_hook(TurnHook::new(
+            "hold",
+            "troll",
+            HookTiming::EndOfTurn,
+            TurnEffect::RepeatSave {
+                condition_id: "held".to_string(),
+                ability: Ability::WIS,
+                dc: 15,
+            },
+        ));
+
+        let start = scheduler.start(&mut roller).unwrap();
+        assert!(matches!(
+            start.start_of_turn[0],
+            HookOutcome::Regenerated { amount: 10, .. }
+        ));
+        assert!(
+            matches!(&start.start_of_turn[1], HookOutcome::Damage { instance, .. }
+            if (1..=4).contains(&instance.amount))
+        );
+
+        let change = scheduler.advance(&mut roller).unwrap();
+        assert!(matches!(
+            &change.end_of_turn[..],
+            [HookOutcome::SaveDue { dc: 15, .. }]
+        ));
+
+        scheduler.record_damage("troll", DamageType::Fire);
+        let change = scheduler.advance(&mut roller).unwrap();
+        assert!(matches!(
+            change.start_of_turn[0],
+            HookOutcome::RegenerationSuppressed { .. }
+        ));
+    }
+}
@@ -0,0 +1,30 @@
+//! D&D 5e Rules Engine
+//!
+//! RAW (Rules As Written) D&D 5th Edition mechanics, served over gRPC by the
+//! `rules-engine` binary.
+
+pub mod action_economy;
+pub mod advantage;
+pub mod armor_class;
+pub mod attack;
+pub mod attack_action;
+pub mod checks;
+pub mod class_features;
+pub mod combat;
+pub mod concentration;
+pub mod conditions;
+pub mod config;
+pub mod damage;
+pub mod dice;
+pub mod hit_points;
+pub mod initiative;
+pub mod reactions;
+pub mod resources;
+pub mod rest;
+pub mod service;
+pub mod special_attacks;
+pub mod spell_resolver;
+pub mod spell_slots;
+pub mod spellbook;
+pub mod spells;
+pub mod weapons;
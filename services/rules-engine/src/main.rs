@@ -2,21 +2,39 @@
 //!
 //! This service implements RAW (Rules As Written) D&D 5th Edition mechanics.
 
+use dnd_proto::rules::v1::rules_service_server::RulesServiceServer;
+use rules_engine::config::Config;
+use rules_engine::service::RulesServiceImpl;
+use std::net::SocketAddr;
+use tonic::transport::Server;
 use tracing::info;
+use tracing_subscriber::EnvFilter;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let config = Config::from_env()?;
+
     // Initialize tracing
-    tracing_subscriber::fmt::init();
+    tracing_subscriber::fmt()
+        .with_env_filter(
+            EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new(&config.log_level)),
+        )
+        .init();
 
     info!("Starting D&D 5e Rules Engine...");
 
-    // TODO: Implement gRPC server in Phase 4
-    info!("Rules Engine ready on port 50051");
+    let addr: SocketAddr = format!("0.0.0.0:{}", config.port).parse()?;
+    info!("Rules Engine ready on port {}", config.port);
 
-    // Keep the server running
-    tokio::signal::ctrl_c().await?;
-    info!("Shutting down Rules Engine");
+    Server::builder()
+        .add_service(RulesServiceServer::new(
+            RulesServiceImpl::new().with_rest_variant(config.rest_variant),
+        ))
+        .serve_with_shutdown(addr, async {
+            let _ = tokio::signal::ctrl_c().await;
+            info!("Shutting down Rules Engine");
+        })
+        .await?;
 
     Ok(())
 }
@@ -0,0 +1,571 @@
+//! Reactions and the trigger windows they interrupt (PHB p.190, 195).
+//!
+//! When something happens that a reaction can answer (a creature leaving
+//! reach, an attack hitting, a spell being cast) the action pauses and a
+//! window opens. Eligible reactors are worked out from what the caller knows
+//! about each creature: whether its reaction is unused, how far it is from
+//! the trigger and whether it can see it. The window resolves once every
+//! reactor has declared or declined, or when its timeout runs out, and the
+//! paused action continues with whatever reactions were declared. A creature
+//! has one reaction per round, however many windows it is offered in, and
+//! regains it at the start of its turn.
+
+use serde::{Deserialize, Serialize};
+use shared_rust::DndError;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{Duration, Instant};
+
+/// How long players get to answer a reaction prompt.
+pub const DEFAULT_REACTION_TIMEOUT: Duration = Duration::from_secs(30);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TriggerKind {
+    /// A creature moves out of another's reach.
+    LeavesReach {
+        mover_id: String,
+        disengaged: bool,
+    },
+    /// An attack roll is about to hit, before damage.
+    AttackHits {
+        attacker_id: String,
+        target_id: String,
+    },
+    /// An attack is made against any target.
+    AttackMade {
+        attacker_id: String,
+        target_id: String,
+    },
+    SpellCast {
+        caster_id: String,
+        spell_level: i32,
+    },
+    DamageTaken {
+        attacker_id: String,
+        target_id: String,
+    },
+}
+
+impl TriggerKind {
+    /// The creature whose doing opened the window.
+    pub fn actor_id(&self) -> &str {
+        match self {
+            TriggerKind::LeavesReach { mover_id, .. } => mover_id,
+            TriggerKind::AttackHits { attacker_id, .. }
+            | TriggerKind::AttackMade { attacker_id, .. }
+            | TriggerKind::DamageTaken { attacker_id, .. } => attacker_id,
+            TriggerKind::SpellCast { caster_id, .. } => caster_id,
+        }
+    }
+
+    fn target_id(&self) -> Option<&str> {
+        match self {
+            TriggerKind::AttackHits { target_id, .. }
+            | TriggerKind::AttackMade { target_id, .. }
+            | TriggerKind::DamageTaken { target_id, .. } => Some(target_id),
+            _ => None,
+        }
+    }
+
+    /// Prompt text for the players.
+    pub fn describe(&self) -> String {
+        match self {
+            TriggerKind::LeavesReach { mover_id, .. } => format!("{} leaves your reach", mover_id),
+            TriggerKind::AttackHits { attacker_id, .. } => {
+                format!("{}'s attack is about to hit you", attacker_id)
+            }
+            TriggerKind::AttackMade {
+                attacker_id,
+                target_id,
+            } => format!("{} attacks {}", attacker_id, target_id),
+            TriggerKind::SpellCast {
+                caster_id,
+                spell_level,
+            } => format!("{} casts a level {} spell", caster_id, spell_level),
+            TriggerKind::DamageTaken { attacker_id, .. } => {
+                format!("{} damaged you", attacker_id)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum ReactionKind {
+    OpportunityAttack,
+    Shield,
+    Counterspell,
+    HellishRebuke,
+    /// The Sentinel feat: attack a creature within 5 feet that attacks
+    /// someone else, and make opportunity attacks through Disengage.
+    Sentinel,
+}
+
+/// What the caller knows about a creature that might react, measured from
+/// the trigger's actor.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Reactor {
+    pub creature_id: String,
+    pub has_reaction: bool,
+    /// Reactions beyond the opportunity attack everyone has: spells
+    /// prepared with a slot to cast them, feats and so on.
+    pub options: Vec<ReactionKind>,
+    pub distance_ft: i32,
+    pub reach_ft: i32,
+    pub can_see_actor: bool,
+}
+
+impl Reactor {
+    pub fn new(creature_id: impl Into<String>, distance_ft: i32) -> Self {
+        Self {
+            creature_id: creature_id.into(),
+            has_reaction: true,
+            options: Vec::new(),
+            distance_ft,
+            reach_ft: 5,
+            can_see_actor: true,
+        }
+    }
+
+    pub fn with_option(mut self, option: ReactionKind) -> Self {
+        self.options.push(option);
+        self
+    }
+
+    /// The reactions this creature could take against `trigger`.
+    pub fn eligible_for(&self, trigger: &TriggerKind) -> Vec<ReactionKind> {
+        if !self.has_reaction || self.creature_id == trigger.actor_id() {
+            return Vec::new();
+        }
+        let is_target = trigger.target_id() == Some(self.creature_id.as_str());
+        let sentinel = self.options.contains(&ReactionKind::Sentinel);
+        let mut eligible = Vec::new();
+        match trigger {
+            TriggerKind::LeavesReach { disengaged, .. } => {
+                if (!disengaged || sentinel)
+                    && self.can_see_actor
+                    && self.distance_ft <= self.reach_ft
+                {
+                    eligible.push(ReactionKind::OpportunityAttack);
+                }
+            }
+            TriggerKind::AttackHits { .. } => {
+                if is_target && self.options.contains(&ReactionKind::Shield) {
+                    eligible.push(ReactionKind::Shield);
+                }
+            }
+            TriggerKind::AttackMade { .. } => {
+                if sentinel && !is_target && self.distance_ft <= 5 {
+                    eligible.push(ReactionKind::Sentinel);
+                }
+            }
+            TriggerKind::SpellCast { .. } => {
+                if self.options.contains(&ReactionKind::Counterspell)
+                    && self.can_see_actor
+                    && self.distance_ft <= 60
+                {
+                    eligible.push(ReactionKind::Counterspell);
+                }
+            }
+            TriggerKind::DamageTaken { .. } => {
+                if is_target
+                    && self.options.contains(&ReactionKind::HellishRebuke)
+                    && self.can_see_actor
+                    && self.distance_ft <= 60
+                {
+                    eligible.push(ReactionKind::HellishRebuke);
+                }
+            }
+        }
+        eligible
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Decision {
+    React(ReactionKind),
+    Decline,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DeclaredReaction {
+    pub creature_id: String,
+    pub reaction: ReactionKind,
+}
+
+/// A window that has closed; the paused action resumes with `reactions`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ResolvedWindow {
+    pub window_id: String,
+    pub trigger: TriggerKind,
+    pub reactions: Vec<DeclaredReaction>,
+    /// Reactors who never answered before the timeout.
+    pub timed_out: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WindowStatus {
+    /// Still waiting on these reactors, each with its options.
+    Waiting {
+        window_id: String,
+        awaiting: BTreeMap<String, Vec<ReactionKind>>,
+    },
+    Resolved(ResolvedWindow),
+}
+
+#[derive(Debug)]
+struct Window {
+    trigger: TriggerKind,
+    awaiting: BTreeMap<String, Vec<ReactionKind>>,
+    reactions: Vec<DeclaredReaction>,
+    deadline: Instant,
+}
+
+impl Window {
+    fn status(&self, window_id: &str) -> WindowStatus {
+        WindowStatus::Waiting {
+            window_id: window_id.to_string(),
+            awaiting: self.awaiting.clone(),
+        }
+    }
+
+    fn resolve(self, window_id: &str) -> ResolvedWindow {
+        ResolvedWindow {
+            window_id: window_id.to_string(),
+            trigger: self.trigger,
+            reactions: self.reactions,
+            timed_out: self.awaiting.into_keys().collect(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct TriggerBus {
+    windows: HashMap<String, Window>,
+    timeout: Duration,
+    /// Creatures that reacted since the start of their last turn.
+    reacted: HashSet<String>,
+}
+
+impl TriggerBus {
+    pub fn new(timeout: Duration) -> Self {
+        Self {
+            windows: HashMap::new(),
+            timeout,
+            reacted: HashSet::new(),
+        }
+    }
+
+    pub fn is_open(&self, window_id: &str) -> bool {
+        self.windows.contains_key(window_id)
+    }
+
+    pub fn has_reacted(&self, creature_id: &str) -> bool {
+        self.reacted.contains(creature_id)
+    }
+
+    /// The creature's turn started, so its reaction is back.
+    pub fn regain_reaction(&mut self, creature_id: &str) {
+        self.reacted.remove(creature_id);
+    }
+
+    /// Pause on `trigger`. With nobody able to react the window resolves
+    /// straight away.
+    pub fn open(
+        &mut self,
+        window_id: impl Into<String>,
+        trigger: TriggerKind,
+        candidates: &[Reactor],
+        now: Instant,
+    ) -> Result<WindowStatus, DndError> {
+        let window_id = window_id.into();
+        if self.windows.contains_key(&window_id) {
+            return Err(DndError::InvalidAction(format!(
+                "reaction window {} is already open",
+                window_id
+            )));
+        }
+        let awaiting: BTreeMap<_, _> = candidates
+            .iter()
+            .filter(|c| !self.reacted.contains(&c.creature_id))
+            .map(|c| (c.creature_id.clone(), c.eligible_for(&trigger)))
+            .filter(|(_, options)| !options.is_empty())
+            .collect();
+        let window = Window {
+            trigger,
+            awaiting,
+            reactions: Vec::new(),
+            deadline: now + self.timeout,
+        };
+        if window.awaiting.is_empty() {
+            return Ok(WindowStatus::Resolved(window.resolve(&window_id)));
+        }
+        let status = window.status(&window_id);
+        self.windows.insert(window_id, window);
+        Ok(status)
+    }
+
+    /// Record a reactor's answer. The window resolves with the last one.
+    /// Answers after the deadline are refused; [`TriggerBus::expire`]
+    /// closes the window.
+    pub fn declare(
+        &mut self,
+        window_id: &str,
+        creature_id: &str,
+        decision: Decision,
+        now: Instant,
+    ) -> Result<WindowStatus, DndError> {
+        let window = self
+            .windows
+            .get_mut(window_id)
+            .ok_or_else(|| DndError::EntityNotFound(format!("reaction window {}", window_id)))?;
+        if now >= window.deadline {
+            return Err(DndError::RulesViolation(format!(
+                "reaction window {} timed out",
+                window_id
+            )));
+        }
+        let options = window.awaiting.get(creature_id).ok_or_else(|| {
+            DndError::RulesViolation(format!(
+                "{} can't react in window {}",
+                creature_id, window_id
+            ))
+        })?;
+        if let Decision::React(reaction) = decision {
+            if !options.contains(&reaction) {
+                return Err(DndError::RulesViolation(format!(
+                    "{} can't use {:?} against this trigger",
+                    creature_id, reaction
+                )));
+            }
+            // Offered in several windows at once, it can still only react
+            // in one of them.
+            if !self.reacted.insert(creature_id.to_string()) {
+                return Err(DndError::RulesViolation(format!(
+                    "{} has already used its reaction",
+                    creature_id
+                )));
+            }
+            window.reactions.push(DeclaredReaction {
+                creature_id: creature_id.to_string(),
+                reaction,
+            });
+        }
+        window.awaiting.remove(creature_id);
+        if !window.awaiting.is_empty() {
+            return Ok(window.status(window_id));
+        }
+        self.windows
+            .remove(window_id)
+            .map(|window| WindowStatus::Resolved(window.resolve(window_id)))
+            .ok_or_else(|| DndError::EntityNotFound(format!("reaction window {}", window_id)))
+    }
+
+    /// Close every window past its deadline; silence counts as declining.
+    pub fn expire(&mut self, now: Instant) -> Vec<ResolvedWindow> {
+        let expired: Vec<String> = self
+            .windows
+            .iter()
+            .filter(|(_, w)| w.deadline <= now)
+            .map(|(id, _)| id.clone())
+            .collect();
+        expired
+            .into_iter()
+            .filter_map(|id| self.windows.remove(&id).map(|w| w.resolve(&id)))
+            .collect()
+    }
+}
+
+impl Default for TriggerBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_REACTION_TIMEOUT)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn leaves(disengaged: bool) -> TriggerKind {
+        TriggerKind::LeavesReach {
+            mover_id: "goblin".to_string(),
+            disengaged,
+        }
+    }
+
+    #[test]
+    fn test_opportunity_attack_eligibility() {
+        let fighter = Reactor::new("fighter", 5);
+        assert_eq!(
+            fighter.eligible_for(&leaves(false)),
+            vec![ReactionKind::OpportunityAttack]
+        );
+        assert!(fighter.eligible_for(&leaves(true)).is_empty());
+
+        let sentinel = Reactor::new("fighter", 5).with_option(ReactionKind::Sentinel);
+        assert_eq!(sentinel.eligible_for(&leaves(true)).len(), 1);
+
+        let far = Reactor::new("wizard", 10);
+        let blind = Reactor {
+            can_see_actor: false,
+            ..Reactor::new("rogue", 5)
+        };
+        let spent = Reactor {
+            has_reaction: false,
+            ..Reactor::new("cleric", 5)
+        };
+        for reactor in [far, blind, spent] {
+            assert!(reactor.eligible_for(&leaves(false)).is_empty());
+        }
+    }
+
+    #[test]
+    fn test_spells_and_feats_need_the_right_trigger() {
+        let hit = TriggerKind::AttackHits {
+            attacker_id: "orc".to_string(),
+            target_id: "wizard".to_string(),
+        };
+        let wizard = Reactor::new("wizard", 5)
+            .with_option(ReactionKind::Shield)
+            .with_option(ReactionKind::Counterspell);
+        let ally = Reactor::new("cleric", 5).with_option(ReactionKind::Shield);
+        assert_eq!(wizard.eligible_for(&hit), vec![ReactionKind::Shield]);
+        assert!(ally.eligible_for(&hit).is_empty());
+
+        let cast = TriggerKind::SpellCast {
+            caster_id: "mage".to_string(),
+            spell_level: 3,
+        };
+        assert_eq!(wizard.eligible_for(&cast), vec![ReactionKind::Counterspell]);
+        let far_wizard = Reactor {
+            distance_ft: 65,
+            ..wizard.clone()
+        };
+        assert!(far_wizard.eligible_for(&cast).is_empty());
+
+        let attack = TriggerKind::AttackMade {
+            attacker_id: "orc".to_string(),
+            target_id: "wizard".to_string(),
+        };
+        let sentinel = Reactor::new("fighter", 5).with_option(ReactionKind::Sentinel);
+        assert_eq!(sentinel.eligible_for(&attack), vec![ReactionKind::Sentinel]);
+
+        let rebuke = TriggerKind::DamageTaken {
+            attacker_id: "orc".to_string(),
+            target_id: "warlock".to_string(),
+        };
+        let warlock = Reactor::new("warlock", 30).with_option(ReactionKind::HellishRebuke);
+        assert_eq!(
+            warlock.eligible_for(&rebuke),
+            vec![ReactionKind::HellishRebuke]
+        );
+    }
+
+    #[test]
+    fn test_window_resolves_when_everyone_answers() {
+        let mut bus = TriggerBus::default();
+        let now = Instant::now();
+        let status = bus
+            .open(
+                "w1",
+                leaves(false),
+                &[
+                    Reactor::new("fighter", 5),
+                    Reactor::new("paladin", 5),
+                    Reactor::new("wizard", 30),
+                ],
+                now,
+            )
+            .unwrap();
+        let WindowStatus::Waiting { awaiting, .. } = status else {
+            panic!("expected a pending window");
+        };
+        assert_eq!(awaiting.len(), 2);
+
+        assert!(bus
+            .declare(
+                "w1",
+                "wizard",
+                Decision::React(ReactionKind::OpportunityAttack),
+                now
+            )
+            .is_err());
+        assert!(bus
+            .declare("w1", "fighter", Decision::React(ReactionKind::Shield), now)
+            .is_err());
+        bus.declare(
+            "w1",
+            "fighter",
+            Decision::React(ReactionKind::OpportunityAttack),
+            now,
+        )
+        .unwrap();
+        let WindowStatus::Resolved(resolved) = bus
+            .declare("w1", "paladin", Decision::Decline, now)
+            .unwrap()
+        else {
+            panic!("expected the window to resolve");
+        };
+        assert_eq!(resolved.reactions.len(), 1);
+        assert!(resolved.timed_out.is_empty());
+        assert!(!bus.is_open("w1"));
+    }
+
+    #[test]
+    fn test_nobody_eligible_and_timeouts() {
+        let mut bus = TriggerBus::new(Duration::from_secs(10));
+        let now = Instant::now();
+        let status = bus
+            .open("w1", leaves(true), &[Reactor::new("fighter", 5)], now)
+            .unwrap();
+        assert!(matches!(status, WindowStatus::Resolved(_)));
+
+        bus.open("w2", leaves(false), &[Reactor::new("fighter", 5)], now)
+            .unwrap();
+        assert!(bus.expire(now + Duration::from_secs(5)).is_empty());
+        let expired = bus.expire(now + Duration::from_secs(10));
+        assert_eq!(expired[0].timed_out, vec!["fighter".to_string()]);
+        assert!(expired[0].reactions.is_empty());
+    }
+
+    #[test]
+    fn test_late_answers_are_refused() {
+        let mut bus = TriggerBus::new(Duration::from_secs(10));
+        let now = Instant::now();
+        bus.open("w1", leaves(false), &[Reactor::new("fighter", 5)], now)
+            .unwrap();
+        let late = now + Duration::from_secs(10);
+        let err = bus
+            .declare(
+                "w1",
+                "fighter",
+                Decision::React(ReactionKind::OpportunityAttack),
+                late,
+            )
+            .unwrap_err();
+        assert!(err.to_string().contains("timed out"));
+        assert!(!bus.has_reacted("fighter"));
+        assert_eq!(bus.expire(late)[0].timed_out, vec!["fighter".to_string()]);
+    }
+
+    #[test]
+    fn test_one_reaction_across_open_windows() {
+        let mut bus = TriggerBus::default();
+        let now = Instant::now();
+        let fighter = [Reactor::new("fighter", 5)];
+        bus.open("w1", leaves(false), &fighter, now).unwrap();
+        bus.open("w2", leaves(false), &fighter, now).unwrap();
+        let attack = Decision::React(ReactionKind::OpportunityAttack);
+        bus.declare("w1", "fighter", attack, now).unwrap();
+
+        let err = bus.declare("w2", "fighter", attack, now).unwrap_err();
+        assert!(err.to_string().contains("already used"));
+        bus.declare("w2", "fighter", Decision::Decline, now)
+            .unwrap();
+        // Spent reactors aren't offered new windows until their turn.
+        let status = bus.open("w3", leaves(false), &fighter, now).unwrap();
+        assert!(matches!(status, WindowStatus::Resolved(_)));
+
+        bus.regain_reaction("fighter");
+        let status = bus.open("w4", leaves(false), &fighter, now).unwrap();
+        assert!(matches!(status, WindowStatus::Waiting { .. }));
+    }
+}
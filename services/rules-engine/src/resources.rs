@@ -0,0 +1,339 @@
+//! Limited-use resources: Rage, Ki, Bardic Inspiration, Channel Divinity,
+//! Wild Shape, item charges and monster abilities like a dragon's breath.
+//!
+//! Every resource has a count and a recharge rule. Rests, dawn and the start
+//! of a creature's turn are [`RechargeTrigger`]s the tracker responds to;
+//! spending checks the count first so callers can validate before
+//! committing anything else.
+
+use crate::dice::{DiceRoller, DieType};
+use serde::{Deserialize, Serialize};
+use shared_rust::{DndError, EntityId};
+use std::collections::{BTreeMap, HashMap};
+
+/// When a resource comes back.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Recharge {
+    /// A short or long rest.
+    ShortRest,
+    LongRest,
+    /// Item charges, usually a die roll's worth (see [`Resource::regain`]).
+    Dawn,
+    /// "Recharge 5-6": at the start of each of the creature's turns, a d6
+    /// roll of at least `min` restores every use.
+    Roll {
+        min: i32,
+    },
+    /// Every turn, like legendary actions.
+    TurnStart,
+    Never,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RechargeTrigger {
+    ShortRest,
+    LongRest,
+    Dawn,
+    TurnStart,
+}
+
+impl Recharge {
+    /// A long rest also counts as a short one.
+    pub fn fires_on(&self, trigger: RechargeTrigger) -> bool {
+        matches!(
+            (self, trigger),
+            (Recharge::ShortRest, RechargeTrigger::ShortRest)
+                | (Recharge::ShortRest, RechargeTrigger::LongRest)
+                | (Recharge::LongRest, RechargeTrigger::LongRest)
+                | (Recharge::Dawn, RechargeTrigger::Dawn)
+                | (Recharge::Roll { .. }, RechargeTrigger::TurnStart)
+                | (Recharge::TurnStart, RechargeTrigger::TurnStart)
+        )
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Resource {
+    pub id: String,
+    pub name: String,
+    pub max: i32,
+    pub current: i32,
+    pub recharge: Recharge,
+    /// Dice regained when it recharges, e.g. "1d6+1" for a wand at dawn.
+    /// All uses come back when unset.
+    pub regain: Option<String>,
+}
+
+impl Resource {
+    pub fn new(
+        id: impl Into<String>,
+        name: impl Into<String>,
+        max: i32,
+        recharge: Recharge,
+    ) -> Self {
+        let max = max.max(0);
+        Self {
+            id: id.into(),
+            name: name.into(),
+            max,
+            current: max,
+            recharge,
+            regain: None,
+        }
+    }
+
+    pub fn with_regain(mut self, dice: impl Into<String>) -> Self {
+        self.regain = Some(dice.into());
+        self
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.current >= self.max
+    }
+}
+
+/// A resource that came back from a trigger.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recharged {
+    pub resource_id: String,
+    pub name: String,
+    pub regained: i32,
+    /// The d6 for a "Recharge X-Y" resource or the regain dice total.
+    pub roll: Option<i32>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ResourceTracker {
+    resources: HashMap<EntityId, BTreeMap<String, Resource>>,
+}
+
+impl ResourceTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add or replace `owner`'s resource with the same id.
+    pub fn add(&mut self, owner: EntityId, resource: Resource) {
+        self.resources
+            .entry(owner)
+            .or_default()
+            .insert(resource.id.clone(), resource);
+    }
+
+    pub fn remove_owner(&mut self, owner: EntityId) {
+        self.resources.remove(&owner);
+    }
+
+    pub fn get(&self, owner: EntityId, id: &str) -> Option<&Resource> {
+        self.resources.get(&owner).and_then(|r| r.get(id))
+    }
+
+    /// `owner`'s resources, ordered by id.
+    pub fn resources(&self, owner: EntityId) -> impl Iterator<Item = &Resource> {
+        self.resources
+            .get(&owner)
+            .into_iter()
+            .flat_map(|r| r.values())
+    }
+
+    fn resource_mut(&mut self, owner: EntityId, id: &str) -> Result<&mut Resource, DndError> {
+        self.resources
+            .get_mut(&owner)
+            .and_then(|r| r.get_mut(id))
+            .ok_or_else(|| DndError::EntityNotFound(format!("{} has no resource {}", owner, id)))
+    }
+
+    /// Whether `owner` can spend `amount` uses of `id`, without spending.
+    pub fn check(&self, owner: EntityId, id: &str, amount: i32) -> Result<&Resource, DndError> {
+        if amount < 1 {
+            return Err(DndError::InvalidAction(format!(
+                "must spend at least one use of {}, not {}",
+                id, amount
+            )));
+        }
+        let resource = self
+            .get(owner, id)
+            .ok_or_else(|| DndError::EntityNotFound(format!("{} has no resource {}", owner, id)))?;
+        if resource.current < amount {
+            return Err(DndError::RulesViolation(format!(
+                "{} has {} of {} uses of {} left, needs {}",
+                owner, resource.current, resource.max, resource.name, amount
+            )));
+        }
+        Ok(resource)
+    }
+
+    /// Spend `amount` uses, returning how many are left.
+    pub fn spend(&mut self, owner: EntityId, id: &str, amount: i32) -> Result<i32, DndError> {
+        self.check(owner, id, amount)?;
+        let resource = self.resource_mut(owner, id)?;
+        resource.current -= amount;
+        Ok(resource.current)
+    }
+
+    /// Give back up to `amount` uses (Wild Shape from Druid's 20th level,
+    /// Font of Magic and the like), returning how many were restored.
+    pub fn restore(&mut self, owner: EntityId, id: &str, amount: i32) -> Result<i32, DndError> {
+        let resource = self.resource_mut(owner, id)?;
+        let restored = amount.clamp(0, resource.max - resource.current);
+        resource.current += restored;
+        Ok(restored)
+    }
+
+    /// Recharge everything of `owner`'s that `trigger` restores. Resources
+    /// already full are skipped, so a breath weapon only rolls once spent.
+    pub fn trigger(
+        &mut self,
+        roller: &mut DiceRoller,
+        owner: EntityId,
+        trigger: RechargeTrigger,
+    ) -> Result<Vec<Recharged>, DndError> {
+        let mut recharged = Vec::new();
+        let Some(resources) = self.resources.get_mut(&owner) else {
+            return Ok(recharged);
+        };
+        for resource in resources.values_mut() {
+            if resource.is_full() || !resource.recharge.fires_on(trigger) {
+                continue;
+            }
+            let (amount, roll) = match (resource.recharge, &resource.regain) {
+                (Recharge::Roll { min }, _) => {
+                    let roll = roller.roll_die(DieType::D6).result;
+                    (if roll >= min { resource.max } else { 0 }, Some(roll))
+                }
+                (_, Some(dice)) => {
+                    let total = roller
+                        .roll_expression(dice)
+                        .map_err(|e| DndError::InvalidAction(e.to_string()))?
+                        .total;
+                    (total, Some(total))
+                }
+                (_, None) => (resource.max, None),
+            };
+            let before = resource.current;
+            resource.current = (resource.current + amount.max(0)).min(resource.max);
+            if resource.current > before {
+                recharged.push(Recharged {
+                    resource_id: resource.id.clone(),
+                    name: resource.name.clone(),
+                    regained: resource.current - before,
+                    roll,
+                });
+            }
+        }
+        Ok(recharged)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monk_and_dragon() -> (ResourceTracker, EntityId, EntityId) {
+        let mut tracker = ResourceTracker::new();
+        let monk = EntityId::new();
+        let dragon = EntityId::new();
+        tracker.add(monk, Resource::new("ki", "Ki", 5, Recharge::ShortRest));
+        tracker.add(
+            monk,
+            Resource::new("wand", "Wand of Magic Missiles", 7, Recharge::Dawn).with_regain("1d6+1"),
+        );
+        tracker.add(
+            dragon,
+            Resource::new("breath", "Fire Breath", 1, Recharge::Roll { min: 5 }),
+        );
+        (tracker, monk, dragon)
+    }
+
+    #[test]
+    fn test_spend_validates_count() {
+        let (mut tracker, monk, dragon) = monk_and_dragon();
+        assert_eq!(tracker.spend(monk, "ki", 2).unwrap(), 3);
+        assert!(matches!(
+            tracker.spend(monk, "ki", 4),
+            Err(DndError::RulesViolation(_))
+        ));
+        assert_eq!(tracker.get(monk, "ki").unwrap().current, 3);
+        assert!(matches!(
+            tracker.check(dragon, "ki", 1),
+            Err(DndError::EntityNotFound(_))
+        ));
+        assert!(matches!(
+            tracker.spend(monk, "ki", 0),
+            Err(DndError::InvalidAction(_))
+        ));
+        assert_eq!(tracker.restore(monk, "ki", 5).unwrap(), 2);
+    }
+
+    #[test]
+    fn test_rest_triggers() {
+        let (mut tracker, monk, _) = monk_and_dragon();
+        tracker.add(
+            monk,
+            Resource::new("wild-shape", "Wild Shape", 2, Recharge::ShortRest),
+        );
+        tracker.add(monk, Resource::new("rage", "Rage", 3, Recharge::LongRest));
+        for id in ["ki", "rage", "wild-shape"] {
+            tracker.spend(monk, id, 1).unwrap();
+        }
+        let mut roller = DiceRoller::with_seed(1);
+
+        let short = tracker
+            .trigger(&mut roller, monk, RechargeTrigger::ShortRest)
+            .unwrap();
+        let ids: Vec<_> = short.iter().map(|r| r.resource_id.as_str()).collect();
+        assert_eq!(ids, vec!["ki", "wild-shape"]);
+        assert_eq!(tracker.get(monk, "rage").unwrap().current, 2);
+
+        let long = tracker
+            .trigger(&mut roller, monk, RechargeTrigger::LongRest)
+            .unwrap();
+        assert_eq!(long.len(), 1);
+        assert_eq!(long[0].resource_id, "rage");
+        assert!(tracker
+            .resources(monk)
+            .all(|r| r.id == "wand" || r.is_full()));
+    }
+
+    #[test]
+    fn test_dawn_regains_dice() {
+        let (mut tracker, monk, _) = monk_and_dragon();
+        tracker.spend(monk, "wand", 7).unwrap();
+        let mut roller = DiceRoller::with_seed(4);
+        let dawn = tracker
+            .trigger(&mut roller, monk, RechargeTrigger::Dawn)
+            .unwrap();
+        let regained = dawn[0].regained;
+        assert!((2..=7).contains(&regained));
+        assert_eq!(dawn[0].roll, Some(regained));
+        assert_eq!(tracker.get(monk, "wand").unwrap().current, regained);
+    }
+
+    #[test]
+    fn test_recharge_roll_at_turn_start() {
+        let (mut tracker, _, dragon) = monk_and_dragon();
+        let mut roller = DiceRoller::with_seed(7);
+        // Nothing to roll while the breath is available.
+        assert!(tracker
+            .trigger(&mut roller, dragon, RechargeTrigger::TurnStart)
+            .unwrap()
+            .is_empty());
+
+        tracker.spend(dragon, "breath", 1).unwrap();
+        let mut turns = 0;
+        while !tracker.get(dragon, "breath").unwrap().is_full() {
+            let recharged = tracker
+                .trigger(&mut roller, dragon, RechargeTrigger::TurnStart)
+                .unwrap();
+            turns += 1;
+            if let Some(r) = recharged.first() {
+                assert!(r.roll.unwrap() >= 5);
+            }
+            assert!(turns < 50);
+        }
+        assert!(tracker
+            .trigger(&mut roller, dragon, RechargeTrigger::LongRest)
+            .unwrap()
+            .is_empty());
+    }
+}
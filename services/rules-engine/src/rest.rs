@@ -0,0 +1,460 @@
+//! Short and long rests (PHB p.186, DMG p.267).
+//!
+//! A short rest lets a creature spend hit dice, each healing the die plus
+//! its Constitution modifier, and recharges short-rest features and pact
+//! slots. A long rest restores hit points and spell slots, gives back half
+//! the creature's hit dice, recharges every feature and removes one level of
+//! exhaustion. Limited-use features recharge through the
+//! [`ResourceTracker`]. How long each rest takes depends on the session's
+//! [`RestVariant`].
+
+use crate::conditions::ConditionManager;
+use crate::dice::{DiceRoller, DieType};
+use crate::hit_points::HitPoints;
+use crate::resources::{RechargeTrigger, Recharged, ResourceTracker};
+use crate::spell_slots::{SorceryPoints, SpellSlots};
+use serde::{Deserialize, Serialize};
+use shared_rust::{DndError, EntityId};
+use std::time::Duration;
+
+const HOUR: Duration = Duration::from_secs(60 * 60);
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RestVariant {
+    /// Short rest 1 hour, long rest 8 hours.
+    #[default]
+    Standard,
+    /// Short rest 8 hours, long rest 7 days.
+    GrittyRealism,
+    /// Short rest 5 minutes, long rest 1 hour.
+    EpicHeroism,
+}
+
+impl RestVariant {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().replace(['-', ' '], "_").as_str() {
+            "standard" => Some(RestVariant::Standard),
+            "gritty_realism" | "gritty" => Some(RestVariant::GrittyRealism),
+            "epic_heroism" | "epic" => Some(RestVariant::EpicHeroism),
+            _ => None,
+        }
+    }
+
+    pub fn duration(&self, kind: RestKind) -> Duration {
+        match (self, kind) {
+            (RestVariant::Standard, RestKind::Short) => HOUR,
+            (RestVariant::Standard, RestKind::Long) => HOUR * 8,
+            (RestVariant::GrittyRealism, RestKind::Short) => HOUR * 8,
+            (RestVariant::GrittyRealism, RestKind::Long) => HOUR * 24 * 7,
+            (RestVariant::EpicHeroism, RestKind::Short) => Duration::from_secs(5 * 60),
+            (RestVariant::EpicHeroism, RestKind::Long) => HOUR,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RestKind {
+    Short,
+    Long,
+}
+
+/// Hit dice of one size; multiclass characters have one pool per size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HitDicePool {
+    pub die: DieType,
+    pub max: i32,
+    pub remaining: i32,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HitDice {
+    pub pools: Vec<HitDicePool>,
+}
+
+impl HitDice {
+    /// Full pools from `(die, class level)` pairs, merging equal dice.
+    pub fn for_classes(classes: &[(DieType, i32)]) -> Self {
+        let mut dice = Self::default();
+        for (die, level) in classes {
+            match dice.pools.iter_mut().find(|p| p.die == *die) {
+                Some(pool) => {
+                    pool.max += (*level).max(0);
+                    pool.remaining = pool.max;
+                }
+                None => dice.pools.push(HitDicePool {
+                    die: *die,
+                    max: (*level).max(0),
+                    remaining: (*level).max(0),
+                }),
+            }
+        }
+        // Largest first, the order dice are given back on a long rest.
+        dice.pools.sort_by_key(|p| std::cmp::Reverse(p.die as i32));
+        dice
+    }
+
+    pub fn total(&self) -> i32 {
+        self.pools.iter().map(|p| p.max).sum()
+    }
+
+    pub fn remaining(&self) -> i32 {
+        self.pools.iter().map(|p| p.remaining).sum()
+    }
+
+    /// Spend one `die`, healing its roll plus `con_modifier` (at least 0).
+    pub fn spend(
+        &mut self,
+        roller: &mut DiceRoller,
+        die: DieType,
+        con_modifier: i32,
+    ) -> Result<HitDieRoll, DndError> {
+        let pool = self
+            .pools
+            .iter_mut()
+            .find(|p| p.die == die && p.remaining > 0)
+            .ok_or_else(|| {
+                DndError::RulesViolation(format!("no d{} hit dice left to spend", die as i32))
+            })?;
+        pool.remaining -= 1;
+        let roll = roller.roll_die(die).result;
+        Ok(HitDieRoll {
+            die,
+            roll,
+            con_modifier,
+            healing: (roll + con_modifier).max(0),
+        })
+    }
+
+    /// A long rest gives back spent dice up to half the total, minimum one.
+    /// Returns how many were regained.
+    pub fn recover(&mut self) -> i32 {
+        let mut budget = (self.total() / 2).max(1);
+        let mut regained = 0;
+        for pool in &mut self.pools {
+            let back = (pool.max - pool.remaining).min(budget);
+            pool.remaining += back;
+            budget -= back;
+            regained += back;
+        }
+        regained
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HitDieRoll {
+    pub die: DieType,
+    pub roll: i32,
+    pub con_modifier: i32,
+    pub healing: i32,
+}
+
+/// Everything a rest can restore on one creature.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestingCreature {
+    pub creature_id: String,
+    /// Owner of the creature's resources in the [`ResourceTracker`].
+    pub entity_id: EntityId,
+    pub hit_points: HitPoints,
+    pub hit_dice: HitDice,
+    pub con_modifier: i32,
+    pub spell_slots: Option<SpellSlots>,
+    pub sorcery_points: Option<SorceryPoints>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RestReport {
+    pub kind: RestKind,
+    pub duration: Duration,
+    pub hit_dice_spent: Vec<HitDieRoll>,
+    pub hit_dice_regained: i32,
+    pub hp_regained: i32,
+    pub recharged: Vec<Recharged>,
+    pub slots_restored: bool,
+    pub exhaustion_removed: i32,
+}
+
+impl RestReport {
+    fn new(kind: RestKind, variant: RestVariant) -> Self {
+        Self {
+            kind,
+            duration: variant.duration(kind),
+            hit_dice_spent: Vec::new(),
+            hit_dice_regained: 0,
+            hp_regained: 0,
+            recharged: Vec::new(),
+            slots_restored: false,
+            exhaustion_removed: 0,
+        }
+    }
+}
+
+fn recharge_features(
+    roller: &mut DiceRoller,
+    creature: &RestingCreature,
+    resources: &mut ResourceTracker,
+    report: &mut RestReport,
+) -> Result<(), DndError> {
+    let trigger = match report.kind {
+        RestKind::Short => RechargeTrigger::ShortRest,
+        RestKind::Long => RechargeTrigger::LongRest,
+    };
+    report.recharged = resources.trigger(roller, creature.entity_id, trigger)?;
+    Ok(())
+}
+
+/// Finish a short rest, spending `hit_dice` in order. Nothing changes if
+/// the creature doesn't have all of them.
+pub fn short_rest(
+    roller: &mut DiceRoller,
+    creature: &mut RestingCreature,
+    resources: &mut ResourceTracker,
+    hit_dice: &[DieType],
+    variant: RestVariant,
+) -> Result<RestReport, DndError> {
+    if creature.hit_points.is_dead() {
+        return Err(DndError::InvalidAction(format!(
+            "{} is dead and can't rest",
+            creature.creature_id
+        )));
+    }
+    for pool in &creature.hit_dice.pools {
+        let wanted = hit_dice.iter().filter(|d| **d == pool.die).count() as i32;
+        if wanted > pool.remaining {
+            return Err(DndError::RulesViolation(format!(
+                "{} has {} d{} hit dice left, not {}",
+                creature.creature_id, pool.remaining, pool.die as i32, wanted
+            )));
+        }
+    }
+    let mut report = RestReport::new(RestKind::Short, variant);
+    for die in hit_dice {
+        let roll = creature
+            .hit_dice
+            .spend(roller, *die, creature.con_modifier)?;
+        let before = creature.hit_points.current;
+        creature.hit_points.heal(roll.healing)?;
+        report.hp_regained += creature.hit_points.current - before;
+        report.hit_dice_spent.push(roll);
+    }
+    if let Some(slots) = creature.spell_slots.as_mut() {
+        report.slots_restored = slots.pact.is_some_and(|p| p.current < p.max);
+        slots.short_rest();
+    }
+    recharge_features(roller, creature, resources, &mut report)?;
+    Ok(report)
+}
+
+/// Finish a long rest. A creature needs at least 1 hit point at the start
+/// to benefit, and loses a level of exhaustion only if it had food and
+/// water.
+pub fn long_rest(
+    roller: &mut DiceRoller,
+    creature: &mut RestingCreature,
+    resources: &mut ResourceTracker,
+    conditions: &mut ConditionManager,
+    had_food_and_water: bool,
+    variant: RestVariant,
+) -> Result<RestReport, DndError> {
+    if creature.hit_points.current < 1 {
+        return Err(DndError::RulesViolation(format!(
+            "{} needs at least 1 hit point to benefit from a long rest",
+            creature.creature_id
+        )));
+    }
+    let mut report = RestReport::new(RestKind::Long, variant);
+    let hp = &mut creature.hit_points;
+    report.hp_regained = hp.max - hp.current;
+    hp.current = hp.max;
+    // Temporary hit points without a duration last until a long rest.
+    hp.temp = 0;
+    report.hit_dice_regained = creature.hit_dice.recover();
+
+    if let Some(slots) = creature.spell_slots.as_mut() {
+        slots.long_rest();
+        report.slots_restored = true;
+    }
+    if let Some(points) = creature.sorcery_points.as_mut() {
+        points.long_rest();
+    }
+    recharge_features(roller, creature, resources, &mut report)?;
+    if had_food_and_water {
+        let before = conditions.exhaustion_level(&creature.creature_id);
+        let after = conditions.reduce_exhaustion(&creature.creature_id, 1);
+        report.exhaustion_removed = before - after;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::conditions::{ActiveCondition, ConditionType};
+    use crate::resources::{Recharge, Resource};
+    use crate::spell_slots::{CasterClass, CasterProgression};
+
+    fn paladin_warlock() -> (RestingCreature, ResourceTracker) {
+        let mut hit_points = HitPoints::new(60);
+        hit_points.current = 20;
+        let mut slots = SpellSlots::for_classes(&[
+            CasterClass::new(CasterProgression::Half, 6),
+            CasterClass::new(CasterProgression::Pact, 2),
+        ]);
+        slots.expend(1).unwrap();
+        slots.expend_pact().unwrap();
+        let creature = RestingCreature {
+            creature_id: "hexblade".to_string(),
+            entity_id: EntityId::new(),
+            hit_points,
+            hit_dice: HitDice::for_classes(&[(DieType::D10, 6), (DieType::D8, 2)]),
+            con_modifier: 2,
+            spell_slots: Some(slots),
+            sorcery_points: None,
+        };
+        let mut resources = ResourceTracker::new();
+        resources.add(
+            creature.entity_id,
+            Resource::new(
+                "channel-divinity",
+                "Channel Divinity",
+                1,
+                Recharge::ShortRest,
+            ),
+        );
+        resources.add(
+            creature.entity_id,
+            Resource::new("lay-on-hands", "Lay on Hands", 30, Recharge::LongRest),
+        );
+        (creature, resources)
+    }
+
+    #[test]
+    fn test_short_rest_spends_hit_dice() {
+        let (mut creature, mut resources) = paladin_warlock();
+        resources
+            .spend(creature.entity_id, "channel-divinity", 1)
+            .unwrap();
+        resources
+            .spend(creature.entity_id, "lay-on-hands", 10)
+            .unwrap();
+        let mut roller = DiceRoller::with_seed(9);
+        let report = short_rest(
+            &mut roller,
+            &mut creature,
+            &mut resources,
+            &[DieType::D10, DieType::D8],
+            RestVariant::Standard,
+        )
+        .unwrap();
+
+        assert_eq!(report.hit_dice_spent.len(), 2);
+        let healed: i32 = report.hit_dice_spent.iter().map(|r| r.healing).sum();
+        assert_eq!(report.hp_regained, healed);
+        assert_eq!(creature.hit_points.current, 20 + healed);
+        assert!(report
+            .hit_dice_spent
+            .iter()
+            .all(|r| r.roll + 2 == r.healing));
+        assert_eq!(creature.hit_dice.remaining(), 6);
+        assert_eq!(report.recharged.len(), 1);
+        assert_eq!(report.recharged[0].name, "Channel Divinity");
+        let lay_on_hands = resources.get(creature.entity_id, "lay-on-hands").unwrap();
+        assert_eq!(lay_on_hands.current, 20);
+        let slots = creature.spell_slots.as_ref().unwrap();
+        assert_eq!(slots.pact.unwrap().current, slots.pact.unwrap().max);
+        assert!(slots.current[0] < slots.max[0]);
+        assert_eq!(report.duration, HOUR);
+
+        creature.hit_dice.pools[1].remaining = 0;
+        assert!(short_rest(
+            &mut roller,
+            &mut creature,
+            &mut resources,
+            &[DieType::D8],
+            RestVariant::Standard
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_long_rest_restores_and_regains_half_hit_dice() {
+        let (mut creature, mut resources) = paladin_warlock();
+        resources
+            .spend(creature.entity_id, "lay-on-hands", 10)
+            .unwrap();
+        creature.hit_points.temp = 5;
+        for pool in &mut creature.hit_dice.pools {
+            pool.remaining = 0;
+        }
+        let mut conditions = ConditionManager::new();
+        conditions.apply_condition(
+            "hexblade",
+            ActiveCondition::new("tired", ConditionType::Exhaustion).with_exhaustion_level(2),
+        );
+
+        let mut roller = DiceRoller::with_seed(2);
+        let report = long_rest(
+            &mut roller,
+            &mut creature,
+            &mut resources,
+            &mut conditions,
+            true,
+            RestVariant::Standard,
+        )
+        .unwrap();
+        assert_eq!(creature.hit_points.current, 60);
+        assert_eq!(creature.hit_points.temp, 0);
+        assert_eq!(report.hit_dice_regained, 4);
+        // Larger dice come back first.
+        assert_eq!(creature.hit_dice.pools[0].remaining, 4);
+        assert_eq!(report.exhaustion_removed, 1);
+        assert_eq!(conditions.exhaustion_level("hexblade"), 1);
+        let slots = creature.spell_slots.as_ref().unwrap();
+        assert_eq!(slots.current, slots.max);
+        assert_eq!(report.recharged.len(), 1);
+        assert_eq!(report.recharged[0].regained, 10);
+
+        long_rest(
+            &mut roller,
+            &mut creature,
+            &mut resources,
+            &mut conditions,
+            false,
+            RestVariant::Standard,
+        )
+        .unwrap();
+        assert_eq!(conditions.exhaustion_level("hexblade"), 1);
+    }
+
+    #[test]
+    fn test_rest_requirements_and_variants() {
+        let (mut creature, mut resources) = paladin_warlock();
+        creature.hit_points.current = 0;
+        let mut conditions = ConditionManager::new();
+        let mut roller = DiceRoller::with_seed(3);
+        assert!(matches!(
+            long_rest(
+                &mut roller,
+                &mut creature,
+                &mut resources,
+                &mut conditions,
+                true,
+                RestVariant::Standard
+            ),
+            Err(DndError::RulesViolation(_))
+        ));
+
+        assert_eq!(
+            RestVariant::GrittyRealism.duration(RestKind::Long),
+            HOUR * 168
+        );
+        assert_eq!(
+            RestVariant::EpicHeroism.duration(RestKind::Short),
+            Duration::from_secs(300)
+        );
+        assert_eq!(
+            RestVariant::from_name("Gritty Realism"),
+            Some(RestVariant::GrittyRealism)
+        );
+        assert_eq!(RestVariant::from_name("nap"), None);
+    }
+}
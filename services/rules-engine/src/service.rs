@@ -0,0 +1,4200 @@
+//! gRPC implementation of rules.v1.RulesService.
+
+// Helpers return tonic::Status directly so they compose with `?` in handlers.
+#![allow(clippy::result_large_err)]
+
+use crate::action_economy::{ActionEconomy, EconomyFeatures};
+use crate::advantage::{D20Sources, RollMode};
+use crate::armor_class::{self, Armor, ArmorCategory, ArmorSetup, NaturalArmor, UnarmoredDefense};
+use crate::attack::{self, AttackInput, AttackSource};
+use crate::attack_action::{self, AttackSequence, TwoWeaponFeatures};
+use crate::checks::{
+    self, CheckFeatures, CheckInput, CheckKind, CheckOutcome, Modifier, Proficiency,
+};
+use crate::class_features::{Activation, AttackContext, FeatureRegistry, FeatureSet, SaveContext};
+use crate::combat::{
+    self, Ability, CombatEngine, CoverType, CreatureStats, DamageResult, DamageType, Size,
+};
+use crate::concentration::{ConcentrationEnded, ConcentrationTracker, DependentEffect, EndReason};
+use crate::conditions::{
+    ActiveCondition, ApplyOutcome, ConditionDuration, ConditionEffects, ConditionManager,
+    ConditionType, SaveToEnd,
+};
+use crate::damage::{self, DamageDefenses, DamageReport, FlatReduction, ReductionScope};
+use crate::dice::{
+    DiceError, DiceExpression, DiceRoller, DieRoll, DieType, RollPurpose, RollRecord, RollResult,
+    SessionDice,
+};
+use crate::hit_points::{DeathRule, HitPoints, HpEvent, LifeState};
+use crate::initiative::{
+    self, CombatScheduler, HookOutcome, HookTiming, TurnChange, TurnEffect, TurnHook,
+};
+use crate::reactions::{self, Decision, Reactor, ResolvedWindow, TriggerBus, WindowStatus};
+use crate::resources::{Recharge, RechargeTrigger, Recharged, Resource, ResourceTracker};
+use crate::rest::{self, HitDice, HitDicePool, RestKind, RestVariant, RestingCreature};
+use crate::special_attacks::{self, Contestant, ShoveEffect};
+use crate::spell_resolver::{self, Aim, CastContext, TargetState};
+use crate::spell_slots::{PactSlots, SorceryPoints, SpellSlots, MAX_SPELL_LEVEL};
+use crate::spellbook::{CastingTime, Resolution, RiderEffect, Spellbook, Targeting};
+use crate::spells::{self, CasterState, SpellRequirements, TargetReach};
+use crate::weapons::{self, Armory, WeaponProperty, WeaponUse};
+use dnd_proto::rules::v1 as pb;
+use dnd_proto::rules::v1::rules_service_server::RulesService;
+use shared_rust::{DamageInstance, DndError, EntityId, GridPosition};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Instant;
+use tonic::{Request, Response, Status};
+use uuid::Uuid;
+
+pub struct RulesServiceImpl {
+    condition_manager: Mutex<ConditionManager>,
+    // Lock before `condition_manager` when both are needed.
+    concentration: Mutex<ConcentrationTracker>,
+    /// Initiative order and turns by encounter id.
+    encounters: Mutex<HashMap<String, Encounter>>,
+    /// Open reaction windows and who has reacted this round.
+    reactions: Mutex<TriggerBus>,
+    /// Each game session's logged dice, by session id. A session's own lock
+    /// may be taken while holding `encounters`, never the other way round.
+    dice_sessions: Mutex<HashMap<String, Arc<Mutex<SessionDice>>>>,
+    rest_variant: RestVariant,
+}
+
+impl RulesServiceImpl {
+    pub fn new() -> Self {
+        Self {
+            condition_manager: Mutex::new(ConditionManager::new()),
+            concentration: Mutex::new(ConcentrationTracker::new()),
+            encounters: Mutex::new(HashMap::new()),
+            reactions: Mutex::new(TriggerBus::default()),
+            dice_sessions: Mutex::new(HashMap::new()),
+            rest_variant: RestVariant::default(),
+        }
+    }
+
+    /// Rest lengths for requests that don't name the session's variant.
+    pub fn with_rest_variant(mut self, variant: RestVariant) -> Self {
+        self.rest_variant = variant;
+        self
+    }
+
+    fn conditions(&self) -> MutexGuard<'_, ConditionManager> {
+        // A panic while holding the lock leaves the map itself consistent,
+        // so recover the guard rather than failing every later request.
+        self.condition_manager
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn concentration(&self) -> MutexGuard<'_, ConcentrationTracker> {
+        self.concentration
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn encounters(&self) -> MutexGuard<'_, HashMap<String, Encounter>> {
+        self.encounters
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn reactions(&self) -> MutexGuard<'_, TriggerBus> {
+        self.reactions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    /// The dice for one request. A request naming a session rolls on that
+    /// session's dice, created on first use from the request's seed; a
+    /// different seed for a session already under way is refused. Any other
+    /// request gets a roller of its own. `acting` is the requester when the
+    /// context doesn't name one.
+    fn dice(
+        &self,
+        context: Option<&pb::RollContext>,
+        seed: Option<i64>,
+        acting: &str,
+    ) -> Result<RequestDice, Status> {
+        let Some(context) = context.filter(|c| !c.session_id.is_empty()) else {
+            return Ok(RequestDice::Local(Box::new(
+                DiceRoller::from_optional_seed(seed),
+            )));
+        };
+        let session = self
+            .dice_sessions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .entry(context.session_id.clone())
+            .or_insert_with(|| {
+                let session = match seed {
+                    Some(seed) => SessionDice::new(context.session_id.clone(), seed as u64),
+                    None => SessionDice::from_entropy(context.session_id.clone()),
+                };
+                Arc::new(Mutex::new(session))
+            })
+            .clone();
+        let session_seed = session
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .seed();
+        if let Some(seed) = seed.filter(|seed| *seed as u64 != session_seed) {
+            return Err(Status::failed_precondition(format!(
+                "Dice session {} is seeded with {}, not {}",
+                context.session_id, session_seed, seed
+            )));
+        }
+        let requester = if context.requester_id.is_empty() {
+            acting.to_string()
+        } else {
+            context.requester_id.clone()
+        };
+        Ok(RequestDice::Session { session, requester })
+    }
+
+    fn dice_session(&self, session_id: &str) -> Result<Arc<Mutex<SessionDice>>, Status> {
+        self.dice_sessions
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+            .get(session_id)
+            .cloned()
+            .ok_or_else(|| Status::not_found(format!("Unknown dice session: {}", session_id)))
+    }
+
+    /// Check it is `creature_id`'s turn in the encounter, starting round 1
+    /// on its first turn. Returns the round, or 0 without an encounter.
+    fn encounter_turn_start(
+        &self,
+        encounter_id: &str,
+        creature_id: &str,
+        dice: &mut RequestDice,
+    ) -> Result<u32, Status> {
+        if encounter_id.is_empty() {
+            return Ok(0);
+        }
+        let mut encounters = self.encounters();
+        let scheduler = &mut encounter(&mut encounters, encounter_id)?.scheduler;
+        if scheduler.round() == 0 {
+            dice.roll(RollPurpose::Initiative, "initiative roll-off", |roller| {
+                scheduler.start(roller)
+            })
+            .map_err(rules_error)?;
+        }
+        require_encounter_turn(scheduler, creature_id)?;
+        Ok(scheduler.round())
+    }
+
+    /// End `creature_id`'s turn in the encounter and move on to the next.
+    fn encounter_turn_end(
+        &self,
+        encounter_id: &str,
+        creature_id: &str,
+        dice: &mut RequestDice,
+    ) -> Result<Option<TurnChange>, Status> {
+        if encounter_id.is_empty() {
+            return Ok(None);
+        }
+        let mut encounters = self.encounters();
+        let encounter = encounter(&mut encounters, encounter_id)?;
+        require_encounter_turn(&encounter.scheduler, creature_id)?;
+        encounter.turns.remove(creature_id);
+        dice.roll(RollPurpose::Initiative, "initiative roll-off", |roller| {
+            encounter.scheduler.advance(roller)
+        })
+        .map(Some)
+        .map_err(rules_error)
+    }
+}
+
+/// Where a request's dice come from; see [`RulesServiceImpl::dice`].
+enum RequestDice {
+    Local(Box<DiceRoller>),
+    Session {
+        session: Arc<Mutex<SessionDice>>,
+        requester: String,
+    },
+}
+
+impl RequestDice {
+    /// Roll through `f`, logged under `purpose` in a session. Each call is
+    /// one log entry, so concurrent requests in a session interleave whole
+    /// rolls rather than single dice.
+    fn roll<T>(
+        &mut self,
+        purpose: RollPurpose,
+        description: &str,
+        f: impl FnOnce(&mut DiceRoller) -> T,
+    ) -> T {
+        match self {
+            RequestDice::Local(roller) => f(roller),
+            RequestDice::Session { session, requester } => session
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .roll_with(purpose, requester, description, f),
+        }
+    }
+
+    fn roll_expression(
+        &mut self,
+        expression: &str,
+        purpose: RollPurpose,
+    ) -> Result<RollResult, DiceError> {
+        match self {
+            RequestDice::Local(roller) => roller.roll_expression(expression),
+            RequestDice::Session { session, requester } => session
+                .lock()
+                .unwrap_or_else(|poisoned| poisoned.into_inner())
+                .roll_expression(expression, purpose, requester),
+        }
+    }
+}
+
+/// An encounter's initiative order, what the creature taking its turn
+/// has left to spend and the state of everyone's class features.
+#[derive(Default)]
+struct Encounter {
+    scheduler: CombatScheduler,
+    /// Budgets by creature id, from the start of its turn to the end.
+    turns: HashMap<String, ActionEconomy>,
+    /// Class features by creature id, kept across attacks and turns.
+    features: HashMap<String, FeatureSet>,
+}
+
+fn encounter<'a>(
+    encounters: &'a mut HashMap<String, Encounter>,
+    encounter_id: &str,
+) -> Result<&'a mut Encounter, Status> {
+    encounters
+        .get_mut(encounter_id)
+        .ok_or_else(|| Status::not_found(format!("Unknown encounter: {}", encounter_id)))
+}
+
+/// What is left of `creature_id`'s turn, which must be under way.
+fn turn_economy<'a>(
+    encounters: &'a mut HashMap<String, Encounter>,
+    encounter_id: &str,
+    creature_id: &str,
+) -> Result<&'a mut ActionEconomy, Status> {
+    let encounter = encounter(encounters, encounter_id)?;
+    require_encounter_turn(&encounter.scheduler, creature_id)?;
+    encounter.turns.get_mut(creature_id).ok_or_else(|| {
+        Status::failed_precondition(format!("{}'s turn has not started", creature_id))
+    })
+}
+
+fn require_encounter_turn(scheduler: &CombatScheduler, creature_id: &str) -> Result<(), Status> {
+    match scheduler.current() {
+        Some(entry) if entry.creature_id == creature_id => Ok(()),
+        Some(entry) => Err(Status::failed_precondition(format!(
+            "It is {}'s turn, not {}'s",
+            entry.creature_id, creature_id
+        ))),
+        None => Err(Status::failed_precondition("No one is taking a turn")),
+    }
+}
+
+impl Default for RulesServiceImpl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[tonic::async_trait]
+impl RulesService for RulesServiceImpl {
+    async fn roll_dice(
+        &self,
+        request: Request<pb::RollDiceRequest>,
+    ) -> Result<Response<pb::RollDiceResponse>, Status> {
+        let req = request.into_inner();
+        let mut dice = self.dice(req.roll_context.as_ref(), req.seed, "")?;
+
+        let result = dice
+            .roll_expression(&req.expression, roll_purpose(&req.context))
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+
+        Ok(Response::new(pb::RollDiceResponse {
+            rolls: result.rolls.iter().map(convert_die_roll).collect(),
+            total: result.total,
+            expression: req.expression,
+            kept_rolls: result.kept,
+            dropped_rolls: result.dropped,
+        }))
+    }
+
+    async fn roll_with_advantage(
+        &self,
+        request: Request<pb::RollAdvantageRequest>,
+    ) -> Result<Response<pb::RollDiceResponse>, Status> {
+        let req = request.into_inner();
+        let mut dice = self.dice(req.roll_context.as_ref(), req.seed, "")?;
+
+        let expression = format_d20_expression(req.modifier);
+        let (all_rolls, used) = dice.roll(roll_purpose(&req.context), &expression, |roller| {
+            roller.roll_d20_with_state(req.advantage, req.disadvantage)
+        });
+        let mut dropped = all_rolls.clone();
+        if let Some(index) = dropped.iter().position(|&r| r == used) {
+            dropped.remove(index);
+        }
+
+        Ok(Response::new(pb::RollDiceResponse {
+            rolls: all_rolls
+                .iter()
+                .map(|&result| pb::DieRoll {
+                    die_type: pb::DieType::D20 as i32,
+                    result,
+                })
+                .collect(),
+            total: used + req.modifier,
+            expression,
+            kept_rolls: vec![used],
+            dropped_rolls: dropped,
+        }))
+    }
+
+    async fn get_roll_log(
+        &self,
+        request: Request<pb::RollLogRequest>,
+    ) -> Result<Response<pb::RollLogResponse>, Status> {
+        let req = request.into_inner();
+        let session = self.dice_session(&req.session_id)?;
+        let session = session
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let verify_error = session.verify().err().map(|e| e.to_string());
+
+        Ok(Response::new(pb::RollLogResponse {
+            session_id: req.session_id,
+            seed: session.seed(),
+            records: session
+                .log()
+                .iter()
+                .filter(|r| req.requester_id.is_empty() || r.requester == req.requester_id)
+                .map(convert_roll_record)
+                .collect(),
+            verified: verify_error.is_none(),
+            verify_error: verify_error.unwrap_or_default(),
+        }))
+    }
+
+    async fn resolve_ability_check(
+        &self,
+        request: Request<pb::AbilityCheckRequest>,
+    ) -> Result<Response<pb::AbilityCheckResponse>, Status> {
+        let req = request.into_inner();
+        let stats = require_stats(req.stats.as_ref())?;
+        let ability = convert_ability(req.ability)?;
+
+        let input = CheckInput::new(CheckKind::Ability(ability), req.dc)
+            .with_features(convert_check_features(req.features.as_ref()))
+            .with_sources(request_sources(req.advantage, req.disadvantage));
+        let conditions = self.conditions_for(&req.creature_id, &stats);
+        let outcome = self
+            .dice(req.roll_context.as_ref(), req.seed, &req.creature_id)?
+            .roll(
+                RollPurpose::AbilityCheck,
+                &format!("{:?} check", ability),
+                |roller| checks::resolve_check(roller, &stats, &conditions, &input),
+            );
+        let d20 = outcome.d20.as_ref();
+
+        Ok(Response::new(pb::AbilityCheckResponse {
+            success: outcome.success,
+            natural_roll: outcome.natural_roll,
+            modifier: outcome.modifier,
+            total: outcome.total,
+            dc: outcome.dc,
+            had_advantage: d20.is_some_and(|d| d.mode == RollMode::Advantage),
+            had_disadvantage: d20.is_some_and(|d| d.mode == RollMode::Disadvantage),
+            all_rolls: d20.map(|d| d.rolls.clone()).unwrap_or_default(),
+            breakdown: convert_modifiers(&outcome.breakdown),
+        }))
+    }
+
+    async fn resolve_skill_check(
+        &self,
+        request: Request<pb::SkillCheckRequest>,
+    ) -> Result<Response<pb::SkillCheckResponse>, Status> {
+        let req = request.into_inner();
+        let stats = require_stats(req.stats.as_ref())?;
+        let kind = CheckKind::skill(&req.skill)
+            .ok_or_else(|| Status::invalid_argument(format!("Unknown skill: {}", req.skill)))?;
+        let ability = kind.ability().unwrap_or(Ability::STR);
+        let proficiency = match (req.proficient, req.expertise) {
+            (_, true) => Proficiency::Expertise,
+            (true, false) => Proficiency::Proficient,
+            (false, false) => Proficiency::None,
+        };
+
+        let input = CheckInput::new(kind, req.dc)
+            .with_proficiency(proficiency)
+            .with_features(convert_check_features(req.features.as_ref()))
+            .with_sources(request_sources(req.advantage, req.disadvantage));
+        let conditions = self.conditions_for(&req.creature_id, &stats);
+        let outcome = self
+            .dice(req.roll_context.as_ref(), req.seed, &req.creature_id)?
+            .roll(RollPurpose::AbilityCheck, &req.skill, |roller| {
+                checks::resolve_check(roller, &stats, &conditions, &input)
+            });
+
+        Ok(Response::new(pb::SkillCheckResponse {
+            success: outcome.success,
+            natural_roll: outcome.natural_roll,
+            ability_modifier: outcome.ability_modifier,
+            proficiency_bonus: outcome.proficiency_bonus,
+            total: outcome.total,
+            dc: outcome.dc,
+            ability_used: ability_to_proto(ability) as i32,
+            breakdown: convert_modifiers(&outcome.breakdown),
+            d20_value: outcome.d20_value,
+        }))
+    }
+
+    async fn resolve_saving_throw(
+        &self,
+        request: Request<pb::SavingThrowRequest>,
+    ) -> Result<Response<pb::SavingThrowResponse>, Status> {
+        let req = request.into_inner();
+        let stats = require_stats(req.stats.as_ref())?;
+        let ability = convert_ability(req.ability)?;
+
+        let mut input = self.save_input(&stats, ability, req.dc, &req.features, &req.auras)?;
+        let request = request_sources(req.advantage, req.disadvantage);
+        input.sources.advantage.extend(request.advantage);
+        input.sources.disadvantage.extend(request.disadvantage);
+        let conditions = self.conditions_for(&req.creature_id, &stats);
+        if !req.condition_id.is_empty() && !conditions.iter().any(|c| c.id == req.condition_id) {
+            return Err(Status::not_found(format!(
+                "{} has no condition {}",
+                req.creature_id, req.condition_id
+            )));
+        }
+        let outcome = self
+            .dice(req.roll_context.as_ref(), req.seed, &req.creature_id)?
+            .roll(
+                RollPurpose::SavingThrow,
+                &format!("{:?} save", ability),
+                |roller| checks::resolve_check(roller, &stats, &conditions, &input),
+            );
+        let condition_ended = !req.condition_id.is_empty()
+            && self
+                .conditions()
+                .resolve_save(&req.creature_id, &req.condition_id, outcome.success)
+                .is_some();
+
+        Ok(Response::new(pb::SavingThrowResponse {
+            success: outcome.success,
+            natural_roll: outcome.natural_roll,
+            modifier: outcome.modifier,
+            total: outcome.total,
+            dc: outcome.dc,
+            auto_fail: outcome.auto_fail.is_some(),
+            auto_success: false,
+            condition_ended,
+        }))
+    }
+
+    async fn resolve_attack(
+        &self,
+        request: Request<pb::AttackRequest>,
+    ) -> Result<Response<pb::AttackResponse>, Status> {
+        let req = request.into_inner();
+        let mut target = require_stats(req.target_stats.as_ref())?;
+        if let Some(setup) = &req.target_armor {
+            target.armor_class =
+                armor_class::calculate(&target, &convert_armor_setup(setup)?).total;
+        }
+        let attacker = req
+            .attacker_stats
+            .as_ref()
+            .map(convert_proto_stats)
+            .unwrap_or_default();
+
+        let distance = if req.distance > 0 { req.distance } else { 5 };
+        let two_weapon = TwoWeaponFeatures {
+            fighting_style: req.two_weapon_fighting_style,
+            dual_wielder: req.dual_wielder,
+        };
+        let weapon = if req.weapon_id.is_empty() {
+            None
+        } else {
+            let definition = Armory::builtin()
+                .get(&req.weapon_id)
+                .ok_or_else(|| Status::not_found(format!("Unknown weapon: {}", req.weapon_id)))?;
+            let usage = WeaponUse {
+                two_hands: req.two_hands,
+                thrown: req.thrown,
+                distance_feet: distance,
+                ignore_loading: false,
+            };
+            if req.off_hand {
+                attack_action::check_two_weapon(definition, &usage, two_weapon)
+                    .map_err(rules_error)?;
+            }
+            Some(weapons::derive_attack(definition, &attacker, &usage).map_err(rules_error)?)
+        };
+
+        let source = if req.is_spell {
+            AttackSource::Spell
+        } else {
+            AttackSource::Weapon
+        };
+        let ability =
+            convert_optional_ability(req.attack_ability).or(weapon.as_ref().map(|w| w.ability));
+        let mut sources = request_sources(req.advantage, req.disadvantage);
+        if let Some(weapon) = &weapon {
+            sources
+                .disadvantage
+                .extend(weapon.sources.disadvantage.iter().cloned());
+        }
+        let mut input = AttackInput::new(source, ability)
+            .with_proficiency(req.proficient)
+            .with_magic_bonus(req.magic_bonus)
+            .with_archery(req.archery)
+            .with_cover(convert_cover(req.target_cover))
+            .at_distance(distance)
+            .with_sources(sources);
+        if req.crit_threshold > 0 {
+            input = input.with_crit_threshold(req.crit_threshold);
+        }
+        if let Some((normal, long)) = weapon.as_ref().and_then(|w| w.range) {
+            input = input.ranged(normal, long);
+        } else if req.is_ranged {
+            let long = if req.long_range > 0 {
+                req.long_range
+            } else {
+                i32::MAX
+            };
+            let normal = if req.range > 0 { req.range } else { long };
+            input = input.ranged(normal, long);
+        }
+        if req.attack_bonus != 0 {
+            input = input.with_bonus("Attack bonus", req.attack_bonus);
+        }
+
+        // In an encounter the attacker's features remember what they did
+        // this turn; otherwise they come fresh from the request.
+        let mut encounters = (!req.encounter_id.is_empty()).then(|| self.encounters());
+        let mut request_features;
+        let features = match encounters.as_mut() {
+            Some(encounters) => {
+                let features = encounter(encounters, &req.encounter_id)?
+                    .features
+                    .entry(req.attacker_id.clone())
+                    .or_default();
+                add_features(features, &req.attacker_features)?;
+                features
+            }
+            None => {
+                request_features = feature_set(&req.attacker_features)?;
+                &mut request_features
+            }
+        };
+        let finesse = Armory::builtin()
+            .get(&req.weapon_id)
+            .is_some_and(|w| w.has(&WeaponProperty::Finesse));
+        let mut context = AttackContext {
+            attacker: &attacker,
+            target: &target,
+            source,
+            melee: !input.ranged,
+            ability,
+            finesse_or_ranged: finesse || input.ranged,
+            ally_adjacent_to_target: req.ally_adjacent_to_target,
+            target_undead_or_fiend: req.target_undead_or_fiend,
+            roll_mode: RollMode::Normal,
+            critical: false,
+        };
+        features.before_attack_roll(&context, &mut input);
+
+        let attacker_conditions = self.conditions_for(&req.attacker_id, &attacker);
+        let target_conditions = self.conditions_for(&req.target_id, &target);
+        let mut dice = self.dice(req.roll_context.as_ref(), req.seed, &req.attacker_id)?;
+        let description = format!("attack on {}", req.target_id);
+        let outcome = dice.roll(RollPurpose::Attack, &description, |roller| {
+            attack::resolve_attack(
+                roller,
+                &attacker,
+                &target,
+                &attacker_conditions,
+                &target_conditions,
+                &input,
+            )
+        });
+        context.roll_mode = outcome.d20.as_ref().map_or(RollMode::Normal, |d| d.mode);
+        context.critical = outcome.is_critical();
+        let (extra_damage, damage_bonuses) = if outcome.hits() {
+            (features.after_hit(&context), features.on_damage(&context))
+        } else {
+            (Vec::new(), Vec::new())
+        };
+
+        // The weapon's dice add its ability modifier, with damage_modifier
+        // on top; explicit dice are taken as given.
+        let (damage_dice, damage_modifier, damage_type) = match &weapon {
+            Some(weapon) if req.damage_dice.is_empty() && !weapon.damage_dice.is_empty() => {
+                let mut ability_modifier = attacker.get_modifier(weapon.ability);
+                if req.off_hand {
+                    ability_modifier =
+                        attack_action::off_hand_damage_modifier(ability_modifier, two_weapon);
+                }
+                (
+                    weapon.damage_dice.clone(),
+                    ability_modifier + req.damage_modifier,
+                    weapon.damage_type.unwrap_or(DamageType::Bludgeoning),
+                )
+            }
+            _ => (
+                req.damage_dice.clone(),
+                req.damage_modifier,
+                convert_damage_type(req.damage_type)?,
+            ),
+        };
+        let damage_modifier = damage_modifier + damage_bonuses.iter().map(|m| m.value).sum::<i32>();
+        let magical = req.magical || req.magic_bonus > 0 || req.is_spell;
+
+        // Every part of the hit goes through the target's defenses together:
+        // the weapon's dice, then feature dice, then the request's extras.
+        let mut parts = Vec::new();
+        let weapon_damage = outcome.hits() && !damage_dice.is_empty();
+        if weapon_damage {
+            parts.push(DamagePart {
+                dice: damage_dice,
+                modifier: damage_modifier,
+                damage_type,
+                magical,
+            });
+        }
+        if outcome.hits() {
+            parts.extend(extra_damage.iter().map(|extra| DamagePart {
+                dice: extra.dice.clone(),
+                modifier: 0,
+                damage_type: extra.damage_type.unwrap_or(damage_type),
+                magical,
+            }));
+            for component in &req.extra_damage {
+                let mut part = convert_damage_component(component)?;
+                part.magical |= magical;
+                parts.push(part);
+            }
+        }
+        let description = format!("damage to {}", req.target_id);
+        let (results, report) = dice.roll(RollPurpose::Damage, &description, |roller| {
+            roll_damage_parts(roller, &parts, outcome.is_critical(), &target)
+        })?;
+        let mut results = results.into_iter();
+        let damage = if weapon_damage { results.next() } else { None };
+        let feature_damage = extra_damage
+            .iter()
+            .zip(results.by_ref())
+            .map(|(extra, damage)| pb::FeatureDamage {
+                feature_id: extra.feature_id.clone(),
+                source: extra.source.clone(),
+                damage: Some(damage),
+                slot_spent: extra.slot_spent.map_or(0, i32::from),
+            })
+            .collect();
+        let d20 = outcome.d20.as_ref();
+
+        Ok(Response::new(pb::AttackResponse {
+            hits: outcome.hits(),
+            natural_roll: outcome.natural_roll,
+            attack_modifier: outcome.modifier,
+            total_attack: outcome.total,
+            target_ac: outcome.target_ac,
+            is_critical: outcome.is_critical(),
+            is_fumble: outcome.is_fumble,
+            had_advantage: d20.is_some_and(|d| d.mode == RollMode::Advantage),
+            had_disadvantage: d20.is_some_and(|d| d.mode == RollMode::Disadvantage),
+            all_attack_rolls: d20.map(|d| d.rolls.clone()).unwrap_or_default(),
+            damage,
+            explanation: outcome.explanation,
+            breakdown: convert_modifiers(&outcome.breakdown),
+            feature_damage,
+            extra_damage: results.collect(),
+            damage_by_type: convert_damage_totals(&report),
+            total_damage: report.total,
+        }))
+    }
+
+    async fn calculate_damage(
+        &self,
+        request: Request<pb::DamageRequest>,
+    ) -> Result<Response<pb::DamageResponse>, Status> {
+        let req = request.into_inner();
+        let target = req
+            .target_stats
+            .as_ref()
+            .map(convert_proto_stats)
+            .unwrap_or_default();
+
+        let mut parts = vec![DamagePart {
+            dice: req.dice_expression.clone(),
+            modifier: req.modifier,
+            damage_type: convert_damage_type(req.damage_type)?,
+            magical: req.magical,
+        }];
+        for component in &req.extra_damage {
+            parts.push(convert_damage_component(component)?);
+        }
+        let (results, report) = self.dice(req.roll_context.as_ref(), req.seed, "")?.roll(
+            RollPurpose::Damage,
+            &req.dice_expression,
+            |roller| roll_damage_parts(roller, &parts, req.is_critical, &target),
+        )?;
+        let mut results = results.into_iter();
+
+        Ok(Response::new(pb::DamageResponse {
+            result: results.next(),
+            extra_damage: results.collect(),
+            by_type: convert_damage_totals(&report),
+            total: report.total,
+        }))
+    }
+
+    async fn apply_damage(
+        &self,
+        request: Request<pb::ApplyDamageRequest>,
+    ) -> Result<Response<pb::ApplyDamageResponse>, Status> {
+        let req = request.into_inner();
+        let stats = require_stats(req.current_stats.as_ref())?;
+        require_max_hp(&stats)?;
+        if req.damage < 0 {
+            return Err(Status::invalid_argument("Damage cannot be negative"));
+        }
+
+        let rule = if req.dies_at_zero {
+            DeathRule::DiesAtZero
+        } else {
+            DeathRule::DeathSaves
+        };
+        let mut hp = HitPoints::from_stats(&stats, rule);
+        if let LifeState::Dying { .. } = hp.state {
+            hp.state = LifeState::Dying {
+                successes: req.death_save_successes.clamp(0, 2) as u8,
+                failures: req.death_save_failures.clamp(0, 2) as u8,
+            };
+        }
+        let before = hp.current + hp.temp;
+        let events = hp
+            .take_damage(req.damage, req.is_critical)
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        let damage_taken = before - (hp.current + hp.temp);
+        let mut concentration_save = None;
+        let concentration_ended = if hp.is_dead() || events.contains(&HpEvent::DroppedToZero) {
+            let reason = if hp.is_dead() {
+                EndReason::Died
+            } else {
+                EndReason::Incapacitated
+            };
+            self.concentration()
+                .end(&req.creature_id, reason, &mut self.conditions())
+        } else if damage_taken > 0 {
+            let mut caster = stats.clone();
+            caster.creature_id = req.creature_id.clone();
+            let conditions = self.conditions_for(&req.creature_id, &caster);
+            let description = format!("concentration after {} damage", damage_taken);
+            concentration_save = self
+                .dice(req.roll_context.as_ref(), req.seed, &req.creature_id)?
+                .roll(RollPurpose::Concentration, &description, |roller| {
+                    self.concentration().on_damage(
+                        roller,
+                        &caster,
+                        &conditions,
+                        &[damage_taken],
+                        &request_sources(req.concentration_advantage, false),
+                        &mut self.conditions(),
+                    )
+                });
+            concentration_save.as_mut().and_then(|s| s.ended.take())
+        } else {
+            None
+        };
+        if events.contains(&HpEvent::DroppedToZero) && !hp.is_dead() {
+            self.fall_unconscious(&req.creature_id);
+        }
+        let save = concentration_save.as_ref().and_then(|s| s.saves.last());
+        let (successes, failures) = death_saves(hp.state);
+
+        Ok(Response::new(pb::ApplyDamageResponse {
+            damage_taken,
+            temp_hp_remaining: hp.temp,
+            current_hp: hp.current,
+            is_unconscious: !hp.is_conscious() && !hp.is_dead(),
+            is_dead: hp.is_dead(),
+            requires_concentration_check: save.is_some(),
+            concentration_dc: CombatEngine::concentration_dc(damage_taken),
+            is_stable: hp.state == LifeState::Stable,
+            death_save_successes: successes,
+            death_save_failures: failures,
+            massive_damage: events.contains(&HpEvent::MassiveDamage),
+            concentration_ended: concentration_ended
+                .as_ref()
+                .map(convert_concentration_ended),
+            concentration_maintained: save.is_some_and(|s| s.success),
+            concentration_roll: save.map_or(0, |s| s.natural_roll),
+            concentration_total: save.map_or(0, |s| s.total),
+        }))
+    }
+
+    async fn resolve_dying(
+        &self,
+        request: Request<pb::DyingRequest>,
+    ) -> Result<Response<pb::DyingResponse>, Status> {
+        let req = request.into_inner();
+        let stats = require_stats(req.current_stats.as_ref())?;
+        require_max_hp(&stats)?;
+
+        let mut hp = HitPoints::from_stats(&stats, DeathRule::DeathSaves);
+        if let LifeState::Dying { .. } = hp.state {
+            hp.state = if req.is_stable {
+                LifeState::Stable
+            } else {
+                LifeState::Dying {
+                    successes: req.death_save_successes.clamp(0, 2) as u8,
+                    failures: req.death_save_failures.clamp(0, 2) as u8,
+                }
+            };
+        }
+        let mut death_save_roll = 0;
+        let events = match pb::DyingAction::try_from(req.action) {
+            Ok(pb::DyingAction::DeathSave) => {
+                let conditions = self.conditions_for(&req.creature_id, &stats);
+                let save = self
+                    .dice(req.roll_context.as_ref(), req.seed, &req.creature_id)?
+                    .roll(RollPurpose::DeathSave, "death save", |roller| {
+                        checks::resolve_check(
+                            roller,
+                            &stats,
+                            &conditions,
+                            &CheckInput::death_save(),
+                        )
+                    });
+                death_save_roll = save.natural_roll;
+                let outcome = save
+                    .death_save
+                    .ok_or_else(|| Status::internal("A death save rolled without an outcome"))?;
+                hp.record_death_save(outcome)
+            }
+            Ok(pb::DyingAction::Stabilize) => hp.make_stable(),
+            Ok(pb::DyingAction::Heal) => hp.heal(req.healing),
+            _ => {
+                return Err(Status::invalid_argument(format!(
+                    "Invalid dying action: {}",
+                    req.action
+                )))
+            }
+        }
+        .map_err(rules_error)?;
+        let regained_consciousness = events.contains(&HpEvent::RegainedConsciousness);
+        if regained_consciousness {
+            self.regain_consciousness(&req.creature_id);
+        }
+        let (successes, failures) = death_saves(hp.state);
+
+        Ok(Response::new(pb::DyingResponse {
+            current_hp: hp.current,
+            is_unconscious: !hp.is_conscious() && !hp.is_dead(),
+            is_dead: hp.is_dead(),
+            is_stable: hp.state == LifeState::Stable,
+            death_save_successes: successes,
+            death_save_failures: failures,
+            death_save_roll,
+            regained_consciousness,
+        }))
+    }
+
+    async fn calculate_armor_class(
+        &self,
+        request: Request<pb::ArmorClassRequest>,
+    ) -> Result<Response<pb::ArmorClassResponse>, Status> {
+        let req = request.into_inner();
+        let stats = require_stats(req.stats.as_ref())?;
+        let setup = convert_armor_setup(&req.setup.unwrap_or_default())?;
+        let ac = armor_class::calculate(&stats, &setup);
+        Ok(Response::new(pb::ArmorClassResponse {
+            armor_class: ac.total,
+            formula: ac.formula.describe(),
+            components: convert_modifiers(&ac.components),
+            alternatives: ac
+                .alternatives
+                .iter()
+                .map(|(formula, total)| pb::ArmorFormula {
+                    formula: formula.describe(),
+                    armor_class: *total,
+                })
+                .collect(),
+        }))
+    }
+
+    async fn resolve_special_attack(
+        &self,
+        request: Request<pb::SpecialAttackRequest>,
+    ) -> Result<Response<pb::SpecialAttackResponse>, Status> {
+        let req = request.into_inner();
+        let attacker_stats = require_stats(req.attacker_stats.as_ref())?;
+        let target_stats = require_stats(req.target_stats.as_ref())?;
+        let attacker_id = if req.attacker_id.is_empty() {
+            attacker_stats.creature_id.clone()
+        } else {
+            req.attacker_id.clone()
+        };
+        let target_id = if req.target_id.is_empty() {
+            target_stats.creature_id.clone()
+        } else {
+            req.target_id.clone()
+        };
+        let attacker_stats = CreatureStats {
+            creature_id: attacker_id.clone(),
+            ..attacker_stats
+        };
+        let target_stats = CreatureStats {
+            creature_id: target_id.clone(),
+            ..target_stats
+        };
+        let attacker_conditions = self.conditions_for(&attacker_id, &attacker_stats);
+        let target_conditions = self.conditions_for(&target_id, &target_stats);
+        let attacker = contestant(
+            &attacker_stats,
+            &attacker_conditions,
+            req.attacker_skills.as_ref(),
+        );
+        let target = contestant(
+            &target_stats,
+            &target_conditions,
+            req.target_skills.as_ref(),
+        );
+
+        // In an encounter the attack comes out of the attacker's turn;
+        // otherwise the caller tracks the turn and this only checks the
+        // attacker is able to act at all.
+        let mut encounters = (!req.encounter_id.is_empty()).then(|| self.encounters());
+        let mut fresh_turn;
+        let economy = match encounters.as_mut() {
+            Some(encounters) => turn_economy(encounters, &req.encounter_id, &attacker_id)?,
+            None => {
+                fresh_turn = ActionEconomy::start_turn(
+                    &attacker_id,
+                    0,
+                    &self.effects_for(&attacker_id, &attacker_stats),
+                    EconomyFeatures::default(),
+                );
+                &mut fresh_turn
+            }
+        };
+        let mut dice = self.dice(req.roll_context.as_ref(), req.seed, &attacker_id)?;
+        let description = format!("contest with {}", target_id);
+        let outcome = dice
+            .roll(RollPurpose::AbilityCheck, &description, |roller| {
+                let mut conditions = self.conditions();
+                let outcome = match pb::SpecialAttackKind::try_from(req.kind) {
+                    Ok(pb::SpecialAttackKind::Grapple) => special_attacks::grapple(
+                        roller,
+                        economy,
+                        &mut conditions,
+                        &attacker,
+                        &target,
+                        req.free_hand,
+                    ),
+                    Ok(pb::SpecialAttackKind::ShoveProne) => special_attacks::shove(
+                        roller,
+                        economy,
+                        &mut conditions,
+                        &attacker,
+                        &target,
+                        ShoveEffect::KnockProne,
+                    ),
+                    Ok(pb::SpecialAttackKind::ShovePush) => special_attacks::shove(
+                        roller,
+                        economy,
+                        &mut conditions,
+                        &attacker,
+                        &target,
+                        ShoveEffect::Push,
+                    ),
+                    Ok(pb::SpecialAttackKind::EscapeGrapple) => special_attacks::escape_grapple(
+                        roller,
+                        economy,
+                        &mut conditions,
+                        &attacker,
+                        &target,
+                    ),
+                    _ => return None,
+                };
+                Some(outcome)
+            })
+            .ok_or_else(|| Status::invalid_argument("Special attack kind required"))?
+            .map_err(rules_error)?;
+
+        let contest_roll = |check: &CheckOutcome| pb::ContestRoll {
+            skill: match &check.kind {
+                CheckKind::Skill { skill, .. } => skill.clone(),
+                other => format!("{:?}", other),
+            },
+            natural_roll: check.natural_roll,
+            total: check.total,
+        };
+        Ok(Response::new(pb::SpecialAttackResponse {
+            success: outcome.contest.success,
+            attacker: Some(contest_roll(&outcome.contest.initiator)),
+            target: Some(contest_roll(&outcome.contest.resister)),
+            condition_id: outcome.condition_id.unwrap_or_default(),
+            pushed_feet: outcome.pushed_feet,
+            ended_conditions: outcome.ended_conditions,
+        }))
+    }
+
+    async fn plan_attack_action(
+        &self,
+        request: Request<pb::AttackPlanRequest>,
+    ) -> Result<Response<pb::AttackPlanResponse>, Status> {
+        let req = request.into_inner();
+        let stats = req
+            .stats
+            .as_ref()
+            .map(convert_proto_stats)
+            .unwrap_or_default();
+        let creature_id = if req.creature_id.is_empty() {
+            stats.creature_id.clone()
+        } else {
+            req.creature_id.clone()
+        };
+        let mut encounters = (!req.encounter_id.is_empty()).then(|| self.encounters());
+        let mut fresh_turn;
+        let turn = match encounters.as_mut() {
+            Some(encounters) => turn_economy(encounters, &req.encounter_id, &creature_id)?,
+            None => {
+                let features = EconomyFeatures {
+                    attacks_per_action: u8::try_from(req.attacks_per_action.clamp(1, 4))
+                        .unwrap_or(1),
+                    action_surge: req.action_surge,
+                    hasted: req.hasted,
+                };
+                fresh_turn = ActionEconomy::start_turn(
+                    &creature_id,
+                    0,
+                    &self.effects_for(&creature_id, &stats),
+                    features,
+                );
+                &mut fresh_turn
+            }
+        };
+        // Only a plan that holds up spends the turn.
+        let mut economy = turn.clone();
+        let mut sequence = AttackSequence::new(TwoWeaponFeatures {
+            fighting_style: req.two_weapon_fighting_style,
+            dual_wielder: req.dual_wielder,
+        });
+
+        let mut error = None;
+        for planned in &req.attacks {
+            if let Err(e) = plan_attack(
+                &mut economy,
+                &mut sequence,
+                &stats,
+                planned,
+                req.crossbow_expert,
+            ) {
+                error = Some(e);
+                break;
+            }
+        }
+        if error.is_none() {
+            turn.clone_from(&economy);
+        }
+
+        Ok(Response::new(pb::AttackPlanResponse {
+            valid: error.is_none(),
+            steps: sequence
+                .steps
+                .iter()
+                .map(|step| pb::AttackStep {
+                    weapon_id: step.weapon_id.clone(),
+                    off_hand: step.off_hand,
+                    number: i32::from(step.number),
+                    damage_modifier: step.damage_modifier,
+                })
+                .collect(),
+            error: error.map(|e| e.to_string()).unwrap_or_default(),
+            attacks_remaining: i32::from(economy.attacks_remaining),
+            bonus_action_available: economy.bonus_action,
+        }))
+    }
+
+    async fn validate_spell_cast(
+        &self,
+        request: Request<pb::ValidateSpellRequest>,
+    ) -> Result<Response<pb::ValidateSpellResponse>, Status> {
+        let req = request.into_inner();
+        let requirements = match req.spell.as_ref() {
+            Some(spell) => SpellRequirements {
+                spell_id: req.spell_id.clone(),
+                level: spell.level,
+                casting_time: parse_casting_time(&spell.casting_time),
+                requires_verbal: spell.requires_verbal,
+                requires_somatic: spell.requires_somatic,
+                requires_material: spell.requires_material,
+                material_cost_gp: spell.material_cost_gp,
+                material_consumed: spell.material_consumed,
+                requires_concentration: spell.requires_concentration,
+                max_targets: spell.max_targets,
+                range_feet: spell.range_feet,
+            },
+            None => {
+                let spell = Spellbook::builtin().get(&req.spell_id).ok_or_else(|| {
+                    Status::invalid_argument(format!(
+                        "Spell definition required for unknown spell {}",
+                        req.spell_id
+                    ))
+                })?;
+                let slot_level = u8::try_from(req.spell_slot_level.max(0)).unwrap_or(u8::MAX);
+                spell.requirements(slot_level.max(spell.level))
+            }
+        };
+        let caster = CasterState {
+            available_slots: req.available_slots,
+            prepared_spells: req.prepared_spells,
+            known_spells: req.known_spells,
+            is_concentrating: req.is_concentrating,
+            has_free_hand: req.has_free_hand,
+            has_focus: req.has_focus,
+            can_speak: req.can_speak,
+            silenced: req.silenced,
+            material_value_gp: req.material_value_gp,
+            cast_bonus_action_spell: req.cast_bonus_action_spell,
+            cast_leveled_spell: req.cast_leveled_spell,
+            is_raging: req.is_raging,
+            armor_not_proficient: req.armor_not_proficient,
+        };
+        let targets: Vec<TargetReach> = if req.targets.is_empty() {
+            req.target_ids.iter().map(TargetReach::unplaced).collect()
+        } else {
+            req.targets
+                .iter()
+                .map(|t| TargetReach {
+                    target_id: t.creature_id.clone(),
+                    distance_feet: t.distance_feet,
+                    line_of_effect: convert_cover(t.cover) != CoverType::Total,
+                })
+                .collect()
+        };
+
+        let result =
+            spells::validate_spell_cast(&requirements, &caster, req.spell_slot_level, &targets);
+
+        Ok(Response::new(pb::ValidateSpellResponse {
+            valid: result.valid,
+            errors: result.errors,
+            will_break_concentration: result.will_break_concentration,
+            slot_to_consume: result.slot_to_consume,
+            material_consumed_gp: result.material_consumed_gp,
+        }))
+    }
+
+    async fn resolve_spell(
+        &self,
+        request: Request<pb::ResolveSpellRequest>,
+    ) -> Result<Response<pb::ResolveSpellResponse>, Status> {
+        let req = request.into_inner();
+        let Some(spell) = req.spell.as_ref() else {
+            return self.resolve_builtin_spell(&req).map(Response::new);
+        };
+        let mut dice = self.dice(req.roll_context.as_ref(), req.seed, &req.caster_id)?;
+        let upcast_levels =
+            cast_level(&req)?.map_or(0, |level| (i32::from(level) - spell.level).max(0));
+        let caster = req
+            .caster_stats
+            .as_ref()
+            .map(convert_proto_stats)
+            .unwrap_or_default();
+        let caster_conditions = self.conditions_for(&req.caster_id, &caster);
+
+        let mut response = pb::ResolveSpellResponse {
+            success: true,
+            requires_concentration: spell.requires_concentration,
+            ..Default::default()
+        };
+
+        let damage_type = if spell.damage_dice.is_empty() {
+            None
+        } else {
+            Some(convert_damage_type(spell.damage_type)?)
+        };
+        let save_ability = if spell.save_ability == pb::Ability::Unspecified as i32 {
+            None
+        } else {
+            Some(convert_ability(spell.save_ability)?)
+        };
+        let damage_dice = match damage_type {
+            Some(_) => Some(upcast_dice(
+                &spell.damage_dice,
+                &spell.upcast_damage_per_level,
+                upcast_levels,
+            )?),
+            None => None,
+        };
+        let healing_dice = if spell.healing_dice.is_empty() {
+            None
+        } else {
+            Some(upcast_dice(
+                &spell.healing_dice,
+                &spell.upcast_healing_per_level,
+                upcast_levels,
+            )?)
+        };
+        let healing_modifier = if healing_dice.is_some() && spell.healing_adds_modifier {
+            caster.get_modifier(spellcasting_ability(&req, &spell.name)?)
+        } else {
+            0
+        };
+        // A save spell's damage is rolled once for every target (PHB p.196).
+        let shared_damage = match (&damage_dice, save_ability) {
+            (Some(expression), Some(_)) => {
+                Some(dice.roll(RollPurpose::Damage, &spell.name, |roller| {
+                    damage::roll_damage(roller, expression, 0, false)
+                }))
+            }
+            _ => None,
+        };
+
+        for target in &req.targets {
+            let stats = target
+                .stats
+                .as_ref()
+                .map(convert_proto_stats)
+                .unwrap_or_default();
+
+            if let Some(damage_type) = damage_type {
+                if let (Some(ability), Some((rolls, rolled))) = (save_ability, &shared_damage) {
+                    let mut input = self.save_input(
+                        &stats,
+                        ability,
+                        req.spell_save_dc,
+                        &target.features,
+                        &target.auras,
+                    )?;
+                    let cover_bonus = convert_cover(target.cover).dex_save_bonus();
+                    if ability == Ability::DEX && cover_bonus > 0 && !spell.ignores_cover {
+                        input = input.with_bonus("Cover", cover_bonus);
+                    }
+                    let conditions = self.conditions_for(&target.creature_id, &stats);
+                    let description = format!("{} save against {}", target.creature_id, spell.name);
+                    let save = dice.roll(RollPurpose::SavingThrow, &description, |roller| {
+                        checks::resolve_check(roller, &stats, &conditions, &input)
+                    });
+
+                    // Halve before resistance, so the damage is rounded
+                    // down at each step rather than once at the end.
+                    let amount = match spell.effect_on_save.as_str() {
+                        "half" if save.success => rolled / 2,
+                        "none" if save.success => 0,
+                        _ => *rolled,
+                    };
+                    let mut damage =
+                        spell_damage_result((rolls.clone(), amount), damage_type, &stats)?;
+                    if amount != *rolled {
+                        damage.base_damage = *rolled;
+                        damage
+                            .steps
+                            .insert(0, format!("{} rolled, saved: {}", rolled, amount));
+                    }
+
+                    response.save_results.push(pb::SpellSaveResult {
+                        target_id: target.creature_id.clone(),
+                        saved: save.success,
+                        save_roll: save.natural_roll,
+                        save_modifier: save.modifier,
+                        save_dc: save.dc,
+                        damage: Some(damage),
+                    });
+                } else {
+                    let cover = if spell.ignores_cover {
+                        CoverType::None
+                    } else {
+                        convert_cover(target.cover)
+                    };
+                    let distance = if target.distance_feet > 0 {
+                        target.distance_feet
+                    } else {
+                        5
+                    };
+                    let input = AttackInput::new(AttackSource::Spell, None)
+                        .with_bonus("Spell attack bonus", req.spell_attack_bonus)
+                        .with_cover(cover)
+                        .at_distance(distance);
+                    let target_conditions = self.conditions_for(&target.creature_id, &stats);
+                    let description = format!("{} at {}", spell.name, target.creature_id);
+                    let attack = dice.roll(RollPurpose::Attack, &description, |roller| {
+                        attack::resolve_attack(
+                            roller,
+                            &caster,
+                            &stats,
+                            &caster_conditions,
+                            &target_conditions,
+                            &input,
+                        )
+                    });
+                    let damage = match &damage_dice {
+                        Some(expression) if attack.hits() => {
+                            let rolled = dice.roll(RollPurpose::Damage, &description, |roller| {
+                                damage::roll_damage(roller, expression, 0, attack.is_critical())
+                            });
+                            Some(spell_damage_result(rolled, damage_type, &stats)?)
+                        }
+                        _ => None,
+                    };
+
+                    response.attack_results.push(pb::SpellAttackResult {
+                        target_id: target.creature_id.clone(),
+                        hits: attack.hits(),
+                        attack_roll: attack.total,
+                        target_ac: attack.target_ac,
+                        is_critical: attack.is_critical(),
+                        damage,
+                    });
+                }
+            }
+
+            if let Some(expression) = &healing_dice {
+                let healing = dice.roll(RollPurpose::Healing, &spell.name, |roller| {
+                    expression.roll(roller)
+                });
+                let modifier = healing_modifier;
+                let total_healing = (healing.total + modifier).max(0);
+                let new_hp = healed_hp(&stats, total_healing);
+
+                response.healing_results.push(pb::HealingResult {
+                    target_id: target.creature_id.clone(),
+                    rolls: healing.rolls.iter().map(convert_die_roll).collect(),
+                    modifier,
+                    total_healing,
+                    new_hp,
+                });
+            }
+        }
+
+        if spell.requires_concentration {
+            let spell_id = if req.spell_id.is_empty() {
+                &spell.spell_id
+            } else {
+                &req.spell_id
+            };
+            response.concentration_ended = self.begin_concentration(&req, spell_id)?;
+        }
+
+        Ok(Response::new(response))
+    }
+
+    async fn check_concentration(
+        &self,
+        request: Request<pb::ConcentrationCheckRequest>,
+    ) -> Result<Response<pb::ConcentrationCheckResponse>, Status> {
+        let req = request.into_inner();
+        let mut caster = require_stats(req.caster_stats.as_ref())?;
+        if !req.caster_id.is_empty() {
+            caster.creature_id = req.caster_id.clone();
+        }
+        let damage = if req.damage_instances.is_empty() {
+            vec![req.damage_taken]
+        } else {
+            req.damage_instances.clone()
+        };
+        if damage.iter().any(|d| *d < 0) {
+            return Err(Status::invalid_argument("Damage cannot be negative"));
+        }
+
+        let conditions = self.conditions_for(&caster.creature_id, &caster);
+        let description = format!("concentration after {:?} damage", damage);
+        let result = self
+            .dice(req.roll_context.as_ref(), req.seed, &caster.creature_id)?
+            .roll(RollPurpose::Concentration, &description, |roller| {
+                self.concentration().on_damage(
+                    roller,
+                    &caster,
+                    &conditions,
+                    &damage,
+                    &request_sources(req.advantage, false),
+                    &mut self.conditions(),
+                )
+            })
+            .ok_or_else(|| {
+                Status::failed_precondition(format!("{} is not concentrating", caster.creature_id))
+            })?;
+
+        // Report the deciding save: the failure, or the last one made.
+        let deciding = result.saves.last();
+        Ok(Response::new(pb::ConcentrationCheckResponse {
+            maintained: result.maintained(),
+            dc: deciding.map_or(CombatEngine::concentration_dc(0), |s| s.dc),
+            roll: deciding.map_or(0, |s| s.natural_roll),
+            modifier: deciding.map_or(0, |s| s.modifier),
+            total: deciding.map_or(0, |s| s.total),
+            saves_made: result.saves.len() as i32,
+            concentration_ended: result.ended.as_ref().map(convert_concentration_ended),
+        }))
+    }
+
+    async fn apply_condition(
+        &self,
+        request: Request<pb::ApplyConditionRequest>,
+    ) -> Result<Response<pb::ApplyConditionResponse>, Status> {
+        let req = request.into_inner();
+        let condition_type = convert_condition_type(req.condition_type)?;
+
+        let instance_id = Uuid::new_v4().to_string();
+        let mut condition = ActiveCondition::new(instance_id.clone(), condition_type)
+            .lasting(condition_duration(req.duration_type, req.duration_value));
+        if !req.source_id.is_empty() {
+            condition = condition.from_source(req.source_id.clone());
+        }
+        if let (Some(ability), true) = (convert_optional_ability(req.save_ability), req.save_dc > 0)
+        {
+            condition = condition.with_save(SaveToEnd {
+                ability,
+                dc: req.save_dc,
+                at_end_of_turn: req.save_at_end_of_turn,
+            });
+        }
+        if condition_type == ConditionType::Exhaustion {
+            condition = condition.with_exhaustion_level(req.exhaustion_level.max(1));
+        }
+        if req.concentration {
+            condition = condition.on_concentration();
+        }
+
+        let outcome = self.conditions().apply_condition(&req.target_id, condition);
+        let applied = outcome.applied();
+        let condition_instance_id = match outcome {
+            ApplyOutcome::Applied { .. } => instance_id,
+            // Exhaustion levels land on the creature's existing instance.
+            ApplyOutcome::ExhaustionIncreased { .. } => self
+                .conditions()
+                .get_conditions(&req.target_id)
+                .iter()
+                .find(|c| c.condition_type == ConditionType::Exhaustion)
+                .map(|c| c.id.clone())
+                .unwrap_or_default(),
+            ApplyOutcome::AlreadyActive => String::new(),
+        };
+        // Exhaustion reports the level the creature is at now.
+        let effects = self
+            .conditions()
+            .get_conditions(&req.target_id)
+            .iter()
+            .find(|c| c.id == condition_instance_id)
+            .map(|c| c.effects());
+        let target_died = applied && effects.as_ref().is_some_and(|e| e.dies);
+        let concentration_ended = if target_died {
+            self.concentration()
+                .end(&req.target_id, EndReason::Died, &mut self.conditions())
+        } else if applied {
+            self.concentration()
+                .check_incapacitated(&req.target_id, &mut self.conditions())
+        } else {
+            None
+        };
+
+        Ok(Response::new(pb::ApplyConditionResponse {
+            applied,
+            condition_instance_id,
+            target_was_immune: false,
+            effects_applied: match effects {
+                Some(effects) if applied => effects.describe(),
+                _ => vec![],
+            },
+            concentration_ended: concentration_ended
+                .as_ref()
+                .map(convert_concentration_ended),
+            target_died,
+        }))
+    }
+
+    async fn remove_condition(
+        &self,
+        request: Request<pb::RemoveConditionRequest>,
+    ) -> Result<Response<pb::RemoveConditionResponse>, Status> {
+        let req = request.into_inner();
+        let removed = self
+            .conditions()
+            .remove_condition(&req.target_id, &req.condition_instance_id);
+
+        Ok(Response::new(pb::RemoveConditionResponse {
+            removed: removed.is_some(),
+            effects_removed: removed.map(|c| c.effects().describe()).unwrap_or_default(),
+        }))
+    }
+
+    async fn get_active_conditions(
+        &self,
+        request: Request<pb::GetConditionsRequest>,
+    ) -> Result<Response<pb::GetConditionsResponse>, Status> {
+        let req = request.into_inner();
+        let manager = self.conditions();
+
+        let conditions = manager
+            .get_conditions(&req.creature_id)
+            .into_iter()
+            .map(convert_condition_instance)
+            .collect();
+
+        Ok(Response::new(pb::GetConditionsResponse { conditions }))
+    }
+
+    async fn roll_initiative(
+        &self,
+        request: Request<pb::InitiativeRequest>,
+    ) -> Result<Response<pb::InitiativeResponse>, Status> {
+        let req = request.into_inner();
+        let mut dice = self.dice(req.roll_context.as_ref(), req.seed, &req.creature_id)?;
+
+        let (natural_roll, total) = dice.roll(RollPurpose::Initiative, "initiative", |roller| {
+            combat::roll_initiative(
+                roller,
+                req.dexterity_modifier,
+                req.initiative_bonus,
+                req.advantage,
+            )
+        });
+
+        let order = if req.encounter_id.is_empty() {
+            Vec::new()
+        } else {
+            let dexterity = if req.dexterity_score > 0 {
+                req.dexterity_score
+            } else {
+                10 + 2 * req.dexterity_modifier
+            };
+            let mut encounters = self.encounters();
+            let scheduler = &mut encounters
+                .entry(req.encounter_id.clone())
+                .or_default()
+                .scheduler;
+            dice.roll(RollPurpose::Initiative, "initiative roll-off", |roller| {
+                scheduler.add(roller, &req.creature_id, total, dexterity)
+            })
+            .map_err(rules_error)?;
+            scheduler
+                .order()
+                .iter()
+                .map(|e| pb::InitiativeSlot {
+                    creature_id: e.creature_id.clone(),
+                    initiative: e.initiative,
+                    dexterity: e.dexterity,
+                    roll_off: e.roll_off,
+                    lair: e.lair,
+                })
+                .collect()
+        };
+
+        Ok(Response::new(pb::InitiativeResponse {
+            creature_id: req.creature_id,
+            initiative: total,
+            natural_roll,
+            total_modifier: req.dexterity_modifier + req.initiative_bonus,
+            order,
+        }))
+    }
+
+    async fn process_turn_start(
+        &self,
+        request: Request<pb::TurnStartRequest>,
+    ) -> Result<Response<pb::TurnStartResponse>, Status> {
+        let req = request.into_inner();
+        let mut dice = self.dice(req.roll_context.as_ref(), req.seed, &req.creature_id)?;
+        let round = self.encounter_turn_start(&req.encounter_id, &req.creature_id, &mut dice)?;
+        self.reactions().regain_reaction(&req.creature_id);
+
+        let mut condition_updates = Vec::new();
+        let mut expired_conditions = Vec::new();
+        for condition in req.conditions.iter().filter(|c| c.remaining_rounds > 0) {
+            let remaining = condition.remaining_rounds - 1;
+            if remaining == 0 {
+                expired_conditions.push(condition.id.clone());
+            }
+            condition_updates.push(pb::ConditionUpdate {
+                condition_id: condition.id.clone(),
+                remaining_duration: remaining,
+                expired: remaining == 0,
+            });
+        }
+
+        // Conditions tracked by the service tick down as well
+        for id in self.conditions().process_turn_start(&req.creature_id) {
+            if !expired_conditions.contains(&id) {
+                condition_updates.push(pb::ConditionUpdate {
+                    condition_id: id.clone(),
+                    remaining_duration: 0,
+                    expired: true,
+                });
+                expired_conditions.push(id);
+            }
+        }
+
+        let hooks = run_turn_hooks(
+            &req.creature_id,
+            req.stats.as_ref(),
+            &req.hooks,
+            &mut dice,
+            HookTiming::StartOfTurn,
+        )?;
+
+        let stats = req
+            .stats
+            .as_ref()
+            .map(convert_proto_stats)
+            .unwrap_or_default();
+        let economy = ActionEconomy::start_turn(
+            &req.creature_id,
+            req.speed,
+            &self.effects_for(&req.creature_id, &stats),
+            EconomyFeatures {
+                attacks_per_action: u8::try_from(req.attacks_per_action.clamp(1, 4)).unwrap_or(1),
+                action_surge: req.action_surge,
+                hasted: req.hasted,
+            },
+        );
+        let speed = economy.speed;
+        let mut feature_notes = Vec::new();
+        if !req.encounter_id.is_empty() {
+            let mut encounters = self.encounters();
+            let encounter = encounter(&mut encounters, &req.encounter_id)?;
+            encounter.turns.insert(req.creature_id.clone(), economy);
+            if let Some(features) = encounter.features.get_mut(&req.creature_id) {
+                feature_notes = features.on_turn_start(&stats);
+            }
+        }
+
+        // Recharge rolls for abilities like a dragon's breath weapon.
+        let (mut resources, owner) = load_resources(&req.creature_id, &req.resources)?;
+        let recharged = dice
+            .roll(
+                RollPurpose::Other("recharge".to_string()),
+                "turn start",
+                |roller| resources.trigger(roller, owner, RechargeTrigger::TurnStart),
+            )
+            .map_err(rules_error)?;
+
+        Ok(Response::new(pb::TurnStartResponse {
+            condition_updates,
+            expired_conditions,
+            damage_taken: hooks.damage_taken,
+            healing_received: hooks.healing_received,
+            save_prompts: hooks.save_prompts,
+            damage: hooks.damage,
+            resources: resources.resources(owner).map(resource_to_proto).collect(),
+            recharged: recharged.iter().map(convert_recharged).collect(),
+            round: round as i32,
+            speed,
+            feature_notes,
+        }))
+    }
+
+    async fn process_turn_end(
+        &self,
+        request: Request<pb::TurnEndRequest>,
+    ) -> Result<Response<pb::TurnEndResponse>, Status> {
+        let req = request.into_inner();
+
+        let mut save_prompts: Vec<pb::SavingThrowPrompt> = req
+            .conditions
+            .iter()
+            .filter(|c| c.save_dc > 0 && c.save_ability != pb::Ability::Unspecified as i32)
+            .map(|c| pb::SavingThrowPrompt {
+                condition_id: c.id.clone(),
+                ability: c.save_ability,
+                dc: c.save_dc,
+                description: format!("End of turn save against {}", c.condition_type),
+            })
+            .collect();
+
+        let mut dice = self.dice(req.roll_context.as_ref(), req.seed, &req.creature_id)?;
+        let hooks = run_turn_hooks(
+            &req.creature_id,
+            req.stats.as_ref(),
+            &req.hooks,
+            &mut dice,
+            HookTiming::EndOfTurn,
+        )?;
+        save_prompts.extend(hooks.save_prompts);
+        let next = self.encounter_turn_end(&req.encounter_id, &req.creature_id, &mut dice)?;
+
+        let mut manager = self.conditions();
+        for condition in manager.get_conditions(&req.creature_id) {
+            if let Some(save) = condition.save.filter(|s| s.at_end_of_turn) {
+                save_prompts.push(pb::SavingThrowPrompt {
+                    condition_id: condition.id.clone(),
+                    ability: ability_to_proto(save.ability) as i32,
+                    dc: save.dc,
+                    description: format!(
+                        "End of turn save against {}",
+                        condition.condition_type.name()
+                    ),
+                });
+            }
+        }
+
+        // Conditions this creature imposed "until the end of its next turn"
+        let expired_conditions = manager.process_turn_end(&req.creature_id);
+        let condition_updates = expired_conditions
+            .iter()
+            .map(|id| pb::ConditionUpdate {
+                condition_id: id.clone(),
+                remaining_duration: 0,
+                expired: true,
+            })
+            .collect();
+
+        Ok(Response::new(pb::TurnEndResponse {
+            condition_updates,
+            expired_conditions,
+            save_prompts,
+            damage_taken: hooks.damage_taken,
+            healing_received: hooks.healing_received,
+            damage: hooks.damage,
+            next_creature_id: next.as_ref().map(|t| t.started.clone()).unwrap_or_default(),
+            round: next.as_ref().map_or(0, |t| t.round as i32),
+            new_round: next.is_some_and(|t| t.new_round),
+        }))
+    }
+
+    async fn rest(
+        &self,
+        request: Request<pb::RestRequest>,
+    ) -> Result<Response<pb::RestResponse>, Status> {
+        let req = request.into_inner();
+        let stats = require_stats(req.stats.as_ref())?;
+        let creature_id = if req.creature_id.is_empty() {
+            stats.creature_id.clone()
+        } else {
+            req.creature_id.clone()
+        };
+        let variant = match pb::RestVariant::try_from(req.rest_variant) {
+            Ok(pb::RestVariant::Standard) => RestVariant::Standard,
+            Ok(pb::RestVariant::GrittyRealism) => RestVariant::GrittyRealism,
+            Ok(pb::RestVariant::EpicHeroism) => RestVariant::EpicHeroism,
+            _ => self.rest_variant,
+        };
+
+        let hit_dice = HitDice {
+            pools: req
+                .hit_dice
+                .iter()
+                .map(|pool| {
+                    Ok(HitDicePool {
+                        die: convert_die_type(pool.die)?,
+                        max: pool.max.max(0),
+                        remaining: pool.remaining.clamp(0, pool.max.max(0)),
+                    })
+                })
+                .collect::<Result<_, Status>>()?,
+        };
+        let (mut resources, owner) = load_resources(&creature_id, &req.resources)?;
+        // Older callers send features keyed by name.
+        for feature in &req.features {
+            let recharge = match convert_rest_kind(feature.recharge) {
+                Some(RestKind::Short) => Recharge::ShortRest,
+                _ => Recharge::LongRest,
+            };
+            let mut resource = Resource::new(&feature.name, &feature.name, feature.max, recharge);
+            resource.current = feature.remaining.clamp(0, resource.max);
+            resources.add(owner, resource);
+        }
+        let (spell_slots, sorcery_points) = req
+            .spell_slots
+            .as_ref()
+            .map(convert_slot_state)
+            .unwrap_or((None, None));
+        let mut creature = RestingCreature {
+            creature_id: creature_id.clone(),
+            entity_id: owner,
+            hit_points: HitPoints::from_stats(&stats, DeathRule::DeathSaves),
+            hit_dice,
+            con_modifier: req.con_modifier,
+            spell_slots,
+            sorcery_points,
+        };
+
+        let kind = convert_rest_kind(req.rest_type)
+            .ok_or_else(|| Status::invalid_argument("Rest type required"))?;
+        let mut dice = self.dice(req.roll_context.as_ref(), req.seed, &creature_id)?;
+        let report = match kind {
+            RestKind::Short => {
+                let hit_dice = req
+                    .hit_dice_to_spend
+                    .iter()
+                    .map(|d| convert_die_type(*d))
+                    .collect::<Result<Vec<_>, Status>>()?;
+                dice.roll(RollPurpose::Healing, "short rest", |roller| {
+                    rest::short_rest(roller, &mut creature, &mut resources, &hit_dice, variant)
+                })
+            }
+            RestKind::Long => dice.roll(
+                RollPurpose::Other("recharge".to_string()),
+                "long rest",
+                |roller| {
+                    rest::long_rest(
+                        roller,
+                        &mut creature,
+                        &mut resources,
+                        &mut self.conditions(),
+                        req.had_food_and_water,
+                        variant,
+                    )
+                },
+            ),
+        }
+        .map_err(rules_error)?;
+
+        Ok(Response::new(pb::RestResponse {
+            duration_minutes: (report.duration.as_secs() / 60) as i32,
+            hit_dice_rolls: report
+                .hit_dice_spent
+                .iter()
+                .map(|r| pb::HitDieRoll {
+                    die: r.die as i32,
+                    roll: r.roll,
+                    con_modifier: r.con_modifier,
+                    healing: r.healing,
+                })
+                .collect(),
+            hp_regained: report.hp_regained,
+            new_hp: creature.hit_points.current,
+            new_temp_hp: creature.hit_points.temp,
+            hit_dice: creature
+                .hit_dice
+                .pools
+                .iter()
+                .map(|p| pb::HitDicePool {
+                    die: p.die as i32,
+                    max: p.max,
+                    remaining: p.remaining,
+                })
+                .collect(),
+            hit_dice_regained: report.hit_dice_regained,
+            features: req
+                .features
+                .iter()
+                .filter_map(|f| resources.get(owner, &f.name))
+                .map(|r| pb::FeatureUses {
+                    name: r.name.clone(),
+                    max: r.max,
+                    remaining: r.current,
+                    recharge: rest_kind_to_proto(match r.recharge {
+                        Recharge::ShortRest => RestKind::Short,
+                        _ => RestKind::Long,
+                    }) as i32,
+                })
+                .collect(),
+            features_recharged: report.recharged.iter().map(|r| r.name.clone()).collect(),
+            resources: req
+                .resources
+                .iter()
+                .filter_map(|r| resources.get(owner, &r.id))
+                .map(resource_to_proto)
+                .collect(),
+            recharged: report.recharged.iter().map(convert_recharged).collect(),
+            spell_slots: creature
+                .spell_slots
+                .as_ref()
+                .map(|slots| slot_state_to_proto(slots, creature.sorcery_points.as_ref())),
+            exhaustion_removed: report.exhaustion_removed,
+            exhaustion_level: self.conditions().exhaustion_level(&creature_id),
+        }))
+    }
+
+    async fn use_resource(
+        &self,
+        request: Request<pb::UseResourceRequest>,
+    ) -> Result<Response<pb::UseResourceResponse>, Status> {
+        let req = request.into_inner();
+        let (mut resources, owner) = load_resources(&req.creature_id, &req.resources)?;
+        let amount = if req.amount == 0 { 1 } else { req.amount };
+        let remaining = resources
+            .spend(owner, &req.resource_id, amount)
+            .map_err(rules_error)?;
+        Ok(Response::new(pb::UseResourceResponse {
+            remaining,
+            resources: resources.resources(owner).map(resource_to_proto).collect(),
+        }))
+    }
+
+    async fn open_reaction_window(
+        &self,
+        request: Request<pb::OpenReactionWindowRequest>,
+    ) -> Result<Response<pb::ReactionWindowResponse>, Status> {
+        let req = request.into_inner();
+        let trigger = convert_trigger(req.trigger.as_ref())?;
+        let candidates = req
+            .candidates
+            .iter()
+            .map(convert_reactor)
+            .collect::<Result<Vec<_>, _>>()?;
+        let window_id = if req.window_id.is_empty() {
+            Uuid::new_v4().to_string()
+        } else {
+            req.window_id
+        };
+        let now = Instant::now();
+        let mut bus = self.reactions();
+        let expired = bus.expire(now);
+        let status = bus
+            .open(window_id, trigger, &candidates, now)
+            .map_err(rules_error)?;
+        Ok(Response::new(reaction_window_response(status, expired)))
+    }
+
+    async fn declare_reaction(
+        &self,
+        request: Request<pb::DeclareReactionRequest>,
+    ) -> Result<Response<pb::ReactionWindowResponse>, Status> {
+        let req = request.into_inner();
+        let reaction = convert_reaction_kind(req.reaction)?;
+        self.answer_reaction(&req.window_id, &req.creature_id, Decision::React(reaction))
+    }
+
+    async fn decline_reaction(
+        &self,
+        request: Request<pb::DeclineReactionRequest>,
+    ) -> Result<Response<pb::ReactionWindowResponse>, Status> {
+        let req = request.into_inner();
+        self.answer_reaction(&req.window_id, &req.creature_id, Decision::Decline)
+    }
+
+    async fn calculate_modifier(
+        &self,
+        request: Request<pb::ModifierRequest>,
+    ) -> Result<Response<pb::ModifierResponse>, Status> {
+        let req = request.into_inner();
+        let modifier = CombatEngine::calculate_modifier(req.ability_score);
+        Ok(Response::new(pb::ModifierResponse { modifier }))
+    }
+
+    async fn get_proficiency_bonus(
+        &self,
+        request: Request<pb::ProficiencyRequest>,
+    ) -> Result<Response<pb::ProficiencyResponse>, Status> {
+        let req = request.into_inner();
+        let bonus = CombatEngine::get_proficiency_bonus(req.level);
+        Ok(Response::new(pb::ProficiencyResponse {
+            proficiency_bonus: bonus,
+        }))
+    }
+}
+
+impl RulesServiceImpl {
+    /// Record a reactor's answer, then close any other windows that timed
+    /// out meanwhile.
+    fn answer_reaction(
+        &self,
+        window_id: &str,
+        creature_id: &str,
+        decision: Decision,
+    ) -> Result<Response<pb::ReactionWindowResponse>, Status> {
+        let now = Instant::now();
+        let mut bus = self.reactions();
+        let status = bus
+            .declare(window_id, creature_id, decision, now)
+            .map_err(rules_error)?;
+        let expired = bus.expire(now);
+        Ok(Response::new(reaction_window_response(status, expired)))
+    }
+
+    /// Start concentrating on `spell_id` for the request's caster, along
+    /// with the summons and zones it created.
+    fn begin_concentration(
+        &self,
+        req: &pb::ResolveSpellRequest,
+        spell_id: &str,
+    ) -> Result<Option<pb::ConcentrationEnded>, Status> {
+        if req.caster_id.is_empty() {
+            return Ok(None);
+        }
+        let mut tracker = self.concentration();
+        let replaced = tracker.begin(&req.caster_id, spell_id, &mut self.conditions());
+        let summons = req.summon_ids.iter().map(|id| DependentEffect::Summon {
+            entity_id: id.clone(),
+        });
+        let zones = req.zone_ids.iter().map(|id| DependentEffect::Zone {
+            zone_id: id.clone(),
+        });
+        for effect in summons.chain(zones) {
+            tracker
+                .add_effect(&req.caster_id, effect)
+                .map_err(|e| Status::internal(e.to_string()))?;
+        }
+        Ok(replaced.as_ref().map(convert_concentration_ended))
+    }
+
+    /// Resolve `req.spell_id` from the built-in spellbook against the
+    /// targets it can reach.
+    fn resolve_builtin_spell(
+        &self,
+        req: &pb::ResolveSpellRequest,
+    ) -> Result<pb::ResolveSpellResponse, Status> {
+        let spell = Spellbook::builtin()
+            .get(&req.spell_id)
+            .ok_or_else(|| Status::not_found(format!("Unknown spell: {}", req.spell_id)))?;
+        let slot_level = cast_level(req)?.unwrap_or(spell.level);
+
+        let mut caster = req
+            .caster_stats
+            .as_ref()
+            .map(convert_proto_stats)
+            .unwrap_or_default();
+        if !req.caster_id.is_empty() {
+            caster.creature_id = req.caster_id.clone();
+        }
+        let caster_conditions = self.conditions_for(&caster.creature_id, &caster);
+        let target_state = |target: &pb::SpellTarget| -> Result<TargetState, Status> {
+            let mut stats = target
+                .stats
+                .as_ref()
+                .map(convert_proto_stats)
+                .unwrap_or_default();
+            stats.creature_id = target.creature_id.clone();
+            let conditions = self.conditions_for(&target.creature_id, &stats);
+            let save = match spell.resolution {
+                Resolution::Save { ability, .. } => Some(self.save_input(
+                    &stats,
+                    ability,
+                    req.spell_save_dc,
+                    &target.features,
+                    &target.auras,
+                )?),
+                _ => None,
+            };
+            let mut state = TargetState {
+                distance_feet: target.distance_feet,
+                cover: convert_cover(target.cover),
+                ..TargetState::new(stats, conditions)
+            };
+            if let Some(save) = save {
+                state = state.with_save_help(save);
+            }
+            Ok(state)
+        };
+        // On a map the grid solver finds who the spell reaches. Without one
+        // the resolver checks the distance and cover sent for each target,
+        // and an area has nowhere to be placed.
+        let targets: Vec<TargetState> = match &req.map {
+            Some(map) => {
+                let grid = grid_solver::service::convert_map(map)?;
+                let aim = Aim {
+                    point: req
+                        .origin_point
+                        .as_ref()
+                        .map(|p| GridPosition::new(p.x, p.y)),
+                    direction: grid_solver::service::convert_direction(req.direction)?,
+                    creature_ids: req.targets.iter().map(|t| t.creature_id.clone()).collect(),
+                };
+                spell_resolver::select_targets(&grid, spell, &caster.creature_id, slot_level, &aim)
+                    .map_err(rules_error)?
+                    .iter()
+                    .map(|placed| {
+                        let target = req
+                            .targets
+                            .iter()
+                            .find(|t| t.creature_id == placed.creature_id)
+                            .ok_or_else(|| {
+                                Status::invalid_argument(format!(
+                                    "{} is caught by {} but has no stats",
+                                    placed.creature_id, spell.name
+                                ))
+                            })?;
+                        Ok(target_state(target)?.placed(placed))
+                    })
+                    .collect::<Result<_, Status>>()?
+            }
+            None if matches!(spell.targeting, Targeting::Area { .. }) => {
+                return Err(Status::invalid_argument(format!(
+                    "{} needs the map to find its targets",
+                    spell.name
+                )));
+            }
+            None => req
+                .targets
+                .iter()
+                .map(target_state)
+                .collect::<Result<_, Status>>()?,
+        };
+        let ctx = CastContext {
+            caster: &caster,
+            caster_conditions: &caster_conditions,
+            slot_level,
+            character_level: req.caster_level,
+            spell_save_dc: req.spell_save_dc,
+            spell_attack_bonus: req.spell_attack_bonus,
+            spellcasting_modifier: if spell.healing.as_ref().is_some_and(|h| h.add_modifier) {
+                caster.get_modifier(spellcasting_ability(req, &spell.name)?)
+            } else {
+                0
+            },
+        };
+        let outcome = self
+            .dice(req.roll_context.as_ref(), req.seed, &req.caster_id)?
+            .roll(
+                RollPurpose::Other("spell".to_string()),
+                &spell.name,
+                |roller| spell_resolver::resolve(roller, spell, &ctx, &targets),
+            )
+            .map_err(rules_error)?;
+
+        let mut response = pb::ResolveSpellResponse {
+            success: true,
+            requires_concentration: spell.concentration,
+            concentration_duration_rounds: if spell.concentration {
+                outcome.duration_rounds.unwrap_or(0)
+            } else {
+                0
+            },
+            ..Default::default()
+        };
+        // Begin concentrating first: it ends the caster's previous spell,
+        // which must not take this spell's conditions with it.
+        if spell.concentration {
+            response.concentration_ended = self.begin_concentration(req, &spell.id)?;
+        }
+
+        for (result, target) in outcome.targets.iter().zip(&targets) {
+            let damage = result.damage.as_ref().map(convert_damage_result);
+            if let Some(save) = &result.save {
+                response.save_results.push(pb::SpellSaveResult {
+                    target_id: result.target_id.clone(),
+                    saved: save.success,
+                    save_roll: save.natural_roll,
+                    save_modifier: save.modifier,
+                    save_dc: save.dc,
+                    damage,
+                });
+            } else if let Some(attack) = &result.attack {
+                response.attack_results.push(pb::SpellAttackResult {
+                    target_id: result.target_id.clone(),
+                    hits: attack.hits(),
+                    attack_roll: attack.total,
+                    target_ac: attack.target_ac,
+                    is_critical: attack.is_critical(),
+                    damage,
+                });
+            } else if damage.is_some() {
+                // Automatic hits such as Magic Missile.
+                response.attack_results.push(pb::SpellAttackResult {
+                    target_id: result.target_id.clone(),
+                    hits: true,
+                    target_ac: target.stats.armor_class,
+                    damage,
+                    ..Default::default()
+                });
+            }
+
+            if let Some(healing) = &result.healing {
+                response.healing_results.push(pb::HealingResult {
+                    target_id: result.target_id.clone(),
+                    rolls: healing.rolls.iter().map(convert_die_roll).collect(),
+                    modifier: healing.modifier,
+                    total_healing: healing.total,
+                    new_hp: healed_hp(&target.stats, healing.total),
+                });
+            }
+
+            for rider in &result.riders {
+                let (effect, feet) = match rider {
+                    RiderEffect::Push { feet } => ("push", *feet),
+                    RiderEffect::SpeedReduction { feet } => ("speed_reduction", *feet),
+                    RiderEffect::Condition { .. } => continue,
+                };
+                response.riders.push(pb::SpellRiderResult {
+                    target_id: result.target_id.clone(),
+                    effect: effect.to_string(),
+                    feet,
+                });
+            }
+
+            for condition in &result.conditions {
+                let applied = pb::AppliedCondition {
+                    target_id: result.target_id.clone(),
+                    condition_type: condition.condition_type.name().to_string(),
+                    duration_rounds: condition.remaining_rounds().unwrap_or(0),
+                    save_ability: condition
+                        .save
+                        .map_or(pb::Ability::Unspecified, |s| ability_to_proto(s.ability))
+                        as i32,
+                    save_dc: condition.save.map_or(0, |s| s.dc),
+                };
+                if self
+                    .conditions()
+                    .apply_condition(&result.target_id, condition.clone())
+                    .applied()
+                {
+                    response.applied_conditions.push(applied);
+                }
+            }
+        }
+
+        Ok(response)
+    }
+
+    /// Conditions tracked by the service plus any named on the stats
+    /// snapshot sent with the request.
+    fn conditions_for(&self, creature_id: &str, stats: &CreatureStats) -> Vec<ActiveCondition> {
+        let mut conditions: Vec<ActiveCondition> = self
+            .conditions()
+            .get_conditions(creature_id)
+            .into_iter()
+            .cloned()
+            .collect();
+        for condition_type in stats
+            .active_conditions
+            .iter()
+            .filter_map(|n| ConditionType::from_name(n))
+        {
+            if !conditions
+                .iter()
+                .any(|c| c.condition_type == condition_type)
+            {
+                conditions.push(condition_type.into());
+            }
+        }
+        conditions
+    }
+
+    /// Effects from conditions tracked by the service plus those listed on
+    /// the stats snapshot sent with the request.
+    fn effects_for(&self, creature_id: &str, stats: &CreatureStats) -> ConditionEffects {
+        let mut effects = self.conditions().get_combined_effects(creature_id);
+        effects.merge(&crate::conditions::combined_effects_of(
+            &stats.active_conditions,
+        ));
+        effects
+    }
+
+    /// A creature at 0 hit points falls unconscious (PHB p.197) until it
+    /// regains a hit point.
+    fn fall_unconscious(&self, creature_id: &str) {
+        let condition =
+            ActiveCondition::new(dying_condition_id(creature_id), ConditionType::Unconscious);
+        self.conditions().apply_condition(creature_id, condition);
+    }
+
+    fn regain_consciousness(&self, creature_id: &str) {
+        self.conditions()
+            .remove_condition(creature_id, &dying_condition_id(creature_id));
+    }
+
+    /// A save by `stats` with what its own `features` and the `auras`
+    /// around it add, e.g. Rage or Aura of Protection.
+    fn save_input(
+        &self,
+        stats: &CreatureStats,
+        ability: Ability,
+        dc: i32,
+        features: &[pb::ClassFeatureUse],
+        auras: &[pb::SaveAura],
+    ) -> Result<CheckInput, Status> {
+        let mut input = CheckInput::new(CheckKind::SavingThrow(ability), dc);
+        feature_set(features)?.on_save(&SaveContext::own(stats), &mut input);
+        for aura in auras {
+            let source = require_stats(aura.source.as_ref())?;
+            let context = SaveContext {
+                owner: &source,
+                saver: stats,
+                distance_ft: aura.distance_ft,
+                friendly: !aura.hostile,
+                owner_conscious: !self
+                    .conditions()
+                    .has_condition(&source.creature_id, ConditionType::Unconscious),
+            };
+            feature_set(&aura.features)?.on_save(&context, &mut input);
+        }
+        Ok(input)
+    }
+}
+
+/// Advantage/disadvantage flags from a request, as named sources.
+fn request_sources(advantage: bool, disadvantage: bool) -> D20Sources {
+    let mut sources = D20Sources::new();
+    if advantage {
+        sources = sources.with_advantage("Request");
+    }
+    if disadvantage {
+        sources = sources.with_disadvantage("Request");
+    }
+    sources
+}
+
+/// A custom spell's dice with `per_level` added for each level it's
+/// upcast.
+fn upcast_dice(base: &str, per_level: &str, upcast_levels: i32) -> Result<DiceExpression, Status> {
+    let per_level = (!per_level.is_empty()).then_some(per_level);
+    let upcast_levels = u8::try_from(upcast_levels.max(0)).unwrap_or(u8::MAX);
+    // The definition came with the request, so bad dice are the caller's.
+    spell_resolver::scaled_expression(base, per_level, 1, upcast_levels)
+        .map_err(|e| Status::invalid_argument(e.to_string()))
+}
+
+/// A spell's damage to one target, through the target's defenses.
+fn spell_damage_result(
+    (rolls, amount): (Vec<DieRoll>, i32),
+    damage_type: DamageType,
+    target: &CreatureStats,
+) -> Result<pb::DamageResult, Status> {
+    let part = DamagePart {
+        dice: String::new(),
+        modifier: 0,
+        damage_type,
+        magical: true,
+    };
+    let (mut results, _) = resolve_rolled_damage(&[part], vec![(rolls, amount)], target)?;
+    Ok(results.remove(0))
+}
+
+#[derive(Default)]
+struct TurnHookResults {
+    damage_taken: i32,
+    healing_received: i32,
+    damage: Vec<pb::DamageResult>,
+    save_prompts: Vec<pb::SavingThrowPrompt>,
+}
+
+/// Fire the request's turn hooks; ongoing damage goes through the
+/// creature's resistances.
+fn run_turn_hooks(
+    creature_id: &str,
+    stats: Option<&pb::CreatureStats>,
+    hooks: &[pb::TurnHook],
+    dice: &mut RequestDice,
+    timing: HookTiming,
+) -> Result<TurnHookResults, Status> {
+    let mut outcomes = Vec::new();
+    for proto in hooks {
+        let hook = convert_turn_hook(proto, creature_id, timing)?;
+        if hook.timing != timing {
+            continue;
+        }
+        // Each hook carries its own suppression: fire damage switches off
+        // the regeneration it names, not every hook sent alongside it.
+        // Only damage hooks roll dice.
+        let fired = dice
+            .roll(RollPurpose::Damage, &hook.id, |roller| {
+                initiative::fire_hooks(roller, [&hook], proto.suppressed)
+            })
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        outcomes.extend(fired);
+    }
+    let defenses = stats
+        .map(|s| DamageDefenses::from_stats(&convert_proto_stats(s)))
+        .unwrap_or_default();
+
+    let mut results = TurnHookResults::default();
+    for outcome in outcomes {
+        match outcome {
+            HookOutcome::Regenerated { amount, .. } => results.healing_received += amount,
+            HookOutcome::RegenerationSuppressed { .. } => {}
+            HookOutcome::Damage {
+                instance, rolls, ..
+            } => {
+                let applied = damage::apply_defenses(instance, &defenses);
+                results.damage_taken += applied.final_amount;
+                results.damage.push(convert_damage_result(&DamageResult {
+                    rolls,
+                    base_damage: instance.amount,
+                    modifier: 0,
+                    damage_type: instance.damage_type,
+                    is_resistant: applied.resisted,
+                    is_vulnerable: applied.vulnerable,
+                    is_immune: applied.immune,
+                    final_damage: applied.final_amount,
+                }));
+            }
+            HookOutcome::SaveDue {
+                condition_id,
+                ability,
+                dc,
+                ..
+            } => {
+                let when = match timing {
+                    HookTiming::StartOfTurn => "Start",
+                    HookTiming::EndOfTurn => "End",
+                };
+                results.save_prompts.push(pb::SavingThrowPrompt {
+                    description: format!("{} of turn save against {}", when, condition_id),
+                    condition_id,
+                    ability: ability_to_proto(ability) as i32,
+                    dc,
+                });
+            }
+        }
+    }
+    Ok(results)
+}
+
+fn convert_turn_hook(
+    proto: &pb::TurnHook,
+    creature_id: &str,
+    timing: HookTiming,
+) -> Result<TurnHook, Status> {
+    let effect = match pb::TurnHookKind::try_from(proto.kind) {
+        Ok(pb::TurnHookKind::Regeneration) => TurnEffect::Regeneration {
+            amount: proto.amount.max(0),
+            suppressed_by: Vec::new(),
+        },
+        Ok(pb::TurnHookKind::OngoingDamage) => TurnEffect::OngoingDamage {
+            dice: proto.damage_dice.clone(),
+            damage_type: convert_damage_type(proto.damage_type)?,
+        },
+        Ok(pb::TurnHookKind::RepeatSave) => TurnEffect::RepeatSave {
+            condition_id: proto.condition_id.clone(),
+            ability: convert_ability(proto.save_ability)?,
+            dc: proto.save_dc,
+        },
+        _ => return Err(Status::invalid_argument("Turn hook kind required")),
+    };
+    let timing = match pb::TurnHookTiming::try_from(proto.timing) {
+        Ok(pb::TurnHookTiming::StartOfTurn) => HookTiming::StartOfTurn,
+        Ok(pb::TurnHookTiming::EndOfTurn) => HookTiming::EndOfTurn,
+        _ => timing,
+    };
+    Ok(TurnHook::new(proto.id.clone(), creature_id, timing, effect))
+}
+
+/// Instance id of the Unconscious condition a creature has at 0 hit points.
+fn dying_condition_id(creature_id: &str) -> String {
+    format!("{}:0-hp", creature_id)
+}
+
+fn require_max_hp(stats: &CreatureStats) -> Result<(), Status> {
+    if stats.max_hp <= 0 {
+        return Err(Status::invalid_argument(format!(
+            "Invalid max HP: {}",
+            stats.max_hp
+        )));
+    }
+    Ok(())
+}
+
+/// Death save (successes, failures) to report for `state`.
+fn death_saves(state: LifeState) -> (i32, i32) {
+    match state {
+        LifeState::Dying {
+            successes,
+            failures,
+        } => (successes as i32, failures as i32),
+        LifeState::Dead => (0, 3),
+        _ => (0, 0),
+    }
+}
+
+/// Hit points after `healing`, capped at the maximum when it is known.
+fn healed_hp(stats: &CreatureStats, healing: i32) -> i32 {
+    let healed = stats.current_hp.max(0) + healing;
+    if stats.max_hp > 0 {
+        healed.min(stats.max_hp)
+    } else {
+        healed
+    }
+}
+
+/// Casting time from a definition's text, e.g. "1 bonus action".
+fn parse_casting_time(text: &str) -> CastingTime {
+    let text = text.to_ascii_lowercase();
+    let count = text
+        .split_whitespace()
+        .next()
+        .and_then(|n| n.parse::<u32>().ok())
+        .unwrap_or(1);
+    if text.contains("bonus") {
+        CastingTime::BonusAction
+    } else if text.contains("reaction") {
+        CastingTime::Reaction
+    } else if text.contains("hour") {
+        CastingTime::Minutes(count * 60)
+    } else if text.contains("minute") {
+        CastingTime::Minutes(count)
+    } else {
+        CastingTime::Action
+    }
+}
+
+fn rules_error(error: DndError) -> Status {
+    match error {
+        DndError::EntityNotFound(_) => Status::not_found(error.to_string()),
+        DndError::RulesViolation(_) => Status::failed_precondition(error.to_string()),
+        DndError::InvalidSpellDefinition(_) | DndError::InvalidWeaponDefinition(_) => {
+            Status::internal(error.to_string())
+        }
+        _ => Status::invalid_argument(error.to_string()),
+    }
+}
+
+/// The slot level the spell is cast at, or `None` for the spell's own level.
+fn cast_level(req: &pb::ResolveSpellRequest) -> Result<Option<u8>, Status> {
+    match req.cast_at_level {
+        level if level <= 0 => Ok(None),
+        level if level <= MAX_SPELL_LEVEL as i32 => Ok(Some(level as u8)),
+        level => Err(Status::invalid_argument(format!(
+            "Invalid slot level: {}; spells go up to {}th level",
+            level, MAX_SPELL_LEVEL
+        ))),
+    }
+}
+
+/// The caster's spellcasting ability, for spells that add its modifier.
+fn spellcasting_ability(
+    req: &pb::ResolveSpellRequest,
+    spell_name: &str,
+) -> Result<Ability, Status> {
+    if req.spellcasting_ability == pb::Ability::Unspecified as i32 {
+        return Err(Status::invalid_argument(format!(
+            "{} adds the caster's spellcasting modifier; spellcasting_ability is required",
+            spell_name
+        )));
+    }
+    convert_ability(req.spellcasting_ability)
+}
+
+fn format_d20_expression(modifier: i32) -> String {
+    match modifier {
+        0 => "1d20".to_string(),
+        m if m > 0 => format!("1d20+{}", m),
+        m => format!("1d20{}", m),
+    }
+}
+
+fn condition_duration(duration_type: i32, value: i32) -> ConditionDuration {
+    match pb::DurationType::try_from(duration_type) {
+        Ok(pb::DurationType::Rounds) => ConditionDuration::Rounds(value),
+        Ok(pb::DurationType::Minutes) => ConditionDuration::Rounds(value * 10),
+        Ok(pb::DurationType::Hours) => ConditionDuration::Rounds(value * 600),
+        _ => ConditionDuration::UntilRemoved,
+    }
+}
+
+fn require_stats(stats: Option<&pb::CreatureStats>) -> Result<CreatureStats, Status> {
+    stats
+        .map(convert_proto_stats)
+        .ok_or_else(|| Status::invalid_argument("Stats required"))
+}
+
+// Helper functions to convert proto types to internal types
+
+fn convert_ability(proto_ability: i32) -> Result<Ability, Status> {
+    convert_optional_ability(proto_ability)
+        .ok_or_else(|| Status::invalid_argument(format!("Invalid ability: {}", proto_ability)))
+}
+
+fn convert_optional_ability(proto_ability: i32) -> Option<Ability> {
+    match pb::Ability::try_from(proto_ability).ok()? {
+        pb::Ability::Str => Some(Ability::STR),
+        pb::Ability::Dex => Some(Ability::DEX),
+        pb::Ability::Con => Some(Ability::CON),
+        pb::Ability::Int => Some(Ability::INT),
+        pb::Ability::Wis => Some(Ability::WIS),
+        pb::Ability::Cha => Some(Ability::CHA),
+        pb::Ability::Unspecified => None,
+    }
+}
+
+fn ability_to_proto(ability: Ability) -> pb::Ability {
+    match ability {
+        Ability::STR => pb::Ability::Str,
+        Ability::DEX => pb::Ability::Dex,
+        Ability::CON => pb::Ability::Con,
+        Ability::INT => pb::Ability::Int,
+        Ability::WIS => pb::Ability::Wis,
+        Ability::CHA => pb::Ability::Cha,
+    }
+}
+
+fn convert_damage_type(proto_type: i32) -> Result<DamageType, Status> {
+    convert_optional_damage_type(proto_type)
+        .ok_or_else(|| Status::invalid_argument(format!("Invalid damage type: {}", proto_type)))
+}
+
+fn convert_optional_damage_type(proto_type: i32) -> Option<DamageType> {
+    match pb::DamageType::try_from(proto_type).ok()? {
+        pb::DamageType::Slashing => Some(DamageType::Slashing),
+        pb::DamageType::Piercing => Some(DamageType::Piercing),
+        pb::DamageType::Bludgeoning => Some(DamageType::Bludgeoning),
+        pb::DamageType::Fire => Some(DamageType::Fire),
+        pb::DamageType::Cold => Some(DamageType::Cold),
+        pb::DamageType::Lightning => Some(DamageType::Lightning),
+        pb::DamageType::Thunder => Some(DamageType::Thunder),
+        pb::DamageType::Acid => Some(DamageType::Acid),
+        pb::DamageType::Poison => Some(DamageType::Poison),
+        pb::DamageType::Necrotic => Some(DamageType::Necrotic),
+        pb::DamageType::Radiant => Some(DamageType::Radiant),
+        pb::DamageType::Force => Some(DamageType::Force),
+        pb::DamageType::Psychic => Some(DamageType::Psychic),
+        pb::DamageType::Unspecified => None,
+    }
+}
+
+fn damage_type_to_proto(damage_type: DamageType) -> pb::DamageType {
+    match damage_type {
+        DamageType::Slashing => pb::DamageType::Slashing,
+        DamageType::Piercing => pb::DamageType::Piercing,
+        DamageType::Bludgeoning => pb::DamageType::Bludgeoning,
+        DamageType::Fire => pb::DamageType::Fire,
+        DamageType::Cold => pb::DamageType::Cold,
+        DamageType::Lightning => pb::DamageType::Lightning,
+        DamageType::Thunder => pb::DamageType::Thunder,
+        DamageType::Acid => pb::DamageType::Acid,
+        DamageType::Poison => pb::DamageType::Poison,
+        DamageType::Necrotic => pb::DamageType::Necrotic,
+        DamageType::Radiant => pb::DamageType::Radiant,
+        DamageType::Force => pb::DamageType::Force,
+        DamageType::Psychic => pb::DamageType::Psychic,
+    }
+}
+
+fn convert_armor_setup(setup: &pb::ArmorSetup) -> Result<ArmorSetup, Status> {
+    let armor = match &setup.armor {
+        None => None,
+        Some(piece) => {
+            let category = match pb::ArmorCategory::try_from(piece.category) {
+                Ok(pb::ArmorCategory::Light) => Some(ArmorCategory::Light),
+                Ok(pb::ArmorCategory::Medium) => Some(ArmorCategory::Medium),
+                Ok(pb::ArmorCategory::Heavy) => Some(ArmorCategory::Heavy),
+                _ => None,
+            };
+            let armor = match category {
+                Some(category) => Armor::new(&piece.name, category, piece.base_ac),
+                None => Armor::srd(&piece.name).ok_or_else(|| {
+                    Status::invalid_argument(format!("Unknown armor: {}", piece.name))
+                })?,
+            };
+            Some(armor.with_magic_bonus(piece.magic_bonus))
+        }
+    };
+    let unarmored_defense = match pb::UnarmoredDefense::try_from(setup.unarmored_defense) {
+        Ok(pb::UnarmoredDefense::Barbarian) => Some(UnarmoredDefense::Barbarian),
+        Ok(pb::UnarmoredDefense::Monk) => Some(UnarmoredDefense::Monk),
+        _ => None,
+    };
+    Ok(ArmorSetup {
+        armor,
+        shield: setup.shield,
+        shield_magic_bonus: setup.shield_magic_bonus,
+        unarmored_defense,
+        mage_armor: setup.mage_armor,
+        natural_armor: (setup.natural_armor_base > 0).then_some(NaturalArmor {
+            base: setup.natural_armor_base,
+            adds_dex: setup.natural_armor_adds_dex,
+        }),
+        draconic_resilience: setup.draconic_resilience,
+        medium_armor_master: setup.medium_armor_master,
+        defense_style: setup.defense_style,
+        bonuses: setup
+            .bonuses
+            .iter()
+            .map(|b| Modifier::new(&b.source, b.value))
+            .collect(),
+    })
+}
+
+/// Build a creature's class features from the request, switching on the
+/// active ones. Features already used this turn are left out.
+fn feature_set(uses: &[pb::ClassFeatureUse]) -> Result<FeatureSet, Status> {
+    let mut features = FeatureSet::new();
+    add_features(&mut features, uses.iter().filter(|f| !f.used_this_turn))?;
+    Ok(features)
+}
+
+/// Add the request's features that `features` doesn't have yet and switch
+/// on the active ones that are off; the state of the rest is kept.
+fn add_features<'a>(
+    features: &mut FeatureSet,
+    uses: impl IntoIterator<Item = &'a pb::ClassFeatureUse>,
+) -> Result<(), Status> {
+    for feature_use in uses {
+        let already_on = match features.get(&feature_use.feature_id) {
+            Some(feature) => feature.is_active(),
+            None => {
+                let level = u8::try_from(feature_use.level.clamp(0, 20)).unwrap_or(0);
+                let feature = FeatureRegistry::builtin()
+                    .create(&feature_use.feature_id, level)
+                    .map_err(rules_error)?;
+                features.add(feature);
+                false
+            }
+        };
+        if feature_use.active && !already_on {
+            let activation = Activation {
+                target_id: (!feature_use.target_id.is_empty())
+                    .then(|| feature_use.target_id.clone()),
+                slot_level: u8::try_from(feature_use.slot_level)
+                    .ok()
+                    .filter(|level| *level > 0),
+            };
+            features
+                .activate(&feature_use.feature_id, &activation)
+                .map_err(rules_error)?;
+        }
+    }
+    Ok(())
+}
+
+/// Add one declared attack to the turn, using Action Surge once the
+/// first action's attacks are spent.
+fn plan_attack(
+    economy: &mut ActionEconomy,
+    sequence: &mut AttackSequence,
+    stats: &CreatureStats,
+    planned: &pb::PlannedAttack,
+    crossbow_expert: bool,
+) -> Result<(), DndError> {
+    let weapon = Armory::builtin().get(&planned.weapon_id).ok_or_else(|| {
+        DndError::EntityNotFound(format!("Unknown weapon: {}", planned.weapon_id))
+    })?;
+    let usage = WeaponUse {
+        two_hands: planned.two_hands,
+        thrown: planned.thrown,
+        distance_feet: if planned.distance > 0 {
+            planned.distance
+        } else {
+            5
+        },
+        ignore_loading: crossbow_expert,
+    };
+    let attack = weapons::derive_attack(weapon, stats, &usage)?;
+    if planned.off_hand {
+        sequence.off_hand_attack(economy, stats, weapon, &attack, &usage)?;
+    } else {
+        if economy.attacks_remaining == 0 && economy.actions == 0 && economy.features.action_surge {
+            economy.use_action_surge()?;
+        }
+        sequence.attack(economy, stats, weapon, &attack, &usage)?;
+    }
+    Ok(())
+}
+
+fn contestant<'a>(
+    stats: &'a CreatureStats,
+    conditions: &'a [ActiveCondition],
+    skills: Option<&pb::ContestantSkills>,
+) -> Contestant<'a> {
+    let proficiency = |proficient: bool, expertise: bool| match (proficient, expertise) {
+        (_, true) => Proficiency::Expertise,
+        (true, false) => Proficiency::Proficient,
+        (false, false) => Proficiency::None,
+    };
+    let skills = skills.cloned().unwrap_or_default();
+    Contestant::new(stats, conditions)
+        .with_athletics(proficiency(
+            skills.athletics_proficient,
+            skills.athletics_expertise,
+        ))
+        .with_acrobatics(proficiency(
+            skills.acrobatics_proficient,
+            skills.acrobatics_expertise,
+        ))
+}
+
+fn convert_cover(proto_cover: i32) -> CoverType {
+    match pb::CoverType::try_from(proto_cover) {
+        Ok(pb::CoverType::Half) => CoverType::Half,
+        Ok(pb::CoverType::ThreeQuarters) => CoverType::ThreeQuarters,
+        Ok(pb::CoverType::Total) => CoverType::Total,
+        _ => CoverType::None,
+    }
+}
+
+fn convert_condition_type(proto_type: i32) -> Result<ConditionType, Status> {
+    let condition = match pb::ConditionType::try_from(proto_type) {
+        Ok(pb::ConditionType::Blinded) => ConditionType::Blinded,
+        Ok(pb::ConditionType::Charmed) => ConditionType::Charmed,
+        Ok(pb::ConditionType::Deafened) => ConditionType::Deafened,
+        Ok(pb::ConditionType::Exhaustion) => ConditionType::Exhaustion,
+        Ok(pb::ConditionType::Frightened) => ConditionType::Frightened,
+        Ok(pb::ConditionType::Grappled) => ConditionType::Grappled,
+        Ok(pb::ConditionType::Incapacitated) => ConditionType::Incapacitated,
+        Ok(pb::ConditionType::Invisible) => ConditionType::Invisible,
+        Ok(pb::ConditionType::Paralyzed) => ConditionType::Paralyzed,
+        Ok(pb::ConditionType::Petrified) => ConditionType::Petrified,
+        Ok(pb::ConditionType::Poisoned) => ConditionType::Poisoned,
+        Ok(pb::ConditionType::Prone) => ConditionType::Prone,
+        Ok(pb::ConditionType::Restrained) => ConditionType::Restrained,
+        Ok(pb::ConditionType::Stunned) => ConditionType::Stunned,
+        Ok(pb::ConditionType::Unconscious) => ConditionType::Unconscious,
+        _ => {
+            return Err(Status::invalid_argument(format!(
+                "Invalid condition type: {}",
+                proto_type
+            )))
+        }
+    };
+    Ok(condition)
+}
+
+fn condition_type_to_proto(condition: ConditionType) -> pb::ConditionType {
+    match condition {
+        ConditionType::Blinded => pb::ConditionType::Blinded,
+        ConditionType::Charmed => pb::ConditionType::Charmed,
+        ConditionType::Deafened => pb::ConditionType::Deafened,
+        ConditionType::Exhaustion => pb::ConditionType::Exhaustion,
+        ConditionType::Frightened => pb::ConditionType::Frightened,
+        ConditionType::Grappled => pb::ConditionType::Grappled,
+        ConditionType::Incapacitated => pb::ConditionType::Incapacitated,
+        ConditionType::Invisible => pb::ConditionType::Invisible,
+        ConditionType::Paralyzed => pb::ConditionType::Paralyzed,
+        ConditionType::Petrified => pb::ConditionType::Petrified,
+        ConditionType::Poisoned => pb::ConditionType::Poisoned,
+        ConditionType::Prone => pb::ConditionType::Prone,
+        ConditionType::Restrained => pb::ConditionType::Restrained,
+        ConditionType::Stunned => pb::ConditionType::Stunned,
+        ConditionType::Unconscious => pb::ConditionType::Unconscious,
+    }
+}
+
+fn convert_condition_instance(condition: &ActiveCondition) -> pb::ConditionInstance {
+    let effects = condition.effects();
+    let duration_type = if condition.remaining_rounds().is_some() {
+        pb::DurationType::Rounds
+    } else if condition.save.is_some() {
+        pb::DurationType::SaveEnds
+    } else {
+        pb::DurationType::UntilDispelled
+    };
+
+    pb::ConditionInstance {
+        instance_id: condition.id.clone(),
+        condition_type: condition_type_to_proto(condition.condition_type) as i32,
+        source_id: condition.source_id.clone().unwrap_or_default(),
+        duration_type: duration_type as i32,
+        remaining_duration: condition.remaining_rounds().unwrap_or(0),
+        save_ability: condition
+            .save
+            .map(|s| ability_to_proto(s.ability) as i32)
+            .unwrap_or_default(),
+        save_dc: condition.save.map_or(0, |s| s.dc),
+        exhaustion_level: condition.exhaustion_level.unwrap_or(0),
+        effects: Some(pb::ConditionEffects {
+            attacks_have_disadvantage: effects.attacks_have_disadvantage,
+            attacks_have_advantage: effects.attacks_have_advantage,
+            attacks_against_have_advantage: effects.attacks_against_have_advantage,
+            attacks_against_have_disadvantage: effects.attacks_against_have_disadvantage,
+            melee_attacks_against_auto_crit: effects.melee_attacks_against_auto_crit,
+            str_saves_auto_fail: effects.str_saves_auto_fail,
+            dex_saves_auto_fail: effects.dex_saves_auto_fail,
+            ability_checks_have_disadvantage: effects.ability_checks_have_disadvantage,
+            speed_is_zero: effects.speed_is_zero,
+            speed_reduction: 0,
+            cant_move_closer_to_source: effects.cant_move_closer_to_source,
+            cant_take_actions: effects.cant_take_actions,
+            cant_take_reactions: effects.cant_take_reactions,
+            cant_take_bonus_actions: effects.cant_take_bonus_actions,
+            cant_be_targeted: false,
+            resistances: vec![],
+            immunities: vec![],
+            drops_held_items: effects.drops_held_items,
+            falls_prone: effects.falls_prone,
+            auto_fail_sight_checks: effects.auto_fail_sight_checks,
+            auto_fail_hearing_checks: effects.auto_fail_hearing_checks,
+        }),
+    }
+}
+
+fn convert_concentration_ended(ended: &ConcentrationEnded) -> pb::ConcentrationEnded {
+    let mut proto = pb::ConcentrationEnded {
+        caster_id: ended.caster_id.clone(),
+        spell_id: ended.spell_id.clone(),
+        reason: ended.reason.name().to_string(),
+        ..Default::default()
+    };
+    for effect in &ended.ended {
+        match effect {
+            DependentEffect::Condition {
+                target_id,
+                condition_id,
+            } => proto.ended_conditions.push(pb::EndedCondition {
+                target_id: target_id.clone(),
+                condition_instance_id: condition_id.clone(),
+            }),
+            DependentEffect::Summon { entity_id } => proto.summon_ids.push(entity_id.clone()),
+            DependentEffect::Zone { zone_id } => proto.zone_ids.push(zone_id.clone()),
+        }
+    }
+    proto
+}
+
+fn convert_die_type(proto_die: i32) -> Result<DieType, Status> {
+    DieType::from_size(proto_die)
+        .ok_or_else(|| Status::invalid_argument(format!("Invalid die type: {}", proto_die)))
+}
+
+fn convert_rest_kind(proto_type: i32) -> Option<RestKind> {
+    match pb::RestType::try_from(proto_type).ok()? {
+        pb::RestType::Short => Some(RestKind::Short),
+        pb::RestType::Long => Some(RestKind::Long),
+        pb::RestType::Unspecified => None,
+    }
+}
+
+fn rest_kind_to_proto(kind: RestKind) -> pb::RestType {
+    match kind {
+        RestKind::Short => pb::RestType::Short,
+        RestKind::Long => pb::RestType::Long,
+    }
+}
+
+fn convert_slot_state(state: &pb::SpellSlotState) -> (Option<SpellSlots>, Option<SorceryPoints>) {
+    let level_counts = |counts: &[i32]| {
+        let mut slots = [0u8; MAX_SPELL_LEVEL];
+        for (slot, count) in slots.iter_mut().zip(counts) {
+            *slot = (*count).clamp(0, u8::MAX as i32) as u8;
+        }
+        slots
+    };
+    let clamp = |value: i32| value.clamp(0, u8::MAX as i32) as u8;
+    let slots = SpellSlots {
+        max: level_counts(&state.max),
+        current: level_counts(&state.current),
+        pact: (state.pact_max > 0).then(|| PactSlots {
+            slot_level: clamp(state.pact_slot_level),
+            max: clamp(state.pact_max),
+            current: clamp(state.pact_current),
+        }),
+        arcane_recovery_used: false,
+    };
+    let sorcery = (state.sorcery_points_max > 0).then(|| SorceryPoints {
+        current: clamp(state.sorcery_points_current),
+        max: clamp(state.sorcery_points_max),
+    });
+    (Some(slots), sorcery)
+}
+
+fn slot_state_to_proto(slots: &SpellSlots, sorcery: Option<&SorceryPoints>) -> pb::SpellSlotState {
+    pb::SpellSlotState {
+        max: slots.max.iter().map(|n| i32::from(*n)).collect(),
+        current: slots.current.iter().map(|n| i32::from(*n)).collect(),
+        pact_slot_level: slots.pact.map_or(0, |p| i32::from(p.slot_level)),
+        pact_max: slots.pact.map_or(0, |p| i32::from(p.max)),
+        pact_current: slots.pact.map_or(0, |p| i32::from(p.current)),
+        sorcery_points_max: sorcery.map_or(0, |p| i32::from(p.max)),
+        sorcery_points_current: sorcery.map_or(0, |p| i32::from(p.current)),
+    }
+}
+
+/// Resources arrive with each request, so the tracker only lives for the
+/// call. Creature ids that aren't UUIDs get a throwaway owner.
+fn load_resources(
+    creature_id: &str,
+    resources: &[pb::Resource],
+) -> Result<(ResourceTracker, EntityId), Status> {
+    let owner = creature_id.parse().unwrap_or_default();
+    let mut tracker = ResourceTracker::new();
+    for resource in resources {
+        tracker.add(owner, convert_resource(resource)?);
+    }
+    Ok((tracker, owner))
+}
+
+fn convert_resource(resource: &pb::Resource) -> Result<Resource, Status> {
+    let recharge = match pb::RechargeKind::try_from(resource.recharge) {
+        Ok(pb::RechargeKind::ShortRest) => Recharge::ShortRest,
+        Ok(pb::RechargeKind::Dawn) => Recharge::Dawn,
+        Ok(pb::RechargeKind::Roll) if (1..=6).contains(&resource.recharge_min) => Recharge::Roll {
+            min: resource.recharge_min,
+        },
+        Ok(pb::RechargeKind::Roll) => {
+            return Err(Status::invalid_argument(format!(
+                "Recharge roll for {} needs a minimum of 1-6, got {}",
+                resource.id, resource.recharge_min
+            )))
+        }
+        Ok(pb::RechargeKind::TurnStart) => Recharge::TurnStart,
+        Ok(pb::RechargeKind::Never) => Recharge::Never,
+        _ => Recharge::LongRest,
+    };
+    let mut converted = Resource::new(&resource.id, &resource.name, resource.max, recharge);
+    converted.current = resource.current.clamp(0, converted.max);
+    converted.regain = (!resource.regain.is_empty()).then(|| resource.regain.clone());
+    Ok(converted)
+}
+
+fn resource_to_proto(resource: &Resource) -> pb::Resource {
+    let (recharge, recharge_min) = match resource.recharge {
+        Recharge::ShortRest => (pb::RechargeKind::ShortRest, 0),
+        Recharge::LongRest => (pb::RechargeKind::LongRest, 0),
+        Recharge::Dawn => (pb::RechargeKind::Dawn, 0),
+        Recharge::Roll { min } => (pb::RechargeKind::Roll, min),
+        Recharge::TurnStart => (pb::RechargeKind::TurnStart, 0),
+        Recharge::Never => (pb::RechargeKind::Never, 0),
+    };
+    pb::Resource {
+        id: resource.id.clone(),
+        name: resource.name.clone(),
+        max: resource.max,
+        current: resource.current,
+        recharge: recharge as i32,
+        recharge_min,
+        regain: resource.regain.clone().unwrap_or_default(),
+    }
+}
+
+fn convert_recharged(recharged: &Recharged) -> pb::ResourceRecharge {
+    pb::ResourceRecharge {
+        resource_id: recharged.resource_id.clone(),
+        name: recharged.name.clone(),
+        regained: recharged.regained,
+        roll: recharged.roll,
+    }
+}
+
+fn convert_trigger(
+    trigger: Option<&pb::ReactionTrigger>,
+) -> Result<reactions::TriggerKind, Status> {
+    let trigger = trigger.ok_or_else(|| Status::invalid_argument("trigger is required"))?;
+    let actor = trigger.actor_id.clone();
+    let target = trigger.target_id.clone();
+    Ok(match pb::TriggerKind::try_from(trigger.kind) {
+        Ok(pb::TriggerKind::LeavesReach) => reactions::TriggerKind::LeavesReach {
+            mover_id: actor,
+            disengaged: trigger.disengaged,
+        },
+        Ok(pb::TriggerKind::AttackHits) => reactions::TriggerKind::AttackHits {
+            attacker_id: actor,
+            target_id: target,
+        },
+        Ok(pb::TriggerKind::AttackMade) => reactions::TriggerKind::AttackMade {
+            attacker_id: actor,
+            target_id: target,
+        },
+        Ok(pb::TriggerKind::SpellCast) => reactions::TriggerKind::SpellCast {
+            caster_id: actor,
+            spell_level: trigger.spell_level,
+        },
+        Ok(pb::TriggerKind::DamageTaken) => reactions::TriggerKind::DamageTaken {
+            attacker_id: actor,
+            target_id: target,
+        },
+        _ => return Err(Status::invalid_argument("trigger kind is required")),
+    })
+}
+
+fn convert_reaction_kind(kind: i32) -> Result<reactions::ReactionKind, Status> {
+    match pb::ReactionKind::try_from(kind) {
+        Ok(pb::ReactionKind::OpportunityAttack) => Ok(reactions::ReactionKind::OpportunityAttack),
+        Ok(pb::ReactionKind::Shield) => Ok(reactions::ReactionKind::Shield),
+        Ok(pb::ReactionKind::Counterspell) => Ok(reactions::ReactionKind::Counterspell),
+        Ok(pb::ReactionKind::HellishRebuke) => Ok(reactions::ReactionKind::HellishRebuke),
+        Ok(pb::ReactionKind::Sentinel) => Ok(reactions::ReactionKind::Sentinel),
+        _ => Err(Status::invalid_argument(format!(
+            "unknown reaction kind {}",
+            kind
+        ))),
+    }
+}
+
+fn reaction_kind_to_proto(kind: reactions::ReactionKind) -> i32 {
+    let kind = match kind {
+        reactions::ReactionKind::OpportunityAttack => pb::ReactionKind::OpportunityAttack,
+        reactions::ReactionKind::Shield => pb::ReactionKind::Shield,
+        reactions::ReactionKind::Counterspell => pb::ReactionKind::Counterspell,
+        reactions::ReactionKind::HellishRebuke => pb::ReactionKind::HellishRebuke,
+        reactions::ReactionKind::Sentinel => pb::ReactionKind::Sentinel,
+    };
+    kind as i32
+}
+
+fn convert_reactor(candidate: &pb::ReactionCandidate) -> Result<Reactor, Status> {
+    Ok(Reactor {
+        creature_id: candidate.creature_id.clone(),
+        has_reaction: !candidate.reaction_used,
+        options: candidate
+            .options
+            .iter()
+            .map(|&kind| convert_reaction_kind(kind))
+            .collect::<Result<_, _>>()?,
+        distance_ft: candidate.distance_ft,
+        reach_ft: if candidate.reach_ft == 0 {
+            5
+        } else {
+            candidate.reach_ft
+        },
+        can_see_actor: !candidate.cannot_see_actor,
+    })
+}
+
+fn resolved_window_to_proto(window: ResolvedWindow) -> pb::ReactionWindow {
+    pb::ReactionWindow {
+        window_id: window.window_id,
+        resolved: true,
+        awaiting: Vec::new(),
+        reactions: window
+            .reactions
+            .into_iter()
+            .map(|r| pb::DeclaredReaction {
+                creature_id: r.creature_id,
+                reaction: reaction_kind_to_proto(r.reaction),
+            })
+            .collect(),
+        timed_out: window.timed_out,
+    }
+}
+
+fn reaction_window_response(
+    status: WindowStatus,
+    expired: Vec<ResolvedWindow>,
+) -> pb::ReactionWindowResponse {
+    let window = match status {
+        WindowStatus::Waiting {
+            window_id,
+            awaiting,
+        } => pb::ReactionWindow {
+            window_id,
+            resolved: false,
+            awaiting: awaiting
+                .into_iter()
+                .map(|(creature_id, options)| pb::ReactionOptions {
+                    creature_id,
+                    options: options.into_iter().map(reaction_kind_to_proto).collect(),
+                })
+                .collect(),
+            ..Default::default()
+        },
+        WindowStatus::Resolved(window) => resolved_window_to_proto(window),
+    };
+    pb::ReactionWindowResponse {
+        window: Some(window),
+        expired: expired.into_iter().map(resolved_window_to_proto).collect(),
+    }
+}
+
+fn convert_check_features(features: Option<&pb::CheckFeatures>) -> CheckFeatures {
+    features.map_or_else(CheckFeatures::default, |f| CheckFeatures {
+        jack_of_all_trades: f.jack_of_all_trades,
+        remarkable_athlete: f.remarkable_athlete,
+        reliable_talent: f.reliable_talent,
+    })
+}
+
+fn convert_modifiers(modifiers: &[Modifier]) -> Vec<pb::RollModifier> {
+    modifiers
+        .iter()
+        .map(|m| pb::RollModifier {
+            source: m.source.clone(),
+            value: m.value,
+        })
+        .collect()
+}
+
+/// Free-form rolls are logged under their context, e.g. "stealth".
+fn roll_purpose(context: &str) -> RollPurpose {
+    if context.is_empty() {
+        RollPurpose::Other("roll".to_string())
+    } else {
+        RollPurpose::Other(context.to_string())
+    }
+}
+
+fn convert_roll_record(record: &RollRecord) -> pb::RollRecord {
+    pb::RollRecord {
+        sequence: record.sequence,
+        seed_position: record.seed_position as u64,
+        purpose: record.purpose.to_string(),
+        requester_id: record.requester.clone(),
+        description: record.description.clone(),
+        dice: record.dice.iter().map(convert_die_roll).collect(),
+        total: record.total,
+    }
+}
+
+fn convert_die_roll(roll: &DieRoll) -> pb::DieRoll {
+    pb::DieRoll {
+        die_type: roll.die_type as i32,
+        result: roll.result,
+    }
+}
+
+fn convert_damage_result(damage: &DamageResult) -> pb::DamageResult {
+    pb::DamageResult {
+        rolls: damage.rolls.iter().map(convert_die_roll).collect(),
+        base_damage: damage.base_damage,
+        modifier: damage.modifier,
+        damage_type: damage_type_to_proto(damage.damage_type) as i32,
+        is_resistant: damage.is_resistant,
+        is_vulnerable: damage.is_vulnerable,
+        is_immune: damage.is_immune,
+        final_damage: damage.final_damage,
+        ..Default::default()
+    }
+}
+
+/// One typed part of an attack's or effect's damage, before it's rolled.
+struct DamagePart {
+    dice: String,
+    modifier: i32,
+    damage_type: DamageType,
+    magical: bool,
+}
+
+fn convert_damage_component(component: &pb::DamageComponent) -> Result<DamagePart, Status> {
+    Ok(DamagePart {
+        dice: component.dice_expression.clone(),
+        modifier: component.modifier,
+        damage_type: convert_damage_type(component.damage_type)?,
+        magical: component.magical,
+    })
+}
+
+/// Roll each part and run them all through `target`'s defenses as one
+/// attack or effect.
+fn roll_damage_parts(
+    roller: &mut DiceRoller,
+    parts: &[DamagePart],
+    critical: bool,
+    target: &CreatureStats,
+) -> Result<(Vec<pb::DamageResult>, DamageReport), Status> {
+    let rolled = parts
+        .iter()
+        .map(|part| {
+            DiceExpression::parse(&part.dice)
+                .map(|dice| damage::roll_damage(roller, &dice, part.modifier, critical))
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| Status::invalid_argument(e.to_string()))?;
+    resolve_rolled_damage(parts, rolled, target)
+}
+
+/// Run parts already rolled, as (dice, amount), through `target`'s defenses.
+fn resolve_rolled_damage(
+    parts: &[DamagePart],
+    rolled: Vec<(Vec<DieRoll>, i32)>,
+    target: &CreatureStats,
+) -> Result<(Vec<pb::DamageResult>, DamageReport), Status> {
+    let instances: Vec<DamageInstance> = parts
+        .iter()
+        .zip(&rolled)
+        .map(|(part, (_, amount))| DamageInstance {
+            damage_type: part.damage_type,
+            amount: *amount,
+            magical: part.magical,
+        })
+        .collect();
+    let report = damage::resolve_damage(&instances, &DamageDefenses::from_stats(target), 0)
+        .map_err(rules_error)?;
+    let results = parts
+        .iter()
+        .zip(rolled)
+        .zip(&report.instances)
+        .map(|((part, (rolls, _)), applied)| pb::DamageResult {
+            rolls: rolls.iter().map(convert_die_roll).collect(),
+            base_damage: applied.instance.amount,
+            modifier: part.modifier,
+            damage_type: damage_type_to_proto(part.damage_type) as i32,
+            is_resistant: applied.resisted,
+            is_vulnerable: applied.vulnerable,
+            is_immune: applied.immune,
+            final_damage: applied.final_amount,
+            magical: part.magical,
+            reduced_by: applied.reduced_by,
+            steps: applied.steps.clone(),
+        })
+        .collect();
+    Ok((results, report))
+}
+
+fn convert_damage_totals(report: &DamageReport) -> Vec<pb::DamageTypeTotal> {
+    report
+        .by_type
+        .iter()
+        .map(|(damage_type, amount)| pb::DamageTypeTotal {
+            damage_type: damage_type_to_proto(*damage_type) as i32,
+            amount: *amount,
+        })
+        .collect()
+}
+
+fn convert_proto_stats(proto: &pb::CreatureStats) -> CreatureStats {
+    let ability_scores: HashMap<Ability, i32> = proto
+        .ability_scores
+        .iter()
+        .filter_map(|(key, value)| Ability::from_abbrev(key).map(|a| (a, *value)))
+        .collect();
+
+    let damage_types = |types: &[i32]| -> HashSet<DamageType> {
+        types
+            .iter()
+            .filter_map(|&t| convert_optional_damage_type(t))
+            .collect()
+    };
+
+    CreatureStats {
+        creature_id: proto.creature_id.clone(),
+        ability_scores,
+        armor_class: proto.armor_class,
+        proficiency_bonus: proto.proficiency_bonus,
+        proficient_saves: proto
+            .proficient_saves
+            .iter()
+            .filter_map(|s| Ability::from_abbrev(s))
+            .collect(),
+        resistances: damage_types(&proto.resistances),
+        vulnerabilities: damage_types(&proto.vulnerabilities),
+        immunities: damage_types(&proto.immunities),
+        active_conditions: proto.active_conditions.clone(),
+        current_hp: proto.current_hp,
+        max_hp: proto.max_hp,
+        temp_hp: proto.temp_hp,
+        size: convert_size(proto.size),
+        nonmagical_physical_resistance: proto.nonmagical_physical_resistance,
+        nonmagical_physical_immunity: proto.nonmagical_physical_immunity,
+        damage_reductions: proto
+            .damage_reductions
+            .iter()
+            .map(convert_damage_reduction)
+            .collect(),
+    }
+}
+
+fn convert_damage_reduction(proto: &pb::DamageReduction) -> FlatReduction {
+    let scope = if proto.nonmagical_physical_only {
+        ReductionScope::NonmagicalPhysical
+    } else if proto.damage_types.is_empty() {
+        ReductionScope::All
+    } else {
+        ReductionScope::Types(
+            proto
+                .damage_types
+                .iter()
+                .filter_map(|&t| convert_optional_damage_type(t))
+                .collect(),
+        )
+    };
+    FlatReduction {
+        source: proto.source.clone(),
+        amount: proto.amount.max(0),
+        scope,
+    }
+}
+
+fn convert_size(proto_size: i32) -> Size {
+    match pb::CreatureSize::try_from(proto_size) {
+        Ok(pb::CreatureSize::Tiny) => Size::Tiny,
+        Ok(pb::CreatureSize::Small) => Size::Small,
+        Ok(pb::CreatureSize::Large) => Size::Large,
+        Ok(pb::CreatureSize::Huge) => Size::Huge,
+        Ok(pb::CreatureSize::Gargantuan) => Size::Gargantuan,
+        _ => Size::Medium,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn creature(id: &str) -> pb::CreatureStats {
+        pb::CreatureStats {
+            creature_id: id.to_string(),
+            ability_scores: ["STR", "DEX", "CON", "INT", "WIS", "CHA"]
+                .iter()
+                .map(|a| (a.to_string(), 10))
+                .collect(),
+            armor_class: 12,
+            proficiency_bonus: 2,
+            current_hp: 20,
+            max_hp: 20,
+            ..Default::default()
+        }
+    }
+
+    fn session(id: &str) -> Option<pb::RollContext> {
+        Some(pb::RollContext {
+            session_id: id.to_string(),
+            requester_id: String::new(),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_tracked_conditions_reach_saves() {
+        let service = RulesServiceImpl::new();
+        service
+            .apply_condition(Request::new(pb::ApplyConditionRequest {
+                target_id: "goblin".to_string(),
+                condition_type: pb::ConditionType::Paralyzed as i32,
+                source_id: "wizard".to_string(),
+                duration_type: pb::DurationType::Rounds as i32,
+                duration_value: 10,
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        let save = |creature_id: &str| pb::SavingThrowRequest {
+            creature_id: creature_id.to_string(),
+            ability: pb::Ability::Dex as i32,
+            dc: 1,
+            stats: Some(creature(creature_id)),
+            seed: Some(3),
+            ..Default::default()
+        };
+
+        let paralyzed = service
+            .resolve_saving_throw(Request::new(save("goblin")))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(paralyzed.auto_fail && !paralyzed.success);
+        let free = service
+            .resolve_saving_throw(Request::new(save("orc")))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!free.auto_fail);
+    }
+
+    #[tokio::test]
+    async fn test_successful_save_ends_the_condition() {
+        let service = RulesServiceImpl::new();
+        let webbed = service
+            .apply_condition(Request::new(pb::ApplyConditionRequest {
+                target_id: "ranger".to_string(),
+                condition_type: pb::ConditionType::Restrained as i32,
+                save_ability: pb::Ability::Str as i32,
+                save_dc: 12,
+                duration_type: pb::DurationType::Rounds as i32,
+                duration_value: 10,
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let save = |condition_id: &str, dc: i32| pb::SavingThrowRequest {
+            creature_id: "ranger".to_string(),
+            ability: pb::Ability::Str as i32,
+            dc,
+            stats: Some(creature("ranger")),
+            seed: Some(1),
+            condition_id: condition_id.to_string(),
+            ..Default::default()
+        };
+
+        let failed = service
+            .resolve_saving_throw(Request::new(save(&webbed.condition_instance_id, 40)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!failed.success && !failed.condition_ended);
+        let freed = service
+            .resolve_saving_throw(Request::new(save(&webbed.condition_instance_id, 1)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(freed.success && freed.condition_ended);
+        let remaining = service
+            .get_active_conditions(Request::new(pb::GetConditionsRequest {
+                creature_id: "ranger".to_string(),
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(remaining.conditions.is_empty());
+        let unknown = service
+            .resolve_saving_throw(Request::new(save("nope", 1)))
+            .await
+            .unwrap_err();
+        assert_eq!(unknown.code(), tonic::Code::NotFound);
+    }
+
+    #[tokio::test]
+    async fn test_sixth_exhaustion_level_kills() {
+        let service = RulesServiceImpl::new();
+        let exhaust = |levels: i32| pb::ApplyConditionRequest {
+            target_id: "explorer".to_string(),
+            condition_type: pb::ConditionType::Exhaustion as i32,
+            exhaustion_level: levels,
+            ..Default::default()
+        };
+        let tired = service
+            .apply_condition(Request::new(exhaust(3)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!tired.target_died);
+        assert!(tired.effects_applied.len() > 1);
+        let dead = service
+            .apply_condition(Request::new(exhaust(3)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(dead.target_died);
+        assert!(dead.effects_applied.iter().any(|e| e == "dies"));
+    }
+
+    #[tokio::test]
+    async fn test_session_dice_are_logged_and_replay() {
+        let service = RulesServiceImpl::new();
+        let roll = |expression: &str| pb::RollDiceRequest {
+            expression: expression.to_string(),
+            seed: Some(42),
+            roll_context: session("table-1"),
+            ..Default::default()
+        };
+        let first = service
+            .roll_dice(Request::new(roll("1d20")))
+            .await
+            .unwrap()
+            .into_inner();
+        service.roll_dice(Request::new(roll("2d6"))).await.unwrap();
+        let check = service
+            .resolve_ability_check(Request::new(pb::AbilityCheckRequest {
+                creature_id: "rogue".to_string(),
+                ability: pb::Ability::Dex as i32,
+                dc: 10,
+                stats: Some(creature("rogue")),
+                roll_context: session("table-1"),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(check.natural_roll > 0);
+
+        let log = service
+            .get_roll_log(Request::new(pb::RollLogRequest {
+                session_id: "table-1".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(log.seed, 42);
+        assert!(log.verified);
+        let purposes: Vec<_> = log.records.iter().map(|r| r.purpose.as_str()).collect();
+        assert_eq!(purposes, ["roll", "roll", "check"]);
+        assert_eq!(log.records[2].requester_id, "rogue");
+        assert_eq!(log.records[0].total, Some(first.total));
+
+        // A fresh session on the same seed replays the same first roll.
+        let replay = service
+            .roll_dice(Request::new(pb::RollDiceRequest {
+                roll_context: session("table-2"),
+                ..roll("1d20")
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(replay.total, first.total);
+        let missing = service
+            .get_roll_log(Request::new(pb::RollLogRequest {
+                session_id: "nope".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(missing.code(), tonic::Code::NotFound);
+
+        // The session keeps its seed: another one is refused, none is fine.
+        let reseeded = service
+            .roll_dice(Request::new(pb::RollDiceRequest {
+                seed: Some(7),
+                ..roll("1d20")
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(reseeded.code(), tonic::Code::FailedPrecondition);
+        service
+            .roll_dice(Request::new(pb::RollDiceRequest {
+                seed: None,
+                ..roll("1d20")
+            }))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_damage_tests_concentration_from_a_cast() {
+        let service = RulesServiceImpl::new();
+        let cast = service
+            .resolve_spell(Request::new(pb::ResolveSpellRequest {
+                caster_id: "cleric".to_string(),
+                spell_id: "hold-person".to_string(),
+                cast_at_level: 2,
+                caster_stats: Some(creature("cleric")),
+                spell_save_dc: 13,
+                targets: vec![pb::SpellTarget {
+                    creature_id: "bandit".to_string(),
+                    stats: Some(creature("bandit")),
+                    distance_feet: 30,
+                    ..Default::default()
+                }],
+                seed: Some(1),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(cast.requires_concentration);
+
+        let hit = |creature_id: &str| pb::ApplyDamageRequest {
+            creature_id: creature_id.to_string(),
+            damage: 4,
+            current_stats: Some(creature(creature_id)),
+            seed: Some(1),
+            ..Default::default()
+        };
+        let damaged = service
+            .apply_damage(Request::new(hit("cleric")))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(damaged.requires_concentration_check);
+        assert_eq!(damaged.concentration_dc, 10);
+        assert!(damaged.concentration_roll > 0);
+        // Someone who isn't concentrating makes no save.
+        let bystander = service
+            .apply_damage(Request::new(hit("fighter")))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!bystander.requires_concentration_check);
+    }
+
+    #[tokio::test]
+    async fn test_dropping_to_zero_and_coming_back() {
+        let service = RulesServiceImpl::new();
+        let mut broken = creature("fighter");
+        broken.max_hp = 0;
+        let invalid = service
+            .apply_damage(Request::new(pb::ApplyDamageRequest {
+                creature_id: "fighter".to_string(),
+                damage: 5,
+                current_stats: Some(broken),
+                ..Default::default()
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(invalid.code(), tonic::Code::InvalidArgument);
+
+        let down = service
+            .apply_damage(Request::new(pb::ApplyDamageRequest {
+                creature_id: "fighter".to_string(),
+                damage: 25,
+                current_stats: Some(creature("fighter")),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(down.is_unconscious);
+        let unconscious = || async {
+            service
+                .get_active_conditions(Request::new(pb::GetConditionsRequest {
+                    creature_id: "fighter".to_string(),
+                }))
+                .await
+                .unwrap()
+                .into_inner()
+                .conditions
+                .iter()
+                .any(|c| c.condition_type == pb::ConditionType::Unconscious as i32)
+        };
+        assert!(unconscious().await);
+
+        let mut at_zero = creature("fighter");
+        at_zero.current_hp = 0;
+        let dying = |action: pb::DyingAction, seed: i64| pb::DyingRequest {
+            creature_id: "fighter".to_string(),
+            current_stats: Some(at_zero.clone()),
+            action: action as i32,
+            healing: 5,
+            death_save_successes: 2,
+            seed: Some(seed),
+            ..Default::default()
+        };
+        for seed in 0..20 {
+            let save = service
+                .resolve_dying(Request::new(dying(pb::DyingAction::DeathSave, seed)))
+                .await
+                .unwrap()
+                .into_inner();
+            match save.death_save_roll {
+                20 => assert!(save.regained_consciousness && save.current_hp == 1),
+                10..=19 => assert!(save.is_stable),
+                2..=9 => assert_eq!(save.death_save_failures, 1),
+                _ => assert_eq!(save.death_save_failures, 2),
+            }
+        }
+        let stable = service
+            .resolve_dying(Request::new(dying(pb::DyingAction::Stabilize, 0)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(stable.is_stable && stable.is_unconscious);
+
+        let healed = service
+            .resolve_dying(Request::new(dying(pb::DyingAction::Heal, 0)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(healed.regained_consciousness);
+        assert_eq!(healed.current_hp, 5);
+        assert!(!unconscious().await);
+        let conscious = service
+            .resolve_dying(Request::new(pb::DyingRequest {
+                current_stats: Some(creature("fighter")),
+                ..dying(pb::DyingAction::Stabilize, 0)
+            }))
+            .await
+            .unwrap_err();
+        assert_eq!(conscious.code(), tonic::Code::InvalidArgument);
+    }
+
+    #[tokio::test]
+    async fn test_encounter_turns_follow_initiative() {
+        let service = RulesServiceImpl::new();
+        for (id, bonus) in [("fast", 30), ("slow", 0)] {
+            service
+                .roll_initiative(Request::new(pb::InitiativeRequest {
+                    creature_id: id.to_string(),
+                    initiative_bonus: bonus,
+                    encounter_id: "e1".to_string(),
+                    seed: Some(9),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap();
+        }
+        let start = |id: &str| pb::TurnStartRequest {
+            creature_id: id.to_string(),
+            encounter_id: "e1".to_string(),
+            ..Default::default()
+        };
+        let out_of_turn = service
+            .process_turn_start(Request::new(start("slow")))
+            .await
+            .unwrap_err();
+        assert_eq!(out_of_turn.code(), tonic::Code::FailedPrecondition);
+        let started = service
+            .process_turn_start(Request::new(start("fast")))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(started.round, 1);
+
+        let ended = service
+            .process_turn_end(Request::new(pb::TurnEndRequest {
+                creature_id: "fast".to_string(),
+                encounter_id: "e1".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(ended.next_creature_id, "slow");
+        assert!(!ended.new_round);
+    }
+
+    #[tokio::test]
+    async fn test_turn_budget_is_kept_for_the_encounter() {
+        let service = RulesServiceImpl::new();
+        service
+            .roll_initiative(Request::new(pb::InitiativeRequest {
+                creature_id: "fighter".to_string(),
+                encounter_id: "e2".to_string(),
+                seed: Some(1),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        let mut stats = creature("fighter");
+        stats.ability_scores.insert("STR".to_string(), 16);
+        let plan = || pb::AttackPlanRequest {
+            creature_id: "fighter".to_string(),
+            stats: Some(stats.clone()),
+            attacks: vec![
+                pb::PlannedAttack {
+                    weapon_id: "longsword".to_string(),
+                    ..Default::default()
+                };
+                2
+            ],
+            encounter_id: "e2".to_string(),
+            ..Default::default()
+        };
+        let shove = pb::SpecialAttackRequest {
+            kind: pb::SpecialAttackKind::ShoveProne as i32,
+            attacker_id: "fighter".to_string(),
+            target_id: "orc".to_string(),
+            attacker_stats: Some(stats.clone()),
+            target_stats: Some(creature("orc")),
+            encounter_id: "e2".to_string(),
+            ..Default::default()
+        };
+
+        let early = service
+            .plan_attack_action(Request::new(plan()))
+            .await
+            .unwrap_err();
+        assert_eq!(early.code(), tonic::Code::FailedPrecondition);
+
+        let started = service
+            .process_turn_start(Request::new(pb::TurnStartRequest {
+                creature_id: "fighter".to_string(),
+                encounter_id: "e2".to_string(),
+                speed: 30,
+                attacks_per_action: 2,
+                hasted: true,
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(started.speed, 60);
+        let planned = service
+            .plan_attack_action(Request::new(plan()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(planned.valid);
+        assert_eq!(planned.attacks_remaining, 0);
+
+        // Haste's action can still attack once more, but not twice.
+        let again = service
+            .plan_attack_action(Request::new(plan()))
+            .await
+            .unwrap()
+            .into_inner();
+        assert!(!again.valid);
+        service
+            .resolve_special_attack(Request::new(shove.clone()))
+            .await
+            .unwrap();
+        let spent = service
+            .resolve_special_attack(Request::new(shove))
+            .await
+            .unwrap_err();
+        assert_eq!(spent.code(), tonic::Code::FailedPrecondition);
+    }
+
+    #[tokio::test]
+    async fn test_sneak_attack_once_per_encounter_turn() {
+        let service = RulesServiceImpl::new();
+        service
+            .roll_initiative(Request::new(pb::InitiativeRequest {
+                creature_id: "rogue".to_string(),
+                encounter_id: "e3".to_string(),
+                seed: Some(1),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        let turn_start = || pb::TurnStartRequest {
+            creature_id: "rogue".to_string(),
+            encounter_id: "e3".to_string(),
+            speed: 30,
+            ..Default::default()
+        };
+        let stab = |seed| pb::AttackRequest {
+            attacker_id: "rogue".to_string(),
+            target_id: "orc".to_string(),
+            attacker_stats: Some(creature("rogue")),
+            target_stats: Some(creature("orc")),
+            weapon_id: "rapier".to_string(),
+            attack_bonus: 30,
+            advantage: true,
+            attacker_features: vec![pb::ClassFeatureUse {
+                feature_id: "sneak-attack".to_string(),
+                level: 5,
+                ..Default::default()
+            }],
+            encounter_id: "e3".to_string(),
+            seed: Some(seed),
+            ..Default::default()
+        };
+        let sneak_attacks = |response: pb::AttackResponse| {
+            assert!(response.hits);
+            response
+                .feature_damage
+                .iter()
+                .filter(|f| f.feature_id == "sneak-attack")
+                .count()
+        };
+
+        service
+            .process_turn_start(Request::new(turn_start()))
+            .await
+            .unwrap();
+        let first = service.resolve_attack(Request::new(stab(2))).await.unwrap();
+        assert_eq!(sneak_attacks(first.into_inner()), 1);
+        let second = service.resolve_attack(Request::new(stab(3))).await.unwrap();
+        assert_eq!(sneak_attacks(second.into_inner()), 0);
+
+        service
+            .process_turn_end(Request::new(pb::TurnEndRequest {
+                creature_id: "rogue".to_string(),
+                encounter_id: "e3".to_string(),
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        service
+            .process_turn_start(Request::new(turn_start()))
+            .await
+            .unwrap();
+        let next_turn = service.resolve_attack(Request::new(stab(4))).await.unwrap();
+        assert_eq!(sneak_attacks(next_turn.into_inner()), 1);
+    }
+
+    #[tokio::test]
+    async fn test_turn_hooks_fire_individually() {
+        let service = RulesServiceImpl::new();
+        let mut troll = creature("troll");
+        troll.resistances.push(pb::DamageType::Fire as i32);
+        let started = service
+            .process_turn_start(Request::new(pb::TurnStartRequest {
+                creature_id: "troll".to_string(),
+                stats: Some(troll),
+                hooks: vec![
+                    pb::TurnHook {
+                        id: "regeneration".to_string(),
+                        kind: pb::TurnHookKind::Regeneration as i32,
+                        amount: 10,
+                        suppressed: true,
+                        ..Default::default()
+                    },
+                    pb::TurnHook {
+                        id: "vampiric".to_string(),
+                        kind: pb::TurnHookKind::Regeneration as i32,
+                        amount: 3,
+                        ..Default::default()
+                    },
+                    pb::TurnHook {
+                        id: "burning".to_string(),
+                        kind: pb::TurnHookKind::OngoingDamage as i32,
+                        damage_dice: "4".to_string(),
+                        damage_type: pb::DamageType::Fire as i32,
+                        ..Default::default()
+                    },
+                    pb::TurnHook {
+                        id: "web".to_string(),
+                        kind: pb::TurnHookKind::RepeatSave as i32,
+                        condition_id: "restrained".to_string(),
+                        save_ability: pb::Ability::Str as i32,
+                        save_dc: 12,
+                        timing: pb::TurnHookTiming::EndOfTurn as i32,
+                        ..Default::default()
+                    },
+                ],
+                seed: Some(5),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        // Only the suppressed regeneration is switched off, fire damage
+        // goes through resistance and the end-of-turn save waits.
+        assert_eq!(started.healing_received, 3);
+        assert_eq!(started.damage_taken, 2);
+        assert!(started.save_prompts.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_custom_spell_saves_see_conditions_and_auras() {
+        let service = RulesServiceImpl::new();
+        service
+            .apply_condition(Request::new(pb::ApplyConditionRequest {
+                target_id: "held".to_string(),
+                condition_type: pb::ConditionType::Paralyzed as i32,
+                duration_type: pb::DurationType::Rounds as i32,
+                duration_value: 10,
+                ..Default::default()
+            }))
+            .await
+            .unwrap();
+        let mut paladin = creature("paladin");
+        paladin.ability_scores.insert("CHA".to_string(), 16);
+        let target = |id: &str| pb::SpellTarget {
+            creature_id: id.to_string(),
+            stats: Some(creature(id)),
+            distance_feet: 20,
+            auras: vec![pb::SaveAura {
+                source: Some(paladin.clone()),
+                features: vec![pb::ClassFeatureUse {
+                    feature_id: "aura-of-protection".to_string(),
+                    level: 6,
+                    ..Default::default()
+                }],
+                distance_ft: 5,
+                hostile: false,
+            }],
+            ..Default::default()
+        };
+        let cast = service
+            .resolve_spell(Request::new(pb::ResolveSpellRequest {
+                caster_id: "wizard".to_string(),
+                caster_stats: Some(creature("wizard")),
+                spell_save_dc: 1,
+                targets: vec![target("squire"), target("held")],
+                spell: Some(pb::SpellDefinition {
+                    spell_id: "flame-burst".to_string(),
+                    name: "Flame Burst".to_string(),
+                    level: 1,
+                    damage_dice: "2d6".to_string(),
+                    damage_type: pb::DamageType::Fire as i32,
+                    save_ability: pb::Ability::Dex as i32,
+                    effect_on_save: "half".to_string(),
+                    ..Default::default()
+                }),
+                seed: Some(4),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let squire = &cast.save_results[0];
+        assert_eq!(squire.save_modifier, 3);
+        assert!(squire.saved);
+        // Paralysis fails the save outright, aura or not.
+        assert!(!cast.save_results[1].saved);
+    }
+
+    #[tokio::test]
+    async fn test_custom_healing_upcasts_and_adds_modifier_only_when_defined() {
+        let service = RulesServiceImpl::new();
+        let mut cleric = creature("cleric");
+        cleric.ability_scores.insert("WIS".to_string(), 16);
+        let heal = |adds_modifier: bool| pb::ResolveSpellRequest {
+            caster_id: "cleric".to_string(),
+            cast_at_level: 3,
+            caster_stats: Some(cleric.clone()),
+            targets: vec![pb::SpellTarget {
+                creature_id: "fighter".to_string(),
+                stats: Some(creature("fighter")),
+                ..Default::default()
+            }],
+            spell: Some(pb::SpellDefinition {
+                name: "Mending Light".to_string(),
+                level: 1,
+                healing_dice: "1d8".to_string(),
+                upcast_healing_per_level: "1d8".to_string(),
+                healing_adds_modifier: adds_modifier,
+                ..Default::default()
+            }),
+            spellcasting_ability: pb::Ability::Wis as i32,
+            seed: Some(8),
+            ..Default::default()
+        };
+
+        let plain = service
+            .resolve_spell(Request::new(heal(false)))
+            .await
+            .unwrap()
+            .into_inner();
+        let healing = &plain.healing_results[0];
+        assert_eq!(healing.rolls.len(), 3);
+        assert_eq!(healing.modifier, 0);
+        let with_modifier = service
+            .resolve_spell(Request::new(heal(true)))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(with_modifier.healing_results[0].modifier, 3);
+    }
+
+    #[tokio::test]
+    async fn test_builtin_spells_only_reach_targets_in_range() {
+        use dnd_proto::grid::v1 as grid_pb;
+
+        let service = RulesServiceImpl::new();
+        let target = |id: &str, distance_feet: i32| pb::SpellTarget {
+            creature_id: id.to_string(),
+            stats: Some(creature(id)),
+            distance_feet,
+            ..Default::default()
+        };
+        let cast = |spell_id: &str, targets: Vec<pb::SpellTarget>| pb::ResolveSpellRequest {
+            caster_id: "wizard".to_string(),
+            spell_id: spell_id.to_string(),
+            caster_stats: Some(creature("wizard")),
+            spell_save_dc: 13,
+            targets,
+            seed: Some(2),
+            ..Default::default()
+        };
+
+        let too_far = service
+            .resolve_spell(Request::new(cast("hold-person", vec![target("orc", 90)])))
+            .await
+            .unwrap_err();
+        assert!(too_far.message().contains("out of range"));
+        let unplaced = service
+            .resolve_spell(Request::new(cast("fireball", vec![target("orc", 20)])))
+            .await
+            .unwrap_err();
+        assert_eq!(unplaced.code(), tonic::Code::InvalidArgument);
+
+        let on_grid = |id: &str, x: i32| grid_pb::GridCreature {
+            creature_id: id.to_string(),
+            position: Some(grid_pb::GridPosition { x, y: 10 }),
+            size: 1,
+            ..Default::default()
+        };
+        let fireball = service
+            .resolve_spell(Request::new(pb::ResolveSpellRequest {
+                origin_point: Some(pb::Position { x: 20, y: 10 }),
+                map: Some(grid_pb::GridMap {
+                    width: 40,
+                    height: 40,
+                    creatures: vec![on_grid("wizard", 0), on_grid("orc", 20), on_grid("far", 30)],
+                    ..Default::default()
+                }),
+                ..cast("fireball", vec![target("orc", 0), target("far", 0)])
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        let hit: Vec<_> = fireball
+            .save_results
+            .iter()
+            .map(|r| r.target_id.as_str())
+            .collect();
+        assert_eq!(hit, ["orc"]);
+    }
+
+    #[tokio::test]
+    async fn test_check_features_and_breakdown() {
+        let service = RulesServiceImpl::new();
+        let mut rogue = creature("rogue");
+        rogue.ability_scores.insert("DEX".to_string(), 16);
+        for seed in 0..20 {
+            let check = service
+                .resolve_skill_check(Request::new(pb::SkillCheckRequest {
+                    creature_id: "rogue".to_string(),
+                    skill: "stealth".to_string(),
+                    dc: 15,
+                    stats: Some(rogue.clone()),
+                    proficient: true,
+                    seed: Some(seed),
+                    features: Some(pb::CheckFeatures {
+                        reliable_talent: true,
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            assert!(check.d20_value >= 10);
+            assert_eq!(check.total, check.d20_value + 5);
+            assert!(!check.breakdown.is_empty());
+        }
+
+        let bard = service
+            .resolve_ability_check(Request::new(pb::AbilityCheckRequest {
+                creature_id: "bard".to_string(),
+                ability: pb::Ability::Int as i32,
+                dc: 10,
+                stats: Some(creature("bard")),
+                features: Some(pb::CheckFeatures {
+                    jack_of_all_trades: true,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            }))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(bard.modifier, 1);
+        assert!(bard.breakdown.iter().any(|m| m.value == 1));
+    }
+}
@@ -0,0 +1,490 @@
+//! Grappling and shoving (PHB p.195-196).
+//!
+//! Both replace one attack of the Attack action with a contest: the
+//! attacker's Strength (Athletics) against the target's Strength (Athletics)
+//! or Dexterity (Acrobatics), whichever the target is better at. A tie
+//! leaves things as they were. The target can be at most one size larger
+//! than the attacker. A grappled creature can use its action to escape the
+//! same way, and a grappler dragging it moves at half speed unless it is
+//! two or more sizes smaller.
+
+use crate::action_economy::{ActionChoice, ActionEconomy};
+use crate::advantage::D20Sources;
+use crate::checks::{self, CheckFeatures, CheckInput, CheckKind, CheckOutcome, Proficiency};
+use crate::combat::{Ability, CreatureStats, Size};
+use crate::conditions::{ActiveCondition, ConditionManager, ConditionType};
+use crate::dice::DiceRoller;
+use serde::{Deserialize, Serialize};
+use shared_rust::DndError;
+
+/// One side of a contest.
+#[derive(Debug, Clone)]
+pub struct Contestant<'a> {
+    pub stats: &'a CreatureStats,
+    pub conditions: &'a [ActiveCondition],
+    pub athletics: Proficiency,
+    pub acrobatics: Proficiency,
+    pub features: CheckFeatures,
+    pub sources: D20Sources,
+}
+
+impl<'a> Contestant<'a> {
+    pub fn new(stats: &'a CreatureStats, conditions: &'a [ActiveCondition]) -> Self {
+        Self {
+            stats,
+            conditions,
+            athletics: Proficiency::None,
+            acrobatics: Proficiency::None,
+            features: CheckFeatures::default(),
+            sources: D20Sources::default(),
+        }
+    }
+
+    pub fn with_athletics(mut self, proficiency: Proficiency) -> Self {
+        self.athletics = proficiency;
+        self
+    }
+
+    pub fn with_acrobatics(mut self, proficiency: Proficiency) -> Self {
+        self.acrobatics = proficiency;
+        self
+    }
+
+    fn check(&self, skill: &str, proficiency: Proficiency) -> CheckInput {
+        let kind = CheckKind::skill(skill).expect("athletics and acrobatics are skills");
+        CheckInput::new(kind, 0)
+            .with_proficiency(proficiency)
+            .with_features(self.features)
+            .with_sources(self.sources.clone())
+    }
+
+    fn athletics_check(&self) -> CheckInput {
+        self.check("athletics", self.athletics)
+    }
+
+    /// Athletics or Acrobatics, whichever has the better modifier.
+    fn resisting_check(&self) -> CheckInput {
+        let bonus = |ability: Ability, proficiency: Proficiency| {
+            let pb = self.stats.proficiency_bonus;
+            self.stats.get_modifier(ability)
+                + match proficiency {
+                    Proficiency::None => 0,
+                    Proficiency::Proficient => pb,
+                    Proficiency::Expertise => pb * 2,
+                }
+        };
+        if bonus(Ability::DEX, self.acrobatics) > bonus(Ability::STR, self.athletics) {
+            self.check("acrobatics", self.acrobatics)
+        } else {
+            self.athletics_check()
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContestOutcome {
+    pub initiator: CheckOutcome,
+    pub resister: CheckOutcome,
+    /// The initiator won outright; ties go to the resister.
+    pub success: bool,
+}
+
+fn contest(
+    roller: &mut DiceRoller,
+    initiator: &Contestant,
+    initiator_check: CheckInput,
+    resister: &Contestant,
+    resister_check: CheckInput,
+) -> ContestOutcome {
+    let initiator_roll = checks::resolve_check(
+        roller,
+        initiator.stats,
+        initiator.conditions,
+        &initiator_check,
+    );
+    let resister_roll =
+        checks::resolve_check(roller, resister.stats, resister.conditions, &resister_check);
+    ContestOutcome {
+        success: initiator_roll.total > resister_roll.total,
+        initiator: initiator_roll,
+        resister: resister_roll,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ShoveEffect {
+    KnockProne,
+    /// 5 feet away from the attacker.
+    Push,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpecialAttackOutcome {
+    pub contest: ContestOutcome,
+    /// Condition applied to the target (grappled or prone).
+    pub condition_id: Option<String>,
+    /// How far the target is pushed; the board moves it.
+    pub pushed_feet: i32,
+    /// Conditions removed by escaping.
+    pub ended_conditions: Vec<String>,
+}
+
+impl SpecialAttackOutcome {
+    fn new(contest: ContestOutcome) -> Self {
+        Self {
+            contest,
+            condition_id: None,
+            pushed_feet: 0,
+            ended_conditions: Vec::new(),
+        }
+    }
+}
+
+fn check_size(
+    attacker: &CreatureStats,
+    target: &CreatureStats,
+    what: &str,
+) -> Result<(), DndError> {
+    if (target.size as i32) - (attacker.size as i32) > 1 {
+        return Err(DndError::RulesViolation(format!(
+            "{} ({:?}) can't {} {} ({:?}): more than one size larger",
+            attacker.creature_id, attacker.size, what, target.creature_id, target.size
+        )));
+    }
+    Ok(())
+}
+
+/// The id of the grappled condition `grappler_id` puts on `target_id`.
+pub fn grapple_condition_id(grappler_id: &str, target_id: &str) -> String {
+    format!("grapple:{}:{}", grappler_id, target_id)
+}
+
+/// Grab `target` with a free hand in place of one attack. On a success the
+/// target is grappled by the attacker.
+pub fn grapple(
+    roller: &mut DiceRoller,
+    economy: &mut ActionEconomy,
+    conditions: &mut ConditionManager,
+    attacker: &Contestant,
+    target: &Contestant,
+    has_free_hand: bool,
+) -> Result<SpecialAttackOutcome, DndError> {
+    check_size(attacker.stats, target.stats, "grapple")?;
+    if !has_free_hand {
+        return Err(DndError::RulesViolation(format!(
+            "{} needs a free hand to grapple",
+            attacker.stats.creature_id
+        )));
+    }
+    economy.attack()?;
+
+    let result = contest(
+        roller,
+        attacker,
+        attacker.athletics_check(),
+        target,
+        target.resisting_check(),
+    );
+    let mut outcome = SpecialAttackOutcome::new(result);
+    if outcome.contest.success {
+        let id = grapple_condition_id(&attacker.stats.creature_id, &target.stats.creature_id);
+        conditions.apply_condition(
+            &target.stats.creature_id,
+            ActiveCondition::new(id.clone(), ConditionType::Grappled)
+                .from_source(attacker.stats.creature_id.clone()),
+        );
+        outcome.condition_id = Some(id);
+    }
+    Ok(outcome)
+}
+
+/// Knock `target` prone or push it 5 feet, in place of one attack.
+pub fn shove(
+    roller: &mut DiceRoller,
+    economy: &mut ActionEconomy,
+    conditions: &mut ConditionManager,
+    attacker: &Contestant,
+    target: &Contestant,
+    effect: ShoveEffect,
+) -> Result<SpecialAttackOutcome, DndError> {
+    check_size(attacker.stats, target.stats, "shove")?;
+    economy.attack()?;
+
+    let result = contest(
+        roller,
+        attacker,
+        attacker.athletics_check(),
+        target,
+        target.resisting_check(),
+    );
+    let mut outcome = SpecialAttackOutcome::new(result);
+    if outcome.contest.success {
+        match effect {
+            ShoveEffect::KnockProne => {
+                let id = format!(
+                    "shove:{}:{}",
+                    attacker.stats.creature_id, target.stats.creature_id
+                );
+                conditions.apply_condition(
+                    &target.stats.creature_id,
+                    ActiveCondition::new(id.clone(), ConditionType::Prone)
+                        .from_source(attacker.stats.creature_id.clone()),
+                );
+                outcome.condition_id = Some(id);
+            }
+            ShoveEffect::Push => outcome.pushed_feet = 5,
+        }
+    }
+    Ok(outcome)
+}
+
+/// Use an action to break free of `grappler`'s grapple.
+pub fn escape_grapple(
+    roller: &mut DiceRoller,
+    economy: &mut ActionEconomy,
+    conditions: &mut ConditionManager,
+    escapee: &Contestant,
+    grappler: &Contestant,
+) -> Result<SpecialAttackOutcome, DndError> {
+    let escapee_id = &escapee.stats.creature_id;
+    let grappler_id = &grappler.stats.creature_id;
+    let grapples: Vec<String> = conditions
+        .get_conditions(escapee_id)
+        .into_iter()
+        .filter(|c| {
+            c.condition_type == ConditionType::Grappled
+                && c.implied_by.is_none()
+                && c.source_id.as_deref() == Some(grappler_id.as_str())
+        })
+        .map(|c| c.id.clone())
+        .collect();
+    if grapples.is_empty() {
+        return Err(DndError::InvalidAction(format!(
+            "{} is not grappled by {}",
+            escapee_id, grappler_id
+        )));
+    }
+    economy.take_action(ActionChoice::EscapeGrapple)?;
+
+    let result = contest(
+        roller,
+        escapee,
+        escapee.resisting_check(),
+        grappler,
+        grappler.athletics_check(),
+    );
+    let mut outcome = SpecialAttackOutcome::new(result);
+    if outcome.contest.success {
+        for id in grapples {
+            conditions.remove_condition(escapee_id, &id);
+            outcome.ended_conditions.push(id);
+        }
+    }
+    Ok(outcome)
+}
+
+/// Spend movement to move `feet` while dragging or carrying a grappled
+/// creature of `dragged` size: double cost unless it is two or more sizes
+/// smaller than the grappler.
+pub fn drag(
+    economy: &mut ActionEconomy,
+    feet: i32,
+    grappler: Size,
+    dragged: Size,
+) -> Result<i32, DndError> {
+    let cost = if (grappler as i32) - (dragged as i32) >= 2 {
+        feet
+    } else {
+        feet.saturating_mul(2)
+    };
+    economy.move_feet(cost)?;
+    Ok(cost)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::action_economy::EconomyFeatures;
+    use crate::conditions::ConditionEffects;
+
+    fn creature(id: &str, str_score: i32, dex_score: i32, size: Size) -> CreatureStats {
+        let mut stats = CreatureStats {
+            creature_id: id.to_string(),
+            proficiency_bonus: 2,
+            size,
+            ..Default::default()
+        };
+        stats.ability_scores.insert(Ability::STR, str_score);
+        stats.ability_scores.insert(Ability::DEX, dex_score);
+        stats
+    }
+
+    fn turn(id: &str, attacks: u8) -> ActionEconomy {
+        ActionEconomy::start_turn(
+            id,
+            30,
+            &ConditionEffects::default(),
+            EconomyFeatures {
+                attacks_per_action: attacks,
+                ..Default::default()
+            },
+        )
+    }
+
+    #[test]
+    fn test_grapple_uses_one_attack_and_applies_grappled() {
+        let fighter = creature("fighter", 20, 10, Size::Medium);
+        let goblin = creature("goblin", 8, 10, Size::Small);
+        let mut economy = turn("fighter", 2);
+        let mut conditions = ConditionManager::new();
+        let attacker = Contestant::new(&fighter, &[]).with_athletics(Proficiency::Expertise);
+        let target = Contestant::new(&goblin, &[]);
+
+        let mut roller = DiceRoller::with_seed(3);
+        let outcome = grapple(
+            &mut roller,
+            &mut economy,
+            &mut conditions,
+            &attacker,
+            &target,
+            true,
+        )
+        .unwrap();
+        assert_eq!(economy.attacks_remaining, 1);
+        assert_eq!(
+            outcome.contest.success,
+            outcome.contest.initiator.total > outcome.contest.resister.total
+        );
+        assert_eq!(
+            conditions.has_condition("goblin", ConditionType::Grappled),
+            outcome.contest.success
+        );
+        // The goblin resists with Acrobatics (+0) over Athletics (-1).
+        assert!(matches!(
+            outcome.contest.resister.kind,
+            CheckKind::Skill { ref skill, .. } if skill == "acrobatics"
+        ));
+
+        assert!(grapple(
+            &mut roller,
+            &mut economy,
+            &mut conditions,
+            &attacker,
+            &target,
+            false
+        )
+        .is_err());
+        assert_eq!(economy.attacks_remaining, 1);
+    }
+
+    #[test]
+    fn test_size_limit() {
+        let halfling = creature("halfling", 14, 10, Size::Small);
+        let ogre = creature("ogre", 19, 8, Size::Large);
+        let horse = creature("horse", 18, 10, Size::Medium);
+        let mut economy = turn("halfling", 1);
+        let mut conditions = ConditionManager::new();
+        let mut roller = DiceRoller::with_seed(1);
+        let attacker = Contestant::new(&halfling, &[]);
+        let err = shove(
+            &mut roller,
+            &mut economy,
+            &mut conditions,
+            &attacker,
+            &Contestant::new(&ogre, &[]),
+            ShoveEffect::KnockProne,
+        )
+        .unwrap_err();
+        assert!(matches!(err, DndError::RulesViolation(_)));
+        assert_eq!(economy.actions, 1);
+        assert!(shove(
+            &mut roller,
+            &mut economy,
+            &mut conditions,
+            &attacker,
+            &Contestant::new(&horse, &[]),
+            ShoveEffect::Push,
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_shove_prone_and_ties_fail() {
+        let a = creature("a", 10, 10, Size::Medium);
+        let b = creature("b", 10, 10, Size::Medium);
+        let mut conditions = ConditionManager::new();
+        let (mut wins, mut ties) = (0, 0);
+        for seed in 0..40 {
+            let mut economy = turn("a", 1);
+            let mut roller = DiceRoller::with_seed(seed);
+            let outcome = shove(
+                &mut roller,
+                &mut economy,
+                &mut conditions,
+                &Contestant::new(&a, &[]),
+                &Contestant::new(&b, &[]),
+                ShoveEffect::KnockProne,
+            )
+            .unwrap();
+            let contest = &outcome.contest;
+            if contest.initiator.total == contest.resister.total {
+                ties += 1;
+                assert!(!contest.success);
+            }
+            if contest.success {
+                wins += 1;
+                assert_eq!(outcome.condition_id.as_deref(), Some("shove:a:b"));
+            }
+        }
+        assert!(wins > 0 && ties > 0);
+        assert!(conditions.has_condition("b", ConditionType::Prone));
+    }
+
+    #[test]
+    fn test_escape_and_drag() {
+        let bear = creature("bear", 19, 10, Size::Large);
+        let rogue = creature("rogue", 8, 20, Size::Medium);
+        let mut conditions = ConditionManager::new();
+        let id = grapple_condition_id("bear", "rogue");
+        conditions.apply_condition(
+            "rogue",
+            ActiveCondition::new(id.clone(), ConditionType::Grappled).from_source("bear"),
+        );
+
+        let escapee = Contestant::new(&rogue, &[]).with_acrobatics(Proficiency::Expertise);
+        let grappler = Contestant::new(&bear, &[]);
+        let mut escaped = false;
+        for seed in 0..20 {
+            let mut economy = turn("rogue", 1);
+            let mut roller = DiceRoller::with_seed(seed);
+            let outcome = escape_grapple(
+                &mut roller,
+                &mut economy,
+                &mut conditions,
+                &escapee,
+                &grappler,
+            )
+            .unwrap();
+            assert_eq!(economy.actions, 0);
+            assert!(matches!(
+                outcome.contest.initiator.kind,
+                CheckKind::Skill { ref skill, .. } if skill == "acrobatics"
+            ));
+            if outcome.contest.success {
+                assert_eq!(outcome.ended_conditions, vec![id.clone()]);
+                escaped = true;
+                break;
+            }
+        }
+        assert!(escaped);
+        assert!(!conditions.has_condition("rogue", ConditionType::Grappled));
+
+        let mut economy = turn("bear", 1);
+        assert_eq!(
+            drag(&mut economy, 10, Size::Large, Size::Medium).unwrap(),
+            20
+        );
+        assert_eq!(drag(&mut economy, 10, Size::Large, Size::Tiny).unwrap(), 10);
+        assert!(drag(&mut economy, 5, Size::Large, Size::Large).is_err());
+    }
+}
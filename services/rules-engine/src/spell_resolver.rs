@@ -0,0 +1,873 @@
+//! Casting a [`SpellDefinition`] against creatures on the grid.
+//!
+//! Targets come from the grid solver: area spells lay their template over
+//! the map and catch everyone inside it with a line of effect, targeted
+//! spells check range and line of sight to each chosen creature. Resolution
+//! then follows the definition: one damage roll shared by everyone making a
+//! save, a separate attack and damage roll per target for attack spells and
+//! per missile for spells like Magic Missile, upcast dice per slot level and
+//! cantrip dice by character level (PHB p.201-205). Riders such as
+//! conditions and pushes are reported for each target they land on.
+
+use crate::advantage::D20Sources;
+use crate::attack::{self, AttackInput, AttackOutcome, AttackSource};
+use crate::checks::{self, CheckInput, CheckKind, CheckOutcome, Modifier};
+use crate::combat::{CoverType, CreatureStats, DamageResult};
+use crate::conditions::{ActiveCondition, ConditionDuration, SaveToEnd};
+use crate::damage::{self, DamageDefenses};
+use crate::dice::expression::TermKind;
+use crate::dice::{DiceExpression, DiceRoller, DieRoll};
+use crate::spellbook::{
+    Resolution, RiderEffect, RiderTrigger, SpellDefinition, SpellRange, Targeting,
+};
+use grid_solver::aoe::{self, AoeTarget, AoeTemplate, Direction};
+use grid_solver::cover::{self, CoverLevel};
+use grid_solver::grid::{footprint_distance_feet, Grid};
+use grid_solver::los;
+use serde::{Deserialize, Serialize};
+use shared_rust::{DamageInstance, DndError, GridPosition};
+
+/// Where the caster aims the spell.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Aim {
+    /// Point of origin for an area placed within range.
+    pub point: Option<GridPosition>,
+    /// Facing for cones, lines and cubes spreading from the caster.
+    pub direction: Option<Direction>,
+    /// Creatures chosen for a targeted spell; for missiles, one entry per
+    /// missile, repeating a creature for each one aimed at it.
+    pub creature_ids: Vec<String>,
+}
+
+pub fn cover_type(level: CoverLevel) -> CoverType {
+    match level {
+        CoverLevel::None => CoverType::None,
+        CoverLevel::Half => CoverType::Half,
+        CoverLevel::ThreeQuarters => CoverType::ThreeQuarters,
+        CoverLevel::Total => CoverType::Total,
+    }
+}
+
+/// Creatures the spell affects when `caster_id` casts it at `slot_level`.
+pub fn select_targets(
+    grid: &Grid,
+    spell: &SpellDefinition,
+    caster_id: &str,
+    slot_level: u8,
+    aim: &Aim,
+) -> Result<Vec<AoeTarget>, DndError> {
+    let caster = grid
+        .creature(caster_id)
+        .ok_or_else(|| DndError::EntityNotFound(caster_id.to_string()))?;
+    let caster_footprint = caster.footprint();
+
+    match spell.targeting {
+        Targeting::Area {
+            shape,
+            size_feet,
+            width_feet,
+            from_self,
+        } => {
+            let template = AoeTemplate {
+                shape,
+                size_feet,
+                width_feet,
+            };
+            let origin = if from_self {
+                caster.position
+            } else {
+                let point = aim.point.ok_or_else(|| {
+                    DndError::InvalidAction(format!("{} needs a point of origin", spell.name))
+                })?;
+                if !grid.in_bounds(point) {
+                    return Err(DndError::InvalidPosition {
+                        x: point.x,
+                        y: point.y,
+                    });
+                }
+                let distance = footprint_distance_feet(&caster_footprint, &[point]);
+                if distance > spell.range.feet() {
+                    return Err(DndError::RulesViolation(format!(
+                        "{} has a range of {} ft; the point is {} ft away",
+                        spell.name,
+                        spell.range.feet(),
+                        distance
+                    )));
+                }
+                if !los::has_line_of_sight(grid, caster.position, point) {
+                    return Err(DndError::RulesViolation(format!(
+                        "{} needs a clear path to its point of origin",
+                        spell.name
+                    )));
+                }
+                point
+            };
+            let exclude = from_self.then_some(caster_id);
+            Ok(aoe::aoe_targets(
+                grid,
+                origin,
+                &template,
+                aim.direction,
+                false,
+                exclude,
+            ))
+        }
+        Targeting::Creatures { .. } | Targeting::Missiles { .. } => {
+            let max_targets = spell.requirements(slot_level).max_targets as usize;
+            if aim.creature_ids.len() > max_targets {
+                return Err(DndError::RulesViolation(format!(
+                    "{} can target {} creature(s) at level {}, not {}",
+                    spell.name,
+                    max_targets,
+                    slot_level,
+                    aim.creature_ids.len()
+                )));
+            }
+            check_distinct_targets(spell, aim.creature_ids.iter().map(String::as_str))?;
+            aim.creature_ids
+                .iter()
+                .map(|id| {
+                    let target = grid
+                        .creature(id)
+                        .ok_or_else(|| DndError::EntityNotFound(id.clone()))?;
+                    let distance_feet =
+                        footprint_distance_feet(&caster_footprint, &target.footprint());
+                    let cover = if id == caster_id {
+                        CoverLevel::None
+                    } else {
+                        cover::calculate_cover(grid, caster.position, target.position).level
+                    };
+                    let visible = id == caster_id
+                        || los::has_line_of_sight(grid, caster.position, target.position);
+                    check_reach(
+                        spell,
+                        caster_id,
+                        id,
+                        distance_feet,
+                        visible && cover != CoverLevel::Total,
+                    )?;
+                    Ok(AoeTarget {
+                        creature_id: id.clone(),
+                        position: target.position,
+                        distance_feet,
+                        cover,
+                    })
+                })
+                .collect()
+        }
+    }
+}
+
+/// A targeted spell reaches creatures within its range that the caster can
+/// see, or only the caster for a spell with a range of self.
+fn check_reach(
+    spell: &SpellDefinition,
+    caster_id: &str,
+    target_id: &str,
+    distance_feet: i32,
+    visible: bool,
+) -> Result<(), DndError> {
+    let in_range = match spell.range {
+        SpellRange::SelfOnly => target_id == caster_id,
+        range => distance_feet <= range.feet(),
+    };
+    if !in_range {
+        return Err(DndError::RulesViolation(format!(
+            "{} is {} ft away, out of range of {}",
+            target_id, distance_feet, spell.name
+        )));
+    }
+    if !visible {
+        return Err(DndError::RulesViolation(format!(
+            "{} can't be seen or targeted by {}",
+            target_id, spell.name
+        )));
+    }
+    Ok(())
+}
+
+/// Only missiles can be aimed at the same creature more than once.
+fn check_distinct_targets<'a>(
+    spell: &SpellDefinition,
+    ids: impl Iterator<Item = &'a str>,
+) -> Result<(), DndError> {
+    if let Targeting::Missiles { .. } = spell.targeting {
+        return Ok(());
+    }
+    let mut seen = std::collections::HashSet::new();
+    for id in ids {
+        if !seen.insert(id) {
+            return Err(DndError::RulesViolation(format!(
+                "{} can't target {} more than once",
+                spell.name, id
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// The caster's side of a cast.
+#[derive(Debug, Clone)]
+pub struct CastContext<'a> {
+    pub caster: &'a CreatureStats,
+    pub caster_conditions: &'a [ActiveCondition],
+    pub slot_level: u8,
+    /// For cantrip scaling.
+    pub character_level: i32,
+    pub spell_save_dc: i32,
+    pub spell_attack_bonus: i32,
+    /// Spellcasting ability modifier, added to healing such as Cure Wounds.
+    pub spellcasting_modifier: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct TargetState {
+    pub stats: CreatureStats,
+    pub conditions: Vec<ActiveCondition>,
+    pub distance_feet: i32,
+    pub cover: CoverType,
+    /// Advantage and bonuses on the target's saves from its own features
+    /// and its allies' auras, e.g. Aura of Protection.
+    pub save_sources: D20Sources,
+    pub save_bonuses: Vec<Modifier>,
+}
+
+impl TargetState {
+    pub fn new(stats: CreatureStats, conditions: Vec<ActiveCondition>) -> Self {
+        Self {
+            stats,
+            conditions,
+            distance_feet: 5,
+            cover: CoverType::None,
+            save_sources: D20Sources::default(),
+            save_bonuses: Vec::new(),
+        }
+    }
+
+    /// The advantage and bonuses features have added to `save`.
+    pub fn with_save_help(mut self, save: CheckInput) -> Self {
+        self.save_sources = save.sources;
+        self.save_bonuses = save.bonuses;
+        self
+    }
+
+    /// Distance and cover as the grid solver found them.
+    pub fn placed(mut self, target: &AoeTarget) -> Self {
+        self.distance_feet = target.distance_feet;
+        self.cover = cover_type(target.cover);
+        self
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HealingRoll {
+    pub rolls: Vec<DieRoll>,
+    pub modifier: i32,
+    pub total: i32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetOutcome {
+    pub target_id: String,
+    pub attack: Option<AttackOutcome>,
+    pub save: Option<CheckOutcome>,
+    pub damage: Option<DamageResult>,
+    pub healing: Option<HealingRoll>,
+    /// Riders whose trigger was met, in definition order.
+    pub riders: Vec<RiderEffect>,
+    /// Conditions from riders, ready for the condition manager.
+    pub conditions: Vec<ActiveCondition>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpellOutcome {
+    pub spell_id: String,
+    pub slot_level: u8,
+    pub targets: Vec<TargetOutcome>,
+    pub concentration: bool,
+    pub duration_rounds: Option<i32>,
+}
+
+/// Cantrip damage dice multiplier at `character_level` (PHB p.201).
+pub fn cantrip_dice_multiplier(character_level: i32) -> u32 {
+    match character_level {
+        l if l >= 17 => 4,
+        l if l >= 11 => 3,
+        l if l >= 5 => 2,
+        _ => 1,
+    }
+}
+
+/// `base` with its dice multiplied and `per_slot_level` added once per
+/// level the spell is upcast.
+pub fn scaled_expression(
+    base: &str,
+    per_slot_level: Option<&str>,
+    multiplier: u32,
+    upcast_levels: u8,
+) -> Result<DiceExpression, DndError> {
+    let invalid = |e: crate::dice::DiceError| DndError::InvalidSpellDefinition(e.to_string());
+    let mut expression = DiceExpression::parse(base).map_err(invalid)?;
+    for term in &mut expression.terms {
+        if let TermKind::Dice(dice) = &mut term.kind {
+            dice.count *= multiplier;
+        }
+    }
+    if let Some(extra) = per_slot_level {
+        let extra = DiceExpression::parse(extra).map_err(invalid)?;
+        for _ in 0..upcast_levels {
+            expression.terms.extend(extra.terms.iter().cloned());
+        }
+    }
+    Ok(expression)
+}
+
+fn damage_against(
+    spell: &SpellDefinition,
+    rolls: Vec<DieRoll>,
+    base_damage: i32,
+    target: &CreatureStats,
+) -> Option<DamageResult> {
+    let damage_type = spell.damage.as_ref()?.damage_type;
+    let applied = damage::apply_defenses(
+        DamageInstance::magical(damage_type, base_damage),
+        &DamageDefenses::from_stats(target),
+    );
+    Some(DamageResult {
+        rolls,
+        base_damage,
+        modifier: 0,
+        damage_type,
+        is_resistant: applied.resisted,
+        is_vulnerable: applied.vulnerable,
+        is_immune: applied.immune,
+        final_damage: applied.final_amount,
+    })
+}
+
+/// Resolve `spell` against `targets`, already selected and in range.
+pub fn resolve(
+    roller: &mut DiceRoller,
+    spell: &SpellDefinition,
+    ctx: &CastContext,
+    targets: &[TargetState],
+) -> Result<SpellOutcome, DndError> {
+    if spell.level > 0 && ctx.slot_level < spell.level {
+        return Err(DndError::RulesViolation(format!(
+            "{} is a level {} spell and can't be cast with a level {} slot",
+            spell.name, spell.level, ctx.slot_level
+        )));
+    }
+    if let Targeting::Creatures { .. } | Targeting::Missiles { .. } = spell.targeting {
+        let max_targets = spell.requirements(ctx.slot_level).max_targets as usize;
+        if targets.len() > max_targets {
+            return Err(DndError::RulesViolation(format!(
+                "{} can target {} creature(s), not {}",
+                spell.name,
+                max_targets,
+                targets.len()
+            )));
+        }
+        check_distinct_targets(spell, targets.iter().map(|t| t.stats.creature_id.as_str()))?;
+        for target in targets {
+            check_reach(
+                spell,
+                &ctx.caster.creature_id,
+                &target.stats.creature_id,
+                target.distance_feet,
+                target.cover != CoverType::Total,
+            )?;
+        }
+    }
+
+    let upcast_levels = if spell.level == 0 {
+        0
+    } else {
+        ctx.slot_level - spell.level
+    };
+    let damage = spell
+        .damage
+        .as_ref()
+        .map(|d| {
+            let multiplier = if d.cantrip_scaling {
+                cantrip_dice_multiplier(ctx.character_level)
+            } else {
+                1
+            };
+            scaled_expression(
+                &d.dice,
+                d.per_slot_level.as_deref(),
+                multiplier,
+                upcast_levels,
+            )
+        })
+        .transpose()?;
+    let healing = spell
+        .healing
+        .as_ref()
+        .map(|h| scaled_expression(&h.dice, h.per_slot_level.as_deref(), 1, upcast_levels))
+        .transpose()?;
+
+    // Everyone saving against the spell takes the same roll.
+    let shared_damage = match (&spell.resolution, &damage) {
+        (Resolution::Save { .. }, Some(expression)) => {
+            Some(damage::roll_damage(roller, expression, 0, false))
+        }
+        _ => None,
+    };
+
+    let mut outcomes = Vec::with_capacity(targets.len());
+    for target in targets {
+        let cover = if spell.ignores_cover {
+            CoverType::None
+        } else {
+            target.cover
+        };
+        let mut outcome = TargetOutcome {
+            target_id: target.stats.creature_id.clone(),
+            attack: None,
+            save: None,
+            damage: None,
+            healing: None,
+            riders: Vec::new(),
+            conditions: Vec::new(),
+        };
+
+        let landed = match spell.resolution {
+            Resolution::Attack => {
+                let input = AttackInput::new(AttackSource::Spell, None)
+                    .with_bonus("Spell attack bonus", ctx.spell_attack_bonus)
+                    .with_cover(cover)
+                    .at_distance(target.distance_feet);
+                let attack = attack::resolve_attack(
+                    roller,
+                    ctx.caster,
+                    &target.stats,
+                    ctx.caster_conditions,
+                    &target.conditions,
+                    &input,
+                );
+                if let (true, Some(expression)) = (attack.hits(), &damage) {
+                    let (rolls, total) =
+                        damage::roll_damage(roller, expression, 0, attack.is_critical());
+                    outcome.damage = damage_against(spell, rolls, total, &target.stats);
+                }
+                let hit = attack.hits();
+                outcome.attack = Some(attack);
+                hit
+            }
+            Resolution::Save {
+                ability,
+                half_on_success,
+            } => {
+                let mut input = CheckInput::new(CheckKind::SavingThrow(ability), ctx.spell_save_dc)
+                    .with_sources(target.save_sources.clone());
+                input.bonuses.extend(target.save_bonuses.iter().cloned());
+                let cover_bonus = cover.dex_save_bonus();
+                if ability == crate::combat::Ability::DEX && cover_bonus > 0 {
+                    input = input.with_bonus("Cover", cover_bonus);
+                }
+                let save = checks::resolve_check(roller, &target.stats, &target.conditions, &input);
+                if let Some((rolls, total)) = &shared_damage {
+                    let amount = match (save.success, half_on_success) {
+                        (false, _) => *total,
+                        (true, true) => total / 2,
+                        (true, false) => 0,
+                    };
+                    outcome.damage = damage_against(spell, rolls.clone(), amount, &target.stats);
+                }
+                let failed = !save.success;
+                outcome.save = Some(save);
+                failed
+            }
+            Resolution::Automatic => {
+                if let Some(expression) = &damage {
+                    let (rolls, total) = damage::roll_damage(roller, expression, 0, false);
+                    outcome.damage = damage_against(spell, rolls, total, &target.stats);
+                }
+                true
+            }
+        };
+
+        if let (Some(expression), Some(definition)) = (&healing, &spell.healing) {
+            let roll = expression.roll(roller);
+            let modifier = if definition.add_modifier {
+                ctx.spellcasting_modifier
+            } else {
+                0
+            };
+            outcome.healing = Some(HealingRoll {
+                rolls: roll.rolls,
+                modifier,
+                total: (roll.total + modifier).max(0),
+            });
+        }
+
+        for rider in &spell.riders {
+            let triggered = match rider.when {
+                RiderTrigger::Always => true,
+                RiderTrigger::OnFailedSave => outcome.save.as_ref().is_some_and(|s| !s.success),
+                RiderTrigger::OnHit => {
+                    landed && !matches!(spell.resolution, Resolution::Save { .. })
+                }
+            };
+            if !triggered {
+                continue;
+            }
+            if let RiderEffect::Condition {
+                condition,
+                save_at_end_of_turn,
+            } = rider.effect
+            {
+                outcome.conditions.push(rider_condition(
+                    spell,
+                    ctx,
+                    &outcome.target_id,
+                    condition,
+                    save_at_end_of_turn,
+                ));
+            }
+            outcome.riders.push(rider.effect);
+        }
+        outcomes.push(outcome);
+    }
+
+    Ok(SpellOutcome {
+        spell_id: spell.id.clone(),
+        slot_level: ctx.slot_level,
+        targets: outcomes,
+        concentration: spell.concentration,
+        duration_rounds: spell.duration.rounds(),
+    })
+}
+
+fn rider_condition(
+    spell: &SpellDefinition,
+    ctx: &CastContext,
+    target_id: &str,
+    condition: crate::conditions::ConditionType,
+    save_at_end_of_turn: bool,
+) -> ActiveCondition {
+    let id = format!(
+        "{}:{}:{}:{}",
+        spell.id,
+        ctx.caster.creature_id,
+        target_id,
+        condition.name().to_lowercase()
+    );
+    let mut active = ActiveCondition::new(id, condition)
+        .from_source(ctx.caster.creature_id.clone())
+        .lasting(
+            spell
+                .duration
+                .rounds()
+                .map_or(ConditionDuration::UntilRemoved, ConditionDuration::Rounds),
+        );
+    if let (true, Resolution::Save { ability, .. }) = (save_at_end_of_turn, spell.resolution) {
+        active = active.with_save(SaveToEnd {
+            ability,
+            dc: ctx.spell_save_dc,
+            at_end_of_turn: true,
+        });
+    }
+    if spell.concentration {
+        active = active.on_concentration();
+    }
+    active
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::advantage::RollMode;
+    use crate::combat::Ability;
+    use crate::conditions::ConditionType;
+    use crate::spellbook::Spellbook;
+    use grid_solver::grid::{Blocking, Creature, Tile, TileType};
+    use shared_rust::DamageType;
+
+    fn creature(id: &str, x: i32, y: i32) -> Creature {
+        Creature {
+            creature_id: id.to_string(),
+            position: GridPosition::new(x, y),
+            size: 1,
+            is_prone: false,
+        }
+    }
+
+    fn stats(id: &str) -> CreatureStats {
+        CreatureStats {
+            creature_id: id.to_string(),
+            armor_class: 12,
+            max_hp: 30,
+            current_hp: 30,
+            ..Default::default()
+        }
+    }
+
+    fn context(caster: &CreatureStats, slot_level: u8) -> CastContext<'_> {
+        CastContext {
+            caster,
+            caster_conditions: &[],
+            slot_level,
+            character_level: 5,
+            spell_save_dc: 15,
+            spell_attack_bonus: 7,
+            spellcasting_modifier: 4,
+        }
+    }
+
+    #[test]
+    fn test_fireball_catches_everyone_in_the_sphere() {
+        let mut grid = Grid::new(40, 40);
+        for c in [
+            creature("wizard", 0, 10),
+            creature("orc", 20, 10),
+            creature("goblin", 23, 12),
+            creature("far", 30, 10),
+        ] {
+            grid.add_creature(c);
+        }
+        let fireball = Spellbook::builtin().get("fireball").unwrap();
+        let aim = Aim {
+            point: Some(GridPosition::new(20, 10)),
+            ..Default::default()
+        };
+        let mut ids: Vec<String> = select_targets(&grid, fireball, "wizard", 3, &aim)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.creature_id)
+            .collect();
+        ids.sort();
+        assert_eq!(ids, vec!["goblin", "orc"]);
+
+        let too_far = Aim {
+            point: Some(GridPosition::new(35, 10)),
+            ..Default::default()
+        };
+        assert!(matches!(
+            select_targets(&grid, fireball, "wizard", 3, &too_far),
+            Err(DndError::RulesViolation(_))
+        ));
+    }
+
+    #[test]
+    fn test_thunderwave_spreads_from_the_caster() {
+        let mut grid = Grid::new(20, 20);
+        grid.add_creature(creature("cleric", 5, 5));
+        grid.add_creature(creature("bandit", 6, 5));
+        grid.add_creature(creature("behind", 4, 5));
+        let thunderwave = Spellbook::builtin().get("thunderwave").unwrap();
+        let aim = Aim {
+            direction: Some(Direction::East),
+            ..Default::default()
+        };
+        let targets = select_targets(&grid, thunderwave, "cleric", 1, &aim).unwrap();
+        let ids: Vec<&str> = targets.iter().map(|t| t.creature_id.as_str()).collect();
+        assert_eq!(ids, vec!["bandit"]);
+    }
+
+    #[test]
+    fn test_targeted_spell_checks_range_count_and_sight() {
+        let mut grid = Grid::new(30, 30);
+        grid.add_creature(creature("wizard", 0, 0));
+        grid.add_creature(creature("guard", 5, 0));
+        grid.add_creature(creature("captain", 0, 5));
+        grid.add_creature(creature("hidden", 10, 10));
+        grid.add_creature(creature("distant", 25, 0));
+        let mut wall = Tile::floor(GridPosition::new(9, 9));
+        wall.tile_type = TileType::Wall;
+        wall.blocking = Blocking::Full;
+        grid.set_tile(wall);
+
+        let hold = Spellbook::builtin().get("hold-person").unwrap();
+        let aim = |ids: &[&str]| Aim {
+            creature_ids: ids.iter().map(|s| s.to_string()).collect(),
+            ..Default::default()
+        };
+        assert_eq!(
+            select_targets(&grid, hold, "wizard", 2, &aim(&["guard"]))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(select_targets(&grid, hold, "wizard", 2, &aim(&["guard", "captain"])).is_err());
+        assert_eq!(
+            select_targets(&grid, hold, "wizard", 3, &aim(&["guard", "captain"]))
+                .unwrap()
+                .len(),
+            2
+        );
+        assert!(select_targets(&grid, hold, "wizard", 2, &aim(&["hidden"])).is_err());
+        assert!(select_targets(&grid, hold, "wizard", 2, &aim(&["distant"])).is_err());
+    }
+
+    #[test]
+    fn test_resolve_rejects_targets_out_of_reach() {
+        let hold = Spellbook::builtin().get("hold-person").unwrap();
+        let cleric = stats("cleric");
+        let ctx = context(&cleric, 2);
+        let placed = |distance_feet, cover| TargetState {
+            distance_feet,
+            cover,
+            ..TargetState::new(stats("bandit"), vec![])
+        };
+
+        let mut roller = DiceRoller::with_seed(2);
+        assert!(resolve(&mut roller, hold, &ctx, &[placed(60, CoverType::Half)]).is_ok());
+        let far = resolve(&mut roller, hold, &ctx, &[placed(65, CoverType::None)]).unwrap_err();
+        assert!(far.to_string().contains("out of range"));
+        assert!(resolve(&mut roller, hold, &ctx, &[placed(30, CoverType::Total)]).is_err());
+    }
+
+    #[test]
+    fn test_save_help_reaches_the_save() {
+        let hold = Spellbook::builtin().get("hold-person").unwrap();
+        let cleric = stats("cleric");
+        let help = CheckInput::new(CheckKind::SavingThrow(Ability::WIS), 0)
+            .with_bonus("Aura of Protection", 3)
+            .with_sources(D20Sources::new().with_advantage("Magic Resistance"));
+        let target = [TargetState::new(stats("bandit"), vec![]).with_save_help(help)];
+
+        let mut roller = DiceRoller::with_seed(6);
+        let outcome = resolve(&mut roller, hold, &context(&cleric, 2), &target).unwrap();
+        let save = outcome.targets[0].save.as_ref().unwrap();
+        assert!(save
+            .breakdown
+            .iter()
+            .any(|m| m.source == "Aura of Protection" && m.value == 3));
+        assert_eq!(save.d20.as_ref().unwrap().mode, RollMode::Advantage);
+    }
+
+    #[test]
+    fn test_save_spell_shares_one_roll_and_halves_on_success() {
+        let fireball = Spellbook::builtin().get("fireball").unwrap();
+        let wizard = stats("wizard");
+        let mut tough = stats("tough");
+        tough.ability_scores.insert(Ability::DEX, 30);
+        tough.proficient_saves.insert(Ability::DEX);
+        tough.proficiency_bonus = 10;
+        let mut weak = stats("weak");
+        weak.ability_scores.insert(Ability::DEX, 1);
+        weak.resistances.insert(DamageType::Fire);
+        let targets = [
+            TargetState::new(tough, vec![]),
+            TargetState::new(weak, vec![]),
+        ];
+
+        let mut ctx = context(&wizard, 5);
+        ctx.spell_save_dc = 20;
+
+        let mut roller = DiceRoller::with_seed(11);
+        let outcome = resolve(&mut roller, fireball, &ctx, &targets).unwrap();
+        let tough = &outcome.targets[0];
+        let weak = &outcome.targets[1];
+        assert!(tough.save.as_ref().unwrap().success);
+        assert!(!weak.save.as_ref().unwrap().success);
+        let full = weak.damage.as_ref().unwrap().base_damage;
+        // 8d6 plus two upcast d6.
+        assert_eq!(weak.damage.as_ref().unwrap().rolls.len(), 10);
+        assert_eq!(tough.damage.as_ref().unwrap().base_damage, full / 2);
+        assert_eq!(weak.damage.as_ref().unwrap().final_damage, full / 2);
+    }
+
+    #[test]
+    fn test_cantrip_scaling_and_crits_roll_more_dice() {
+        let fire_bolt = Spellbook::builtin().get("fire-bolt").unwrap();
+        let wizard = stats("wizard");
+        let mut ctx = context(&wizard, 0);
+        ctx.character_level = 11;
+        let target = [TargetState::new(stats("dummy"), vec![])];
+
+        let mut roller = DiceRoller::with_seed(3);
+        for _ in 0..50 {
+            let outcome = resolve(&mut roller, fire_bolt, &ctx, &target).unwrap();
+            let result = &outcome.targets[0];
+            let attack = result.attack.as_ref().unwrap();
+            let expected = match (attack.hits(), attack.is_critical()) {
+                (_, true) => Some(6),
+                (true, false) => Some(3),
+                (false, _) => None,
+            };
+            assert_eq!(result.damage.as_ref().map(|d| d.rolls.len()), expected);
+        }
+    }
+
+    #[test]
+    fn test_sacred_flame_ignores_cover() {
+        let flame = Spellbook::builtin().get("sacred-flame").unwrap();
+        let fireball = Spellbook::builtin().get("fireball").unwrap();
+        let cleric = stats("cleric");
+        let behind_wall = |cover| TargetState {
+            cover,
+            ..TargetState::new(stats("orc"), vec![])
+        };
+        let covered = [behind_wall(CoverType::ThreeQuarters)];
+
+        let mut roller = DiceRoller::with_seed(4);
+        let outcome = resolve(&mut roller, flame, &context(&cleric, 0), &covered).unwrap();
+        let save = outcome.targets[0].save.as_ref().unwrap();
+        assert!(save.breakdown.iter().all(|m| m.source != "Cover"));
+
+        let outcome = resolve(&mut roller, fireball, &context(&cleric, 3), &covered).unwrap();
+        let save = outcome.targets[0].save.as_ref().unwrap();
+        assert!(save
+            .breakdown
+            .iter()
+            .any(|m| m.source == "Cover" && m.value == 5));
+    }
+
+    #[test]
+    fn test_magic_missile_darts_can_share_a_target() {
+        let missile = Spellbook::builtin().get("magic-missile").unwrap();
+        let wizard = stats("wizard");
+        let ctx = context(&wizard, 1);
+        let darts = vec![TargetState::new(stats("orc"), vec![]); 3];
+
+        let mut roller = DiceRoller::with_seed(9);
+        let outcome = resolve(&mut roller, missile, &ctx, &darts).unwrap();
+        assert_eq!(outcome.targets.len(), 3);
+        let total: i32 = outcome
+            .targets
+            .iter()
+            .map(|t| t.damage.as_ref().unwrap().final_damage)
+            .sum();
+        // Each dart rolls its own 1d4 + 1.
+        assert!((6..=15).contains(&total));
+        assert!(resolve(&mut roller, missile, &ctx, &vec![darts[0].clone(); 4]).is_err());
+
+        let hold = Spellbook::builtin().get("hold-person").unwrap();
+        let twice = [darts[0].clone(), darts[0].clone()];
+        let err = resolve(&mut roller, hold, &context(&wizard, 3), &twice).unwrap_err();
+        assert!(err.to_string().contains("more than once"));
+    }
+
+    #[test]
+    fn test_hold_person_applies_a_concentration_condition() {
+        let hold = Spellbook::builtin().get("hold-person").unwrap();
+        let cleric = stats("cleric");
+        let mut dazed = stats("bandit");
+        dazed.ability_scores.insert(Ability::WIS, 1);
+        let targets = [TargetState::new(dazed, vec![])];
+        let mut ctx = context(&cleric, 2);
+        ctx.spell_save_dc = 30;
+
+        let mut roller = DiceRoller::with_seed(5);
+        let outcome = resolve(&mut roller, hold, &ctx, &targets).unwrap();
+        let held = &outcome.targets[0];
+        assert_eq!(held.conditions.len(), 1);
+        let condition = &held.conditions[0];
+        assert_eq!(condition.condition_type, ConditionType::Paralyzed);
+        assert!(condition.concentration);
+        assert_eq!(condition.duration, ConditionDuration::Rounds(10));
+        assert_eq!(condition.save.map(|s| s.dc), Some(30));
+
+        let two = [
+            TargetState::new(stats("a"), vec![]),
+            TargetState::new(stats("b"), vec![]),
+        ];
+        assert!(resolve(&mut roller, hold, &ctx, &two).is_err());
+        ctx.slot_level = 1;
+        assert!(resolve(&mut roller, hold, &ctx, &targets[..]).is_err());
+    }
+}
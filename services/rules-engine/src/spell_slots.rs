@@ -0,0 +1,441 @@
+//! Spell slots for every kind of caster (PHB p.164-165, 107, 133).
+//!
+//! Full, half and third casters share one slot table, indexed by caster
+//! level; a multiclass character adds up its levels as the PHB's
+//! "Multiclass Spellcaster" table describes. Warlock Pact Magic slots are
+//! tracked separately: they all share one level and come back on a short
+//! rest.
+
+use serde::{Deserialize, Serialize};
+use shared_rust::DndError;
+use std::collections::HashMap;
+
+pub const MAX_SPELL_LEVEL: usize = 9;
+
+/// Slots per spell level for caster levels 1-20.
+const SLOT_TABLE: [[u8; MAX_SPELL_LEVEL]; 20] = [
+    [2, 0, 0, 0, 0, 0, 0, 0, 0],
+    [3, 0, 0, 0, 0, 0, 0, 0, 0],
+    [4, 2, 0, 0, 0, 0, 0, 0, 0],
+    [4, 3, 0, 0, 0, 0, 0, 0, 0],
+    [4, 3, 2, 0, 0, 0, 0, 0, 0],
+    [4, 3, 3, 0, 0, 0, 0, 0, 0],
+    [4, 3, 3, 1, 0, 0, 0, 0, 0],
+    [4, 3, 3, 2, 0, 0, 0, 0, 0],
+    [4, 3, 3, 3, 1, 0, 0, 0, 0],
+    [4, 3, 3, 3, 2, 0, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 0, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 0, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 0],
+    [4, 3, 3, 3, 2, 1, 1, 1, 1],
+    [4, 3, 3, 3, 3, 1, 1, 1, 1],
+    [4, 3, 3, 3, 3, 2, 1, 1, 1],
+    [4, 3, 3, 3, 3, 2, 2, 1, 1],
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum CasterProgression {
+    /// Bard, cleric, druid, sorcerer, wizard.
+    Full,
+    /// Paladin, ranger.
+    Half,
+    /// Eldritch Knight, Arcane Trickster.
+    Third,
+    /// Warlock.
+    Pact,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CasterClass {
+    pub progression: CasterProgression,
+    pub level: u8,
+}
+
+impl CasterClass {
+    pub fn new(progression: CasterProgression, level: u8) -> Self {
+        Self {
+            progression,
+            level: level.min(20),
+        }
+    }
+}
+
+/// Caster level for the shared slot table. A single class uses its own
+/// table (half casters round up from 2nd level); multiclassing rounds each
+/// partial class down.
+pub fn caster_level(classes: &[CasterClass]) -> u8 {
+    let slotted: Vec<_> = classes
+        .iter()
+        .filter(|c| c.progression != CasterProgression::Pact && c.level > 0)
+        .collect();
+    if let [single] = slotted[..] {
+        return match single.progression {
+            CasterProgression::Full => single.level,
+            CasterProgression::Half if single.level >= 2 => single.level.div_ceil(2),
+            CasterProgression::Third if single.level >= 3 => single.level.div_ceil(3),
+            _ => 0,
+        };
+    }
+    let level: u8 = slotted
+        .iter()
+        .map(|c| match c.progression {
+            CasterProgression::Full => c.level,
+            CasterProgression::Half => c.level / 2,
+            CasterProgression::Third => c.level / 3,
+            CasterProgression::Pact => 0,
+        })
+        .sum();
+    level.min(20)
+}
+
+/// Slots per spell level (index 0 is 1st level) for a caster level.
+pub fn slots_for_caster_level(level: u8) -> [u8; MAX_SPELL_LEVEL] {
+    match level {
+        0 => [0; MAX_SPELL_LEVEL],
+        l => SLOT_TABLE[usize::from(l.min(20)) - 1],
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PactSlots {
+    pub slot_level: u8,
+    pub max: u8,
+    pub current: u8,
+}
+
+impl PactSlots {
+    /// Pact Magic by warlock level (PHB p.106).
+    pub fn for_warlock_level(level: u8) -> Option<Self> {
+        let (max, slot_level) = match level {
+            0 => return None,
+            1 => (1, 1),
+            2 => (2, 1),
+            3..=10 => (2, level.div_ceil(2)),
+            11..=16 => (3, 5),
+            _ => (4, 5),
+        };
+        Some(Self {
+            slot_level,
+            max,
+            current: max,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpellSlots {
+    pub max: [u8; MAX_SPELL_LEVEL],
+    pub current: [u8; MAX_SPELL_LEVEL],
+    pub pact: Option<PactSlots>,
+    pub arcane_recovery_used: bool,
+}
+
+fn slot_index(level: u8) -> Result<usize, DndError> {
+    match level {
+        1..=9 => Ok(usize::from(level) - 1),
+        _ => Err(DndError::InvalidAction(format!(
+            "there are no level {} spell slots",
+            level
+        ))),
+    }
+}
+
+impl SpellSlots {
+    pub fn for_classes(classes: &[CasterClass]) -> Self {
+        let max = slots_for_caster_level(caster_level(classes));
+        let warlock_level: u8 = classes
+            .iter()
+            .filter(|c| c.progression == CasterProgression::Pact)
+            .map(|c| c.level)
+            .sum();
+        Self {
+            max,
+            current: max,
+            pact: PactSlots::for_warlock_level(warlock_level.min(20)),
+            arcane_recovery_used: false,
+        }
+    }
+
+    /// Regular slots of `level` left, not counting pact slots.
+    pub fn available(&self, level: u8) -> u8 {
+        slot_index(level).map_or(0, |i| self.current[i])
+    }
+
+    /// Everything castable right now by slot level, pact slots included,
+    /// in the shape [`crate::spells::CasterState`] takes.
+    pub fn available_slots(&self) -> HashMap<i32, i32> {
+        let mut slots: HashMap<i32, i32> = (1..=MAX_SPELL_LEVEL as i32)
+            .zip(self.current)
+            .filter(|(_, n)| *n > 0)
+            .map(|(level, n)| (level, i32::from(n)))
+            .collect();
+        if let Some(pact) = self.pact.filter(|p| p.current > 0) {
+            *slots.entry(i32::from(pact.slot_level)).or_insert(0) += i32::from(pact.current);
+        }
+        slots
+    }
+
+    pub fn expend(&mut self, level: u8) -> Result<(), DndError> {
+        let index = slot_index(level)?;
+        if self.current[index] == 0 {
+            return Err(DndError::RulesViolation(format!(
+                "no level {} spell slots left",
+                level
+            )));
+        }
+        self.current[index] -= 1;
+        Ok(())
+    }
+
+    /// Spend a Pact Magic slot; returns the level it is cast at.
+    pub fn expend_pact(&mut self) -> Result<u8, DndError> {
+        match self.pact.as_mut() {
+            Some(pact) if pact.current > 0 => {
+                pact.current -= 1;
+                Ok(pact.slot_level)
+            }
+            Some(_) => Err(DndError::RulesViolation(
+                "no Pact Magic slots left".to_string(),
+            )),
+            None => Err(DndError::InvalidAction(
+                "no Pact Magic slots to spend".to_string(),
+            )),
+        }
+    }
+
+    /// Add a slot that may go above the maximum (Font of Magic); it is lost
+    /// on the next long rest.
+    fn gain(&mut self, level: u8) -> Result<(), DndError> {
+        let index = slot_index(level)?;
+        self.current[index] += 1;
+        Ok(())
+    }
+
+    pub fn short_rest(&mut self) {
+        if let Some(pact) = self.pact.as_mut() {
+            pact.current = pact.max;
+        }
+    }
+
+    pub fn long_rest(&mut self) {
+        self.current = self.max;
+        self.short_rest();
+        self.arcane_recovery_used = false;
+    }
+
+    /// Arcane Recovery: once a day after a short rest, regain expended slots
+    /// whose levels add up to at most half the wizard level (rounded up),
+    /// none of 6th level or higher.
+    pub fn arcane_recovery(&mut self, wizard_level: u8, levels: &[u8]) -> Result<(), DndError> {
+        if self.arcane_recovery_used {
+            return Err(DndError::RulesViolation(
+                "Arcane Recovery has already been used today".to_string(),
+            ));
+        }
+        let budget = u32::from(wizard_level.div_ceil(2));
+        let total: u32 = levels.iter().map(|l| u32::from(*l)).sum();
+        if total > budget {
+            return Err(DndError::RulesViolation(format!(
+                "Arcane Recovery can regain {} slot levels, not {}",
+                budget, total
+            )));
+        }
+        if levels.iter().any(|l| *l >= 6) {
+            return Err(DndError::RulesViolation(
+                "Arcane Recovery can't regain slots of 6th level or higher".to_string(),
+            ));
+        }
+        let mut recovered = self.current;
+        for level in levels {
+            let index = slot_index(*level)?;
+            if recovered[index] >= self.max[index] {
+                return Err(DndError::RulesViolation(format!(
+                    "no expended level {} slot to recover",
+                    level
+                )));
+            }
+            recovered[index] += 1;
+        }
+        self.current = recovered;
+        self.arcane_recovery_used = true;
+        Ok(())
+    }
+}
+
+/// Sorcery points and Font of Magic's conversions (PHB p.101).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SorceryPoints {
+    pub current: u8,
+    pub max: u8,
+}
+
+impl SorceryPoints {
+    /// A sorcerer has points equal to their level from 2nd level.
+    pub fn for_sorcerer_level(level: u8) -> Self {
+        let max = if level >= 2 { level.min(20) } else { 0 };
+        Self { current: max, max }
+    }
+
+    /// Points it costs to create a slot; 5th level is the highest.
+    pub fn slot_cost(level: u8) -> Option<u8> {
+        match level {
+            1 => Some(2),
+            2 => Some(3),
+            3 => Some(5),
+            4 => Some(6),
+            5 => Some(7),
+            _ => None,
+        }
+    }
+
+    /// Sorcerer level Flexible Casting needs to create a slot.
+    pub fn min_sorcerer_level(level: u8) -> Option<u8> {
+        match level {
+            1 => Some(2),
+            2 => Some(3),
+            3 => Some(5),
+            4 => Some(7),
+            5 => Some(9),
+            _ => None,
+        }
+    }
+
+    pub fn create_slot(
+        &mut self,
+        slots: &mut SpellSlots,
+        sorcerer_level: u8,
+        level: u8,
+    ) -> Result<(), DndError> {
+        let (cost, min_level) = Self::slot_cost(level)
+            .zip(Self::min_sorcerer_level(level))
+            .ok_or_else(|| {
+                DndError::RulesViolation(format!(
+                    "Font of Magic can't create a level {} slot",
+                    level
+                ))
+            })?;
+        if sorcerer_level < min_level {
+            return Err(DndError::RulesViolation(format!(
+                "a level {} slot needs sorcerer level {}, not {}",
+                level, min_level, sorcerer_level
+            )));
+        }
+        if self.current < cost {
+            return Err(DndError::RulesViolation(format!(
+                "a level {} slot costs {} sorcery points, {} left",
+                level, cost, self.current
+            )));
+        }
+        slots.gain(level)?;
+        self.current -= cost;
+        Ok(())
+    }
+
+    /// Expend a slot for sorcery points equal to its level, up to the max.
+    pub fn convert_slot(&mut self, slots: &mut SpellSlots, level: u8) -> Result<(), DndError> {
+        slots.expend(level)?;
+        self.current = (self.current + level).min(self.max);
+        Ok(())
+    }
+
+    pub fn long_rest(&mut self) {
+        self.current = self.max;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use CasterProgression::*;
+
+    #[test]
+    fn test_single_class_tables() {
+        assert_eq!(caster_level(&[CasterClass::new(Full, 5)]), 5);
+        // Paladin 5 casts like a 3rd-level full caster; paladin 1 has none.
+        assert_eq!(caster_level(&[CasterClass::new(Half, 5)]), 3);
+        assert_eq!(caster_level(&[CasterClass::new(Half, 1)]), 0);
+        // Eldritch Knight 19 has 4/3/3/1.
+        let ek = SpellSlots::for_classes(&[CasterClass::new(Third, 19)]);
+        assert_eq!(&ek.max[..5], &[4, 3, 3, 1, 0]);
+        assert_eq!(
+            SpellSlots::for_classes(&[CasterClass::new(Full, 20)]).max,
+            [4, 3, 3, 3, 3, 2, 2, 1, 1]
+        );
+    }
+
+    #[test]
+    fn test_multiclass_rounds_down_and_keeps_pact_slots_apart() {
+        // Paladin 3 / sorcerer 2 / warlock 3: caster level 1 + 2 = 3.
+        let classes = [
+            CasterClass::new(Half, 3),
+            CasterClass::new(Full, 2),
+            CasterClass::new(Pact, 3),
+        ];
+        assert_eq!(caster_level(&classes), 3);
+        let mut slots = SpellSlots::for_classes(&classes);
+        assert_eq!(&slots.max[..3], &[4, 2, 0]);
+        assert_eq!(
+            slots.pact,
+            Some(PactSlots {
+                slot_level: 2,
+                max: 2,
+                current: 2
+            })
+        );
+        assert_eq!(slots.available_slots()[&2], 4);
+
+        assert_eq!(slots.expend_pact().unwrap(), 2);
+        slots.expend(1).unwrap();
+        slots.short_rest();
+        assert_eq!(slots.pact.unwrap().current, 2);
+        assert_eq!(slots.available(1), 3);
+        assert!(slots.expend(3).is_err());
+    }
+
+    #[test]
+    fn test_arcane_recovery() {
+        let mut slots = SpellSlots::for_classes(&[CasterClass::new(Full, 6)]);
+        for level in [1, 2, 3] {
+            slots.expend(level).unwrap();
+        }
+        // Wizard 6 regains up to 3 levels of slots.
+        assert!(slots.arcane_recovery(6, &[1, 3]).is_err());
+        assert!(slots.arcane_recovery(6, &[1, 1]).is_err());
+        slots.arcane_recovery(6, &[1, 2]).unwrap();
+        assert_eq!(&slots.current[..4], &[4, 3, 2, 0]);
+        assert!(slots.arcane_recovery(6, &[3]).is_err());
+        slots.long_rest();
+        assert!(!slots.arcane_recovery_used);
+    }
+
+    #[test]
+    fn test_font_of_magic() {
+        let mut slots = SpellSlots::for_classes(&[CasterClass::new(Full, 5)]);
+        let mut points = SorceryPoints::for_sorcerer_level(5);
+        points.create_slot(&mut slots, 5, 3).unwrap();
+        assert_eq!((slots.available(3), points.current), (3, 0));
+        assert!(points.create_slot(&mut slots, 5, 1).is_err());
+        assert!(points.create_slot(&mut slots, 5, 6).is_err());
+
+        points.convert_slot(&mut slots, 3).unwrap();
+        points.convert_slot(&mut slots, 3).unwrap();
+        assert_eq!(points.current, 5);
+        slots.long_rest();
+        assert_eq!(slots.available(3), 2);
+    }
+
+    #[test]
+    fn test_flexible_casting_needs_sorcerer_level() {
+        // Six points would pay for a 4th-level slot, but that takes 7th level.
+        let mut slots = SpellSlots::for_classes(&[CasterClass::new(Full, 6)]);
+        let mut points = SorceryPoints::for_sorcerer_level(6);
+        let err = points.create_slot(&mut slots, 6, 4).unwrap_err();
+        assert!(err.to_string().contains("sorcerer level 7"));
+        assert_eq!(points.current, 6);
+        points.create_slot(&mut slots, 6, 3).unwrap();
+        assert_eq!(points.current, 1);
+    }
+}
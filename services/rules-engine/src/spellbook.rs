@@ -0,0 +1,355 @@
+//! Spells described as data.
+//!
+//! A [`SpellDefinition`] says everything the resolver needs: range,
+//! components, casting time, what it targets, whether it is an attack or a
+//! save, damage or healing per slot level, riders such as conditions or
+//! pushes, and how long it lasts. The built-in definitions live in
+//! `data/spells.json`; adding a spell there needs no code.
+
+use crate::combat::Ability;
+use crate::conditions::ConditionType;
+use crate::dice::DiceExpression;
+use crate::spells::SpellRequirements;
+use grid_solver::aoe::AoeShape;
+use serde::{Deserialize, Serialize};
+use shared_rust::{DamageType, DndError};
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const BUILTIN_SPELLS: &str = include_str!("../data/spells.json");
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CastingTime {
+    #[default]
+    Action,
+    BonusAction,
+    Reaction,
+    Minutes(u32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpellRange {
+    SelfOnly,
+    Touch,
+    Feet(i32),
+}
+
+impl SpellRange {
+    pub fn feet(&self) -> i32 {
+        match self {
+            SpellRange::SelfOnly => 0,
+            SpellRange::Touch => 5,
+            SpellRange::Feet(feet) => *feet,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Components {
+    pub verbal: bool,
+    pub somatic: bool,
+    pub material: Option<String>,
+    /// Components with a cost must be on hand; a focus won't do.
+    pub material_cost_gp: i32,
+    pub material_consumed: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Targeting {
+    /// Chosen creatures, e.g. Hold Person's extra target per slot level.
+    Creatures {
+        count: u32,
+        #[serde(default)]
+        extra_per_slot_level: u32,
+    },
+    /// Darts or rays aimed one at a time, any number of them at the same
+    /// creature, e.g. Magic Missile's three darts plus one per slot level.
+    Missiles {
+        count: u32,
+        #[serde(default)]
+        extra_per_slot_level: u32,
+    },
+    /// An area template, placed on a point in range or centred on / spreading
+    /// from the caster.
+    Area {
+        shape: AoeShape,
+        size_feet: i32,
+        #[serde(default)]
+        width_feet: i32,
+        #[serde(default)]
+        from_self: bool,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Resolution {
+    Attack,
+    Save {
+        ability: Ability,
+        #[serde(default)]
+        half_on_success: bool,
+    },
+    /// Hits without a roll, like Magic Missile or Cure Wounds.
+    Automatic,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpellDamage {
+    pub dice: String,
+    pub damage_type: DamageType,
+    /// Added for each slot level above the spell's level.
+    #[serde(default)]
+    pub per_slot_level: Option<String>,
+    /// Cantrip dice grow at character levels 5, 11 and 17.
+    #[serde(default)]
+    pub cantrip_scaling: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpellHealing {
+    pub dice: String,
+    #[serde(default)]
+    pub per_slot_level: Option<String>,
+    /// Add the caster's spellcasting modifier.
+    #[serde(default)]
+    pub add_modifier: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RiderTrigger {
+    OnFailedSave,
+    OnHit,
+    Always,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum RiderEffect {
+    Condition {
+        condition: ConditionType,
+        /// The target repeats the save at the end of each of its turns.
+        #[serde(default)]
+        save_at_end_of_turn: bool,
+    },
+    /// Pushed directly away from the caster or the area's origin.
+    Push {
+        feet: i32,
+    },
+    SpeedReduction {
+        feet: i32,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Rider {
+    pub when: RiderTrigger,
+    pub effect: RiderEffect,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpellDuration {
+    Instantaneous,
+    Rounds(i32),
+    Minutes(i32),
+    Hours(i32),
+}
+
+impl SpellDuration {
+    /// Duration in 6-second rounds, if it has one.
+    pub fn rounds(&self) -> Option<i32> {
+        match self {
+            SpellDuration::Instantaneous => None,
+            SpellDuration::Rounds(r) => Some(*r),
+            SpellDuration::Minutes(m) => Some(m * 10),
+            SpellDuration::Hours(h) => Some(h * 600),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpellDefinition {
+    pub id: String,
+    pub name: String,
+    pub level: u8,
+    pub casting_time: CastingTime,
+    pub range: SpellRange,
+    #[serde(default)]
+    pub components: Components,
+    pub targeting: Targeting,
+    pub resolution: Resolution,
+    #[serde(default)]
+    pub damage: Option<SpellDamage>,
+    #[serde(default)]
+    pub healing: Option<SpellHealing>,
+    #[serde(default)]
+    pub riders: Vec<Rider>,
+    #[serde(default)]
+    pub concentration: bool,
+    pub duration: SpellDuration,
+    /// The target gains no benefit from cover, e.g. Sacred Flame.
+    #[serde(default)]
+    pub ignores_cover: bool,
+}
+
+impl SpellDefinition {
+    /// What [`crate::spells::validate_spell_cast`] checks.
+    pub fn requirements(&self, slot_level: u8) -> SpellRequirements {
+        let max_targets = match self.targeting {
+            Targeting::Creatures {
+                count,
+                extra_per_slot_level,
+            }
+            | Targeting::Missiles {
+                count,
+                extra_per_slot_level,
+            } => count + extra_per_slot_level * u32::from(slot_level.saturating_sub(self.level)),
+            Targeting::Area { .. } => 0,
+        };
+        SpellRequirements {
+            spell_id: self.id.clone(),
+            level: i32::from(self.level),
+            casting_time: self.casting_time,
+            requires_verbal: self.components.verbal,
+            requires_somatic: self.components.somatic,
+            requires_material: self.components.material.is_some(),
+            material_cost_gp: self.components.material_cost_gp,
+            material_consumed: self.components.material_consumed,
+            requires_concentration: self.concentration,
+            max_targets: max_targets as i32,
+            range_feet: self.range.feet(),
+        }
+    }
+
+    fn validate(&self) -> Result<(), DndError> {
+        let invalid = |reason: String| {
+            Err(DndError::InvalidSpellDefinition(format!(
+                "{}: {}",
+                self.id, reason
+            )))
+        };
+        if self.id.is_empty() {
+            return invalid("missing id".to_string());
+        }
+        if self.level > 9 {
+            return invalid(format!("level {} is above 9th", self.level));
+        }
+        let dice = self
+            .damage
+            .iter()
+            .flat_map(|d| std::iter::once(&d.dice).chain(&d.per_slot_level))
+            .chain(
+                self.healing
+                    .iter()
+                    .flat_map(|h| std::iter::once(&h.dice).chain(&h.per_slot_level)),
+            );
+        for expression in dice {
+            if let Err(e) = DiceExpression::parse(expression) {
+                return invalid(e.to_string());
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Spellbook {
+    spells: HashMap<String, SpellDefinition>,
+}
+
+impl Spellbook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a JSON array of definitions.
+    pub fn from_json(json: &str) -> Result<Self, DndError> {
+        let definitions: Vec<SpellDefinition> = serde_json::from_str(json)
+            .map_err(|e| DndError::InvalidSpellDefinition(e.to_string()))?;
+        let mut book = Self::new();
+        for definition in definitions {
+            book.insert(definition)?;
+        }
+        Ok(book)
+    }
+
+    /// The spells shipped in `data/spells.json`.
+    pub fn builtin() -> &'static Spellbook {
+        static BUILTIN: OnceLock<Spellbook> = OnceLock::new();
+        BUILTIN.get_or_init(|| {
+            Spellbook::from_json(BUILTIN_SPELLS).expect("data/spells.json is valid")
+        })
+    }
+
+    pub fn insert(&mut self, definition: SpellDefinition) -> Result<(), DndError> {
+        definition.validate()?;
+        if self.spells.contains_key(&definition.id) {
+            return Err(DndError::InvalidSpellDefinition(format!(
+                "{} is defined twice",
+                definition.id
+            )));
+        }
+        self.spells.insert(definition.id.clone(), definition);
+        Ok(())
+    }
+
+    pub fn get(&self, spell_id: &str) -> Option<&SpellDefinition> {
+        self.spells.get(spell_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.spells.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.spells.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_spells_load() {
+        let book = Spellbook::builtin();
+        let fireball = book.get("fireball").unwrap();
+        assert_eq!(fireball.level, 3);
+        assert!(matches!(
+            fireball.targeting,
+            Targeting::Area {
+                shape: AoeShape::Sphere,
+                size_feet: 20,
+                ..
+            }
+        ));
+        let hold = book.get("hold-person").unwrap();
+        assert!(hold.concentration);
+        assert_eq!(hold.duration.rounds(), Some(10));
+        assert_eq!(hold.requirements(4).max_targets, 3);
+        let missile = book.get("magic-missile").unwrap();
+        assert!(matches!(
+            missile.targeting,
+            Targeting::Missiles { count: 3, .. }
+        ));
+        assert_eq!(missile.requirements(2).max_targets, 4);
+    }
+
+    #[test]
+    fn test_bad_definitions_are_rejected() {
+        let spell = r#"[{
+            "id": "bad", "name": "Bad", "level": 1, "casting_time": "Action",
+            "range": {"Feet": 60}, "targeting": {"Creatures": {"count": 1}},
+            "resolution": "Automatic", "duration": "Instantaneous",
+            "damage": {"dice": "2x6", "damage_type": "Fire"}
+        }]"#;
+        assert!(matches!(
+            Spellbook::from_json(spell),
+            Err(DndError::InvalidSpellDefinition(_))
+        ));
+        let fixed = spell.replace("2x6", "2d6");
+        assert_eq!(Spellbook::from_json(&fixed).unwrap().len(), 1);
+
+        let twice = format!("[{0},{0}]", &fixed[1..fixed.len() - 1]);
+        assert!(Spellbook::from_json(&twice).is_err());
+    }
+}
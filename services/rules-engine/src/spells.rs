@@ -0,0 +1,346 @@
+//! Spellcasting basics: save DCs, attack bonuses and cast validation.
+//! Concentration saves are rolled in [`crate::concentration`].
+
+use crate::spellbook::CastingTime;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Spell save DC = 8 + proficiency bonus + spellcasting ability modifier (PHB p.205)
+pub fn spell_save_dc(proficiency_bonus: i32, ability_modifier: i32) -> i32 {
+    8 + proficiency_bonus + ability_modifier
+}
+
+/// Spell attack modifier = proficiency bonus + spellcasting ability modifier (PHB p.205)
+pub fn spell_attack_bonus(proficiency_bonus: i32, ability_modifier: i32) -> i32 {
+    proficiency_bonus + ability_modifier
+}
+
+/// The subset of a spell definition needed to validate a cast.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SpellRequirements {
+    pub spell_id: String,
+    pub level: i32,
+    pub casting_time: CastingTime,
+    pub requires_verbal: bool,
+    pub requires_somatic: bool,
+    pub requires_material: bool,
+    /// Components with a listed cost can't be replaced by a focus.
+    pub material_cost_gp: i32,
+    pub material_consumed: bool,
+    pub requires_concentration: bool,
+    pub max_targets: i32,
+    /// 0 when the range isn't checked, e.g. for self-only spells.
+    pub range_feet: i32,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CasterState {
+    pub available_slots: HashMap<i32, i32>,
+    pub prepared_spells: Vec<String>,
+    /// Spells known, for casters who don't prepare.
+    pub known_spells: Vec<String>,
+    pub is_concentrating: bool,
+    pub has_free_hand: bool,
+    /// Holding an arcane focus, holy symbol or component pouch.
+    pub has_focus: bool,
+    pub can_speak: bool,
+    /// Inside a Silence spell or similar.
+    pub silenced: bool,
+    /// Value of the costly material components the caster carries.
+    pub material_value_gp: i32,
+    /// Spells already cast on this turn (PHB p.202).
+    pub cast_bonus_action_spell: bool,
+    pub cast_leveled_spell: bool,
+    pub is_raging: bool,
+    /// Wearing armor the caster isn't proficient with (PHB p.144).
+    pub armor_not_proficient: bool,
+}
+
+/// Where a target sits relative to the caster, as the grid solver sees it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TargetReach {
+    pub target_id: String,
+    pub distance_feet: i32,
+    pub line_of_effect: bool,
+}
+
+impl TargetReach {
+    /// A target whose position isn't known; range isn't checked.
+    pub fn unplaced(target_id: impl Into<String>) -> Self {
+        Self {
+            target_id: target_id.into(),
+            distance_feet: 0,
+            line_of_effect: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpellValidation {
+    pub valid: bool,
+    pub errors: Vec<String>,
+    pub will_break_concentration: bool,
+    pub slot_to_consume: i32,
+    /// Gold value of components the spell consumes.
+    pub material_consumed_gp: i32,
+}
+
+/// Check whether a caster can cast `spell` with a slot of `slot_level`.
+/// Every problem is reported rather than stopping at the first one.
+pub fn validate_spell_cast(
+    spell: &SpellRequirements,
+    caster: &CasterState,
+    slot_level: i32,
+    targets: &[TargetReach],
+) -> SpellValidation {
+    let mut errors = Vec::new();
+
+    let prepared = caster.prepared_spells.contains(&spell.spell_id)
+        || caster.known_spells.contains(&spell.spell_id);
+    let tracks_spells = !caster.prepared_spells.is_empty() || !caster.known_spells.is_empty();
+    if tracks_spells && !prepared {
+        errors.push(format!("Spell {} is not known or prepared", spell.spell_id));
+    }
+
+    if caster.is_raging {
+        errors.push("Cannot cast spells while raging".to_string());
+    }
+    if caster.armor_not_proficient {
+        errors.push("Cannot cast spells in armor without proficiency".to_string());
+    }
+
+    let slot_to_consume = if spell.level == 0 {
+        0
+    } else if slot_level < spell.level {
+        errors.push(format!(
+            "Cannot cast a level {} spell with a level {} slot",
+            spell.level, slot_level
+        ));
+        0
+    } else if caster
+        .available_slots
+        .get(&slot_level)
+        .copied()
+        .unwrap_or(0)
+        <= 0
+    {
+        errors.push(format!("No level {} spell slots available", slot_level));
+        0
+    } else {
+        slot_level
+    };
+
+    if spell.requires_verbal && caster.silenced {
+        errors.push("Verbal component required but caster is silenced".to_string());
+    } else if spell.requires_verbal && !caster.can_speak {
+        errors.push("Verbal component required but caster cannot speak".to_string());
+    }
+    // The hand holding a focus or the material can also perform the
+    // somatic component (PHB p.203).
+    let focus_hand = spell.requires_material && caster.has_focus;
+    if spell.requires_somatic && !caster.has_free_hand && !focus_hand {
+        errors.push("Somatic component required but caster has no free hand".to_string());
+    }
+    if spell.requires_material {
+        if spell.material_cost_gp > 0 {
+            if caster.material_value_gp < spell.material_cost_gp {
+                errors.push(format!(
+                    "Material component worth {} gp required (caster has {} gp)",
+                    spell.material_cost_gp, caster.material_value_gp
+                ));
+            }
+        } else if !caster.has_focus && !caster.has_free_hand && !spell.requires_somatic {
+            // With a somatic component the missing hand is already reported.
+            errors.push(
+                "Material component required but caster has no focus or free hand".to_string(),
+            );
+        }
+    }
+
+    let bonus_action = spell.casting_time == CastingTime::BonusAction;
+    let action_cantrip = spell.level == 0 && spell.casting_time == CastingTime::Action;
+    if bonus_action && (caster.cast_bonus_action_spell || caster.cast_leveled_spell) {
+        errors.push(
+            "A bonus action spell can't be cast after another spell this turn, \
+             other than a cantrip with a casting time of 1 action"
+                .to_string(),
+        );
+    } else if caster.cast_bonus_action_spell && !action_cantrip {
+        errors.push(
+            "After casting a bonus action spell, only a cantrip with a casting time \
+             of 1 action can be cast this turn"
+                .to_string(),
+        );
+    }
+
+    if spell.max_targets > 0 && targets.len() > spell.max_targets as usize {
+        errors.push(format!(
+            "Too many targets: {} (maximum {})",
+            targets.len(),
+            spell.max_targets
+        ));
+    }
+    for target in targets {
+        if spell.range_feet > 0 && target.distance_feet > spell.range_feet {
+            errors.push(format!(
+                "Target {} is {} ft away (range {} ft)",
+                target.target_id, target.distance_feet, spell.range_feet
+            ));
+        }
+        if !target.line_of_effect {
+            errors.push(format!("No clear path to target {}", target.target_id));
+        }
+    }
+
+    SpellValidation {
+        valid: errors.is_empty(),
+        errors,
+        will_break_concentration: spell.requires_concentration && caster.is_concentrating,
+        slot_to_consume,
+        material_consumed_gp: if spell.material_consumed {
+            spell.material_cost_gp
+        } else {
+            0
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fireball() -> SpellRequirements {
+        SpellRequirements {
+            spell_id: "fireball".to_string(),
+            level: 3,
+            requires_verbal: true,
+            requires_somatic: true,
+            requires_material: true,
+            range_feet: 150,
+            ..Default::default()
+        }
+    }
+
+    fn caster() -> CasterState {
+        CasterState {
+            available_slots: HashMap::from([(3, 2)]),
+            prepared_spells: vec!["fireball".to_string()],
+            has_free_hand: true,
+            can_speak: true,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_save_dc_and_attack_bonus() {
+        assert_eq!(spell_save_dc(3, 4), 15);
+        assert_eq!(spell_attack_bonus(3, 4), 7);
+    }
+
+    #[test]
+    fn test_valid_cast_consumes_slot() {
+        let result = validate_spell_cast(&fireball(), &caster(), 3, &[]);
+        assert!(result.valid);
+        assert_eq!(result.slot_to_consume, 3);
+    }
+
+    #[test]
+    fn test_reports_all_errors() {
+        let mut state = caster();
+        state.can_speak = false;
+        state.has_free_hand = false;
+
+        let result = validate_spell_cast(&fireball(), &state, 2, &[]);
+        assert!(!result.valid);
+        assert_eq!(result.errors.len(), 3);
+    }
+
+    #[test]
+    fn test_focus_hand_and_costly_components() {
+        let mut state = caster();
+        state.has_free_hand = false;
+        state.has_focus = true;
+        assert!(validate_spell_cast(&fireball(), &state, 3, &[]).valid);
+
+        let revivify = SpellRequirements {
+            spell_id: "revivify".to_string(),
+            level: 3,
+            requires_verbal: true,
+            requires_somatic: true,
+            requires_material: true,
+            material_cost_gp: 300,
+            material_consumed: true,
+            range_feet: 5,
+            ..Default::default()
+        };
+        state.prepared_spells.push("revivify".to_string());
+        let result = validate_spell_cast(&revivify, &state, 3, &[]);
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].contains("300 gp"));
+
+        state.material_value_gp = 300;
+        let result = validate_spell_cast(&revivify, &state, 3, &[]);
+        assert!(result.valid);
+        assert_eq!(result.material_consumed_gp, 300);
+    }
+
+    #[test]
+    fn test_bonus_action_spell_rule() {
+        let healing_word = SpellRequirements {
+            spell_id: "healing-word".to_string(),
+            level: 1,
+            casting_time: CastingTime::BonusAction,
+            requires_verbal: true,
+            ..Default::default()
+        };
+        let mut state = caster();
+        state.available_slots.insert(1, 1);
+        state.prepared_spells.push("healing-word".to_string());
+        assert!(validate_spell_cast(&healing_word, &state, 1, &[]).valid);
+
+        state.cast_leveled_spell = true;
+        assert!(!validate_spell_cast(&healing_word, &state, 1, &[]).valid);
+
+        let fire_bolt = SpellRequirements {
+            spell_id: "fire-bolt".to_string(),
+            requires_verbal: true,
+            requires_somatic: true,
+            ..Default::default()
+        };
+        state.cast_leveled_spell = false;
+        state.cast_bonus_action_spell = true;
+        state.known_spells.push("fire-bolt".to_string());
+        assert!(validate_spell_cast(&fire_bolt, &state, 0, &[]).valid);
+        assert!(!validate_spell_cast(&fireball(), &state, 3, &[]).valid);
+    }
+
+    #[test]
+    fn test_range_line_of_effect_and_restrictions() {
+        let mut state = caster();
+        state.is_raging = true;
+        state.silenced = true;
+        let targets = [
+            TargetReach {
+                target_id: "near".to_string(),
+                distance_feet: 100,
+                line_of_effect: true,
+            },
+            TargetReach {
+                target_id: "far".to_string(),
+                distance_feet: 200,
+                line_of_effect: true,
+            },
+            TargetReach {
+                target_id: "walled".to_string(),
+                distance_feet: 30,
+                line_of_effect: false,
+            },
+        ];
+        let result = validate_spell_cast(&fireball(), &state, 3, &targets);
+        assert_eq!(result.errors.len(), 4, "{:?}", result.errors);
+        assert!(result.errors.iter().any(|e| e.contains("raging")));
+        assert!(result.errors.iter().any(|e| e.contains("silenced")));
+        assert!(result.errors.iter().any(|e| e.contains("far")));
+        assert!(result.errors.iter().any(|e| e.contains("walled")));
+    }
+}
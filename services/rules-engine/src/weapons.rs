@@ -0,0 +1,503 @@
+//! Weapons and their properties (PHB p.146-149).
+//!
+//! Definitions are data, loaded from `data/weapons.json` like the
+//! spellbook. [`derive_attack`] turns a weapon and how it is being used into
+//! what the attack roll needs: the ability, the damage dice, reach or range
+//! bands and any disadvantage the weapon itself imposes. Restrictions such
+//! as two-handed grips and out-of-range targets are errors.
+
+use crate::advantage::D20Sources;
+use crate::attack::AttackInput;
+use crate::combat::{Ability, CreatureStats, DamageType, Size};
+use crate::dice::DiceExpression;
+use serde::{Deserialize, Serialize};
+use shared_rust::DndError;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const BUILTIN_WEAPONS: &str = include_str!("../data/weapons.json");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeaponCategory {
+    Simple,
+    Martial,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeaponKind {
+    Melee,
+    Ranged,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum WeaponProperty {
+    /// STR or DEX, whichever is better.
+    Finesse,
+    /// Damage dice when wielded in two hands.
+    Versatile(String),
+    TwoHanded,
+    /// Can be used for two-weapon fighting.
+    Light,
+    /// Small creatures have disadvantage.
+    Heavy,
+    /// +5 ft of reach.
+    Reach,
+    Thrown {
+        normal: i32,
+        long: i32,
+    },
+    Ammunition {
+        normal: i32,
+        long: i32,
+    },
+    /// One shot per action, bonus action or reaction.
+    Loading,
+    /// Rules of its own (lance, net).
+    Special(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeaponDamage {
+    pub dice: String,
+    pub damage_type: DamageType,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Weapon {
+    pub id: String,
+    pub name: String,
+    pub category: WeaponCategory,
+    pub kind: WeaponKind,
+    /// None for weapons that deal no damage, like a net.
+    #[serde(default)]
+    pub damage: Option<WeaponDamage>,
+    #[serde(default)]
+    pub properties: Vec<WeaponProperty>,
+}
+
+impl Weapon {
+    pub fn has(&self, property: &WeaponProperty) -> bool {
+        self.properties.contains(property)
+    }
+
+    pub fn versatile_dice(&self) -> Option<&str> {
+        self.properties.iter().find_map(|p| match p {
+            WeaponProperty::Versatile(dice) => Some(dice.as_str()),
+            _ => None,
+        })
+    }
+
+    /// Normal and long range when thrown.
+    pub fn thrown_range(&self) -> Option<(i32, i32)> {
+        self.properties.iter().find_map(|p| match p {
+            WeaponProperty::Thrown { normal, long } => Some((*normal, *long)),
+            _ => None,
+        })
+    }
+
+    /// Normal and long range when fired.
+    pub fn ammunition_range(&self) -> Option<(i32, i32)> {
+        self.properties.iter().find_map(|p| match p {
+            WeaponProperty::Ammunition { normal, long } => Some((*normal, *long)),
+            _ => None,
+        })
+    }
+
+    pub fn reach_feet(&self) -> i32 {
+        if self.has(&WeaponProperty::Reach) {
+            10
+        } else {
+            5
+        }
+    }
+
+    fn validate(&self) -> Result<(), DndError> {
+        let invalid = |reason: String| {
+            Err(DndError::InvalidWeaponDefinition(format!(
+                "{}: {}",
+                self.id, reason
+            )))
+        };
+        let dice = self
+            .damage
+            .iter()
+            .map(|d| d.dice.as_str())
+            .chain(self.versatile_dice());
+        for dice in dice {
+            if let Err(e) = DiceExpression::parse(dice) {
+                return invalid(e.to_string());
+            }
+        }
+        let ranges = self
+            .thrown_range()
+            .into_iter()
+            .chain(self.ammunition_range());
+        for (normal, long) in ranges {
+            if normal <= 0 || long < normal {
+                return invalid(format!("bad range {}/{}", normal, long));
+            }
+        }
+        if self.kind == WeaponKind::Ranged
+            && self.ammunition_range().is_none()
+            && self.thrown_range().is_none()
+        {
+            return invalid("ranged weapons need a range".to_string());
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Armory {
+    weapons: HashMap<String, Weapon>,
+}
+
+impl Armory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parse a JSON array of definitions.
+    pub fn from_json(json: &str) -> Result<Self, DndError> {
+        let weapons: Vec<Weapon> = serde_json::from_str(json)
+            .map_err(|e| DndError::InvalidWeaponDefinition(e.to_string()))?;
+        let mut armory = Self::new();
+        for weapon in weapons {
+            armory.insert(weapon)?;
+        }
+        Ok(armory)
+    }
+
+    /// The PHB weapon table in `data/weapons.json`.
+    pub fn builtin() -> &'static Armory {
+        static BUILTIN: OnceLock<Armory> = OnceLock::new();
+        BUILTIN
+            .get_or_init(|| Armory::from_json(BUILTIN_WEAPONS).expect("data/weapons.json is valid"))
+    }
+
+    pub fn insert(&mut self, weapon: Weapon) -> Result<(), DndError> {
+        weapon.validate()?;
+        if self.weapons.contains_key(&weapon.id) {
+            return Err(DndError::InvalidWeaponDefinition(format!(
+                "{} is defined twice",
+                weapon.id
+            )));
+        }
+        self.weapons.insert(weapon.id.clone(), weapon);
+        Ok(())
+    }
+
+    pub fn get(&self, weapon_id: &str) -> Option<&Weapon> {
+        self.weapons.get(weapon_id)
+    }
+
+    pub fn len(&self) -> usize {
+        self.weapons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.weapons.is_empty()
+    }
+}
+
+/// How the weapon is being used for this attack.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WeaponUse {
+    /// Held in both hands: needed for two-handed weapons, and versatile
+    /// weapons roll their larger die.
+    pub two_hands: bool,
+    /// Thrown rather than swung.
+    pub thrown: bool,
+    pub distance_feet: i32,
+    /// Crossbow Expert ignores loading.
+    pub ignore_loading: bool,
+}
+
+impl Default for WeaponUse {
+    fn default() -> Self {
+        Self {
+            two_hands: false,
+            thrown: false,
+            distance_feet: 5,
+            ignore_loading: false,
+        }
+    }
+}
+
+/// Everything a weapon decides about an attack.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeaponAttack {
+    pub weapon_id: String,
+    pub ability: Ability,
+    /// Empty for weapons that deal no damage.
+    pub damage_dice: String,
+    pub damage_type: Option<DamageType>,
+    pub ranged: bool,
+    pub reach_feet: i32,
+    /// Normal and long range for ranged and thrown attacks.
+    pub range: Option<(i32, i32)>,
+    /// Beyond normal range: the attack roll has disadvantage.
+    pub long_range: bool,
+    /// Disadvantage from the weapon itself, e.g. a heavy weapon in a Small
+    /// creature's hands. Long range is left to the attack roll.
+    pub sources: D20Sources,
+    /// Loading weapons fire once per action however many attacks the
+    /// creature has.
+    pub max_attacks_per_action: Option<u8>,
+    pub notes: Vec<String>,
+}
+
+impl WeaponAttack {
+    /// An attack input with the weapon's ability, range and disadvantage;
+    /// proficiency, magic and the rest are up to the caller.
+    pub fn attack_input(&self, distance_feet: i32) -> AttackInput {
+        let mut input = AttackInput::weapon(self.ability)
+            .at_distance(distance_feet)
+            .with_sources(self.sources.clone());
+        if let Some((normal, long)) = self.range {
+            input = input.ranged(normal, long);
+        }
+        input
+    }
+
+    /// Attacks this weapon can make in one Attack action.
+    pub fn attacks_per_action(&self, attacks: u8) -> u8 {
+        self.max_attacks_per_action
+            .map_or(attacks, |max| attacks.min(max))
+    }
+}
+
+/// Work out `wielder`'s attack with `weapon` used as `usage`.
+pub fn derive_attack(
+    weapon: &Weapon,
+    wielder: &CreatureStats,
+    usage: &WeaponUse,
+) -> Result<WeaponAttack, DndError> {
+    let violation =
+        |reason: String| DndError::RulesViolation(format!("{}: {}", weapon.name, reason));
+
+    if weapon.has(&WeaponProperty::TwoHanded) && !usage.two_hands {
+        return Err(violation("needs two hands".to_string()));
+    }
+
+    let ability = if weapon.has(&WeaponProperty::Finesse) {
+        let str_mod = wielder.get_modifier(Ability::STR);
+        let dex_mod = wielder.get_modifier(Ability::DEX);
+        if dex_mod > str_mod {
+            Ability::DEX
+        } else {
+            Ability::STR
+        }
+    } else {
+        // Thrown melee weapons keep the ability they'd use in melee.
+        match weapon.kind {
+            WeaponKind::Melee => Ability::STR,
+            WeaponKind::Ranged => Ability::DEX,
+        }
+    };
+
+    let range = if usage.thrown {
+        Some(
+            weapon
+                .thrown_range()
+                .ok_or_else(|| violation("can't be thrown".to_string()))?,
+        )
+    } else if weapon.kind == WeaponKind::Ranged {
+        weapon.ammunition_range().or(weapon.thrown_range())
+    } else {
+        None
+    };
+    let ranged = range.is_some();
+    let reach_feet = weapon.reach_feet();
+    let mut long_range = false;
+    match range {
+        Some((normal, long)) => {
+            if usage.distance_feet > long {
+                return Err(violation(format!(
+                    "target at {} ft is beyond its {} ft long range",
+                    usage.distance_feet, long
+                )));
+            }
+            long_range = usage.distance_feet > normal;
+        }
+        None if usage.distance_feet > reach_feet => {
+            return Err(violation(format!(
+                "target at {} ft is beyond its {} ft reach",
+                usage.distance_feet, reach_feet
+            )));
+        }
+        None => {}
+    }
+
+    let mut sources = D20Sources::default();
+    if weapon.has(&WeaponProperty::Heavy) && wielder.size <= Size::Small {
+        sources
+            .disadvantage
+            .push(format!("Heavy weapon ({:?} wielder)", wielder.size));
+    }
+
+    let damage_dice = match (&weapon.damage, weapon.versatile_dice()) {
+        (Some(_), Some(versatile)) if usage.two_hands && !usage.thrown => versatile.to_string(),
+        (Some(damage), _) => damage.dice.clone(),
+        (None, _) => String::new(),
+    };
+
+    let loading = weapon.has(&WeaponProperty::Loading) && !usage.ignore_loading;
+    let notes = weapon
+        .properties
+        .iter()
+        .filter_map(|p| match p {
+            WeaponProperty::Special(text) => Some(text.clone()),
+            _ => None,
+        })
+        .collect();
+
+    Ok(WeaponAttack {
+        weapon_id: weapon.id.clone(),
+        ability,
+        damage_dice,
+        damage_type: weapon.damage.as_ref().map(|d| d.damage_type),
+        ranged,
+        reach_feet,
+        range,
+        long_range,
+        sources,
+        max_attacks_per_action: loading.then_some(1),
+        notes,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn wielder(str_score: i32, dex_score: i32, size: Size) -> CreatureStats {
+        let mut stats = CreatureStats {
+            size,
+            ..Default::default()
+        };
+        stats.ability_scores.insert(Ability::STR, str_score);
+        stats.ability_scores.insert(Ability::DEX, dex_score);
+        stats
+    }
+
+    fn weapon(id: &str) -> &'static Weapon {
+        Armory::builtin().get(id).unwrap()
+    }
+
+    #[test]
+    fn test_builtin_weapons_load() {
+        let armory = Armory::builtin();
+        assert_eq!(armory.len(), 37);
+        assert_eq!(weapon("longsword").versatile_dice(), Some("1d10"));
+        assert_eq!(weapon("glaive").reach_feet(), 10);
+
+        let bad = r#"[{"id": "stick", "name": "Stick", "category": "Simple", "kind": "Ranged",
+            "damage": {"dice": "1d4", "damage_type": "Bludgeoning"}}]"#;
+        assert!(matches!(
+            Armory::from_json(bad),
+            Err(DndError::InvalidWeaponDefinition(_))
+        ));
+    }
+
+    #[test]
+    fn test_ability_and_dice() {
+        let rogue = wielder(10, 16, Size::Medium);
+        let rapier = derive_attack(weapon("rapier"), &rogue, &WeaponUse::default()).unwrap();
+        assert_eq!(rapier.ability, Ability::DEX);
+        let brute = wielder(16, 10, Size::Medium);
+        let rapier = derive_attack(weapon("rapier"), &brute, &WeaponUse::default()).unwrap();
+        assert_eq!(rapier.ability, Ability::STR);
+
+        let one_hand = derive_attack(weapon("longsword"), &brute, &WeaponUse::default()).unwrap();
+        assert_eq!(one_hand.damage_dice, "1d8");
+        let two_hands = WeaponUse {
+            two_hands: true,
+            ..Default::default()
+        };
+        let gripped = derive_attack(weapon("longsword"), &brute, &two_hands).unwrap();
+        assert_eq!(gripped.damage_dice, "1d10");
+
+        assert!(matches!(
+            derive_attack(weapon("greatsword"), &brute, &WeaponUse::default()),
+            Err(DndError::RulesViolation(_))
+        ));
+    }
+
+    #[test]
+    fn test_range_bands_and_reach() {
+        let brute = wielder(16, 10, Size::Medium);
+        let thrown = |distance_feet| WeaponUse {
+            thrown: true,
+            distance_feet,
+            ..Default::default()
+        };
+        let handaxe = derive_attack(weapon("handaxe"), &brute, &thrown(20)).unwrap();
+        assert_eq!(handaxe.ability, Ability::STR);
+        assert!(handaxe.ranged && !handaxe.long_range);
+        let far = derive_attack(weapon("handaxe"), &brute, &thrown(40)).unwrap();
+        assert!(far.long_range);
+        assert!(derive_attack(weapon("handaxe"), &brute, &thrown(70)).is_err());
+        assert!(derive_attack(weapon("mace"), &brute, &thrown(10)).is_err());
+
+        let reach = WeaponUse {
+            two_hands: true,
+            distance_feet: 10,
+            ..Default::default()
+        };
+        assert!(derive_attack(weapon("halberd"), &brute, &reach).is_ok());
+        assert!(derive_attack(weapon("greataxe"), &brute, &reach).is_err());
+
+        // Long range shows up as disadvantage once the attack is rolled.
+        let longbow = derive_attack(
+            weapon("longbow"),
+            &brute,
+            &WeaponUse {
+                two_hands: true,
+                distance_feet: 200,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(longbow.ability, Ability::DEX);
+        assert_eq!(longbow.range, Some((150, 600)));
+        let input = longbow.attack_input(200);
+        assert_eq!(input.range, Some((150, 600)));
+    }
+
+    #[test]
+    fn test_loading_and_heavy_restrictions() {
+        let halfling = wielder(8, 16, Size::Small);
+        let usage = WeaponUse {
+            two_hands: true,
+            distance_feet: 30,
+            ..Default::default()
+        };
+        let crossbow = derive_attack(weapon("heavy-crossbow"), &halfling, &usage).unwrap();
+        assert_eq!(crossbow.sources.disadvantage.len(), 1);
+        assert_eq!(crossbow.attacks_per_action(2), 1);
+
+        let expert = WeaponUse {
+            ignore_loading: true,
+            ..usage
+        };
+        let crossbow = derive_attack(weapon("heavy-crossbow"), &halfling, &expert).unwrap();
+        assert_eq!(crossbow.attacks_per_action(2), 2);
+
+        let human = wielder(8, 16, Size::Medium);
+        let crossbow = derive_attack(weapon("heavy-crossbow"), &human, &usage).unwrap();
+        assert!(crossbow.sources.disadvantage.is_empty());
+
+        let net = derive_attack(
+            weapon("net"),
+            &human,
+            &WeaponUse {
+                thrown: true,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert!(net.damage_dice.is_empty());
+        assert_eq!(net.notes.len(), 1);
+    }
+}
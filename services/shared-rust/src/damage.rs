@@ -0,0 +1,118 @@
+//! Damage types and typed damage instances.
+
+use crate::errors::DndError;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// The thirteen damage types (PHB p.196).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+pub enum DamageType {
+    Slashing,
+    Piercing,
+    Bludgeoning,
+    Fire,
+    Cold,
+    Lightning,
+    Thunder,
+    Acid,
+    Poison,
+    Necrotic,
+    Radiant,
+    Force,
+    Psychic,
+}
+
+impl DamageType {
+    pub const ALL: [DamageType; 13] = [
+        DamageType::Slashing,
+        DamageType::Piercing,
+        DamageType::Bludgeoning,
+        DamageType::Fire,
+        DamageType::Cold,
+        DamageType::Lightning,
+        DamageType::Thunder,
+        DamageType::Acid,
+        DamageType::Poison,
+        DamageType::Necrotic,
+        DamageType::Radiant,
+        DamageType::Force,
+        DamageType::Psychic,
+    ];
+
+    /// Bludgeoning, piercing and slashing, the types "nonmagical attacks"
+    /// resistances refer to.
+    pub fn is_physical(&self) -> bool {
+        matches!(
+            self,
+            DamageType::Bludgeoning | DamageType::Piercing | DamageType::Slashing
+        )
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            DamageType::Slashing => "slashing",
+            DamageType::Piercing => "piercing",
+            DamageType::Bludgeoning => "bludgeoning",
+            DamageType::Fire => "fire",
+            DamageType::Cold => "cold",
+            DamageType::Lightning => "lightning",
+            DamageType::Thunder => "thunder",
+            DamageType::Acid => "acid",
+            DamageType::Poison => "poison",
+            DamageType::Necrotic => "necrotic",
+            DamageType::Radiant => "radiant",
+            DamageType::Force => "force",
+            DamageType::Psychic => "psychic",
+        }
+    }
+}
+
+impl fmt::Display for DamageType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for DamageType {
+    type Err = DndError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        DamageType::ALL
+            .into_iter()
+            .find(|t| t.name().eq_ignore_ascii_case(s.trim()))
+            .ok_or_else(|| DndError::UnknownDamageType(s.to_string()))
+    }
+}
+
+/// One typed chunk of damage from a single attack or effect, e.g. the
+/// slashing and the fire of a flame tongue hit are two instances.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DamageInstance {
+    pub damage_type: DamageType,
+    pub amount: i32,
+    /// From a magical attack or effect; matters for B/P/S resistances.
+    pub magical: bool,
+}
+
+impl DamageInstance {
+    pub fn new(damage_type: DamageType, amount: i32) -> Self {
+        Self {
+            damage_type,
+            amount,
+            magical: false,
+        }
+    }
+
+    pub fn magical(damage_type: DamageType, amount: i32) -> Self {
+        Self {
+            magical: true,
+            ..Self::new(damage_type, amount)
+        }
+    }
+
+    /// Nonmagical bludgeoning, piercing or slashing.
+    pub fn is_nonmagical_physical(&self) -> bool {
+        !self.magical && self.damage_type.is_physical()
+    }
+}
@@ -1,5 +1,6 @@
 //! Common error types.
 
+use crate::damage::DamageType;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -7,6 +8,9 @@ pub enum DndError {
     #[error("Invalid position: ({x}, {y})")]
     InvalidPosition { x: i32, y: i32 },
 
+    #[error("Invalid entity id: {0}")]
+    InvalidEntityId(String),
+
     #[error("Entity not found: {0}")]
     EntityNotFound(String),
 
@@ -15,4 +19,19 @@ pub enum DndError {
 
     #[error("Rules violation: {0}")]
     RulesViolation(String),
+
+    #[error("Invalid spell definition: {0}")]
+    InvalidSpellDefinition(String),
+
+    #[error("Invalid weapon definition: {0}")]
+    InvalidWeaponDefinition(String),
+
+    #[error("Unknown damage type: {0}")]
+    UnknownDamageType(String),
+
+    #[error("Negative damage: {amount} {damage_type}")]
+    NegativeDamage {
+        damage_type: DamageType,
+        amount: i32,
+    },
 }
@@ -1,7 +1,9 @@
 //! Shared Rust types and utilities for D&D Platform services.
 
 pub mod types;
+pub mod damage;
 pub mod errors;
 
 pub use types::*;
+pub use damage::*;
 pub use errors::*;
@@ -1,6 +1,9 @@
 //! Common types used across Rust services.
 
+use crate::errors::DndError;
 use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
 use uuid::Uuid;
 
 /// A position on the game grid.
@@ -37,3 +40,33 @@ impl Default for EntityId {
         Self::new()
     }
 }
+
+impl fmt::Display for EntityId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for EntityId {
+    type Err = DndError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(s)
+            .map(Self)
+            .map_err(|_| DndError::InvalidEntityId(s.to_string()))
+    }
+}
+
+impl TryFrom<&str> for EntityId {
+    type Error = DndError;
+
+    fn try_from(s: &str) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<EntityId> for String {
+    fn from(id: EntityId) -> Self {
+        id.to_string()
+    }
+}